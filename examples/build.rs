@@ -3,5 +3,6 @@ fn main() {
         "resources",
         "resources/resources.gresource.xml",
         "compiled.gresource",
-    );
+    )
+    .unwrap();
 }
@@ -1,7 +1,13 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
-use crate::{ActionMap, SimpleAction};
-use glib::{IsA, Variant};
+use std::marker::PhantomData;
+
+use crate::{
+    prelude::*,
+    typed_action::{from_optional_variant, optional_variant_type, to_optional_variant},
+    ActionMap, SimpleAction, TypedAction,
+};
+use glib::{FromVariant, IsA, ToVariant, Variant};
 
 #[doc(alias = "GActionEntry")]
 pub struct ActionEntry<O>
@@ -13,6 +19,8 @@ where
     state: Option<String>,
     pub(crate) activate: Option<Box<dyn Fn(&O, &SimpleAction, Option<&Variant>) + 'static>>,
     pub(crate) change_state: Option<Box<dyn Fn(&O, &SimpleAction, Option<&Variant>) + 'static>>,
+    // Checks the types of the action created from a typed entry.
+    pub(crate) check_types: Option<fn(&SimpleAction) -> Result<(), glib::BoolError>>,
 }
 
 impl<O> ActionEntry<O>
@@ -65,6 +73,7 @@ where
             state: Default::default(),
             activate: Default::default(),
             change_state: Default::default(),
+            check_types: Default::default(),
         })
     }
 
@@ -99,10 +108,105 @@ where
     }
 }
 
+// rustdoc-stripper-ignore-next
+/// Builder for an [`ActionEntry`] with a parameter of type `P` and a state of type `S`, with
+/// `()` standing for no parameter, respectively no state.
+///
+/// The callbacks receive the action as [`TypedAction`] and the converted parameter or state.
+pub struct TypedActionEntryBuilder<O, P, S = ()>
+where
+    O: IsA<ActionMap>,
+{
+    builder: ActionEntryBuilder<O>,
+    phantom: PhantomData<fn(P, S)>,
+}
+
+impl<O, P, S> std::fmt::Debug for TypedActionEntryBuilder<O, P, S>
+where
+    O: IsA<ActionMap>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TypedActionEntryBuilder")
+            .field(&self.builder)
+            .finish()
+    }
+}
+
+impl<O, P> TypedActionEntryBuilder<O, P>
+where
+    O: IsA<ActionMap>,
+    P: FromVariant + ToVariant,
+{
+    pub fn new(name: &str) -> Self {
+        Self::with_state(name, None)
+    }
+}
+
+impl<O, P, S> TypedActionEntryBuilder<O, P, S>
+where
+    O: IsA<ActionMap>,
+    P: FromVariant + ToVariant,
+    S: FromVariant + ToVariant,
+{
+    pub fn new_stateful(name: &str, state: &S) -> Self {
+        Self::with_state(name, to_optional_variant(state))
+    }
+
+    fn with_state(name: &str, state: Option<Variant>) -> Self {
+        let mut builder = ActionEntryBuilder::new(name);
+        if let Some(parameter_type) = optional_variant_type::<P>() {
+            builder = builder.parameter_type(parameter_type.as_str());
+        }
+        if let Some(state) = state {
+            builder = builder.state(&state.print(true));
+        }
+        builder.0.check_types = Some(TypedAction::<P, S>::check_types);
+        Self {
+            builder,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn activate<F: Fn(&O, &TypedAction<P, S>, P) + 'static>(mut self, callback: F) -> Self {
+        self.builder = self.builder.activate(move |map, action, parameter| {
+            // The types were checked when adding the entry to the action map.
+            let action = TypedAction::wrap(action);
+            match from_optional_variant(parameter) {
+                Some(parameter) => callback(map, &action, parameter),
+                None => glib::g_critical!(
+                    "gio",
+                    "Invalid parameter {:?} for action {}",
+                    parameter,
+                    action.name()
+                ),
+            }
+        });
+        self
+    }
+
+    pub fn change_state<F: Fn(&O, &TypedAction<P, S>, S) + 'static>(mut self, callback: F) -> Self {
+        self.builder = self.builder.change_state(move |map, action, state| {
+            let action = TypedAction::wrap(action);
+            match from_optional_variant(state) {
+                Some(state) => callback(map, &action, state),
+                None => glib::g_critical!(
+                    "gio",
+                    "Invalid state {:?} for action {}",
+                    state,
+                    action.name()
+                ),
+            }
+        });
+        self
+    }
+
+    pub fn build(self) -> ActionEntry<O> {
+        self.builder.build()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::prelude::*;
-
     use super::*;
 
     #[test]
@@ -117,4 +221,54 @@ mod tests {
         app.add_action_entries(vec![close]).unwrap();
         assert!(app.lookup_action("close").is_some());
     }
+
+    #[test]
+    fn typed_action_entry() {
+        let group = crate::SimpleActionGroup::new();
+
+        let volume = TypedActionEntryBuilder::new_stateful("volume", &50u8)
+            .activate(|_group: &crate::SimpleActionGroup, action, delta: i16| {
+                let volume = (action.state() as i16 + delta).clamp(0, 255);
+                action.change_state(&(volume as u8));
+            })
+            .change_state(|_group, action, volume| {
+                action.set_state(&volume.min(100));
+            })
+            .build();
+        assert_eq!(volume.parameter_type(), Some("n"));
+        assert_eq!(volume.state(), Some("byte 0x32"));
+        let mute = TypedActionEntryBuilder::<_, ()>::new("mute")
+            .activate(|group: &crate::SimpleActionGroup, _, ()| {
+                group
+                    .lookup_typed_action::<i16, u8>("volume")
+                    .unwrap()
+                    .change_state(&0);
+            })
+            .build();
+        group.add_action_entries(vec![volume, mute]).unwrap();
+
+        let volume = group.lookup_typed_action::<i16, u8>("volume").unwrap();
+        group.activate_typed_action(&volume, &70);
+        assert_eq!(volume.state(), 100);
+        volume.activate(&-20);
+        assert_eq!(volume.state(), 80);
+        group.activate_action("mute", None);
+        assert_eq!(volume.state(), 0);
+    }
+
+    #[test]
+    fn typed_action_entry_mismatch() {
+        let group = crate::SimpleActionGroup::new();
+
+        let mut entry = TypedActionEntryBuilder::<_, i32>::new("jump")
+            .activate(|_group: &crate::SimpleActionGroup, _, _| unreachable!())
+            .build();
+        entry.parameter_type = Some("s".to_owned());
+        let err = group.add_action_entries(vec![entry]).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Action jump has parameter type Some(\"s\") and state type None, expected Some(\"i\") and None"
+        );
+        assert!(group.lookup_action("jump").is_none());
+    }
 }
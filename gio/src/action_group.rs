@@ -0,0 +1,64 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::{FromVariant, IsA, ToVariant};
+
+use crate::{
+    prelude::*,
+    typed_action::{from_optional_variant, to_optional_variant},
+    ActionGroup, TypedAction,
+};
+
+// rustdoc-stripper-ignore-next
+/// Activation and state changes of actions in a group, with the parameter and state types of
+/// a [`TypedAction`] of the same name.
+pub trait ActionGroupExtManual: 'static {
+    #[doc(alias = "g_action_group_activate_action")]
+    fn activate_typed_action<P: FromVariant + ToVariant, S>(
+        &self,
+        action: &TypedAction<P, S>,
+        parameter: &P,
+    );
+
+    #[doc(alias = "g_action_group_change_action_state")]
+    fn change_typed_action_state<P, S: FromVariant + ToVariant>(
+        &self,
+        action: &TypedAction<P, S>,
+        state: &S,
+    );
+
+    // rustdoc-stripper-ignore-next
+    /// The state of the action in this group, or `None` if it doesn't exist or has a
+    /// different state type.
+    #[doc(alias = "g_action_group_get_action_state")]
+    fn typed_action_state<P, S: FromVariant>(&self, action: &TypedAction<P, S>) -> Option<S>;
+}
+
+impl<O: IsA<ActionGroup>> ActionGroupExtManual for O {
+    fn activate_typed_action<P: FromVariant + ToVariant, S>(
+        &self,
+        action: &TypedAction<P, S>,
+        parameter: &P,
+    ) {
+        self.activate_action(
+            &action.as_ref().name(),
+            to_optional_variant(parameter).as_ref(),
+        );
+    }
+
+    fn change_typed_action_state<P, S: FromVariant + ToVariant>(
+        &self,
+        action: &TypedAction<P, S>,
+        state: &S,
+    ) {
+        if let Some(state) = to_optional_variant(state) {
+            self.change_action_state(&action.as_ref().name(), &state);
+        }
+    }
+
+    fn typed_action_state<P, S: FromVariant>(&self, action: &TypedAction<P, S>) -> Option<S> {
+        if !self.has_action(&action.as_ref().name()) {
+            return None;
+        }
+        from_optional_variant(self.action_state(&action.as_ref().name()).as_ref())
+    }
+}
@@ -1,7 +1,7 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
-use crate::{prelude::*, ActionEntry, ActionMap, SimpleAction};
-use glib::{clone, Cast, IsA};
+use crate::{prelude::*, ActionEntry, ActionMap, SimpleAction, TypedAction};
+use glib::{clone, Cast, FromVariant, IsA, ToVariant};
 
 pub trait ActionMapExtManual {
     #[doc(alias = "g_action_map_add_action_entries")]
@@ -11,6 +11,18 @@ pub trait ActionMapExtManual {
     ) -> Result<(), glib::BoolError>
     where
         Self: IsA<ActionMap>;
+
+    #[doc(alias = "g_action_map_add_action")]
+    fn add_typed_action<P, S>(&self, action: &TypedAction<P, S>);
+
+    // rustdoc-stripper-ignore-next
+    /// Looks up the action `name`, returning `None` if it doesn't exist, is not a
+    /// [`SimpleAction`] or has a different parameter or state type.
+    #[doc(alias = "g_action_map_lookup_action")]
+    fn lookup_typed_action<P: FromVariant + ToVariant, S: FromVariant + ToVariant>(
+        &self,
+        name: &str,
+    ) -> Option<TypedAction<P, S>>;
 }
 
 impl<O: IsA<ActionMap>> ActionMapExtManual for O {
@@ -36,6 +48,9 @@ impl<O: IsA<ActionMap>> ActionMapExtManual for O {
             } else {
                 SimpleAction::new(entry.name(), parameter_type.as_deref())
             };
+            if let Some(check_types) = entry.check_types {
+                check_types(&action)?;
+            }
             let action_map = self.as_ref();
             if let Some(callback) = entry.activate {
                 action.connect_activate(clone!(@strong action_map =>  move |action, state| {
@@ -53,4 +68,16 @@ impl<O: IsA<ActionMap>> ActionMapExtManual for O {
         }
         Ok(())
     }
+
+    fn add_typed_action<P, S>(&self, action: &TypedAction<P, S>) {
+        self.add_action(action.as_ref());
+    }
+
+    fn lookup_typed_action<P: FromVariant + ToVariant, S: FromVariant + ToVariant>(
+        &self,
+        name: &str,
+    ) -> Option<TypedAction<P, S>> {
+        let action = self.lookup_action(name)?.downcast::<SimpleAction>().ok()?;
+        TypedAction::from_action(action)
+    }
 }
@@ -1,12 +1,14 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+use crate::prelude::*;
 use crate::Application;
+use crate::ApplicationCommandLine;
 use crate::File;
-use glib::prelude::*;
 use glib::signal::{connect_raw, SignalHandlerId};
 use glib::translate::*;
 use glib::GString;
 use std::boxed::Box as Box_;
+use std::future::Future;
 use std::mem::transmute;
 
 pub trait ApplicationExtManual {
@@ -16,11 +18,73 @@ pub trait ApplicationExtManual {
     fn run_with_args<S: AsRef<str>>(&self, args: &[S]) -> i32;
     fn connect_open<F: Fn(&Self, &[File], &str) + 'static>(&self, f: F) -> SignalHandlerId;
 
+    // rustdoc-stripper-ignore-next
+    /// Connects to `command-line` with a handler returning the exit status as a future.
+    ///
+    /// The future is spawned on the thread-default main context and the application is held
+    /// until it resolves. Remote instances receive the exit status once the future resolved, so
+    /// their invocation only completes then.
+    ///
+    /// For command lines handled in the primary instance itself, [`run`](Self::run) returns `0`
+    /// as `GApplication` only supports delayed exit statuses for remote invocations.
+    #[doc(alias = "command-line")]
+    fn connect_command_line_async<F, Fut>(&self, f: F) -> SignalHandlerId
+    where
+        F: Fn(&Self, ApplicationCommandLine) -> Fut + 'static,
+        Fut: Future<Output = i32> + 'static;
+
     #[doc(alias = "g_application_hold")]
     fn hold(&self) -> ApplicationHoldGuard;
 
     #[doc(alias = "g_application_mark_busy")]
     fn mark_busy(&self) -> ApplicationBusyGuard;
+
+    // rustdoc-stripper-ignore-next
+    /// Adds options to the application's main group. Parsed values are passed to the
+    /// `handle-local-options` signal.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an entry has a callback, which `GApplication` doesn't support for main
+    /// options. Use [`add_option_group`](Self::add_option_group) for those.
+    #[doc(alias = "g_application_add_main_option_entries")]
+    fn add_main_option_entries(&self, entries: impl IntoIterator<Item = glib::OptionEntry>);
+
+    // rustdoc-stripper-ignore-next
+    /// Adds a group of options. Parsed values are available from [`glib::OptionGroup::values`]
+    /// and are not passed to the `handle-local-options` signal.
+    #[doc(alias = "g_application_add_option_group")]
+    fn add_option_group(&self, group: &glib::OptionGroup);
+
+    // rustdoc-stripper-ignore-next
+    /// Adds the entries of `T` as main options.
+    ///
+    /// ```no_run
+    /// use gio::prelude::*;
+    ///
+    /// #[derive(glib::Options)]
+    /// struct Args {
+    ///     #[option(short = 'v')]
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let app = gio::Application::new(None, gio::ApplicationFlags::empty());
+    /// app.add_main_options::<Args>();
+    /// app.connect_handle_local_options(|_, dict| match Args::from_variant_dict(dict) {
+    ///     Ok(args) => {
+    ///         if args.verbose {
+    ///             println!("verbose");
+    ///         }
+    ///         -1
+    ///     }
+    ///     Err(err) => {
+    ///         eprintln!("{}", err);
+    ///         1
+    ///     }
+    /// });
+    /// app.run();
+    /// ```
+    fn add_main_options<T: glib::Options>(&self);
 }
 
 impl<O: IsA<Application>> ApplicationExtManual for O {
@@ -67,6 +131,26 @@ impl<O: IsA<Application>> ApplicationExtManual for O {
         }
     }
 
+    fn connect_command_line_async<F, Fut>(&self, f: F) -> SignalHandlerId
+    where
+        F: Fn(&Self, ApplicationCommandLine) -> Fut + 'static,
+        Fut: Future<Output = i32> + 'static,
+    {
+        self.connect_command_line(move |application, command_line| {
+            let guard = application.hold();
+            let future = f(application, command_line.clone());
+            let command_line = command_line.clone();
+            // The exit status returned from the signal is set right after emission, so this
+            // always overrides it. Dropping the command line then replies to the remote instance.
+            glib::MainContext::ref_thread_default().spawn_local(async move {
+                command_line.set_exit_status(future.await);
+                drop(command_line);
+                drop(guard);
+            });
+            0
+        })
+    }
+
     fn hold(&self) -> ApplicationHoldGuard {
         unsafe {
             ffi::g_application_hold(self.as_ref().to_glib_none().0);
@@ -80,6 +164,37 @@ impl<O: IsA<Application>> ApplicationExtManual for O {
         }
         ApplicationBusyGuard(self.as_ref().downgrade())
     }
+
+    fn add_main_option_entries(&self, entries: impl IntoIterator<Item = glib::OptionEntry>) {
+        // g_application_add_main_option() copies the strings, unlike the entries variant.
+        for entry in entries {
+            assert!(
+                !entry.has_callback(),
+                "callback options are not supported as main options of an application"
+            );
+            self.add_main_option(
+                entry.long_name(),
+                entry.short_name().map_or(0, |c| c as u8).into(),
+                entry.flags(),
+                entry.arg(),
+                entry.description().unwrap_or_default(),
+                entry.arg_description(),
+            );
+        }
+    }
+
+    fn add_option_group(&self, group: &glib::OptionGroup) {
+        unsafe {
+            ffi::g_application_add_option_group(
+                self.as_ref().to_glib_none().0,
+                group.to_glib_full(),
+            );
+        }
+    }
+
+    fn add_main_options<T: glib::Options>(&self) {
+        self.add_main_option_entries(T::option_entries());
+    }
 }
 
 #[derive(Debug)]
@@ -109,3 +224,98 @@ impl Drop for ApplicationBusyGuard {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApplicationFlags;
+    use glib::{OptionArg, OptionEntry, OptionGroup};
+    use serial_test::serial;
+
+    #[test]
+    #[serial(application)]
+    fn test_options() {
+        #[derive(Debug, PartialEq, glib::Options)]
+        struct Args {
+            #[option(short = 'c')]
+            count: i32,
+            verbose: bool,
+            name: Option<String>,
+            input: Vec<std::path::PathBuf>,
+        }
+
+        let app = Application::new(None, ApplicationFlags::NON_UNIQUE);
+        app.add_main_options::<Args>();
+
+        let group = OptionGroup::new("extra", "Extra options", "Show extra options");
+        group.add_entries([OptionEntry::builder("level").arg(OptionArg::Int).build()]);
+        app.add_option_group(&group);
+
+        app.connect_handle_local_options(move |_, dict| {
+            let args = Args::from_variant_dict(dict).unwrap();
+            assert_eq!(
+                args,
+                Args {
+                    count: 3,
+                    verbose: true,
+                    name: None,
+                    input: vec!["a".into(), "b".into()],
+                }
+            );
+            assert_eq!(group.values().lookup::<i32>("level").unwrap(), Some(5));
+            7
+        });
+
+        assert_eq!(
+            app.run_with_args(&[
+                "prog",
+                "-c",
+                "3",
+                "--verbose",
+                "--level=5",
+                "--input",
+                "a",
+                "--input",
+                "b"
+            ]),
+            7
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial(application)]
+    fn remote_invocation() {
+        use crate::test_util::{ApplicationHarness, Invocation};
+
+        let harness = ApplicationHarness::new(
+            "org.gtk_rs.RemoteInvocation",
+            ApplicationFlags::HANDLES_COMMAND_LINE | ApplicationFlags::HANDLES_OPEN,
+            |app| {
+                app.connect_command_line_async(|_, command_line| async move {
+                    assert!(command_line.is_remote());
+                    let args = command_line.arguments();
+                    glib::timeout_future(std::time::Duration::from_millis(10)).await;
+                    command_line.print(&format!("{} arguments\n", args.len()));
+                    command_line.printerr("no files given\n");
+                    args.len() as i32
+                });
+            },
+        );
+
+        let context = glib::MainContext::new();
+        let invocation = context.block_on(harness.command_line(&["prog", "--verbose", "a"]));
+        assert_eq!(
+            invocation,
+            Invocation {
+                exit_status: 3,
+                stdout: "3 arguments\n".into(),
+                stderr: "no files given\n".into(),
+            }
+        );
+
+        let (files, hint) = harness.open(&["file:///tmp/a.txt", "file:///tmp/b.txt"], "edit");
+        assert_eq!(files, ["file:///tmp/a.txt", "file:///tmp/b.txt"]);
+        assert_eq!(hint, "edit");
+    }
+}
@@ -0,0 +1,42 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::translate::*;
+
+use crate::{prelude::*, ApplicationCommandLine};
+
+pub trait ApplicationCommandLineExtManual {
+    // rustdoc-stripper-ignore-next
+    /// Prints `message` on the standard output of the invoking process, which is a different
+    /// process for remote invocations.
+    #[doc(alias = "g_application_command_line_print")]
+    fn print(&self, message: &str);
+
+    // rustdoc-stripper-ignore-next
+    /// Prints `message` on the standard error of the invoking process.
+    #[doc(alias = "g_application_command_line_printerr")]
+    fn printerr(&self, message: &str);
+}
+
+impl<O: IsA<ApplicationCommandLine>> ApplicationCommandLineExtManual for O {
+    fn print(&self, message: &str) {
+        let message: Stash<*const libc::c_char, _> = message.to_glib_none();
+        unsafe {
+            ffi::g_application_command_line_print(
+                self.as_ref().to_glib_none().0,
+                b"%s\0".as_ptr() as *const _,
+                message.0,
+            )
+        }
+    }
+
+    fn printerr(&self, message: &str) {
+        let message: Stash<*const libc::c_char, _> = message.to_glib_none();
+        unsafe {
+            ffi::g_application_command_line_printerr(
+                self.as_ref().to_glib_none().0,
+                b"%s\0".as_ptr() as *const _,
+                message.0,
+            )
+        }
+    }
+}
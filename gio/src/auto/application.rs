@@ -159,12 +159,6 @@ pub trait ApplicationExt: 'static {
         arg_description: Option<&str>,
     );
 
-    //#[doc(alias = "g_application_add_main_option_entries")]
-    //fn add_main_option_entries(&self, entries: /*Ignored*/&[glib::OptionEntry]);
-
-    //#[doc(alias = "g_application_add_option_group")]
-    //fn add_option_group(&self, group: /*Ignored*/&glib::OptionGroup);
-
     #[doc(alias = "g_application_bind_busy_property")]
     fn bind_busy_property(&self, object: &impl IsA<glib::Object>, property: &str);
 
@@ -329,14 +323,6 @@ impl<O: IsA<Application>> ApplicationExt for O {
         }
     }
 
-    //fn add_main_option_entries(&self, entries: /*Ignored*/&[glib::OptionEntry]) {
-    //    unsafe { TODO: call ffi:g_application_add_main_option_entries() }
-    //}
-
-    //fn add_option_group(&self, group: /*Ignored*/&glib::OptionGroup) {
-    //    unsafe { TODO: call ffi:g_application_add_option_group() }
-    //}
-
     fn bind_busy_property(&self, object: &impl IsA<glib::Object>, property: &str) {
         unsafe {
             ffi::g_application_bind_busy_property(
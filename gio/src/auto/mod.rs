@@ -316,6 +316,9 @@ pub use self::socket_connectable::SocketConnectable;
 mod socket_connection;
 pub use self::socket_connection::SocketConnection;
 
+mod socket_control_message;
+pub use self::socket_control_message::SocketControlMessage;
+
 mod socket_listener;
 pub use self::socket_listener::SocketListener;
 
@@ -364,6 +367,13 @@ pub use self::tls_password::TlsPassword;
 mod tls_server_connection;
 pub use self::tls_server_connection::TlsServerConnection;
 
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+mod unix_credentials_message;
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+pub use self::unix_credentials_message::UnixCredentialsMessage;
+
 #[cfg(any(unix, feature = "dox"))]
 #[cfg_attr(feature = "dox", doc(cfg(unix)))]
 mod unix_fd_list;
@@ -371,6 +381,13 @@ mod unix_fd_list;
 #[cfg_attr(feature = "dox", doc(cfg(unix)))]
 pub use self::unix_fd_list::UnixFDList;
 
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+mod unix_fd_message;
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+pub use self::unix_fd_message::UnixFDMessage;
+
 #[cfg(any(unix, feature = "dox"))]
 #[cfg_attr(feature = "dox", doc(cfg(unix)))]
 mod unix_input_stream;
@@ -766,6 +783,7 @@ pub mod traits {
     pub use super::socket_client::SocketClientExt;
     pub use super::socket_connectable::SocketConnectableExt;
     pub use super::socket_connection::SocketConnectionExt;
+    pub use super::socket_control_message::SocketControlMessageExt;
     pub use super::socket_listener::SocketListenerExt;
     pub use super::socket_service::SocketServiceExt;
     pub use super::tcp_connection::TcpConnectionExt;
@@ -781,9 +799,15 @@ pub mod traits {
     pub use super::tls_server_connection::TlsServerConnectionExt;
     #[cfg(any(unix, feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    pub use super::unix_credentials_message::UnixCredentialsMessageExt;
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
     pub use super::unix_fd_list::UnixFDListExt;
     #[cfg(any(unix, feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    pub use super::unix_fd_message::UnixFDMessageExt;
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
     pub use super::unix_input_stream::UnixInputStreamExt;
     #[cfg(any(unix, feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(unix)))]
@@ -121,6 +121,118 @@ where
     }
 }
 
+// rustdoc-stripper-ignore-next
+/// Spawning futures on a [`MainContext`](glib::MainContext) that are aborted when a
+/// [`Cancellable`] is cancelled.
+pub trait MainContextCancellableExt {
+    // rustdoc-stripper-ignore-next
+    /// Spawns `f` on the main context, wrapped in a [`CancellableFuture`].
+    ///
+    /// Once `cancellable` is cancelled the future is dropped and the returned
+    /// [`JoinHandle`](glib::JoinHandle) resolves to `Err(Cancelled)`.
+    fn spawn_with_cancellable<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        f: F,
+    ) -> glib::JoinHandle<Result<R, Cancelled>>;
+
+    // rustdoc-stripper-ignore-next
+    /// Local variant of [`Self::spawn_with_cancellable`].
+    ///
+    /// The given `Future` does not have to be `Send`.
+    fn spawn_local_with_cancellable<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        f: F,
+    ) -> glib::JoinHandle<Result<R, Cancelled>>;
+}
+
+impl MainContextCancellableExt for glib::MainContext {
+    fn spawn_with_cancellable<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        f: F,
+    ) -> glib::JoinHandle<Result<R, Cancelled>> {
+        self.spawn_task(CancellableFuture::new(f, cancellable.clone()))
+    }
+
+    fn spawn_local_with_cancellable<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        f: F,
+    ) -> glib::JoinHandle<Result<R, Cancelled>> {
+        self.spawn_local_task(CancellableFuture::new(f, cancellable.clone()))
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Extension trait for pushing cancellable jobs to a [`glib::ThreadPool`].
+pub trait ThreadPoolCancellableExt {
+    // rustdoc-stripper-ignore-next
+    /// Pushes `func` to the pool, unless `cancellable` is cancelled before the job starts.
+    ///
+    /// `func` gets `cancellable` passed so it can check for cancellation while running.
+    fn push_with_cancellable<F: FnOnce(&Cancellable) + Send + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        func: F,
+    ) -> Result<(), glib::Error>;
+
+    // rustdoc-stripper-ignore-next
+    /// Like [`Self::push_with_cancellable`] but returns a future for the result of `func`.
+    ///
+    /// The future resolves to `Err(Cancelled)` as soon as `cancellable` is cancelled, even if
+    /// `func` is still running.
+    fn push_future_with_cancellable<
+        T: Send + 'static,
+        F: FnOnce(&Cancellable) -> T + Send + 'static,
+    >(
+        &self,
+        cancellable: &Cancellable,
+        func: F,
+    ) -> Result<Pin<Box<dyn Future<Output = Result<T, Cancelled>> + Send + 'static>>, glib::Error>;
+}
+
+impl ThreadPoolCancellableExt for glib::ThreadPool {
+    fn push_with_cancellable<F: FnOnce(&Cancellable) + Send + 'static>(
+        &self,
+        cancellable: &Cancellable,
+        func: F,
+    ) -> Result<(), glib::Error> {
+        let cancellable = cancellable.clone();
+        self.push(move || {
+            if !cancellable.is_cancelled() {
+                func(&cancellable);
+            }
+        })
+    }
+
+    fn push_future_with_cancellable<
+        T: Send + 'static,
+        F: FnOnce(&Cancellable) -> T + Send + 'static,
+    >(
+        &self,
+        cancellable: &Cancellable,
+        func: F,
+    ) -> Result<Pin<Box<dyn Future<Output = Result<T, Cancelled>> + Send + 'static>>, glib::Error>
+    {
+        let (sender, receiver) = futures_channel::oneshot::channel();
+
+        // The sender is dropped without a result if the job is skipped
+        self.push_with_cancellable(cancellable, move |cancellable| {
+            let _ = sender.send(func(cancellable));
+        })?;
+
+        let f = CancellableFuture::new(receiver, cancellable.clone());
+        Ok(Box::pin(async move {
+            match f.await {
+                Ok(Ok(res)) => Ok(res),
+                _ => Err(Cancelled),
+            }
+        }))
+    }
+}
+
 impl From<Cancelled> for glib::Error {
     fn from(_: Cancelled) -> Self {
         glib::Error::new(IOErrorEnum::Cancelled, "Task cancelled")
@@ -147,6 +259,8 @@ mod tests {
     use super::CancellableExt;
     use super::CancellableFuture;
     use super::Cancelled;
+    use super::MainContextCancellableExt;
+    use super::ThreadPoolCancellableExt;
     use futures_channel::oneshot;
 
     #[test]
@@ -192,4 +306,55 @@ mod tests {
 
         ctx.block_on(rx).unwrap();
     }
+
+    #[test]
+    fn spawn_local_with_cancellable() {
+        let ctx = glib::MainContext::new();
+        let c = Cancellable::new();
+
+        let handle = ctx
+            .with_thread_default(|| ctx.spawn_local_with_cancellable(&c, async { 42 }))
+            .unwrap();
+        assert!(matches!(ctx.block_on(handle), Ok(Ok(42))));
+
+        let (tx, rx) = oneshot::channel::<()>();
+        let handle = ctx.spawn_with_cancellable(&c, async move {
+            let _tx = tx;
+            std::future::pending::<()>().await
+        });
+        std::thread::spawn(move || c.cancel()).join().unwrap();
+        assert!(matches!(ctx.block_on(handle), Ok(Err(Cancelled))));
+        // The future was dropped
+        assert!(ctx.block_on(rx).is_err());
+    }
+
+    #[test]
+    fn thread_pool_with_cancellable() {
+        let ctx = glib::MainContext::new();
+        let pool = glib::ThreadPool::exclusive(1).unwrap();
+        let c = Cancellable::new();
+
+        let res = pool.push_future_with_cancellable(&c, |_| 42).unwrap();
+        assert!(matches!(ctx.block_on(res), Ok(42)));
+
+        // Block the only thread so the next job is only started after cancellation
+        let (block_tx, block_rx) = std::sync::mpsc::channel::<()>();
+        pool.push(move || {
+            let _ = block_rx.recv();
+        })
+        .unwrap();
+
+        let (tx, rx) = oneshot::channel::<()>();
+        pool.push_with_cancellable(&c, move |_| tx.send(()).unwrap())
+            .unwrap();
+        let res = pool
+            .push_future_with_cancellable(&c, |c| c.is_cancelled())
+            .unwrap();
+
+        c.cancel();
+        assert!(matches!(ctx.block_on(res), Err(Cancelled)));
+        drop(block_tx);
+        // The job was skipped
+        assert!(ctx.block_on(rx).is_err());
+    }
 }
@@ -13,16 +13,19 @@ pub use ffi;
 pub use glib;
 
 mod action_entry;
+mod action_group;
 mod action_map;
 mod app_info;
 mod application;
-pub use action_entry::{ActionEntry, ActionEntryBuilder};
+mod application_command_line;
+pub use action_entry::{ActionEntry, ActionEntryBuilder, TypedActionEntryBuilder};
 pub use application::{ApplicationBusyGuard, ApplicationHoldGuard};
 mod async_initable;
 mod cancellable;
 mod cancellable_future;
 pub use crate::cancellable_future::CancellableFuture;
 pub use crate::cancellable_future::Cancelled;
+pub use crate::cancellable_future::{MainContextCancellableExt, ThreadPoolCancellableExt};
 mod converter;
 mod data_input_stream;
 mod dbus;
@@ -64,6 +67,10 @@ mod list_store;
 mod memory_input_stream;
 #[cfg(test)]
 mod memory_output_stream;
+mod menu;
+mod menu_item;
+mod notification;
+pub use crate::notification::NotificationBuilder;
 mod output_stream;
 pub use crate::output_stream::OutputStreamWrite;
 mod pollable_input_stream;
@@ -74,14 +81,26 @@ mod resource;
 pub use crate::resource::resources_register_include_impl;
 mod settings;
 pub use crate::settings::BindingBuilder;
+#[cfg(feature = "include_resources")]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "include_resources")))]
+pub use glib_macros::include_resources;
+#[cfg(feature = "settings")]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "settings")))]
+pub use glib_macros::settings;
 mod simple_proxy_resolver;
 mod socket;
+mod socket_message;
+pub use crate::socket_message::{InputMessage, OutputMessage};
 mod subprocess;
 mod subprocess_launcher;
 mod threaded_socket_service;
+mod typed_action;
+pub use crate::typed_action::TypedAction;
 #[cfg(any(unix, feature = "dox"))]
 mod unix_fd_list;
 #[cfg(any(unix, feature = "dox"))]
+mod unix_fd_message;
+#[cfg(any(unix, feature = "dox"))]
 mod unix_input_stream;
 #[cfg(any(unix, feature = "dox"))]
 mod unix_mount_entry;
@@ -92,8 +111,9 @@ mod unix_output_stream;
 #[cfg(any(unix, feature = "dox"))]
 mod unix_socket_address;
 
-#[cfg(test)]
-mod test_util;
+#[cfg(any(test, feature = "test_util"))]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "test_util")))]
+pub mod test_util;
 
 pub use crate::auto::functions::*;
 pub use crate::auto::*;
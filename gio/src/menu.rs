@@ -0,0 +1,476 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::collections::BTreeMap;
+
+use glib::{translate::*, Cast, IsA, StaticType, Variant};
+
+use crate::{prelude::*, Menu, MenuItem, MenuModel};
+
+// rustdoc-stripper-ignore-next
+/// Builds a [`Menu`](crate::Menu) from a declarative description.
+///
+/// The following entries are supported, separated by commas:
+/// - `item(label, detailed_action)` and `item(label, action, target)`, with `target` being
+///   anything implementing `ToVariant`.
+/// - `action(label, namespace, typed_action, target)` for a
+///   [`TypedAction`](crate::TypedAction) in the action group `namespace`, for example `"win"`.
+///   The target has to match the parameter type of the action, and can be omitted for actions
+///   without parameter.
+/// - `section { ... }` and `section(label) { ... }`.
+/// - `submenu(label) { ... }`.
+///
+/// ```
+/// # use gio::prelude::*;
+/// let zoom = gio::TypedAction::<i32>::new("zoom");
+/// let menu = gio::menu! {
+///     section {
+///         item("New Window", "app.new-window"),
+///         item("Open Home", "app.open", "/home"),
+///     },
+///     submenu("View") {
+///         action("Zoom In", "win", zoom, 1),
+///         action("Zoom Out", "win", zoom, -1),
+///     },
+///     item("Quit", "app.quit"),
+/// };
+/// assert_eq!(menu.n_items(), 3);
+/// ```
+#[macro_export]
+macro_rules! menu {
+    ($($body:tt)*) => {{
+        let menu = $crate::Menu::new();
+        $crate::__menu_entries!(menu; $($body)*);
+        menu
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __menu_entries {
+    ($menu:ident;) => {};
+    ($menu:ident; item($label:expr, $action:expr) $(, $($rest:tt)*)?) => {
+        $menu.append(::std::option::Option::Some($label), ::std::option::Option::Some($action));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+    ($menu:ident; item($label:expr, $action:expr, $target:expr) $(, $($rest:tt)*)?) => {
+        $menu.append_item(&$crate::MenuItem::with_target(
+            ::std::option::Option::Some($label),
+            $action,
+            &$target,
+        ));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+    ($menu:ident; action($label:expr, $namespace:expr, $action:expr) $(, $($rest:tt)*)?) => {
+        $crate::__menu_entries!($menu; action($label, $namespace, $action, ()) $(, $($rest)*)?);
+    };
+    ($menu:ident; action($label:expr, $namespace:expr, $action:expr, $target:expr) $(, $($rest:tt)*)?) => {
+        $menu.append_item(&$crate::MenuItem::with_typed_action(
+            ::std::option::Option::Some($label),
+            $namespace,
+            &$action,
+            &$target,
+        ));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+    ($menu:ident; section { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $menu.append_section(::std::option::Option::None, &$crate::menu!($($inner)*));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+    ($menu:ident; section($label:expr) { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $menu.append_section(::std::option::Option::Some($label), &$crate::menu!($($inner)*));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+    ($menu:ident; submenu($label:expr) { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $menu.append_submenu(::std::option::Option::Some($label), &$crate::menu!($($inner)*));
+        $crate::__menu_entries!($menu; $($($rest)*)?);
+    };
+}
+
+struct Item {
+    attributes: BTreeMap<String, Variant>,
+    links: BTreeMap<String, MenuModel>,
+}
+
+impl Item {
+    // Same attributes and links of the same names, the linked models may differ.
+    fn matches(&self, other: &Item) -> bool {
+        self.attributes == other.attributes && self.links.keys().eq(other.links.keys())
+    }
+
+    fn to_menu_item(&self) -> MenuItem {
+        let item = MenuItem::new(None, None);
+        for (name, value) in &self.attributes {
+            item.set_attribute_value(name, Some(value));
+        }
+        for (name, link) in &self.links {
+            let menu = Menu::new();
+            menu.update_from(link);
+            item.set_link(name, Some(&menu));
+        }
+        item
+    }
+}
+
+fn items(model: &MenuModel) -> Vec<Item> {
+    (0..model.n_items())
+        .map(|index| {
+            let mut attributes = BTreeMap::new();
+            let iter = model.iterate_item_attributes(index);
+            while let Some((name, value)) = iter.next() {
+                attributes.insert(name.into(), value);
+            }
+
+            let mut links = BTreeMap::new();
+            let iter = model.iterate_item_links(index);
+            while let Some((name, link)) = iter.next() {
+                links.insert(name.into(), link);
+            }
+
+            Item { attributes, links }
+        })
+        .collect()
+}
+
+fn models_equal(a: &MenuModel, b: &MenuModel) -> bool {
+    if a == b {
+        return true;
+    }
+    let (a, b) = (items(a), items(b));
+    a.len() == b.len()
+        && a.iter().zip(&b).all(|(a, b)| {
+            a.matches(b)
+                && a.links
+                    .values()
+                    .zip(b.links.values())
+                    .all(|(a, b)| models_equal(a, b))
+        })
+}
+
+enum Edit {
+    Keep(usize, usize),
+    Remove,
+    Insert(usize),
+}
+
+// Edits turning `old` into `new`, keeping their longest common subsequence of matching items.
+fn diff(old: &[Item], new: &[Item]) -> Vec<Edit> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i].matches(&new[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i].matches(&new[j]) {
+            edits.push(Edit::Keep(i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    edits
+}
+
+impl Menu {
+    // rustdoc-stripper-ignore-next
+    /// Updates the menu to have the same items as `model`, with as few changes as possible.
+    ///
+    /// Items with the same attributes are kept. If their sections or submenus differ and are
+    /// mutable [`Menu`]s, these are updated recursively, otherwise the item is replaced. Only
+    /// the changed items are reported with `items-changed`, with each run of adjacent removed,
+    /// inserted or replaced items as a single change, so that menus shown to the user, or
+    /// exported over D-Bus, don't flicker when updated.
+    ///
+    /// Sections and submenus of new items are copied from `model`, they are never shared with
+    /// it.
+    ///
+    /// Sections and submenus that are kept are updated in place, so a [`Menu`] that is also
+    /// linked from another menu, or used on its own, changes there as well. Link separate
+    /// copies, or [`freeze`](Menu::freeze) the shared one to have it replaced instead, if that
+    /// is not intended.
+    pub fn update_from(&self, model: &impl IsA<MenuModel>) {
+        let model = model.as_ref();
+        let this = self.upcast_ref::<MenuModel>();
+        if this == model {
+            return;
+        }
+
+        let old = items(this);
+        let new = items(model);
+        // Adjacent removals and insertions are collected and applied as a single change.
+        let mut position = 0;
+        let mut removed = 0;
+        let mut added = Vec::new();
+        for edit in diff(&old, &new) {
+            match edit {
+                Edit::Keep(i, j) => {
+                    let mut replace = false;
+                    for (old_link, new_link) in old[i].links.values().zip(new[j].links.values()) {
+                        if models_equal(old_link, new_link) {
+                            continue;
+                        }
+                        match old_link.downcast_ref::<Menu>() {
+                            Some(menu) if menu.is_mutable() => menu.update_from(new_link),
+                            _ => replace = true,
+                        }
+                    }
+                    if replace {
+                        removed += 1;
+                        added.push(new[j].to_menu_item());
+                    } else {
+                        self.splice(position, removed, &added);
+                        position += added.len() as i32 + 1;
+                        removed = 0;
+                        added.clear();
+                    }
+                }
+                Edit::Remove => removed += 1,
+                Edit::Insert(j) => added.push(new[j].to_menu_item()),
+            }
+        }
+        self.splice(position, removed, &added);
+    }
+
+    // Replaces `removed` items at `position` by `added`, emitting a single `items-changed`.
+    fn splice(&self, position: i32, removed: i32, added: &[MenuItem]) {
+        if removed == 0 && added.is_empty() {
+            return;
+        }
+
+        // GLib only blocks all handlers matching a closure, function or data, so block the
+        // unblocked handlers of `items-changed` one by one.
+        let signal_id = glib::subclass::SignalId::lookup("items-changed", MenuModel::static_type())
+            .unwrap()
+            .into_glib();
+        let instance = self.as_ptr() as *mut glib::gobject_ffi::GObject;
+        let mut blocked = Vec::new();
+        unsafe {
+            loop {
+                let handler = glib::gobject_ffi::g_signal_handler_find(
+                    instance,
+                    glib::gobject_ffi::G_SIGNAL_MATCH_ID
+                        | glib::gobject_ffi::G_SIGNAL_MATCH_UNBLOCKED,
+                    signal_id,
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                );
+                if handler == 0 {
+                    break;
+                }
+                glib::gobject_ffi::g_signal_handler_block(instance, handler);
+                blocked.push(handler);
+            }
+        }
+        for _ in 0..removed {
+            self.remove(position);
+        }
+        for (offset, item) in added.iter().enumerate() {
+            self.insert_item(position + offset as i32, item);
+        }
+        for handler in blocked {
+            unsafe { glib::gobject_ffi::g_signal_handler_unblock(instance, handler) };
+        }
+        self.items_changed(position, removed, added.len() as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{self as gio, DBusMenuModel, TypedAction};
+
+    fn record_changes(model: &impl IsA<MenuModel>) -> Rc<RefCell<Vec<(i32, i32, i32)>>> {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        model.connect_items_changed(
+            glib::clone!(@strong changes => move |_, position, removed, added| {
+                changes.borrow_mut().push((position, removed, added));
+            }),
+        );
+        changes
+    }
+
+    fn recent(files: &[&str]) -> Menu {
+        let menu = Menu::new();
+        for file in files {
+            menu.append_item(&MenuItem::with_target(Some(file), "app.open", file));
+        }
+        menu
+    }
+
+    #[test]
+    fn macro_items() {
+        let zoom = TypedAction::<i32>::new("zoom");
+        let quit = TypedAction::<()>::new("quit");
+        let menu = gio::menu! {
+            item("New", "app.new"),
+            item("Open", "app.open", "a.txt"),
+            section("Zoom") {
+                action("Zoom In", "win", zoom, 1),
+            },
+            submenu("More") {
+                action("Quit", "app", quit),
+            }
+        };
+        assert_eq!(menu.n_items(), 4);
+
+        let item = MenuItem::from_model(&menu, 1);
+        assert_eq!(
+            item.attribute_value("label", None),
+            Some("Open".to_variant())
+        );
+        assert_eq!(
+            item.attribute_value("target", None),
+            Some("a.txt".to_variant())
+        );
+
+        let section = menu.item_link(2, "section").unwrap();
+        let item = MenuItem::from_model(&section, 0);
+        assert_eq!(
+            item.attribute_value("action", None),
+            Some("win.zoom".to_variant())
+        );
+        assert_eq!(item.attribute_value("target", None), Some(1.to_variant()));
+
+        let submenu = menu.item_link(3, "submenu").unwrap();
+        let item = MenuItem::from_model(&submenu, 0);
+        assert_eq!(
+            item.attribute_value("action", None),
+            Some("app.quit".to_variant())
+        );
+        assert_eq!(item.attribute_value("target", None), None);
+    }
+
+    #[test]
+    fn update_from() {
+        let menu = recent(&["a", "b", "c", "d"]);
+        let changes = record_changes(&menu);
+
+        menu.update_from(&recent(&["a", "c", "d", "e"]));
+        assert!(models_equal(
+            menu.upcast_ref(),
+            recent(&["a", "c", "d", "e"]).upcast_ref()
+        ));
+        assert_eq!(*changes.borrow(), [(1, 1, 0), (3, 0, 1)]);
+
+        changes.borrow_mut().clear();
+        menu.update_from(&recent(&["a", "c", "d", "e"]));
+        assert!(changes.borrow().is_empty());
+
+        menu.update_from(&recent(&["a", "x", "y", "e"]));
+        assert!(models_equal(
+            menu.upcast_ref(),
+            recent(&["a", "x", "y", "e"]).upcast_ref()
+        ));
+        assert_eq!(*changes.borrow(), [(1, 2, 2)]);
+
+        changes.borrow_mut().clear();
+        menu.update_from(&Menu::new());
+        assert_eq!(menu.n_items(), 0);
+        assert_eq!(*changes.borrow(), [(0, 4, 0)]);
+    }
+
+    #[test]
+    fn update_exported() {
+        async fn wait_for(changes: &RefCell<Vec<(i32, i32, i32)>>, n: usize) {
+            for _ in 0..500 {
+                if changes.borrow().len() >= n {
+                    return;
+                }
+                glib::timeout_future(std::time::Duration::from_millis(10)).await;
+            }
+            panic!("expected {} changes, got {:?}", n, changes.borrow());
+        }
+
+        let (server, client) = crate::test_util::dbus_peer_pair();
+        let context = glib::MainContext::new();
+        context.block_on(async {
+            let menu = recent(&["a", "b", "c"]);
+            let id = server.export_menu_model("/org/gtk_rs/menu", &menu).unwrap();
+
+            let remote = DBusMenuModel::get(&client, None, "/org/gtk_rs/menu");
+            let changes = record_changes(&remote);
+            remote.n_items();
+            wait_for(&changes, 1).await;
+            assert_eq!(*changes.borrow(), [(0, 0, 3)]);
+
+            menu.update_from(&recent(&["a", "x", "y"]));
+            wait_for(&changes, 2).await;
+            assert_eq!(*changes.borrow(), [(0, 0, 3), (1, 2, 2)]);
+            assert!(models_equal(remote.upcast_ref(), menu.upcast_ref()));
+
+            server.unexport_menu_model(id);
+        });
+    }
+
+    #[test]
+    fn update_nested() {
+        let menu = Menu::new();
+        menu.append_section(None, &recent(&["a", "b"]));
+        menu.append(Some("Quit"), Some("app.quit"));
+        let section = menu.item_link(0, "section").unwrap();
+        let changes = record_changes(&menu);
+        let section_changes = record_changes(&section);
+
+        let new = Menu::new();
+        new.append_section(None, &recent(&["b", "c"]));
+        new.append(Some("Quit"), Some("app.quit"));
+        menu.update_from(&new);
+
+        assert!(models_equal(menu.upcast_ref(), new.upcast_ref()));
+        assert!(changes.borrow().is_empty());
+        assert_eq!(*section_changes.borrow(), [(0, 1, 0), (1, 0, 1)]);
+        assert_eq!(menu.item_link(0, "section").unwrap(), section);
+        assert_ne!(section, new.item_link(0, "section").unwrap());
+
+        // Frozen sections can't be updated and are replaced instead.
+        section.downcast_ref::<Menu>().unwrap().freeze();
+        new.item_link(0, "section")
+            .unwrap()
+            .downcast::<Menu>()
+            .unwrap()
+            .remove(0);
+        menu.update_from(&new);
+        assert!(models_equal(menu.upcast_ref(), new.upcast_ref()));
+        assert_eq!(*changes.borrow(), [(0, 1, 1)]);
+    }
+
+    #[test]
+    fn update_shared() {
+        let section = recent(&["a", "b"]);
+        let menu = Menu::new();
+        menu.append_section(None, &section);
+        let other = Menu::new();
+        other.append_section(None, &section);
+
+        let new = Menu::new();
+        new.append_section(None, &recent(&["b"]));
+        menu.update_from(&new);
+        assert!(models_equal(other.upcast_ref(), new.upcast_ref()));
+
+        section.freeze();
+        let new = Menu::new();
+        new.append_section(None, &recent(&["c"]));
+        menu.update_from(&new);
+        assert!(models_equal(menu.upcast_ref(), new.upcast_ref()));
+        assert!(models_equal(
+            section.upcast_ref(),
+            recent(&["b"]).upcast_ref()
+        ));
+    }
+}
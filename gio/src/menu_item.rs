@@ -0,0 +1,34 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::{FromVariant, ToVariant};
+
+use crate::{prelude::*, typed_action::to_optional_variant, MenuItem, TypedAction};
+
+impl MenuItem {
+    // rustdoc-stripper-ignore-next
+    /// Creates an item activating `action` with `target` as parameter.
+    #[doc(alias = "g_menu_item_set_action_and_target_value")]
+    pub fn with_target(label: Option<&str>, action: &str, target: &impl ToVariant) -> MenuItem {
+        let item = MenuItem::new(label, None);
+        item.set_action_and_target_value(Some(action), Some(&target.to_variant()));
+        item
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Creates an item activating `action` in the action group `namespace`, usually `app` or
+    /// `win`, with `target` as parameter. Use `&()` for actions without parameter.
+    #[doc(alias = "g_menu_item_set_action_and_target_value")]
+    pub fn with_typed_action<P: FromVariant + ToVariant, S>(
+        label: Option<&str>,
+        namespace: &str,
+        action: &TypedAction<P, S>,
+        target: &P,
+    ) -> MenuItem {
+        let item = MenuItem::new(label, None);
+        item.set_action_and_target_value(
+            Some(&format!("{}.{}", namespace, action.as_ref().name())),
+            to_optional_variant(target).as_ref(),
+        );
+        item
+    }
+}
@@ -0,0 +1,218 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use glib::{FromVariant, IsA, ToVariant};
+
+use crate::{
+    prelude::*, typed_action::to_optional_variant, Icon, Notification, NotificationPriority,
+    TypedAction,
+};
+
+impl Notification {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new builder-pattern struct instance to construct a [`Notification`] with
+    /// `title`.
+    pub fn builder(title: &str) -> NotificationBuilder {
+        NotificationBuilder::new(title)
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A [builder-pattern] type to construct [`Notification`] objects.
+///
+/// Actions are given either as detailed action names, with a target converted from any
+/// `ToVariant` type, or as a [`TypedAction`] with a target of its parameter type. Actions must
+/// be in the `app` namespace to be activated from the notification.
+///
+/// ```
+/// # use gio::prelude::*;
+/// let reply = gio::TypedAction::<String>::new("reply");
+/// let notification = gio::Notification::builder("New message")
+///     .body("Are you coming?")
+///     .icon(&gio::ThemedIcon::new("mail-unread"))
+///     .priority(gio::NotificationPriority::High)
+///     .default_action_with_target("app.show-message", &42u32)
+///     .typed_button("Yes", "app", &reply, &"Yes".to_owned())
+///     .button("Mark as Read", "app.mark-read")
+///     .build();
+/// # drop(notification);
+/// ```
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+#[derive(Debug)]
+#[must_use = "The builder must be built to be used"]
+pub struct NotificationBuilder(Notification);
+
+impl NotificationBuilder {
+    pub fn new(title: &str) -> Self {
+        Self(Notification::new(title))
+    }
+
+    pub fn body(self, body: &str) -> Self {
+        self.0.set_body(Some(body));
+        self
+    }
+
+    pub fn icon(self, icon: &impl IsA<Icon>) -> Self {
+        self.0.set_icon(icon);
+        self
+    }
+
+    pub fn priority(self, priority: NotificationPriority) -> Self {
+        self.0.set_priority(priority);
+        self
+    }
+
+    pub fn default_action(self, detailed_action: &str) -> Self {
+        self.0.set_default_action(detailed_action);
+        self
+    }
+
+    pub fn default_action_with_target(self, action: &str, target: &impl ToVariant) -> Self {
+        self.0
+            .set_default_action_and_target_value(action, Some(&target.to_variant()));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets `action` of the action group `namespace` as default action, activated with
+    /// `target`. Use `&()` for actions without parameter.
+    pub fn typed_default_action<P: FromVariant + ToVariant, S>(
+        self,
+        namespace: &str,
+        action: &TypedAction<P, S>,
+        target: &P,
+    ) -> Self {
+        self.0.set_default_action_and_target_value(
+            &format!("{}.{}", namespace, action.as_ref().name()),
+            to_optional_variant(target).as_ref(),
+        );
+        self
+    }
+
+    pub fn button(self, label: &str, detailed_action: &str) -> Self {
+        self.0.add_button(label, detailed_action);
+        self
+    }
+
+    pub fn button_with_target(self, label: &str, action: &str, target: &impl ToVariant) -> Self {
+        self.0
+            .add_button_with_target_value(label, action, Some(&target.to_variant()));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds a button activating `action` of the action group `namespace` with `target`. Use
+    /// `&()` for actions without parameter.
+    pub fn typed_button<P: FromVariant + ToVariant, S>(
+        self,
+        label: &str,
+        namespace: &str,
+        action: &TypedAction<P, S>,
+        target: &P,
+    ) -> Self {
+        self.0.add_button_with_target_value(
+            label,
+            &format!("{}.{}", namespace, action.as_ref().name()),
+            to_optional_variant(target).as_ref(),
+        );
+        self
+    }
+
+    pub fn build(self) -> Notification {
+        self.0
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::collections::HashMap;
+
+    use glib::Variant;
+    use serial_test::serial;
+
+    use super::*;
+    use crate::{
+        test_util::{NotificationEvent, NotificationServer},
+        Application, ApplicationFlags, ThemedIcon,
+    };
+
+    #[test]
+    #[serial(application)]
+    fn send_and_withdraw() {
+        let server = NotificationServer::new();
+        let context = glib::MainContext::new();
+        context
+            .with_thread_default(|| {
+                let app = Application::new(
+                    Some("org.gtk_rs.Notifications"),
+                    ApplicationFlags::NON_UNIQUE,
+                );
+                app.register(crate::Cancellable::NONE).unwrap();
+
+                let reply = TypedAction::<String>::new("reply");
+                let icon = ThemedIcon::new("mail-unread");
+                let notification = Notification::builder("New message")
+                    .body("Are you coming?")
+                    .icon(&icon)
+                    .priority(NotificationPriority::High)
+                    .default_action_with_target("app.show-message", &42u32)
+                    .typed_button("Yes", "app", &reply, &"Yes".to_owned())
+                    .button("Mark as Read", "app.mark-read")
+                    .build();
+                app.send_notification(Some("message"), &notification);
+
+                let event = server.next_event();
+                let notification = match &event {
+                    NotificationEvent::Added {
+                        app_id,
+                        id,
+                        notification,
+                    } if app_id == "org.gtk_rs.Notifications" && id == "message" => notification,
+                    _ => panic!("unexpected event {:?}", event),
+                };
+                assert_eq!(notification["title"], "New message".to_variant());
+                assert_eq!(notification["body"], "Are you coming?".to_variant());
+                assert_eq!(notification["priority"], "high".to_variant());
+                assert_eq!(notification["icon"], icon.serialize().unwrap());
+                assert_eq!(
+                    notification["default-action"],
+                    "app.show-message".to_variant()
+                );
+                assert_eq!(notification["default-action-target"], 42u32.to_variant());
+                let buttons = notification["buttons"]
+                    .get::<Vec<HashMap<String, Variant>>>()
+                    .unwrap();
+                assert_eq!(buttons.len(), 2);
+                assert_eq!(buttons[0]["label"], "Yes".to_variant());
+                assert_eq!(buttons[0]["action"], "app.reply".to_variant());
+                assert_eq!(buttons[0]["target"], "Yes".to_variant());
+                assert_eq!(buttons[1]["action"], "app.mark-read".to_variant());
+                assert!(!buttons[1].contains_key("target"));
+
+                // Sending with the same id replaces the notification.
+                app.send_notification(Some("message"), &Notification::new("Updated"));
+                assert!(matches!(
+                    server.next_event(),
+                    NotificationEvent::Added { ref id, .. } if id == "message"
+                ));
+                let notifications = server.notifications();
+                assert_eq!(notifications.len(), 1);
+                assert_eq!(
+                    notifications[&("org.gtk_rs.Notifications".to_owned(), "message".to_owned())]
+                        ["title"],
+                    "Updated".to_variant()
+                );
+
+                app.withdraw_notification("message");
+                assert_eq!(
+                    server.next_event(),
+                    NotificationEvent::Removed {
+                        app_id: "org.gtk_rs.Notifications".to_owned(),
+                        id: "message".to_owned(),
+                    }
+                );
+                assert!(server.notifications().is_empty());
+            })
+            .unwrap();
+    }
+}
@@ -8,11 +8,14 @@ pub use glib::prelude::*;
 
 pub use crate::auto::traits::*;
 
+pub use crate::action_group::ActionGroupExtManual;
 pub use crate::action_map::ActionMapExtManual;
 #[cfg(any(feature = "v2_60", feature = "dox"))]
 pub use crate::app_info::AppInfoExtManual;
 pub use crate::application::*;
+pub use crate::application_command_line::ApplicationCommandLineExtManual;
 pub use crate::cancellable::*;
+pub use crate::cancellable_future::{MainContextCancellableExt, ThreadPoolCancellableExt};
 pub use crate::converter::*;
 pub use crate::data_input_stream::DataInputStreamExtManual;
 pub use crate::dbus_proxy::DBusProxyExtManual;
@@ -37,6 +40,8 @@ pub use crate::tls_connection::TlsConnectionExtManual;
 #[cfg(any(unix, feature = "dox"))]
 pub use crate::unix_fd_list::UnixFDListExtManual;
 #[cfg(any(unix, feature = "dox"))]
+pub use crate::unix_fd_message::UnixFDMessageExtManual;
+#[cfg(any(unix, feature = "dox"))]
 pub use crate::unix_input_stream::UnixInputStreamExtManual;
 #[cfg(any(unix, feature = "dox"))]
 pub use crate::unix_output_stream::UnixOutputStreamExtManual;
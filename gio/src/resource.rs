@@ -54,3 +54,40 @@ macro_rules! resources_register_include {
         )))
     };
 }
+
+#[cfg(all(test, feature = "include_resources"))]
+mod tests {
+    use crate::{self as gio, ResourceLookupFlags};
+
+    #[test]
+    fn include_resources() {
+        for _ in 0..2 {
+            gio::include_resources!("tests/resources/", prefix = "/com/github/gtk-rs/included");
+        }
+
+        let data = gio::resources_lookup_data(
+            "/com/github/gtk-rs/included/style.css",
+            ResourceLookupFlags::NONE,
+        )
+        .unwrap();
+        assert_eq!(&*data, b"label {\n  color: red;\n}\n");
+        let data = gio::resources_lookup_data(
+            "/com/github/gtk-rs/included/ui/window.ui",
+            ResourceLookupFlags::NONE,
+        )
+        .unwrap();
+        assert_eq!(
+            &*data,
+            &b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><interface><object class=\"GtkWindow\">\
+               <property name=\"title\">Test</property></object></interface>\n"[..]
+        );
+
+        let mut children = gio::resources_enumerate_children(
+            "/com/github/gtk-rs/included/",
+            ResourceLookupFlags::NONE,
+        )
+        .unwrap();
+        children.sort();
+        assert_eq!(children, ["style.css", "ui/"]);
+    }
+}
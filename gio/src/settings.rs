@@ -231,4 +231,74 @@ mod test {
         let settings = Settings::new("com.github.gtk-rs.test");
         settings.get::<u8>("test-string");
     }
+
+    #[cfg(feature = "settings")]
+    use crate as gio;
+
+    #[cfg(feature = "settings")]
+    #[crate::settings(file = "tests/com.github.gtk-rs.typed.gschema.xml")]
+    struct TypedSettings;
+
+    #[cfg(feature = "settings")]
+    #[test]
+    #[serial_test::serial]
+    fn typed_settings() {
+        set_env();
+        let settings = TypedSettings::new();
+
+        assert_eq!(settings.window_width(), 640);
+        settings.set_window_width(800).unwrap();
+        assert_eq!(settings.window_width(), 800);
+        assert!(settings.set_window_width(50).is_err());
+        assert_eq!(settings.window_width(), 800);
+
+        assert!(settings.set_zoom(8.0).is_err());
+        settings.set_zoom(2.5).unwrap();
+        assert_eq!(settings.zoom(), 2.5);
+
+        assert_eq!(settings.theme(), "light");
+        settings.set_theme("dark").unwrap();
+        assert!(settings.set_theme("blue").is_err());
+        assert_eq!(settings.theme(), "dark");
+
+        settings.set_recent_files(&["a", "b"]).unwrap();
+        assert_eq!(settings.recent_files(), ["a", "b"]);
+
+        settings.set_geometry(&(1, 2).to_variant()).unwrap();
+        assert_eq!(settings.geometry().get::<(i32, i32)>(), Some((1, 2)));
+
+        assert_eq!(settings.mode(), Mode::Balanced);
+        settings.set_mode(Mode::Quiet).unwrap();
+        assert_eq!(settings.mode(), Mode::Quiet);
+        assert_eq!(settings.get::<String>("mode"), Mode::Quiet.nick());
+
+        assert_eq!(settings.features(), Features::SOUND);
+        settings
+            .set_features(Features::AUTO_SAVE | Features::SPELL_CHECK)
+            .unwrap();
+        assert_eq!(
+            settings.get::<Vec<String>>("features"),
+            ["auto-save", "spell-check"]
+        );
+
+        let action = crate::SimpleAction::new("test", None);
+        settings.bind_dark_mode(&action, "enabled").build();
+        assert!(!action.is_enabled());
+        settings.set_dark_mode(true).unwrap();
+        assert!(action.is_enabled());
+
+        let changed = std::rc::Rc::new(std::cell::Cell::new(false));
+        settings.connect_window_width_changed({
+            let changed = changed.clone();
+            move |settings| {
+                assert_eq!(settings.window_width(), 1024);
+                changed.set(true);
+            }
+        });
+        let other = TypedSettings::from_settings(Settings::new(TypedSettings::SCHEMA_ID));
+        other.set_window_width(1024).unwrap();
+        let ctx = glib::MainContext::default();
+        while ctx.iteration(false) {}
+        assert!(changed.get());
+    }
 }
@@ -1,11 +1,15 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
 use crate::Cancellable;
+use crate::InputMessage;
+use crate::OutputMessage;
 use crate::Socket;
 use crate::SocketAddress;
-use glib::object::{Cast, IsA};
+use crate::SocketControlMessage;
+use glib::object::{Cast, IsA, ObjectType};
 use glib::translate::*;
 use std::cell::RefCell;
+use std::io::{IoSlice, IoSliceMut};
 use std::mem::transmute;
 #[cfg(all(not(unix), feature = "dox"))]
 use std::os::raw::c_int;
@@ -103,6 +107,54 @@ pub trait SocketExtManual: Sized {
         cancellable: Option<&C>,
     ) -> Result<usize, glib::Error>;
 
+    // rustdoc-stripper-ignore-next
+    /// Sends the data of all `vectors` as one message together with `control_messages`, to
+    /// `address` for unconnected sockets. `flags` are the platform's `MSG_*` flags.
+    #[doc(alias = "g_socket_send_message")]
+    fn send_message<P: IsA<SocketAddress>, C: IsA<Cancellable>>(
+        &self,
+        address: Option<&P>,
+        vectors: &[IoSlice<'_>],
+        control_messages: &[SocketControlMessage],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error>;
+
+    // rustdoc-stripper-ignore-next
+    /// Sends several messages at once, using `sendmmsg()` where available. Returns the number
+    /// of messages sent, their [`bytes_sent`](OutputMessage::bytes_sent) are updated.
+    #[doc(alias = "g_socket_send_messages")]
+    fn send_messages<C: IsA<Cancellable>>(
+        &self,
+        messages: &mut [OutputMessage<'_, '_>],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error>;
+
+    // rustdoc-stripper-ignore-next
+    /// Receives one message into the vectors of `message`, which is then updated with the
+    /// address, control messages and flags it was received with. Returns the number of bytes
+    /// received.
+    #[doc(alias = "g_socket_receive_message")]
+    fn receive_message<C: IsA<Cancellable>>(
+        &self,
+        message: &mut InputMessage<'_, '_>,
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error>;
+
+    // rustdoc-stripper-ignore-next
+    /// Receives several messages at once, using `recvmmsg()` where available. Returns the number
+    /// of messages received, which are updated like with
+    /// [`receive_message`](Self::receive_message).
+    #[doc(alias = "g_socket_receive_messages")]
+    fn receive_messages<C: IsA<Cancellable>>(
+        &self,
+        messages: &mut [InputMessage<'_, '_>],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error>;
+
     #[cfg(any(unix, feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(unix)))]
     #[doc(alias = "get_fd")]
@@ -319,6 +371,193 @@ impl<O: IsA<Socket>> SocketExtManual for O {
         }
     }
 
+    fn send_message<P: IsA<SocketAddress>, C: IsA<Cancellable>>(
+        &self,
+        address: Option<&P>,
+        vectors: &[IoSlice<'_>],
+        control_messages: &[SocketControlMessage],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error> {
+        let cancellable = cancellable.map(|c| c.as_ref());
+        let gcancellable = cancellable.to_glib_none();
+        let mut vectors = output_vectors(vectors);
+        let mut control_messages = control_message_ptrs(control_messages);
+        unsafe {
+            let mut error = ptr::null_mut();
+            let ret = ffi::g_socket_send_message(
+                self.as_ref().to_glib_none().0,
+                address.map(|p| p.as_ref()).to_glib_none().0,
+                vectors.as_mut_ptr(),
+                vectors.len() as i32,
+                control_messages.as_mut_ptr(),
+                control_messages.len() as i32,
+                flags,
+                gcancellable.0,
+                &mut error,
+            );
+            if error.is_null() {
+                Ok(ret as usize)
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
+    fn send_messages<C: IsA<Cancellable>>(
+        &self,
+        messages: &mut [OutputMessage<'_, '_>],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error> {
+        let cancellable = cancellable.map(|c| c.as_ref());
+        let gcancellable = cancellable.to_glib_none();
+        let mut vectors = messages
+            .iter()
+            .map(|m| output_vectors(m.vectors))
+            .collect::<Vec<_>>();
+        let mut control_messages = messages
+            .iter()
+            .map(|m| control_message_ptrs(m.control_messages))
+            .collect::<Vec<_>>();
+        let mut ffi_messages = messages
+            .iter()
+            .zip(vectors.iter_mut())
+            .zip(control_messages.iter_mut())
+            .map(|((m, vectors), control_messages)| ffi::GOutputMessage {
+                address: m.address.as_ref().map_or(ptr::null_mut(), |a| a.as_ptr()),
+                vectors: vectors.as_mut_ptr(),
+                num_vectors: vectors.len() as u32,
+                bytes_sent: 0,
+                control_messages: control_messages.as_mut_ptr(),
+                num_control_messages: control_messages.len() as u32,
+            })
+            .collect::<Vec<_>>();
+        unsafe {
+            let mut error = ptr::null_mut();
+            let ret = ffi::g_socket_send_messages(
+                self.as_ref().to_glib_none().0,
+                ffi_messages.as_mut_ptr(),
+                ffi_messages.len() as u32,
+                flags,
+                gcancellable.0,
+                &mut error,
+            );
+            if error.is_null() {
+                for (message, ffi_message) in messages.iter_mut().zip(&ffi_messages) {
+                    message.bytes_sent = ffi_message.bytes_sent as usize;
+                }
+                Ok(ret as usize)
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
+    fn receive_message<C: IsA<Cancellable>>(
+        &self,
+        message: &mut InputMessage<'_, '_>,
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error> {
+        let cancellable = cancellable.map(|c| c.as_ref());
+        let gcancellable = cancellable.to_glib_none();
+        let mut vectors = input_vectors(message.vectors);
+        unsafe {
+            let mut error = ptr::null_mut();
+            let mut address = ptr::null_mut();
+            let mut control_messages = ptr::null_mut();
+            let mut num_control_messages = 0;
+            let mut flags = flags;
+            let ret = ffi::g_socket_receive_message(
+                self.as_ref().to_glib_none().0,
+                &mut address,
+                vectors.as_mut_ptr(),
+                vectors.len() as i32,
+                &mut control_messages,
+                &mut num_control_messages,
+                &mut flags,
+                gcancellable.0,
+                &mut error,
+            );
+            if error.is_null() {
+                message.address = from_glib_full(address);
+                message.control_messages = FromGlibContainer::from_glib_full_num(
+                    control_messages,
+                    num_control_messages as usize,
+                );
+                message.bytes_received = ret as usize;
+                message.flags = flags;
+                Ok(ret as usize)
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
+    fn receive_messages<C: IsA<Cancellable>>(
+        &self,
+        messages: &mut [InputMessage<'_, '_>],
+        flags: i32,
+        cancellable: Option<&C>,
+    ) -> Result<usize, glib::Error> {
+        let cancellable = cancellable.map(|c| c.as_ref());
+        let gcancellable = cancellable.to_glib_none();
+        let mut vectors = messages
+            .iter_mut()
+            .map(|m| input_vectors(m.vectors))
+            .collect::<Vec<_>>();
+        let mut addresses = vec![ptr::null_mut(); messages.len()];
+        let mut control_messages = vec![ptr::null_mut(); messages.len()];
+        let mut num_control_messages = vec![0; messages.len()];
+        let mut ffi_messages = vectors
+            .iter_mut()
+            .zip(addresses.iter_mut())
+            .zip(control_messages.iter_mut())
+            .zip(num_control_messages.iter_mut())
+            .map(
+                |(((vectors, address), control_messages), num_control_messages)| {
+                    ffi::GInputMessage {
+                        address,
+                        vectors: vectors.as_mut_ptr(),
+                        num_vectors: vectors.len() as u32,
+                        bytes_received: 0,
+                        flags: 0,
+                        control_messages,
+                        num_control_messages,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+        unsafe {
+            let mut error = ptr::null_mut();
+            let ret = ffi::g_socket_receive_messages(
+                self.as_ref().to_glib_none().0,
+                ffi_messages.as_mut_ptr(),
+                ffi_messages.len() as u32,
+                flags,
+                gcancellable.0,
+                &mut error,
+            );
+            if error.is_null() {
+                let received = ret as usize;
+                for (message, ffi_message) in messages.iter_mut().zip(&ffi_messages).take(received)
+                {
+                    message.address = from_glib_full(*ffi_message.address);
+                    message.control_messages = FromGlibContainer::from_glib_full_num(
+                        *ffi_message.control_messages,
+                        *ffi_message.num_control_messages as usize,
+                    );
+                    message.bytes_received = ffi_message.bytes_received;
+                    message.flags = ffi_message.flags;
+                }
+                Ok(received)
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
     #[cfg(any(unix, feature = "dox"))]
     #[cfg_attr(feature = "dox", doc(cfg(unix)))]
     fn fd<T: FromRawFd>(&self) -> T {
@@ -444,6 +683,30 @@ impl<O: IsA<Socket>> SocketExtManual for O {
     }
 }
 
+fn output_vectors(vectors: &[IoSlice<'_>]) -> Vec<ffi::GOutputVector> {
+    vectors
+        .iter()
+        .map(|v| ffi::GOutputVector {
+            buffer: v.as_ptr() as glib::ffi::gconstpointer,
+            size: v.len(),
+        })
+        .collect()
+}
+
+fn input_vectors(vectors: &mut [IoSliceMut<'_>]) -> Vec<ffi::GInputVector> {
+    vectors
+        .iter_mut()
+        .map(|v| ffi::GInputVector {
+            buffer: v.as_mut_ptr() as glib::ffi::gpointer,
+            size: v.len(),
+        })
+        .collect()
+}
+
+fn control_message_ptrs(messages: &[SocketControlMessage]) -> Vec<*mut ffi::GSocketControlMessage> {
+    messages.iter().map(|m| m.as_ptr()).collect()
+}
+
 #[cfg(all(not(unix), feature = "dox"))]
 pub trait IntoRawFd {
     fn into_raw_fd(self) -> c_int;
@@ -479,3 +742,172 @@ pub trait AsRawSocket {
 
 #[cfg(all(not(windows), feature = "dox"))]
 pub type RawSocket = *mut c_void;
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        os::unix::net::{UnixDatagram, UnixStream},
+    };
+
+    use super::*;
+    use crate::{prelude::*, UnixFDMessage};
+
+    fn socket(fd: impl IntoRawFd) -> Socket {
+        unsafe { Socket::from_fd(fd).unwrap() }
+    }
+
+    #[test]
+    fn vectored() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (a, b) = (socket(a), socket(b));
+
+        let vectors = [IoSlice::new(b"hello "), IoSlice::new(b"world")];
+        let sent = a
+            .send_message(SocketAddress::NONE, &vectors, &[], 0, Cancellable::NONE)
+            .unwrap();
+        assert_eq!(sent, 11);
+
+        let (mut first, mut second) = ([0u8; 4], [0u8; 16]);
+        let mut vectors = [IoSliceMut::new(&mut first), IoSliceMut::new(&mut second)];
+        let mut message = InputMessage::new(&mut vectors);
+        let received = b
+            .receive_message(&mut message, 0, Cancellable::NONE)
+            .unwrap();
+        assert_eq!(received, 11);
+        assert_eq!(message.bytes_received(), 11);
+        assert!(message.control_messages().is_empty());
+        assert_eq!(&*vectors[0], b"hell");
+        assert_eq!(&vectors[1][..7], b"o world");
+
+        // The vectors can be reused for further messages.
+        a.send_message(
+            SocketAddress::NONE,
+            &[IoSlice::new(b"bye")],
+            &[],
+            0,
+            Cancellable::NONE,
+        )
+        .unwrap();
+        let mut message = InputMessage::new(&mut vectors);
+        let received = b
+            .receive_message(&mut message, 0, Cancellable::NONE)
+            .unwrap();
+        assert_eq!(received, 3);
+        assert_eq!(&vectors[0][..3], b"bye");
+    }
+
+    #[test]
+    fn fd_passing() {
+        let (a, b) = UnixStream::pair().unwrap();
+        let (a, b) = (socket(a), socket(b));
+        let (mut local, remote) = UnixStream::pair().unwrap();
+
+        let fds = UnixFDMessage::new();
+        fds.append_fd(remote.as_raw_fd()).unwrap();
+        drop(remote);
+        a.send_message(
+            SocketAddress::NONE,
+            &[IoSlice::new(b"fd")],
+            &[fds.upcast()],
+            0,
+            Cancellable::NONE,
+        )
+        .unwrap();
+
+        let mut buffer = [0u8; 2];
+        let mut vectors = [IoSliceMut::new(&mut buffer)];
+        let mut message = InputMessage::new(&mut vectors);
+        b.receive_message(&mut message, 0, Cancellable::NONE)
+            .unwrap();
+        let fds = match message.control_messages() {
+            [fds] => fds.downcast_ref::<UnixFDMessage>().unwrap().steal_fds(),
+            messages => panic!("unexpected control messages {:?}", messages),
+        };
+        assert_eq!(fds.len(), 1);
+        assert_eq!(&buffer, b"fd");
+
+        let mut received = unsafe { UnixStream::from_raw_fd(fds[0]) };
+        received.write_all(b"ping").unwrap();
+        let mut ping = [0u8; 4];
+        local.read_exact(&mut ping).unwrap();
+        assert_eq!(&ping, b"ping");
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn credentials() {
+        use crate::UnixCredentialsMessage;
+
+        assert!(UnixCredentialsMessage::is_supported());
+        let (a, b) = UnixDatagram::pair().unwrap();
+        let (a, b) = (socket(a), socket(b));
+        b.set_option(libc::SOL_SOCKET, libc::SO_PASSCRED, 1)
+            .unwrap();
+
+        a.send_message(
+            SocketAddress::NONE,
+            &[IoSlice::new(b"x")],
+            &[UnixCredentialsMessage::new().upcast()],
+            0,
+            Cancellable::NONE,
+        )
+        .unwrap();
+
+        let mut buffer = [0u8; 1];
+        let mut vectors = [IoSliceMut::new(&mut buffer)];
+        let mut message = InputMessage::new(&mut vectors);
+        b.receive_message(&mut message, 0, Cancellable::NONE)
+            .unwrap();
+        let credentials = message
+            .control_messages()
+            .iter()
+            .find_map(|m| m.downcast_ref::<UnixCredentialsMessage>())
+            .unwrap()
+            .credentials();
+        assert_eq!(credentials.unix_pid().unwrap(), std::process::id() as i32);
+        assert_eq!(credentials.unix_user().unwrap(), unsafe { libc::getuid() });
+    }
+
+    #[test]
+    fn batched_datagrams() {
+        let (a, b) = UnixDatagram::pair().unwrap();
+        let (a, b) = (socket(a), socket(b));
+
+        let vectors = [b"one".as_ref(), b"two", b"three"].map(|p| [IoSlice::new(p)]);
+        let mut messages = vectors
+            .iter()
+            .map(|v| OutputMessage::new(SocketAddress::NONE, v, &[]))
+            .collect::<Vec<_>>();
+        let sent = a
+            .send_messages(&mut messages, 0, Cancellable::NONE)
+            .unwrap();
+        assert_eq!(sent, 3);
+        assert_eq!(
+            messages.iter().map(|m| m.bytes_sent()).collect::<Vec<_>>(),
+            [3, 3, 5]
+        );
+
+        let mut buffers = [[0u8; 4]; 3];
+        let mut vectors = buffers
+            .iter_mut()
+            .map(|b| [IoSliceMut::new(b)])
+            .collect::<Vec<_>>();
+        let mut messages = vectors
+            .iter_mut()
+            .map(|v| InputMessage::new(v))
+            .collect::<Vec<_>>();
+        let received = b
+            .receive_messages(&mut messages, 0, Cancellable::NONE)
+            .unwrap();
+        assert_eq!(received, 3);
+        assert_eq!(
+            messages
+                .iter()
+                .map(|m| (m.bytes_received(), m.flags() & libc::MSG_TRUNC != 0))
+                .collect::<Vec<_>>(),
+            [(3, false), (3, false), (4, true)]
+        );
+        assert_eq!(&buffers, &[*b"one\0", *b"two\0", *b"thre"]);
+    }
+}
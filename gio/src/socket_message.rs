@@ -0,0 +1,94 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::io::{IoSlice, IoSliceMut};
+
+use glib::IsA;
+
+use crate::{SocketAddress, SocketControlMessage};
+
+// rustdoc-stripper-ignore-next
+/// A message to send with [`SocketExtManual::send_messages`](crate::prelude::SocketExtManual::send_messages).
+#[doc(alias = "GOutputMessage")]
+#[derive(Debug)]
+pub struct OutputMessage<'a, 'b> {
+    pub(crate) address: Option<SocketAddress>,
+    pub(crate) vectors: &'a [IoSlice<'b>],
+    pub(crate) control_messages: &'a [SocketControlMessage],
+    pub(crate) bytes_sent: usize,
+}
+
+impl<'a, 'b> OutputMessage<'a, 'b> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a message consisting of the data of all `vectors` and the `control_messages`,
+    /// sent to `address` for unconnected sockets.
+    pub fn new(
+        address: Option<&impl IsA<SocketAddress>>,
+        vectors: &'a [IoSlice<'b>],
+        control_messages: &'a [SocketControlMessage],
+    ) -> Self {
+        Self {
+            address: address.map(|a| a.as_ref().clone()),
+            vectors,
+            control_messages,
+            bytes_sent: 0,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of bytes of the message that were sent.
+    pub fn bytes_sent(&self) -> usize {
+        self.bytes_sent
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A message to receive with
+/// [`SocketExtManual::receive_message`](crate::prelude::SocketExtManual::receive_message) or
+/// [`SocketExtManual::receive_messages`](crate::prelude::SocketExtManual::receive_messages).
+#[doc(alias = "GInputMessage")]
+#[derive(Debug)]
+pub struct InputMessage<'a, 'b> {
+    pub(crate) vectors: &'a mut [IoSliceMut<'b>],
+    pub(crate) address: Option<SocketAddress>,
+    pub(crate) control_messages: Vec<SocketControlMessage>,
+    pub(crate) bytes_received: usize,
+    pub(crate) flags: i32,
+}
+
+impl<'a, 'b> InputMessage<'a, 'b> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a message whose data is received into `vectors`, filling them in order.
+    pub fn new(vectors: &'a mut [IoSliceMut<'b>]) -> Self {
+        Self {
+            vectors,
+            address: None,
+            control_messages: Vec::new(),
+            bytes_received: 0,
+            flags: 0,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The address the message was received from, if the socket provides it.
+    pub fn address(&self) -> Option<&SocketAddress> {
+        self.address.as_ref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The control messages received with the message, for example
+    /// [`UnixFDMessage`](crate::UnixFDMessage)s.
+    pub fn control_messages(&self) -> &[SocketControlMessage] {
+        &self.control_messages
+    }
+
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The flags of the received message, for example `MSG_TRUNC` if it didn't fit into the
+    /// vectors.
+    pub fn flags(&self) -> i32 {
+        self.flags
+    }
+}
@@ -505,6 +505,7 @@ mod tests {
     }
 
     #[test]
+    #[serial_test::serial(application)]
     fn test_simple_application() {
         let app = glib::Object::new::<SimpleApplication>(&[
             ("application-id", &"org.gtk-rs.SimpleApplication"),
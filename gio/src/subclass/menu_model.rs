@@ -0,0 +1,433 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::collections::HashMap;
+
+use glib::{subclass::prelude::*, translate::*, Cast, Variant, VariantTy};
+
+use crate::{MenuAttributeIter, MenuLinkIter, MenuModel};
+
+pub trait MenuModelImpl: ObjectImpl {
+    // rustdoc-stripper-ignore-next
+    /// Whether the model may change, in which case `items-changed` must be emitted for every
+    /// change with [`MenuModelExt::items_changed`](crate::prelude::MenuModelExt::items_changed).
+    fn is_mutable(&self) -> bool;
+
+    #[doc(alias = "get_n_items")]
+    fn n_items(&self) -> i32;
+
+    #[doc(alias = "get_item_attributes")]
+    fn item_attributes(&self, item_index: i32) -> HashMap<String, Variant>;
+
+    #[doc(alias = "get_item_links")]
+    fn item_links(&self, item_index: i32) -> HashMap<String, MenuModel>;
+
+    fn iterate_item_attributes(&self, item_index: i32) -> MenuAttributeIter {
+        self.parent_iterate_item_attributes(item_index)
+    }
+
+    #[doc(alias = "get_item_attribute_value")]
+    fn item_attribute_value(
+        &self,
+        item_index: i32,
+        attribute: &str,
+        expected_type: Option<&VariantTy>,
+    ) -> Option<Variant> {
+        self.parent_item_attribute_value(item_index, attribute, expected_type)
+    }
+
+    fn iterate_item_links(&self, item_index: i32) -> MenuLinkIter {
+        self.parent_iterate_item_links(item_index)
+    }
+
+    #[doc(alias = "get_item_link")]
+    fn item_link(&self, item_index: i32, link: &str) -> Option<MenuModel> {
+        self.parent_item_link(item_index, link)
+    }
+}
+
+pub trait MenuModelImplExt: ObjectSubclass {
+    fn parent_iterate_item_attributes(&self, item_index: i32) -> MenuAttributeIter;
+    fn parent_item_attribute_value(
+        &self,
+        item_index: i32,
+        attribute: &str,
+        expected_type: Option<&VariantTy>,
+    ) -> Option<Variant>;
+    fn parent_iterate_item_links(&self, item_index: i32) -> MenuLinkIter;
+    fn parent_item_link(&self, item_index: i32, link: &str) -> Option<MenuModel>;
+}
+
+impl<T: MenuModelImpl> MenuModelImplExt for T {
+    fn parent_iterate_item_attributes(&self, item_index: i32) -> MenuAttributeIter {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GMenuModelClass;
+            let f = (*parent_class)
+                .iterate_item_attributes
+                .expect("No parent class implementation for \"iterate_item_attributes\"");
+            from_glib_full(f(
+                self.instance()
+                    .unsafe_cast_ref::<MenuModel>()
+                    .to_glib_none()
+                    .0,
+                item_index,
+            ))
+        }
+    }
+
+    fn parent_item_attribute_value(
+        &self,
+        item_index: i32,
+        attribute: &str,
+        expected_type: Option<&VariantTy>,
+    ) -> Option<Variant> {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GMenuModelClass;
+            let f = (*parent_class)
+                .get_item_attribute_value
+                .expect("No parent class implementation for \"item_attribute_value\"");
+            from_glib_full(f(
+                self.instance()
+                    .unsafe_cast_ref::<MenuModel>()
+                    .to_glib_none()
+                    .0,
+                item_index,
+                attribute.to_glib_none().0,
+                expected_type.to_glib_none().0,
+            ))
+        }
+    }
+
+    fn parent_iterate_item_links(&self, item_index: i32) -> MenuLinkIter {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GMenuModelClass;
+            let f = (*parent_class)
+                .iterate_item_links
+                .expect("No parent class implementation for \"iterate_item_links\"");
+            from_glib_full(f(
+                self.instance()
+                    .unsafe_cast_ref::<MenuModel>()
+                    .to_glib_none()
+                    .0,
+                item_index,
+            ))
+        }
+    }
+
+    fn parent_item_link(&self, item_index: i32, link: &str) -> Option<MenuModel> {
+        unsafe {
+            let data = T::type_data();
+            let parent_class = data.as_ref().parent_class() as *mut ffi::GMenuModelClass;
+            let f = (*parent_class)
+                .get_item_link
+                .expect("No parent class implementation for \"item_link\"");
+            from_glib_full(f(
+                self.instance()
+                    .unsafe_cast_ref::<MenuModel>()
+                    .to_glib_none()
+                    .0,
+                item_index,
+                link.to_glib_none().0,
+            ))
+        }
+    }
+}
+
+unsafe impl<T: MenuModelImpl> IsSubclassable<T> for MenuModel {
+    fn class_init(class: &mut ::glib::Class<Self>) {
+        Self::parent_class_init::<T>(class);
+
+        let klass = class.as_mut();
+        klass.is_mutable = Some(menu_model_is_mutable::<T>);
+        klass.get_n_items = Some(menu_model_get_n_items::<T>);
+        klass.get_item_attributes = Some(menu_model_get_item_attributes::<T>);
+        klass.iterate_item_attributes = Some(menu_model_iterate_item_attributes::<T>);
+        klass.get_item_attribute_value = Some(menu_model_get_item_attribute_value::<T>);
+        klass.get_item_links = Some(menu_model_get_item_links::<T>);
+        klass.iterate_item_links = Some(menu_model_iterate_item_links::<T>);
+        klass.get_item_link = Some(menu_model_get_item_link::<T>);
+    }
+}
+
+unsafe extern "C" fn menu_model_is_mutable<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+) -> glib::ffi::gboolean {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    imp.is_mutable().into_glib()
+}
+
+unsafe extern "C" fn menu_model_get_n_items<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+) -> libc::c_int {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    imp.n_items()
+}
+
+unsafe extern "C" fn variant_unref(ptr: glib::ffi::gpointer) {
+    glib::ffi::g_variant_unref(ptr as *mut _);
+}
+
+unsafe extern "C" fn object_unref(ptr: glib::ffi::gpointer) {
+    glib::gobject_ffi::g_object_unref(ptr as *mut _);
+}
+
+// Creates a hash table owning its string keys and values, as expected by the caller.
+unsafe fn to_hash_table<V>(
+    items: HashMap<String, V>,
+    value_destroy: unsafe extern "C" fn(glib::ffi::gpointer),
+    into_ptr: impl Fn(V) -> glib::ffi::gpointer,
+) -> *mut glib::ffi::GHashTable {
+    let table = glib::ffi::g_hash_table_new_full(
+        Some(glib::ffi::g_str_hash),
+        Some(glib::ffi::g_str_equal),
+        Some(glib::ffi::g_free),
+        Some(value_destroy),
+    );
+    for (key, value) in items {
+        glib::ffi::g_hash_table_insert(
+            table,
+            ToGlibPtr::<*mut libc::c_char>::to_glib_full(&key) as glib::ffi::gpointer,
+            into_ptr(value),
+        );
+    }
+    table
+}
+
+unsafe extern "C" fn menu_model_get_item_attributes<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+    attributes: *mut *mut glib::ffi::GHashTable,
+) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    *attributes = to_hash_table(imp.item_attributes(item_index), variant_unref, |v| {
+        ToGlibPtr::<*mut glib::ffi::GVariant>::to_glib_full(&v) as glib::ffi::gpointer
+    });
+}
+
+unsafe extern "C" fn menu_model_iterate_item_attributes<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+) -> *mut ffi::GMenuAttributeIter {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    imp.iterate_item_attributes(item_index).to_glib_full()
+}
+
+unsafe extern "C" fn menu_model_get_item_attribute_value<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+    attribute: *const libc::c_char,
+    expected_type: *const glib::ffi::GVariantType,
+) -> *mut glib::ffi::GVariant {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    let expected_type: Option<&VariantTy> = if expected_type.is_null() {
+        None
+    } else {
+        Some(VariantTy::from_ptr(expected_type))
+    };
+    imp.item_attribute_value(
+        item_index,
+        &glib::GString::from_glib_borrow(attribute),
+        expected_type,
+    )
+    .to_glib_full()
+}
+
+unsafe extern "C" fn menu_model_get_item_links<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+    links: *mut *mut glib::ffi::GHashTable,
+) {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    *links = to_hash_table(imp.item_links(item_index), object_unref, |model| {
+        ToGlibPtr::<*mut ffi::GMenuModel>::to_glib_full(&model) as glib::ffi::gpointer
+    });
+}
+
+unsafe extern "C" fn menu_model_iterate_item_links<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+) -> *mut ffi::GMenuLinkIter {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    imp.iterate_item_links(item_index).to_glib_full()
+}
+
+unsafe extern "C" fn menu_model_get_item_link<T: MenuModelImpl>(
+    ptr: *mut ffi::GMenuModel,
+    item_index: libc::c_int,
+    link: *const libc::c_char,
+) -> *mut ffi::GMenuModel {
+    let instance = &*(ptr as *mut T::Instance);
+    let imp = instance.imp();
+
+    imp.item_link(item_index, &glib::GString::from_glib_borrow(link))
+        .to_glib_full()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{prelude::*, DBusMenuModel, Menu, MenuItem};
+
+    mod imp {
+        use super::*;
+
+        #[derive(Default)]
+        pub struct WindowList {
+            pub titles: RefCell<Vec<String>>,
+        }
+
+        #[glib::object_subclass]
+        impl ObjectSubclass for WindowList {
+            const NAME: &'static str = "WindowList";
+            type Type = super::WindowList;
+            type ParentType = MenuModel;
+        }
+
+        impl ObjectImpl for WindowList {}
+
+        impl MenuModelImpl for WindowList {
+            fn is_mutable(&self) -> bool {
+                true
+            }
+
+            fn n_items(&self) -> i32 {
+                self.titles.borrow().len() as i32
+            }
+
+            fn item_attributes(&self, item_index: i32) -> HashMap<String, Variant> {
+                let title = &self.titles.borrow()[item_index as usize];
+                HashMap::from([
+                    ("label".to_owned(), title.to_variant()),
+                    ("action".to_owned(), "app.raise".to_variant()),
+                    ("target".to_owned(), item_index.to_variant()),
+                ])
+            }
+
+            fn item_links(&self, item_index: i32) -> HashMap<String, MenuModel> {
+                let section = Menu::new();
+                section.append(Some("Close"), Some(&format!("app.close({})", item_index)));
+                HashMap::from([("section".to_owned(), section.upcast())])
+            }
+        }
+    }
+
+    glib::wrapper! {
+        pub struct WindowList(ObjectSubclass<imp::WindowList>) @extends MenuModel;
+    }
+
+    impl WindowList {
+        fn set_titles(&self, titles: &[&str]) {
+            let removed = self.n_items();
+            *self.imp().titles.borrow_mut() = titles.iter().map(|t| t.to_string()).collect();
+            self.items_changed(0, removed, titles.len() as i32);
+        }
+    }
+
+    fn label(model: &impl IsA<MenuModel>, index: i32) -> Option<String> {
+        model
+            .item_attribute_value(index, "label", Some(VariantTy::STRING))
+            .and_then(|v| v.get())
+    }
+
+    #[test]
+    fn local() {
+        let list = glib::Object::new::<WindowList>(&[]);
+        list.set_titles(&["a", "b"]);
+
+        assert!(list.is_mutable());
+        assert_eq!(list.n_items(), 2);
+        assert_eq!(label(&list, 1).as_deref(), Some("b"));
+        assert_eq!(
+            list.item_attribute_value(1, "target", Some(VariantTy::INT32)),
+            Some(1.to_variant())
+        );
+        assert_eq!(
+            list.item_attribute_value(1, "label", Some(VariantTy::INT32)),
+            None
+        );
+        assert_eq!(list.item_attribute_value(1, "icon", None), None);
+
+        let item = MenuItem::from_model(&list, 0);
+        assert_eq!(
+            item.attribute_value("action", None),
+            Some("app.raise".to_variant())
+        );
+        let section = list.item_link(0, "section").unwrap();
+        assert_eq!(label(&section, 0).as_deref(), Some("Close"));
+        assert!(list.item_link(0, "submenu").is_none());
+
+        let iter = list.iterate_item_links(1);
+        let (name, _) = iter.next().unwrap();
+        assert_eq!(name, "section");
+        assert!(iter.next().is_none());
+
+        // Can be copied into a regular menu.
+        let menu = Menu::new();
+        menu.update_from(&list);
+        assert_eq!(menu.n_items(), 2);
+        assert_eq!(label(&menu, 0).as_deref(), Some("a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dbus_export() {
+        // Remote models are only kept up to date while connected to `items-changed`.
+        async fn wait_for(model: &impl IsA<MenuModel>, labels: &[&str]) {
+            let handler = model.connect_items_changed(|_, _, _, _| ());
+            for _ in 0..500 {
+                let current = (0..model.n_items())
+                    .map(|i| label(model, i).unwrap_or_default())
+                    .collect::<Vec<_>>();
+                if current == labels {
+                    model.disconnect(handler);
+                    return;
+                }
+                glib::timeout_future(std::time::Duration::from_millis(10)).await;
+            }
+            panic!("menu model was not updated to {:?}", labels);
+        }
+
+        let (server, client) = crate::test_util::dbus_peer_pair();
+        let context = glib::MainContext::new();
+        context.block_on(async {
+            let list = glib::Object::new::<WindowList>(&[]);
+            list.set_titles(&["Window 1", "Window 2"]);
+            let id = server.export_menu_model("/org/gtk_rs/menu", &list).unwrap();
+
+            let remote = DBusMenuModel::get(&client, None, "/org/gtk_rs/menu");
+            wait_for(&remote, &["Window 1", "Window 2"]).await;
+
+            let section = remote.item_link(1, "section").unwrap();
+            wait_for(&section, &["Close"]).await;
+            let item = MenuItem::from_model(&section, 0);
+            assert_eq!(
+                item.attribute_value("action", None),
+                Some("app.close".to_variant())
+            );
+            assert_eq!(item.attribute_value("target", None), Some(1.to_variant()));
+
+            list.set_titles(&["Window 2", "Window 3", "Window 4"]);
+            wait_for(&remote, &["Window 2", "Window 3", "Window 4"]).await;
+
+            server.unexport_menu_model(id);
+        });
+    }
+}
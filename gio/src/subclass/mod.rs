@@ -8,6 +8,7 @@ mod initable;
 mod input_stream;
 mod io_stream;
 mod list_model;
+mod menu_model;
 mod output_stream;
 mod seekable;
 
@@ -25,6 +26,7 @@ pub mod prelude {
     pub use super::input_stream::{InputStreamImpl, InputStreamImplExt};
     pub use super::io_stream::{IOStreamImpl, IOStreamImplExt};
     pub use super::list_model::{ListModelImpl, ListModelImplExt};
+    pub use super::menu_model::{MenuModelImpl, MenuModelImplExt};
     pub use super::output_stream::{OutputStreamImpl, OutputStreamImplExt};
     pub use super::seekable::{SeekableImpl, SeekableImplExt};
 }
@@ -1,10 +1,15 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+// rustdoc-stripper-ignore-next
+//! Helpers for testing applications on a private D-Bus session bus.
+
 use glib::*;
-use std::sync::mpsc::{channel, Sender};
+use std::sync::mpsc::channel;
+#[cfg(test)]
+use std::sync::mpsc::Sender;
 
 #[cfg(test)]
-pub fn run_async<T: Send + 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'static>(
+pub(crate) fn run_async<T: Send + 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'static>(
     start: Q,
 ) -> T {
     let c = MainContext::new();
@@ -23,7 +28,9 @@ pub fn run_async<T: Send + 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'sta
 }
 
 #[cfg(test)]
-pub fn run_async_local<T: 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'static>(start: Q) -> T {
+pub(crate) fn run_async_local<T: 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'static>(
+    start: Q,
+) -> T {
     let c = MainContext::new();
     let l = MainLoop::new(Some(&c), false);
     let l_clone = l.clone();
@@ -38,3 +45,440 @@ pub fn run_async_local<T: 'static, Q: FnOnce(Sender<T>, MainLoop) + Send + 'stat
 
     rx.recv().unwrap()
 }
+
+// Returns the server and client ends of a peer-to-peer D-Bus connection.
+#[cfg(all(test, unix))]
+pub(crate) fn dbus_peer_pair() -> (crate::DBusConnection, crate::DBusConnection) {
+    use crate::{prelude::*, DBusConnection, DBusConnectionFlags, Socket};
+
+    let (server, client) = std::os::unix::net::UnixStream::pair().unwrap();
+    let connection = |stream| unsafe {
+        Socket::from_fd(stream)
+            .unwrap()
+            .connection_factory_create_connection()
+    };
+    let (server, client) = (connection(server), connection(client));
+
+    let guid = crate::dbus_generate_guid();
+    MainContext::new()
+        .block_on(futures_util::future::try_join(
+            DBusConnection::new_future(
+                &server,
+                Some(&guid),
+                DBusConnectionFlags::AUTHENTICATION_SERVER,
+                None,
+            ),
+            DBusConnection::new_future(
+                &client,
+                None,
+                DBusConnectionFlags::AUTHENTICATION_CLIENT,
+                None,
+            ),
+        ))
+        .unwrap()
+}
+
+// rustdoc-stripper-ignore-next
+/// Makes the process use a private session bus, started on first use and kept until the process
+/// exits.
+#[cfg(unix)]
+#[doc(alias = "g_test_dbus_up")]
+pub fn private_session_bus() {
+    static BUS: std::sync::Once = std::sync::Once::new();
+    BUS.call_once(|| unsafe {
+        let bus = crate::ffi::g_test_dbus_new(crate::ffi::G_TEST_DBUS_NONE);
+        crate::ffi::g_test_dbus_up(bus);
+    });
+}
+
+// A main loop with its own main context running in its own thread.
+#[cfg(unix)]
+struct LoopThread {
+    main_loop: MainLoop,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+impl LoopThread {
+    // Calls `setup` with the main context as thread default, and the closure it returns once the
+    // main loop quit.
+    fn spawn<F, G>(setup: F) -> Self
+    where
+        F: FnOnce() -> G + Send + 'static,
+        G: FnOnce(),
+    {
+        let (started_tx, started) = channel();
+        let thread = std::thread::spawn(move || {
+            let context = MainContext::new();
+            let main_loop = MainLoop::new(Some(&context), false);
+            context
+                .with_thread_default(|| {
+                    let teardown = setup();
+                    started_tx.send(main_loop.clone()).unwrap();
+                    main_loop.run();
+                    teardown();
+                })
+                .unwrap();
+        });
+
+        Self {
+            main_loop: started.recv().unwrap(),
+            thread: Some(thread),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for LoopThread {
+    fn drop(&mut self) {
+        self.main_loop.quit();
+        if let Some(thread) = self.thread.take() {
+            // Don't panic again if dropped while unwinding from a panic in the thread
+            if thread.join().is_err() && !std::thread::panicking() {
+                panic!("main loop thread panicked");
+            }
+        }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Result of a command line forwarded to the primary instance by [`ApplicationHarness`].
+#[cfg(unix)]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Invocation {
+    // rustdoc-stripper-ignore-next
+    /// The exit status returned by the primary instance.
+    pub exit_status: i32,
+    // rustdoc-stripper-ignore-next
+    /// Everything printed with `g_print()` while the command line was handled.
+    pub stdout: String,
+    // rustdoc-stripper-ignore-next
+    /// Everything printed with `g_printerr()` while the command line was handled.
+    pub stderr: String,
+}
+
+// Output of `g_print()` and `g_printerr()` while an `ApplicationHarness` exists.
+#[cfg(unix)]
+static OUTPUT: std::sync::Mutex<(String, String)> =
+    std::sync::Mutex::new((String::new(), String::new()));
+
+// rustdoc-stripper-ignore-next
+/// Runs the primary instance of an application on a [private session bus](private_session_bus)
+/// in its own thread, and invokes it remotely from secondary instances of the same process.
+///
+/// The print handlers of GLib are replaced while the harness exists to capture the output of
+/// command lines, and restored afterwards.
+///
+/// Tests using it must not run in parallel, as `g_application_run()` needs the global default
+/// main context and the print handlers are process-wide.
+#[cfg(unix)]
+pub struct ApplicationHarness {
+    application_id: String,
+    flags: crate::ApplicationFlags,
+    opened: std::sync::mpsc::Receiver<(Vec<String>, String)>,
+    print_handler: glib::ffi::GPrintFunc,
+    printerr_handler: glib::ffi::GPrintFunc,
+    primary: Option<LoopThread>,
+}
+
+#[cfg(unix)]
+impl ApplicationHarness {
+    // rustdoc-stripper-ignore-next
+    /// Registers the primary instance with `application_id` and `flags` after `setup` connected
+    /// its handlers.
+    ///
+    /// The `open` signal is always handled by the harness, see [`Self::open`].
+    pub fn new<F: FnOnce(&crate::Application) + Send + 'static>(
+        application_id: &str,
+        flags: crate::ApplicationFlags,
+        setup: F,
+    ) -> Self {
+        use crate::{prelude::*, Application};
+        use std::ffi::CStr;
+
+        unsafe extern "C" fn print(s: *const std::os::raw::c_char) {
+            OUTPUT
+                .lock()
+                .unwrap()
+                .0
+                .push_str(&CStr::from_ptr(s).to_string_lossy());
+        }
+        unsafe extern "C" fn printerr(s: *const std::os::raw::c_char) {
+            OUTPUT
+                .lock()
+                .unwrap()
+                .1
+                .push_str(&CStr::from_ptr(s).to_string_lossy());
+        }
+
+        private_session_bus();
+
+        // The handlers are installed before the primary instance can print anything
+        *OUTPUT.lock().unwrap() = Default::default();
+        let (print_handler, printerr_handler) = unsafe {
+            (
+                glib::ffi::g_set_print_handler(Some(print)),
+                glib::ffi::g_set_printerr_handler(Some(printerr)),
+            )
+        };
+
+        let (opened_tx, opened) = channel();
+        let id = application_id.to_owned();
+        let primary = LoopThread::spawn(move || {
+            let application = Application::new(Some(&id), flags);
+            application.connect_open(move |_, files, hint| {
+                let uris = files.iter().map(|f| f.uri().into()).collect();
+                opened_tx.send((uris, hint.to_owned())).unwrap();
+            });
+            setup(&application);
+            application.register(crate::Cancellable::NONE).unwrap();
+            assert!(!application.is_remote());
+
+            // The application is kept alive and registered until the main loop quits
+            let guard = application.hold();
+            move || drop((guard, application))
+        });
+
+        Self {
+            application_id: application_id.to_owned(),
+            flags,
+            opened,
+            print_handler,
+            printerr_handler,
+            primary: Some(primary),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Runs a secondary instance with `args`, resolving once the primary instance handled them.
+    pub fn command_line(&self, args: &[&str]) -> impl std::future::Future<Output = Invocation> {
+        use crate::prelude::*;
+
+        let (tx, rx) = futures_channel::oneshot::channel();
+        let application_id = self.application_id.clone();
+        let flags = self.flags | crate::ApplicationFlags::IS_LAUNCHER;
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        std::thread::spawn(move || {
+            let application = crate::Application::new(Some(&application_id), flags);
+            let exit_status = application.run_with_args(&args);
+            let (stdout, stderr) = std::mem::take(&mut *OUTPUT.lock().unwrap());
+            let _ = tx.send(Invocation {
+                exit_status,
+                stdout,
+                stderr,
+            });
+        });
+        async move { rx.await.unwrap() }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Opens `uris` with `hint` from a secondary instance and returns the URIs and hint received
+    /// by the primary instance.
+    pub fn open(&self, uris: &[&str], hint: &str) -> (Vec<String>, String) {
+        use crate::prelude::*;
+
+        let application = crate::Application::new(
+            Some(&self.application_id),
+            self.flags | crate::ApplicationFlags::IS_LAUNCHER,
+        );
+        application.register(crate::Cancellable::NONE).unwrap();
+        assert!(application.is_remote());
+        let files = uris
+            .iter()
+            .map(|uri| crate::File::for_uri(uri))
+            .collect::<Vec<_>>();
+        application.open(&files, hint);
+        self.opened
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap()
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ApplicationHarness {
+    fn drop(&mut self) {
+        // The primary instance can print until it's shut down
+        self.primary.take();
+        unsafe {
+            glib::ffi::g_set_print_handler(self.print_handler);
+            glib::ffi::g_set_printerr_handler(self.printerr_handler);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl std::fmt::Debug for ApplicationHarness {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ApplicationHarness")
+            .field("application_id", &self.application_id)
+            .field("flags", &self.flags)
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A change of the notifications recorded by [`NotificationServer`].
+#[cfg(unix)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationEvent {
+    // rustdoc-stripper-ignore-next
+    /// A notification was sent or replaced.
+    Added {
+        app_id: String,
+        id: String,
+        notification: std::collections::HashMap<String, Variant>,
+    },
+    // rustdoc-stripper-ignore-next
+    /// A notification was withdrawn.
+    Removed { app_id: String, id: String },
+}
+
+#[cfg(unix)]
+type Notifications =
+    std::collections::BTreeMap<(String, String), std::collections::HashMap<String, Variant>>;
+
+// rustdoc-stripper-ignore-next
+/// A stand-in for the `org.gtk.Notifications` desktop service on the
+/// [private session bus](private_session_bus).
+///
+/// [`Application::send_notification()`](crate::prelude::ApplicationExt::send_notification) uses
+/// it if it is running. The service runs in its own thread and records the notifications of all
+/// applications.
+///
+/// Tests using it must not run in parallel, as the bus name is process-wide.
+#[cfg(unix)]
+pub struct NotificationServer {
+    events: std::sync::mpsc::Receiver<NotificationEvent>,
+    notifications: std::sync::Arc<std::sync::Mutex<Notifications>>,
+    _service: LoopThread,
+}
+
+#[cfg(unix)]
+impl NotificationServer {
+    const XML: &'static str = r#"
+        <node>
+          <interface name="org.gtk.Notifications">
+            <method name="AddNotification">
+              <arg type="s" direction="in"/>
+              <arg type="s" direction="in"/>
+              <arg type="a{sv}" direction="in"/>
+            </method>
+            <method name="RemoveNotification">
+              <arg type="s" direction="in"/>
+              <arg type="s" direction="in"/>
+            </method>
+          </interface>
+        </node>"#;
+
+    // rustdoc-stripper-ignore-next
+    /// Starts the service and waits until it owns its bus name.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        use std::{
+            collections::HashMap,
+            sync::{Arc, Mutex},
+        };
+
+        private_session_bus();
+
+        let (events_tx, events) = channel();
+        let notifications = Arc::new(Mutex::new(Notifications::new()));
+        let service = LoopThread::spawn(glib::clone!(@strong notifications => move || {
+            let connection =
+                crate::bus_get_sync(crate::BusType::Session, crate::Cancellable::NONE).unwrap();
+            let info = crate::DBusNodeInfo::for_xml(Self::XML)
+                .unwrap()
+                .lookup_interface("org.gtk.Notifications")
+                .unwrap();
+            let events_tx = Mutex::new(events_tx);
+            let id = connection
+                .register_object(
+                    "/org/gtk/Notifications",
+                    &info,
+                    move |_, _, _, _, method, parameters, invocation| {
+                        let mut notifications = notifications.lock().unwrap();
+                        let event = match method {
+                            "AddNotification" => {
+                                let (app_id, id, notification) = parameters
+                                    .get::<(String, String, HashMap<String, Variant>)>()
+                                    .unwrap();
+                                notifications
+                                    .insert((app_id.clone(), id.clone()), notification.clone());
+                                NotificationEvent::Added {
+                                    app_id,
+                                    id,
+                                    notification,
+                                }
+                            }
+                            _ => {
+                                let (app_id, id) = parameters.get::<(String, String)>().unwrap();
+                                notifications.remove(&(app_id.clone(), id.clone()));
+                                NotificationEvent::Removed { app_id, id }
+                            }
+                        };
+                        events_tx.lock().unwrap().send(event).unwrap();
+                        invocation.return_value(None);
+                    },
+                    |_, _, _, _, _| unreachable!(),
+                    |_, _, _, _, _, _| unreachable!(),
+                )
+                .unwrap();
+            Self::call_bus(&connection, "RequestName", &("org.gtk.Notifications", 4u32));
+
+            move || {
+                Self::call_bus(&connection, "ReleaseName", &("org.gtk.Notifications",));
+                connection.unregister_object(id).unwrap();
+            }
+        }));
+
+        Self {
+            events,
+            notifications,
+            _service: service,
+        }
+    }
+
+    fn call_bus(connection: &crate::DBusConnection, method: &str, parameters: &impl ToVariant) {
+        connection
+            .call_sync(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                method,
+                Some(&parameters.to_variant()),
+                None,
+                crate::DBusCallFlags::NONE,
+                -1,
+                crate::Cancellable::NONE,
+            )
+            .unwrap();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for the next notification to be added or removed.
+    ///
+    /// This panics if nothing happens within 10 seconds.
+    pub fn next_event(&self) -> NotificationEvent {
+        self.events
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The current notifications by application id and notification id.
+    pub fn notifications(
+        &self,
+    ) -> std::collections::BTreeMap<(String, String), std::collections::HashMap<String, Variant>>
+    {
+        self.notifications.lock().unwrap().clone()
+    }
+}
+
+#[cfg(unix)]
+impl std::fmt::Debug for NotificationServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NotificationServer")
+            .field("notifications", &self.notifications)
+            .finish()
+    }
+}
@@ -0,0 +1,322 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::{fmt, marker::PhantomData, ops::Deref};
+
+use glib::{
+    FromVariant, SignalHandlerId, StaticVariantType, ToVariant, Variant, VariantTy, VariantType,
+};
+
+use crate::{prelude::*, SimpleAction};
+
+fn is_unit<T: StaticVariantType>() -> bool {
+    *T::static_variant_type() == *glib::VariantTy::UNIT
+}
+
+// `()` stands for actions without parameter or state.
+pub(crate) fn optional_variant_type<T: StaticVariantType>() -> Option<VariantType> {
+    if is_unit::<T>() {
+        None
+    } else {
+        Some(T::static_variant_type().into_owned())
+    }
+}
+
+pub(crate) fn to_optional_variant<T: StaticVariantType + ToVariant>(value: &T) -> Option<Variant> {
+    if is_unit::<T>() {
+        None
+    } else {
+        Some(value.to_variant())
+    }
+}
+
+pub(crate) fn from_optional_variant<T: FromVariant>(variant: Option<&Variant>) -> Option<T> {
+    match variant {
+        Some(variant) => variant.get(),
+        None if is_unit::<T>() => ().to_variant().get(),
+        None => None,
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A [`SimpleAction`] with a parameter of type `P` and a state of type `S`.
+///
+/// The variant types of the action are derived from the Rust types, with `()` standing for
+/// actions without parameter, respectively stateless actions. Activating it with a wrongly typed
+/// parameter or changing it to a wrongly typed state then fails to compile.
+///
+/// It dereferences to the underlying [`SimpleAction`] for everything that doesn't depend on
+/// these types.
+///
+/// ```
+/// # use gio::{prelude::*, TypedAction};
+/// let zoom = TypedAction::<i32, f64>::new_stateful("zoom", &1.0);
+/// zoom.connect_activate(|action, steps| {
+///     action.set_state(&(action.state() * 1.25f64.powi(steps)));
+/// });
+/// zoom.activate(&2);
+/// assert_eq!(zoom.state(), 1.5625);
+/// ```
+pub struct TypedAction<P, S = ()> {
+    action: SimpleAction,
+    phantom: PhantomData<fn(P, S)>,
+}
+
+impl<P: FromVariant + ToVariant> TypedAction<P> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new stateless action.
+    #[doc(alias = "g_simple_action_new")]
+    pub fn new(name: &str) -> Self {
+        Self {
+            action: SimpleAction::new(name, optional_variant_type::<P>().as_deref()),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<P: FromVariant + ToVariant, S: FromVariant + ToVariant> TypedAction<P, S> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new stateful action with the initial `state`.
+    #[doc(alias = "g_simple_action_new_stateful")]
+    pub fn new_stateful(name: &str, state: &S) -> Self {
+        let action = match to_optional_variant(state) {
+            Some(state) => {
+                SimpleAction::new_stateful(name, optional_variant_type::<P>().as_deref(), &state)
+            }
+            None => SimpleAction::new(name, optional_variant_type::<P>().as_deref()),
+        };
+        Self {
+            action,
+            phantom: PhantomData,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Wraps `action` if its parameter and state types match `P` and `S`.
+    pub fn from_action(action: SimpleAction) -> Option<Self> {
+        Self::check_types(&action).ok()?;
+        Some(Self {
+            action,
+            phantom: PhantomData,
+        })
+    }
+
+    pub(crate) fn check_types(action: &SimpleAction) -> Result<(), glib::BoolError> {
+        let parameter_type = action.parameter_type();
+        let state_type = action.state().map(|s| s.type_().to_owned());
+        let expected_parameter_type = optional_variant_type::<P>();
+        let expected_state_type = optional_variant_type::<S>();
+        if parameter_type == expected_parameter_type && state_type == expected_state_type {
+            Ok(())
+        } else {
+            Err(glib::bool_error!(
+                "Action {} has parameter type {:?} and state type {:?}, expected {:?} and {:?}",
+                action.name(),
+                parameter_type.as_deref().map(VariantTy::as_str),
+                state_type.as_deref().map(VariantTy::as_str),
+                expected_parameter_type.as_deref().map(VariantTy::as_str),
+                expected_state_type.as_deref().map(VariantTy::as_str)
+            ))
+        }
+    }
+
+    pub fn action(&self) -> &SimpleAction {
+        &self.action
+    }
+
+    #[doc(alias = "g_action_activate")]
+    pub fn activate(&self, parameter: &P) {
+        self.action
+            .activate(to_optional_variant(parameter).as_ref());
+    }
+
+    #[doc(alias = "g_action_get_state")]
+    pub fn state(&self) -> S {
+        from_optional_variant(self.action.state().as_ref())
+            .expect("action state has the wrong type")
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the state directly, without emitting `change-state`.
+    #[doc(alias = "g_simple_action_set_state")]
+    pub fn set_state(&self, state: &S) {
+        if let Some(state) = to_optional_variant(state) {
+            self.action.set_state(&state);
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Requests a change of the state, which is handled by the
+    /// [`connect_change_state`](Self::connect_change_state) callbacks or applied directly if
+    /// there are none.
+    #[doc(alias = "g_action_change_state")]
+    pub fn change_state(&self, state: &S) {
+        if let Some(state) = to_optional_variant(state) {
+            self.action.change_state(&state);
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Parameters that can't be converted to `P` are rejected with a critical warning, this
+    /// can only happen if the action is activated from C with a `GVariant` that doesn't satisfy
+    /// the constraints of `P`.
+    #[doc(alias = "activate")]
+    pub fn connect_activate<F: Fn(&Self, P) + 'static>(&self, f: F) -> SignalHandlerId {
+        self.action.connect_activate(move |action, parameter| {
+            match from_optional_variant(parameter) {
+                Some(parameter) => f(&Self::wrap(action), parameter),
+                None => glib::g_critical!(
+                    "gio",
+                    "Invalid parameter {:?} for action {}",
+                    parameter,
+                    action.name()
+                ),
+            }
+        })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The callback decides whether to apply the requested state, usually by calling
+    /// [`set_state`](Self::set_state) after validating it. Requested states that can't be
+    /// converted to `S` are rejected with a critical warning.
+    #[doc(alias = "change-state")]
+    pub fn connect_change_state<F: Fn(&Self, S) + 'static>(&self, f: F) -> SignalHandlerId {
+        self.action
+            .connect_change_state(move |action, state| match from_optional_variant(state) {
+                Some(state) => f(&Self::wrap(action), state),
+                None => glib::g_critical!(
+                    "gio",
+                    "Invalid state {:?} for action {}",
+                    state,
+                    action.name()
+                ),
+            })
+    }
+
+    pub(crate) fn wrap(action: &SimpleAction) -> Self {
+        Self {
+            action: action.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<P, S> Clone for TypedAction<P, S> {
+    fn clone(&self) -> Self {
+        Self {
+            action: self.action.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<P, S> Deref for TypedAction<P, S> {
+    type Target = SimpleAction;
+
+    fn deref(&self) -> &SimpleAction {
+        &self.action
+    }
+}
+
+impl<P, S> AsRef<SimpleAction> for TypedAction<P, S> {
+    fn as_ref(&self) -> &SimpleAction {
+        &self.action
+    }
+}
+
+impl<P, S> From<TypedAction<P, S>> for SimpleAction {
+    fn from(action: TypedAction<P, S>) -> Self {
+        action.action
+    }
+}
+
+impl<P: StaticVariantType, S: StaticVariantType> fmt::Debug for TypedAction<P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypedAction")
+            .field("name", &self.action.name())
+            .field("parameter_type", &P::static_variant_type())
+            .field("state_type", &S::static_variant_type())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::SimpleActionGroup;
+
+    #[test]
+    fn stateless() {
+        let action = TypedAction::<String>::new("open");
+        assert_eq!(
+            action.parameter_type().as_deref(),
+            Some(glib::VariantTy::STRING)
+        );
+        assert!(ActionExt::state(action.action()).is_none());
+
+        let opened = Rc::new(RefCell::new(Vec::new()));
+        action.connect_activate(glib::clone!(@strong opened => move |_, file| {
+            opened.borrow_mut().push(file);
+        }));
+        action.activate(&"a.txt".to_owned());
+        ActionExt::activate(action.action(), Some(&"b.txt".to_variant()));
+        assert_eq!(*opened.borrow(), ["a.txt", "b.txt"]);
+
+        let quit = TypedAction::<()>::new("quit");
+        assert!(quit.parameter_type().is_none());
+        let count = Rc::new(RefCell::new(0));
+        quit.connect_activate(glib::clone!(@strong count => move |_, ()| {
+            *count.borrow_mut() += 1;
+        }));
+        quit.activate(&());
+        assert_eq!(*count.borrow(), 1);
+    }
+
+    #[test]
+    fn stateful() {
+        let action = TypedAction::<(), u32>::new_stateful("level", &1);
+        assert_eq!(ActionExt::state(action.action()), Some(1u32.to_variant()));
+        assert_eq!(action.state(), 1);
+
+        // Without handler the state is applied directly.
+        action.change_state(&2);
+        assert_eq!(action.state(), 2);
+
+        action.connect_change_state(|action, level| {
+            if level <= 10 {
+                action.set_state(&level);
+            }
+        });
+        action.change_state(&5);
+        assert_eq!(action.state(), 5);
+        action.change_state(&11);
+        assert_eq!(action.state(), 5);
+    }
+
+    #[test]
+    fn from_action() {
+        let action =
+            SimpleAction::new_stateful("a", Some(glib::VariantTy::INT32), &true.to_variant());
+        assert!(TypedAction::<i32, bool>::from_action(action.clone()).is_some());
+        assert!(TypedAction::<i32>::from_action(action.clone()).is_none());
+        assert!(TypedAction::<u32, bool>::from_action(action).is_none());
+
+        let group = SimpleActionGroup::new();
+        let action = TypedAction::<i32, bool>::new_stateful("toggle", &false);
+        group.add_typed_action(&action);
+        let found = group.lookup_typed_action::<i32, bool>("toggle").unwrap();
+        assert_eq!(found.action(), action.action());
+        assert!(group.lookup_typed_action::<i32, u8>("toggle").is_none());
+        assert!(group.lookup_typed_action::<i32, bool>("missing").is_none());
+
+        group.change_typed_action_state(&action, &true);
+        assert_eq!(group.typed_action_state(&action), Some(true));
+        let parameters = Rc::new(RefCell::new(Vec::new()));
+        action.connect_activate(glib::clone!(@strong parameters => move |_, p| {
+            parameters.borrow_mut().push(p);
+        }));
+        group.activate_typed_action(&action, &3);
+        assert_eq!(*parameters.borrow(), [3]);
+    }
+}
@@ -0,0 +1,911 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! In-process compiler for GResource bundles.
+//!
+//! This produces the same format as `glib-compile-resources` without requiring it to be
+//! installed, which is mostly useful when cross-compiling.
+
+use std::{
+    collections::BTreeMap,
+    error, fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use quick_xml::events::Event;
+
+use crate::gvdb;
+
+const FLAGS_COMPRESSED: u32 = 1;
+
+// rustdoc-stripper-ignore-next
+/// Processing applied to a file before it is added to a bundle, as given by the `preprocess`
+/// attribute of a `<file>` in the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Preprocess {
+    // rustdoc-stripper-ignore-next
+    /// `xml-stripblanks`, removes whitespace-only text between XML elements like libxml2's
+    /// `noblanks` option.
+    XmlStripBlanks,
+    // rustdoc-stripper-ignore-next
+    /// `json-stripblanks`, removes whitespace outside of JSON strings.
+    JsonStripBlanks,
+    // rustdoc-stripper-ignore-next
+    /// `to-pixdata`, converts a PNG image to the `GdkPixdata` format.
+    ToPixdata,
+}
+
+impl Preprocess {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::XmlStripBlanks => "xml-stripblanks",
+            Self::JsonStripBlanks => "json-stripblanks",
+            Self::ToPixdata => "to-pixdata",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "xml-stripblanks" => Some(Self::XmlStripBlanks),
+            "json-stripblanks" => Some(Self::JsonStripBlanks),
+            "to-pixdata" => Some(Self::ToPixdata),
+            _ => None,
+        }
+    }
+
+    fn apply(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::XmlStripBlanks => xml_strip_blanks(data),
+            Self::JsonStripBlanks => json_strip_blanks(data),
+            Self::ToPixdata => to_pixdata(data),
+        }
+    }
+}
+
+impl fmt::Display for Preprocess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Errors while compiling a resource bundle.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    // rustdoc-stripper-ignore-next
+    /// Reading or writing `path` failed.
+    Io { path: PathBuf, source: io::Error },
+    // rustdoc-stripper-ignore-next
+    /// The manifest at `path` is not valid.
+    Manifest { path: PathBuf, message: String },
+    // rustdoc-stripper-ignore-next
+    /// `file` was not found in any of the source directories.
+    FileNotFound {
+        file: String,
+        source_dirs: Vec<PathBuf>,
+    },
+    // rustdoc-stripper-ignore-next
+    /// `resource_path` was added more than once.
+    Duplicate { resource_path: String },
+    // rustdoc-stripper-ignore-next
+    /// `resource_path` is not an absolute path naming a file.
+    InvalidPath { resource_path: String },
+    // rustdoc-stripper-ignore-next
+    /// Preprocessing `path` failed.
+    Preprocess {
+        path: PathBuf,
+        preprocess: Preprocess,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::Manifest { path, message } => write!(f, "{}: {}", path.display(), message),
+            Self::FileNotFound { file, source_dirs } => {
+                write!(f, "Failed to locate “{}” in any source directory (", file)?;
+                for (i, dir) in source_dirs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", dir.display())?;
+                }
+                f.write_str(")")
+            }
+            Self::Duplicate { resource_path } => write!(
+                f,
+                "File {} appears multiple times in the resource",
+                resource_path
+            ),
+            Self::InvalidPath { resource_path } => {
+                write!(f, "Invalid resource path “{}”", resource_path)
+            }
+            Self::Preprocess {
+                path,
+                preprocess,
+                message,
+            } => write!(
+                f,
+                "{}: failed to apply {}: {}",
+                path.display(),
+                preprocess,
+                message
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|source| Error::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+// rustdoc-stripper-ignore-next
+/// A GResource bundle under construction.
+///
+/// ```no_run
+/// use glib_gresource::gresource::ResourceBundle;
+///
+/// let mut bundle = ResourceBundle::from_manifest("resources.gresource.xml", &["resources"])?;
+/// bundle.add_data("/org/example/app/version", b"1.0".to_vec(), false)?;
+/// bundle.write("app.gresource")?;
+/// # Ok::<(), glib_gresource::gresource::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct ResourceBundle {
+    // Serialized `(uuay)` values, keyed by resource path.
+    entries: BTreeMap<String, Vec<u8>>,
+    dependencies: Vec<PathBuf>,
+}
+
+impl ResourceBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Creates a bundle from a `.gresource.xml` manifest. Files are looked up in
+    /// `source_dirs` in order, or relative to the current directory if it is empty.
+    pub fn from_manifest<P: AsRef<Path>, S: AsRef<Path>>(
+        manifest: P,
+        source_dirs: &[S],
+    ) -> Result<Self, Error> {
+        let manifest = manifest.as_ref();
+        let source_dirs = if source_dirs.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            source_dirs.iter().map(|d| d.as_ref().to_owned()).collect()
+        };
+
+        let xml = read(manifest)?;
+        let files = parse_manifest(&xml).map_err(|message| Error::Manifest {
+            path: manifest.to_owned(),
+            message,
+        })?;
+
+        let mut bundle = Self::new();
+        bundle.dependencies.push(manifest.to_owned());
+        for file in files {
+            let source = source_dirs
+                .iter()
+                .map(|dir| dir.join(&file.name))
+                .find(|path| path.is_file())
+                .ok_or_else(|| Error::FileNotFound {
+                    file: file.name.clone(),
+                    source_dirs: source_dirs.clone(),
+                })?;
+            let alias = file.alias.as_deref().unwrap_or(&file.name);
+            let resource_path = resource_path(&file.prefix, alias);
+            bundle.add_file(&resource_path, &source, file.compressed, &file.preprocess)?;
+        }
+
+        Ok(bundle)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds all files below `dir`, with resource paths relative to `prefix`. Hidden files are
+    /// skipped.
+    ///
+    /// Text formats are compressed, and blanks are stripped from `.ui`, `.xml` and `.json`
+    /// files. Other files, like images, are stored unmodified.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P, prefix: &str) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        let io_error = |source| Error::Io {
+            path: dir.to_owned(),
+            source,
+        };
+        let mut entries = fs::read_dir(dir)
+            .map_err(io_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io_error)?;
+        entries.sort();
+
+        for path in entries {
+            let name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(name) if !name.starts_with('.') => name,
+                _ => continue,
+            };
+            let resource_path = resource_path(prefix, name);
+            if path.is_dir() {
+                self.add_dir(&path, &resource_path)?;
+                continue;
+            }
+
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let (compressed, preprocess) = match extension {
+                "ui" | "xml" => (true, &[Preprocess::XmlStripBlanks][..]),
+                "json" => (true, &[Preprocess::JsonStripBlanks][..]),
+                "css" | "svg" | "txt" | "md" | "html" | "js" => (true, &[][..]),
+                _ => (false, &[][..]),
+            };
+            self.add_file(&resource_path, &path, compressed, preprocess)?;
+        }
+
+        Ok(())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds the contents of `source` as `resource_path`, applying `preprocess` in order.
+    pub fn add_file<P: AsRef<Path>>(
+        &mut self,
+        resource_path: &str,
+        source: P,
+        compressed: bool,
+        preprocess: &[Preprocess],
+    ) -> Result<(), Error> {
+        let source = source.as_ref();
+        let mut data = read(source)?;
+        for preprocess in preprocess {
+            data = preprocess
+                .apply(&data)
+                .map_err(|message| Error::Preprocess {
+                    path: source.to_owned(),
+                    preprocess: *preprocess,
+                    message,
+                })?;
+        }
+        self.add_data(resource_path, data, compressed)?;
+        self.dependencies.push(source.to_owned());
+        Ok(())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds `data` as `resource_path`, which must be an absolute path like `/org/example/file`.
+    pub fn add_data(
+        &mut self,
+        resource_path: &str,
+        data: Vec<u8>,
+        compressed: bool,
+    ) -> Result<(), Error> {
+        let valid = resource_path
+            .strip_prefix('/')
+            .map_or(false, |path| !path.split('/').any(str::is_empty));
+        if !valid {
+            return Err(Error::InvalidPath {
+                resource_path: resource_path.to_owned(),
+            });
+        }
+        if self.entries.contains_key(resource_path) {
+            return Err(Error::Duplicate {
+                resource_path: resource_path.to_owned(),
+            });
+        }
+
+        let size = data.len() as u32;
+        let (flags, content) = if compressed {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(&data).unwrap();
+            (FLAGS_COMPRESSED, encoder.finish().unwrap())
+        } else {
+            // GResource keeps uncompressed data NUL terminated.
+            let mut data = data;
+            data.push(0);
+            (0, data)
+        };
+
+        // Serialized `(uuay)`.
+        let mut value = Vec::with_capacity(content.len() + 8);
+        value.extend_from_slice(&size.to_le_bytes());
+        value.extend_from_slice(&flags.to_le_bytes());
+        value.extend_from_slice(&content);
+
+        self.entries.insert(resource_path.to_owned(), value);
+        Ok(())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// All files read so far, including manifests.
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Serializes the bundle, which can be loaded with `gio::Resource::from_data`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut table = gvdb::HashTableBuilder::new();
+        for (path, value) in &self.entries {
+            table.insert_variant(path, "(uuay)", value.clone());
+
+            // Every directory is listed in its parent, down to `/`.
+            let mut child = path.as_str();
+            while child != "/" {
+                let parent = &child[..child[..child.len() - 1].rfind('/').unwrap() + 1];
+                table.set_parent(child, parent);
+                child = parent;
+            }
+        }
+        table.to_bytes()
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()).map_err(|source| Error::Io {
+            path: path.to_owned(),
+            source,
+        })
+    }
+}
+
+fn resource_path(prefix: &str, name: &str) -> String {
+    let mut path = String::from("/");
+    for component in prefix.split('/').chain(name.split('/')) {
+        if !component.is_empty() {
+            if !path.ends_with('/') {
+                path.push('/');
+            }
+            path.push_str(component);
+        }
+    }
+    path
+}
+
+struct ManifestFile {
+    prefix: String,
+    name: String,
+    alias: Option<String>,
+    compressed: bool,
+    preprocess: Vec<Preprocess>,
+}
+
+fn parse_manifest(xml: &[u8]) -> Result<Vec<ManifestFile>, String> {
+    let mut reader = quick_xml::Reader::from_reader(xml);
+    reader.trim_text(true);
+
+    let mut files = Vec::new();
+    let mut prefix = None;
+    let mut file: Option<ManifestFile> = None;
+    let mut buf = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("at position {}: {}", reader.buffer_position(), e))?;
+        match event {
+            Event::Start(e) | Event::Empty(e) => {
+                let mut attrs = BTreeMap::new();
+                for attr in e.attributes() {
+                    let attr = attr.map_err(|e| e.to_string())?;
+                    let value = attr.unescape_value().map_err(|e| e.to_string())?;
+                    attrs.insert(
+                        String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+                        value.into_owned(),
+                    );
+                }
+                match e.name().as_ref() {
+                    b"gresources" => (),
+                    b"gresource" => prefix = Some(attrs.remove("prefix").unwrap_or_default()),
+                    b"file" => {
+                        let prefix = prefix
+                            .clone()
+                            .ok_or("<file> outside of a <gresource> element")?;
+                        let compressed = match attrs.get("compressed").map(String::as_str) {
+                            None | Some("false") => false,
+                            Some("true") => true,
+                            Some(v) => return Err(format!("Invalid compressed value “{}”", v)),
+                        };
+                        let preprocess = attrs
+                            .get("preprocess")
+                            .map(|p| {
+                                p.split(',')
+                                    .map(str::trim)
+                                    .filter(|p| !p.is_empty())
+                                    .map(|p| {
+                                        Preprocess::from_name(p).ok_or_else(|| {
+                                            format!("Unknown processing option “{}”", p)
+                                        })
+                                    })
+                                    .collect::<Result<Vec<_>, _>>()
+                            })
+                            .transpose()?
+                            .unwrap_or_default();
+                        file = Some(ManifestFile {
+                            prefix,
+                            name: String::new(),
+                            alias: attrs.remove("alias"),
+                            compressed,
+                            preprocess,
+                        });
+                    }
+                    name => {
+                        return Err(format!(
+                            "Unknown element <{}>",
+                            String::from_utf8_lossy(name)
+                        ))
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let Some(file) = file.as_mut() {
+                    file.name
+                        .push_str(&t.unescape().map_err(|e| e.to_string())?);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"file" => {
+                    let file = file.take().unwrap();
+                    if file.name.is_empty() {
+                        return Err("Empty <file> element".to_owned());
+                    }
+                    files.push(file);
+                }
+                b"gresource" => prefix = None,
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    Ok(files)
+}
+
+// Element that is being stripped, to decide which whitespace-only text is kept.
+#[derive(Default)]
+struct XmlElement {
+    // Whether `xml:space="preserve"` is in scope.
+    preserve: bool,
+    has_children: bool,
+    first_child_text: bool,
+    last_child_text: bool,
+}
+
+impl XmlElement {
+    fn add_child(&mut self, text: bool) {
+        if !self.has_children {
+            self.first_child_text = text;
+        }
+        self.has_children = true;
+        self.last_child_text = text;
+    }
+}
+
+// Same rules as libxml2's `XML_PARSE_NOBLANKS`, which `glib-compile-resources` uses: whitespace-only
+// text is removed unless `xml:space="preserve"` is in scope, it's the only child of its element or
+// its element has other text as first or previous child.
+fn xml_strip_blanks(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = quick_xml::Reader::from_reader(data);
+    let mut writer = quick_xml::Writer::new(Vec::with_capacity(data.len()));
+    let mut buf = Vec::new();
+    let mut elements: Vec<XmlElement> = Vec::new();
+    // Whitespace-only first child that is only kept if the element ends right after it.
+    let mut pending = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| format!("at position {}: {}", reader.buffer_position(), e))?;
+
+        if let Some(text) = pending.take() {
+            if matches!(event, Event::End(_)) {
+                writer
+                    .write_event(Event::Text(text))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        match event {
+            Event::Eof => break,
+            Event::Text(t) if t.iter().all(u8::is_ascii_whitespace) => {
+                let element = match elements.last_mut() {
+                    Some(element) => element,
+                    None => continue,
+                };
+                if element.preserve || element.first_child_text || element.last_child_text {
+                    element.add_child(true);
+                    writer
+                        .write_event(Event::Text(t))
+                        .map_err(|e| e.to_string())?;
+                } else if !element.has_children {
+                    pending = Some(t.into_owned());
+                }
+            }
+            event => {
+                if let Some(element) = elements.last_mut() {
+                    element.add_child(matches!(event, Event::Text(_)));
+                }
+                match &event {
+                    Event::Start(e) => {
+                        let mut preserve = elements.last().map_or(false, |e| e.preserve);
+                        if let Some(space) = e
+                            .try_get_attribute("xml:space")
+                            .map_err(|e| e.to_string())?
+                        {
+                            match &*space.value {
+                                b"preserve" => preserve = true,
+                                b"default" => preserve = false,
+                                _ => (),
+                            }
+                        }
+                        elements.push(XmlElement {
+                            preserve,
+                            ..Default::default()
+                        });
+                    }
+                    Event::End(_) => {
+                        elements.pop();
+                    }
+                    _ => (),
+                }
+                writer.write_event(event).map_err(|e| e.to_string())?;
+            }
+        }
+        buf.clear();
+    }
+
+    let mut data = writer.into_inner();
+    data.push(b'\n');
+    Ok(data)
+}
+
+fn json_strip_blanks(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &c in data {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == b'\\' {
+                escaped = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+        } else if c == b'"' {
+            in_string = true;
+            out.push(c);
+        } else if !c.is_ascii_whitespace() {
+            out.push(c);
+        }
+    }
+
+    if in_string {
+        return Err("unterminated string".to_owned());
+    }
+    Ok(out)
+}
+
+fn to_pixdata(data: &[u8]) -> Result<Vec<u8>, String> {
+    const MAGIC: u32 = 0x4764_6b50;
+    const HEADER_LENGTH: u32 = 24;
+    const COLOR_TYPE_RGB: u32 = 0x01;
+    const COLOR_TYPE_RGBA: u32 = 0x02;
+    const SAMPLE_WIDTH_8: u32 = 0x01 << 16;
+    const ENCODING_RAW: u32 = 0x01 << 24;
+
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|e| e.to_string())?;
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let (channels, color_type) = match info.color_type {
+        png::ColorType::Rgb | png::ColorType::Grayscale => (3, COLOR_TYPE_RGB),
+        png::ColorType::Rgba | png::ColorType::GrayscaleAlpha => (4, COLOR_TYPE_RGBA),
+        other => return Err(format!("unsupported PNG color type {:?}", other)),
+    };
+    // Rows are padded to 4 bytes like in GdkPixbuf.
+    let rowstride = (width * channels + 3) & !3;
+
+    let mut out = Vec::with_capacity(HEADER_LENGTH as usize + rowstride * height);
+    let length = HEADER_LENGTH + (rowstride * height) as u32;
+    for v in [
+        MAGIC,
+        length,
+        color_type | SAMPLE_WIDTH_8 | ENCODING_RAW,
+        rowstride as u32,
+        width as u32,
+        height as u32,
+    ] {
+        out.extend_from_slice(&v.to_be_bytes());
+    }
+
+    for row in pixels.chunks(info.line_size).take(height) {
+        let start = out.len();
+        match info.color_type {
+            png::ColorType::Grayscale => {
+                out.extend(row.iter().flat_map(|&g| [g, g, g]));
+            }
+            png::ColorType::GrayscaleAlpha => {
+                out.extend(row.chunks(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]));
+            }
+            _ => out.extend_from_slice(&row[..width * channels]),
+        }
+        out.resize(start + rowstride, 0);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(dir: &Path, name: &str, data: &[u8]) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        write_file(
+            dir,
+            "app.gresource.xml",
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<gresources>
+  <gresource prefix="/org/gtk-rs/test">
+    <file>plain.txt</file>
+    <file compressed="true" alias="data/big.txt">large.txt</file>
+    <file preprocess="xml-stripblanks">ui/window.ui</file>
+    <file preprocess="json-stripblanks" compressed="true">data.json</file>
+  </gresource>
+  <gresource prefix="/org/gtk-rs/other/">
+    <file alias="copy.txt">plain.txt</file>
+  </gresource>
+</gresources>"#,
+        );
+        let src = dir.join("src");
+        write_file(&src, "plain.txt", b"hello");
+        write_file(&src, "large.txt", &b"compressible ".repeat(100));
+        write_file(
+            &src,
+            "ui/window.ui",
+            b"<interface>\n  <object class=\"GtkWindow\">\n    <property name=\"title\"> A  </property>\n  </object>\n</interface>\n",
+        );
+        write_file(
+            &src,
+            "data.json",
+            b"{ \"a b\" : [1, 2],\n  \"c\": \" \\\" x\" }\n",
+        );
+
+        let bundle = ResourceBundle::from_manifest(dir.join("app.gresource.xml"), &[&src]).unwrap();
+        assert_eq!(bundle.dependencies().len(), 6);
+
+        let resource =
+            gio::Resource::from_data(&gio::glib::Bytes::from_owned(bundle.to_bytes())).unwrap();
+        let lookup = |path| {
+            resource
+                .lookup_data(path, gio::ResourceLookupFlags::NONE)
+                .unwrap()
+        };
+        assert_eq!(&*lookup("/org/gtk-rs/test/plain.txt"), b"hello");
+        assert_eq!(&*lookup("/org/gtk-rs/other/copy.txt"), b"hello");
+        assert_eq!(
+            &*lookup("/org/gtk-rs/test/data/big.txt"),
+            &*b"compressible ".repeat(100)
+        );
+        assert_eq!(
+            &*lookup("/org/gtk-rs/test/ui/window.ui"),
+            b"<interface><object class=\"GtkWindow\"><property name=\"title\"> A  </property></object></interface>\n"
+        );
+        assert_eq!(
+            &*lookup("/org/gtk-rs/test/data.json"),
+            b"{\"a b\":[1,2],\"c\":\" \\\" x\"}"
+        );
+
+        let (size, flags) = resource
+            .info(
+                "/org/gtk-rs/test/data/big.txt",
+                gio::ResourceLookupFlags::NONE,
+            )
+            .unwrap();
+        assert_eq!(size, 1300);
+        assert_eq!(flags, FLAGS_COMPRESSED);
+
+        let mut children = resource
+            .enumerate_children("/org/gtk-rs/test/", gio::ResourceLookupFlags::NONE)
+            .unwrap();
+        children.sort();
+        assert_eq!(children, ["data.json", "data/", "plain.txt", "ui/"]);
+        let mut children = resource
+            .enumerate_children("/org/gtk-rs/", gio::ResourceLookupFlags::NONE)
+            .unwrap();
+        children.sort();
+        assert_eq!(children, ["other/", "test/"]);
+    }
+
+    #[test]
+    fn xml_blanks() {
+        let strip =
+            |xml: &str| String::from_utf8(xml_strip_blanks(xml.as_bytes()).unwrap()).unwrap();
+
+        assert_eq!(
+            strip("<a>\n  <b> </b>\n  <c>\t</c><d/>\n</a>\n"),
+            "<a><b> </b><c>\t</c><d/></a>\n"
+        );
+        assert_eq!(
+            strip("<a xml:space=\"preserve\">\n  <b>\n    <c/>\n  </b>\n  <d xml:space=\"default\">\n    <e/>\n  </d>\n</a>"),
+            "<a xml:space=\"preserve\">\n  <b>\n    <c/>\n  </b>\n  <d xml:space=\"default\"><e/></d>\n</a>\n"
+        );
+        // Whitespace in mixed content is kept
+        assert_eq!(
+            strip("<p>\n  <b>x</b> <i>y</i>\n</p>"),
+            "<p><b>x</b><i>y</i></p>\n"
+        );
+        assert_eq!(
+            strip("<p>Hello <b>x</b> <i>y</i> </p>"),
+            "<p>Hello <b>x</b> <i>y</i> </p>\n"
+        );
+    }
+
+    #[test]
+    fn dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        write_file(dir, "style.css", b"a { }");
+        write_file(dir, ".hidden", b"");
+        write_file(
+            dir,
+            "ui/window.ui",
+            b"<interface>\n  <object/>\n</interface>",
+        );
+        write_file(dir, "icons/icon.png", b"not a png");
+
+        let mut bundle = ResourceBundle::new();
+        bundle.add_dir(dir, "/org/gtk-rs/app/").unwrap();
+        let mut dependencies = bundle.dependencies().to_vec();
+        dependencies.sort();
+        assert_eq!(
+            dependencies,
+            [
+                dir.join("icons/icon.png"),
+                dir.join("style.css"),
+                dir.join("ui/window.ui")
+            ]
+        );
+
+        let data = bundle.to_bytes();
+        let file = gvdb::File::new(&data).unwrap();
+        let flags = |path| {
+            let value = file.root().value(path).unwrap();
+            assert_eq!(value.type_string(), "(uuay)");
+            u32::from_le_bytes(value.data()[4..8].try_into().unwrap())
+        };
+        assert_eq!(flags("/org/gtk-rs/app/style.css"), FLAGS_COMPRESSED);
+        assert_eq!(flags("/org/gtk-rs/app/icons/icon.png"), 0);
+        assert_eq!(
+            file.root().list("/org/gtk-rs/app/"),
+            Some(vec!["icons/", "style.css", "ui/"])
+        );
+
+        let resource = gio::Resource::from_data(&gio::glib::Bytes::from_owned(data)).unwrap();
+        assert_eq!(
+            &*resource
+                .lookup_data(
+                    "/org/gtk-rs/app/ui/window.ui",
+                    gio::ResourceLookupFlags::NONE
+                )
+                .unwrap(),
+            b"<interface><object/></interface>\n"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        write_file(dir, "a.txt", b"a");
+
+        let manifest = |body: &str| {
+            write_file(
+                dir,
+                "test.gresource.xml",
+                format!(
+                    "<gresources><gresource prefix=\"/p\">{}</gresource></gresources>",
+                    body
+                )
+                .as_bytes(),
+            );
+            ResourceBundle::from_manifest(dir.join("test.gresource.xml"), &[dir]).unwrap_err()
+        };
+
+        let err = manifest("<file>missing.txt</file>");
+        assert!(matches!(err, Error::FileNotFound { ref file, .. } if file == "missing.txt"));
+        let err = manifest("<file>a.txt</file><file>a.txt</file>");
+        assert!(
+            matches!(err, Error::Duplicate { ref resource_path } if resource_path == "/p/a.txt")
+        );
+        let err = manifest("<file preprocess=\"foo\">a.txt</file>");
+        assert!(
+            err.to_string().contains("Unknown processing option “foo”"),
+            "{}",
+            err
+        );
+        let err = manifest("<file preprocess=\"to-pixdata\">a.txt</file>");
+        assert!(matches!(
+            err,
+            Error::Preprocess {
+                preprocess: Preprocess::ToPixdata,
+                ..
+            }
+        ));
+        let err = manifest("<file>a.txt");
+        assert!(matches!(err, Error::Manifest { .. }));
+        write_file(
+            dir,
+            "test.gresource.xml",
+            b"<gresources><gresource prefix=\"/\"><file alias=\"\">a.txt</file></gresource></gresources>",
+        );
+        let err =
+            ResourceBundle::from_manifest(dir.join("test.gresource.xml"), &[dir]).unwrap_err();
+        assert!(matches!(err, Error::InvalidPath { ref resource_path } if resource_path == "/"));
+
+        let mut bundle = ResourceBundle::new();
+        for path in ["", "/", "a", "/a/", "/a//b"] {
+            let err = bundle.add_data(path, Vec::new(), false).unwrap_err();
+            assert!(
+                matches!(err, Error::InvalidPath { ref resource_path } if resource_path == path)
+            );
+        }
+
+        let err = ResourceBundle::from_manifest(dir.join("nothing.xml"), &[dir]).unwrap_err();
+        assert!(matches!(err, Error::Io { .. }));
+    }
+
+    #[test]
+    fn pixdata() {
+        let mut png = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png, 3, 2);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&(0..18).collect::<Vec<u8>>())
+                .unwrap();
+        }
+
+        let pixdata = to_pixdata(&png).unwrap();
+        let header = pixdata[..24]
+            .chunks(4)
+            .map(|c| u32::from_be_bytes(c.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(header, [0x4764_6b50, 24 + 24, 0x0101_0001, 12, 3, 2]);
+        assert_eq!(&pixdata[24..33], &[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(&pixdata[36..45], &[9, 10, 11, 12, 13, 14, 15, 16, 17]);
+    }
+}
@@ -0,0 +1,639 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Reading and writing of GVDB files, the format of GResource bundles and dconf databases.
+//!
+//! Values are stored as serialized `GVariant`s in normal form together with their type string,
+//! which for a `glib::Variant` are given by `Variant::data()` and `Variant::type_()`, and can be
+//! turned back into one with `Variant::from_data_with_type()`.
+//!
+//! ```
+//! use glib_gresource::gvdb::{File, HashTableBuilder};
+//!
+//! let mut settings = HashTableBuilder::new();
+//! settings.insert_variant("/org/example/name", "s", b"example\0".to_vec());
+//! let mut root = HashTableBuilder::new();
+//! root.insert_variant("version", "u", 1u32.to_le_bytes().to_vec());
+//! root.insert_table("settings", settings);
+//! let data = root.to_bytes();
+//!
+//! let file = File::new(&data).unwrap();
+//! let version = file.root().value("version").unwrap();
+//! assert_eq!((version.type_string(), version.data()), ("u", &[1, 0, 0, 0][..]));
+//! let settings = file.root().table("settings").unwrap();
+//! assert_eq!(settings.keys(), ["/org/example/name"]);
+//! ```
+
+use std::{collections::HashMap, error, fmt, fs, io, path::Path};
+
+const HEADER_SIZE: usize = 24;
+const ITEM_SIZE: usize = 24;
+const SIGNATURE: &[u8; 8] = b"GVariant";
+const SIGNATURE_SWAPPED: &[u8; 8] = b"raVGtnai";
+const NO_PARENT: u32 = u32::MAX;
+
+// Hash function used by GVDB, djb2 over signed chars.
+fn hash(key: &str) -> u32 {
+    key.bytes().fold(5381u32, |h, b| {
+        h.wrapping_mul(33).wrapping_add(b as i8 as u32)
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+}
+
+enum ItemValue {
+    List,
+    Variant(Vec<u8>),
+    Table(HashTableBuilder),
+}
+
+struct Item {
+    key: String,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    value: ItemValue,
+}
+
+// rustdoc-stripper-ignore-next
+/// A GVDB hash table under construction.
+///
+/// The table passed to [`to_bytes`](Self::to_bytes) is the root of the file, other tables can
+/// be nested in it with [`insert_table`](Self::insert_table).
+#[derive(Default)]
+pub struct HashTableBuilder {
+    items: Vec<Item>,
+    index: HashMap<String, usize>,
+}
+
+impl fmt::Debug for HashTableBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashTableBuilder")
+            .field(
+                "keys",
+                &self.items.iter().map(|i| &i.key).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl HashTableBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn item(&mut self, key: &str) -> usize {
+        if let Some(index) = self.index.get(key) {
+            return *index;
+        }
+        let index = self.items.len();
+        self.items.push(Item {
+            key: key.to_owned(),
+            parent: None,
+            children: Vec::new(),
+            value: ItemValue::List,
+        });
+        self.index.insert(key.to_owned(), index);
+        index
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Inserts a value of type `type_string`, with `data` being its serialized form. This
+    /// replaces any previous value of `key`.
+    pub fn insert_variant(&mut self, key: &str, type_string: &str, data: Vec<u8>) {
+        assert!(
+            !type_string.is_empty() && !type_string.contains('\0'),
+            "invalid type string `{}`",
+            type_string
+        );
+
+        // Stored as a variant of type `v`.
+        let mut data = data;
+        data.push(0);
+        data.extend_from_slice(type_string.as_bytes());
+        let index = self.item(key);
+        self.items[index].value = ItemValue::Variant(data);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Inserts a nested hash table. This replaces any previous value of `key`.
+    pub fn insert_table(&mut self, key: &str, table: HashTableBuilder) {
+        let index = self.item(key);
+        self.items[index].value = ItemValue::Table(table);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Makes `key` a child of `parent`, which must be a prefix of `key`. Both are created if they
+    /// don't exist yet.
+    ///
+    /// The key of the child is then stored relative to its parent. Items without a value are
+    /// stored as the list of their children's relative keys, which GResource uses for
+    /// directories.
+    pub fn set_parent(&mut self, key: &str, parent: &str) {
+        assert!(
+            key.starts_with(parent) && key != parent,
+            "`{}` is not a prefix of `{}`",
+            parent,
+            key
+        );
+        let index = self.item(key);
+        let parent = self.item(parent);
+        if let Some(old) = self.items[index].parent.replace(parent) {
+            self.items[old].children.retain(|c| *c != index);
+        }
+        self.items[parent].children.push(index);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Serializes the table as a complete GVDB file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut file = FileBuilder {
+            data: vec![0; HEADER_SIZE],
+        };
+        let (start, end) = file.add_hash(self);
+
+        file.data[0..8].copy_from_slice(SIGNATURE);
+        // version and options stay 0
+        file.data[16..20].copy_from_slice(&start.to_le_bytes());
+        file.data[20..24].copy_from_slice(&end.to_le_bytes());
+        file.data
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+struct FileBuilder {
+    data: Vec<u8>,
+}
+
+impl FileBuilder {
+    fn allocate(&mut self, alignment: usize, size: usize) -> (u32, u32) {
+        let start = (self.data.len() + alignment - 1) & !(alignment - 1);
+        self.data.resize(start + size, 0);
+        (start as u32, (start + size) as u32)
+    }
+
+    fn add(&mut self, alignment: usize, bytes: &[u8]) -> (u32, u32) {
+        let (start, end) = self.allocate(alignment, bytes.len());
+        self.data[start as usize..end as usize].copy_from_slice(bytes);
+        (start, end)
+    }
+
+    fn add_hash(&mut self, table: &HashTableBuilder) -> (u32, u32) {
+        let n_items = table.items.len();
+        let n_buckets = n_items;
+
+        // Items are stored ordered by bucket.
+        let hashes = table
+            .items
+            .iter()
+            .map(|item| hash(&item.key))
+            .collect::<Vec<_>>();
+        let mut order = (0..n_items).collect::<Vec<_>>();
+        order.sort_by_key(|i| hashes[*i] as usize % n_buckets);
+        let mut new_index = vec![0u32; n_items];
+        for (new, old) in order.iter().enumerate() {
+            new_index[*old] = new as u32;
+        }
+
+        let size = 8 + 4 * n_buckets + ITEM_SIZE * n_items;
+        let (start, end) = self.allocate(4, size);
+        let mut header = Vec::with_capacity(size);
+        // No bloom filter words.
+        header.extend_from_slice(&0u32.to_le_bytes());
+        header.extend_from_slice(&(n_buckets as u32).to_le_bytes());
+        let mut item = 0;
+        for bucket in 0..n_buckets {
+            while item < n_items && hashes[order[item]] as usize % n_buckets < bucket {
+                item += 1;
+            }
+            header.extend_from_slice(&(item as u32).to_le_bytes());
+        }
+
+        for old in &order {
+            let item = &table.items[*old];
+            let (parent, key) = match item.parent {
+                Some(parent) => (
+                    new_index[parent],
+                    &item.key[table.items[parent].key.len()..],
+                ),
+                None => (NO_PARENT, item.key.as_str()),
+            };
+            let key_size = u16::try_from(key.len()).expect("GVDB key too long");
+            let (key_start, _) = self.add(1, key.as_bytes());
+
+            let (type_, (value_start, value_end)) = match &item.value {
+                ItemValue::Variant(data) => (b'v', self.add(8, data)),
+                ItemValue::Table(table) => (b'H', self.add_hash(table)),
+                ItemValue::List => {
+                    let children = item
+                        .children
+                        .iter()
+                        .flat_map(|c| new_index[*c].to_le_bytes())
+                        .collect::<Vec<_>>();
+                    (b'L', self.add(4, &children))
+                }
+            };
+
+            header.extend_from_slice(&hashes[*old].to_le_bytes());
+            header.extend_from_slice(&parent.to_le_bytes());
+            header.extend_from_slice(&key_start.to_le_bytes());
+            header.extend_from_slice(&key_size.to_le_bytes());
+            header.push(type_);
+            header.push(0);
+            header.extend_from_slice(&value_start.to_le_bytes());
+            header.extend_from_slice(&value_end.to_le_bytes());
+        }
+
+        debug_assert_eq!(header.len(), size);
+        self.data[start as usize..end as usize].copy_from_slice(&header);
+        (start, end)
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Errors when opening a GVDB file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    // rustdoc-stripper-ignore-next
+    /// The data does not start with a GVDB header.
+    InvalidHeader,
+    // rustdoc-stripper-ignore-next
+    /// The file was written in big endian byte order, which is not supported.
+    BigEndian,
+    // rustdoc-stripper-ignore-next
+    /// The root hash table is out of bounds or truncated.
+    InvalidTable,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidHeader => "Invalid GVDB header",
+            Self::BigEndian => "Big endian GVDB files are not supported",
+            Self::InvalidTable => "Invalid GVDB hash table",
+        })
+    }
+}
+
+impl error::Error for Error {}
+
+// rustdoc-stripper-ignore-next
+/// A GVDB file, read without copying from `data`.
+#[derive(Debug, Clone, Copy)]
+pub struct File<'a> {
+    root: HashTable<'a>,
+}
+
+impl<'a> File<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        if data.len() < HEADER_SIZE {
+            return Err(Error::InvalidHeader);
+        }
+        match &data[0..8] {
+            s if s == SIGNATURE => (),
+            s if s == SIGNATURE_SWAPPED => return Err(Error::BigEndian),
+            _ => return Err(Error::InvalidHeader),
+        }
+        if read_u32(data, 8) != Some(0) {
+            return Err(Error::InvalidHeader);
+        }
+
+        let start = read_u32(data, 16).unwrap() as usize;
+        let end = read_u32(data, 20).unwrap() as usize;
+        let root = HashTable::new(data, start, end).ok_or(Error::InvalidTable)?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> HashTable<'a> {
+        self.root
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A value stored in a GVDB file, as a type string and serialized data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializedVariant<'a> {
+    type_string: &'a str,
+    data: &'a [u8],
+}
+
+impl<'a> SerializedVariant<'a> {
+    pub fn type_string(&self) -> &'a str {
+        self.type_string
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A hash table in a GVDB file.
+///
+/// Like in the C implementation, items that point outside of the file are treated as missing.
+#[derive(Clone, Copy)]
+pub struct HashTable<'a> {
+    data: &'a [u8],
+    buckets: &'a [u8],
+    items: &'a [u8],
+}
+
+impl<'a> fmt::Debug for HashTable<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashTable")
+            .field("keys", &self.keys())
+            .finish()
+    }
+}
+
+struct RawItem {
+    hash: u32,
+    parent: u32,
+    key_start: u32,
+    key_size: u16,
+    type_: u8,
+    value_start: u32,
+    value_end: u32,
+}
+
+impl<'a> HashTable<'a> {
+    fn new(data: &'a [u8], start: usize, end: usize) -> Option<Self> {
+        if start % 4 != 0 || start > end {
+            return None;
+        }
+        let table = data.get(start..end)?;
+        let bloom_words = read_u32(table, 0)? as usize & ((1 << 27) - 1);
+        let n_buckets = read_u32(table, 4)? as usize;
+        let buckets_start = 8usize.checked_add(bloom_words.checked_mul(4)?)?;
+        let items_start = buckets_start.checked_add(n_buckets.checked_mul(4)?)?;
+        let items = table.get(items_start..)?;
+        if items.len() % ITEM_SIZE != 0 {
+            return None;
+        }
+
+        Some(Self {
+            data,
+            buckets: &table[buckets_start..items_start],
+            items,
+        })
+    }
+
+    fn n_items(&self) -> usize {
+        self.items.len() / ITEM_SIZE
+    }
+
+    fn raw_item(&self, index: usize) -> Option<RawItem> {
+        let item = self.items.get(index * ITEM_SIZE..(index + 1) * ITEM_SIZE)?;
+        Some(RawItem {
+            hash: read_u32(item, 0)?,
+            parent: read_u32(item, 4)?,
+            key_start: read_u32(item, 8)?,
+            key_size: u16::from_le_bytes([item[12], item[13]]),
+            type_: item[14],
+            value_start: read_u32(item, 16)?,
+            value_end: read_u32(item, 20)?,
+        })
+    }
+
+    fn item_key(&self, item: &RawItem) -> Option<&'a str> {
+        let start = item.key_start as usize;
+        let key = self.data.get(start..start + item.key_size as usize)?;
+        std::str::from_utf8(key).ok()
+    }
+
+    fn item_value(&self, item: &RawItem) -> Option<&'a [u8]> {
+        self.data
+            .get(item.value_start as usize..item.value_end as usize)
+    }
+
+    // Checks that the full key of `item`, following its parents, is `key`.
+    fn check_key(&self, item: &RawItem, key: &str) -> bool {
+        let mut item_key = match self.item_key(item) {
+            Some(item_key) => item_key,
+            None => return false,
+        };
+        let mut key = key;
+        let mut parent = item.parent;
+        // Bounded to not loop forever on corrupt files.
+        for _ in 0..=self.n_items() {
+            key = match key.strip_suffix(item_key) {
+                Some(key) => key,
+                None => return false,
+            };
+            if parent == NO_PARENT {
+                return key.is_empty();
+            }
+            match self
+                .raw_item(parent as usize)
+                .and_then(|p| Some((self.item_key(&p)?, p.parent)))
+            {
+                Some((k, p)) => {
+                    item_key = k;
+                    parent = p;
+                }
+                None => return false,
+            }
+        }
+        false
+    }
+
+    fn lookup(&self, key: &str) -> Option<RawItem> {
+        let n_buckets = self.buckets.len() / 4;
+        if n_buckets == 0 {
+            return None;
+        }
+        let hash = hash(key);
+        let bucket = hash as usize % n_buckets;
+        let start = read_u32(self.buckets, bucket * 4)? as usize;
+        let end = read_u32(self.buckets, (bucket + 1) * 4)
+            .map(|end| end as usize)
+            .filter(|end| *end <= self.n_items())
+            .unwrap_or_else(|| self.n_items());
+
+        (start..end)
+            .filter_map(|i| self.raw_item(i))
+            .find(|item| item.hash == hash && self.check_key(item, key))
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// All keys in this table, including the ones of lists.
+    pub fn keys(&self) -> Vec<String> {
+        (0..self.n_items())
+            .filter_map(|i| {
+                let mut item = self.raw_item(i)?;
+                let mut key = self.item_key(&item)?.to_owned();
+                for _ in 0..=self.n_items() {
+                    if item.parent == NO_PARENT {
+                        return Some(key);
+                    }
+                    item = self.raw_item(item.parent as usize)?;
+                    key.insert_str(0, self.item_key(&item)?);
+                }
+                None
+            })
+            .collect()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The value of `key`, or `None` if it doesn't exist or isn't a value.
+    pub fn value(&self, key: &str) -> Option<SerializedVariant<'a>> {
+        let item = self.lookup(key).filter(|item| item.type_ == b'v')?;
+        let value = self.item_value(&item)?;
+        let nul = value.iter().rposition(|b| *b == 0)?;
+        Some(SerializedVariant {
+            type_string: std::str::from_utf8(&value[nul + 1..]).ok()?,
+            data: &value[..nul],
+        })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The nested table `key`, or `None` if it doesn't exist or isn't a table.
+    pub fn table(&self, key: &str) -> Option<HashTable<'a>> {
+        let item = self.lookup(key).filter(|item| item.type_ == b'H')?;
+        HashTable::new(
+            self.data,
+            item.value_start as usize,
+            item.value_end as usize,
+        )
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The keys of the children of `key`, relative to it, or `None` if it doesn't exist or isn't
+    /// a list.
+    pub fn list(&self, key: &str) -> Option<Vec<&'a str>> {
+        let item = self.lookup(key).filter(|item| item.type_ == b'L')?;
+        let value = self.item_value(&item)?;
+        value
+            .chunks_exact(4)
+            .map(|index| {
+                let index = u32::from_le_bytes(index.try_into().unwrap());
+                self.item_key(&self.raw_item(index as usize)?)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gio::glib::{self, ToVariant};
+
+    fn insert(table: &mut HashTableBuilder, key: &str, value: glib::Variant) {
+        table.insert_variant(key, value.type_().as_str(), value.data().to_vec());
+    }
+
+    fn get(table: &HashTable, key: &str) -> Option<glib::Variant> {
+        let value = table.value(key)?;
+        Some(glib::Variant::from_data_with_type(
+            value.data(),
+            glib::VariantTy::new(value.type_string()).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut nested = HashTableBuilder::new();
+        insert(&mut nested, "inner", (1u8, "two").to_variant());
+
+        let mut root = HashTableBuilder::new();
+        insert(&mut root, "string", "hello".to_variant());
+        insert(&mut root, "int", 42i64.to_variant());
+        insert(&mut root, "array", vec!["a", "b"].to_variant());
+        insert(&mut root, "int", 43i64.to_variant());
+        root.insert_table("table", nested);
+        root.set_parent("/dir/file", "/dir/");
+        root.set_parent("/dir/", "/");
+        insert(&mut root, "/dir/file", true.to_variant());
+        for i in 0..100 {
+            insert(&mut root, &format!("key{}", i), i.to_variant());
+        }
+        let data = root.to_bytes();
+
+        let file = File::new(&data).unwrap();
+        let root = file.root();
+        assert_eq!(get(&root, "string").unwrap().str(), Some("hello"));
+        assert_eq!(get(&root, "int").unwrap().get::<i64>(), Some(43));
+        assert_eq!(
+            get(&root, "array").unwrap().get::<Vec<String>>().unwrap(),
+            ["a", "b"]
+        );
+        assert_eq!(get(&root, "/dir/file").unwrap().get::<bool>(), Some(true));
+        for i in 0..100 {
+            assert_eq!(
+                get(&root, &format!("key{}", i)).unwrap().get::<i32>(),
+                Some(i)
+            );
+        }
+        assert!(get(&root, "missing").is_none());
+        assert!(get(&root, "table").is_none());
+        assert!(root.contains_key("table"));
+
+        let table = root.table("table").unwrap();
+        assert_eq!(
+            get(&table, "inner").unwrap().get::<(u8, String)>(),
+            Some((1, "two".to_owned()))
+        );
+        assert_eq!(table.keys(), ["inner"]);
+
+        assert_eq!(root.list("/"), Some(vec!["dir/"]));
+        assert_eq!(root.list("/dir/"), Some(vec!["file"]));
+        assert_eq!(root.list("string"), None);
+
+        let mut keys = root.keys();
+        keys.sort();
+        assert_eq!(keys.len(), 107);
+        assert_eq!(keys[..3], ["/", "/dir/", "/dir/file"]);
+    }
+
+    #[test]
+    fn gresource() {
+        let mut root = HashTableBuilder::new();
+        insert(
+            &mut root,
+            "/org/gtk-rs/file",
+            (5u32, 0u32, b"data\0".to_vec()).to_variant(),
+        );
+        root.set_parent("/org/gtk-rs/file", "/org/gtk-rs/");
+        root.set_parent("/org/gtk-rs/", "/org/");
+        root.set_parent("/org/", "/");
+
+        let resource = gio::Resource::from_data(&glib::Bytes::from_owned(root.to_bytes())).unwrap();
+        assert_eq!(
+            &*resource
+                .lookup_data("/org/gtk-rs/file", gio::ResourceLookupFlags::NONE)
+                .unwrap(),
+            b"data"
+        );
+        assert_eq!(
+            resource
+                .enumerate_children("/org/", gio::ResourceLookupFlags::NONE)
+                .unwrap(),
+            ["gtk-rs/"]
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(File::new(b"GVariant").unwrap_err(), Error::InvalidHeader);
+        let mut data = HashTableBuilder::new().to_bytes();
+        let root = File::new(&data).unwrap().root();
+        assert!(root.keys().is_empty());
+        assert!(root.value("a").is_none());
+
+        data[0..8].copy_from_slice(SIGNATURE_SWAPPED);
+        assert_eq!(File::new(&data).unwrap_err(), Error::BigEndian);
+        data[0..8].copy_from_slice(SIGNATURE);
+        data[20] = 0xff;
+        assert_eq!(File::new(&data).unwrap_err(), Error::InvalidTable);
+    }
+}
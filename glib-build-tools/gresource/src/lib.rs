@@ -0,0 +1,6 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+#![doc = include_str!("../README.md")]
+
+pub mod gresource;
+pub mod gvdb;
@@ -6,46 +6,76 @@ use std::env;
 use std::path::Path;
 use std::process::Command;
 
+pub use glib_gresource::{gresource, gvdb};
+
 // rustdoc-stripper-ignore-next
-/// Call to run `glib-compile-resources` to generate compiled gresources to embed
-/// in binary with [`gio::resources_register_include`]. `target` is relative to `OUT_DIR`.
+/// Compiles the resources listed in the `gresource` manifest into `target`, relative to
+/// `OUT_DIR`, to embed in the binary with [`gio::resources_register_include`]. Files are looked
+/// up in `source_dir`.
+///
+/// This does not need the `glib-compile-resources` tool. Cargo is told to rerun the build script
+/// when the manifest or any of the listed files change.
 ///
 /// ```no_run
 /// glib_build_tools::compile_resources(
 ///     "resources",
 ///     "resources/resources.gresource.xml",
 ///     "compiled.gresource",
-/// );
+/// )
+/// .unwrap();
+/// ```
+pub fn compile_resources<P: AsRef<Path>>(
+    source_dir: P,
+    gresource: &str,
+    target: &str,
+) -> Result<(), gresource::Error> {
+    let out_dir = env::var("OUT_DIR").unwrap();
+
+    let bundle = gresource::ResourceBundle::from_manifest(gresource, &[source_dir])?;
+    for dep in bundle.dependencies() {
+        println!("cargo:rerun-if-changed={}", dep.display());
+    }
+    bundle.write(Path::new(&out_dir).join(target))
+}
+
+// rustdoc-stripper-ignore-next
+/// Call to run `glib-compile-schemas` on all schemas in `schema_dir`, writing
+/// `gschemas.compiled` to `OUT_DIR`. Invalid schemas fail the build.
+///
+/// The compiled schemas can be loaded with [`gio::SettingsSchemaSource::from_directory`], or
+/// the schemas installed to `$prefix/share/glib-2.0/schemas` on installation. Typed accessors
+/// for a schema can be generated with [`gio::settings`].
+///
+/// ```no_run
+/// glib_build_tools::compile_schemas("data");
 /// ```
-pub fn compile_resources<P: AsRef<Path>>(source_dir: P, gresource: &str, target: &str) {
+pub fn compile_schemas<P: AsRef<Path>>(schema_dir: P) {
     let out_dir = env::var("OUT_DIR").unwrap();
+    let schema_dir = schema_dir.as_ref();
 
-    let status = Command::new("glib-compile-resources")
-        .arg("--sourcedir")
-        .arg(source_dir.as_ref())
-        .arg("--target")
-        .arg(&format!("{}/{}", out_dir, target))
-        .arg(gresource)
+    let status = Command::new("glib-compile-schemas")
+        .arg("--strict")
+        .arg("--targetdir")
+        .arg(&out_dir)
+        .arg(schema_dir)
         .status()
         .unwrap();
 
     assert!(
         status.success(),
-        "glib-compile-resources failed with exit status {}",
+        "glib-compile-schemas failed with exit status {}",
         status
     );
 
-    println!("cargo:rerun-if-changed={}", gresource);
-    let output = Command::new("glib-compile-resources")
-        .arg("--sourcedir")
-        .arg(source_dir.as_ref())
-        .arg("--generate-dependencies")
-        .arg(gresource)
-        .output()
-        .unwrap()
-        .stdout;
-    let output = String::from_utf8(output).unwrap();
-    for dep in output.split_whitespace() {
-        println!("cargo:rerun-if-changed={}", dep);
+    println!("cargo:rerun-if-changed={}", schema_dir.display());
+    for entry in std::fs::read_dir(schema_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy();
+        if name.ends_with(".gschema.xml")
+            || name.ends_with(".enums.xml")
+            || name.ends_with(".gschema.override")
+        {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
     }
 }
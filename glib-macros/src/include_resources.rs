@@ -0,0 +1,77 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::path::PathBuf;
+
+use glib_gresource::gresource::ResourceBundle;
+use proc_macro2::{Literal, TokenStream};
+use proc_macro_error::abort;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Ident, LitStr, Token,
+};
+
+use crate::utils::gio_crate_ident;
+
+pub struct IncludeResources {
+    dir: LitStr,
+    prefix: Option<LitStr>,
+}
+
+impl Parse for IncludeResources {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dir = input.parse()?;
+        let mut prefix = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name = input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+            if name == "prefix" {
+                prefix = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "unknown argument, expected `prefix`",
+                ));
+            }
+        }
+        Ok(Self { dir, prefix })
+    }
+}
+
+pub fn impl_include_resources(input: IncludeResources) -> TokenStream {
+    let gio = gio_crate_ident();
+
+    let dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default())
+        .join(input.dir.value());
+    let prefix = input.prefix.as_ref().map(LitStr::value).unwrap_or_default();
+    if !prefix.is_empty() && !prefix.starts_with('/') {
+        abort!(input.prefix, "the prefix must be an absolute path");
+    }
+
+    let mut bundle = ResourceBundle::new();
+    if let Err(err) = bundle.add_dir(&dir, &prefix) {
+        abort!(input.dir, "failed to include resources: {}", err);
+    }
+
+    // Makes the compiler rebuild when one of the files changes.
+    let dependencies = bundle.dependencies().iter().map(|path| {
+        let path = path.to_string_lossy();
+        quote! { const _: &[u8] = ::std::include_bytes!(#path); }
+    });
+    let data = Literal::byte_string(&bundle.to_bytes());
+
+    quote! {
+        {
+            #(#dependencies)*
+            static REGISTER: ::std::sync::Once = ::std::sync::Once::new();
+            REGISTER.call_once(|| {
+                #gio::resources_register_include_impl(#data)
+                    .expect("included resources are invalid");
+            });
+        }
+    }
+}
@@ -7,8 +7,13 @@ mod downgrade_derive;
 mod enum_derive;
 mod error_domain_derive;
 mod flags_attribute;
+#[cfg(feature = "include_resources")]
+mod include_resources;
 mod object_interface_attribute;
 mod object_subclass_attribute;
+mod options_derive;
+#[cfg(feature = "settings")]
+mod settings_attribute;
 mod shared_boxed_derive;
 mod variant_derive;
 
@@ -820,6 +825,140 @@ pub fn variant_derive(input: TokenStream) -> TokenStream {
     variant_derive::impl_variant(input)
 }
 
+/// Derive macro for turning a struct into a set of command line options, implementing the
+/// [`glib::Options`] trait.
+///
+/// Every named field becomes an option whose argument type is derived from the field type,
+/// which must implement [`glib::OptionArgType`]. The long name defaults to the field name with
+/// underscores replaced by dashes and the description to the field's doc comment. Options that
+/// are not given on the command line are left at their default value, or `None` for `Option`
+/// fields.
+///
+/// The following attributes are supported on fields:
+/// - `#[option(long = "name")]` sets the long name.
+/// - `#[option(short = 'n')]` sets the short name.
+/// - `#[option(description = "...")]` sets the description shown by `--help`.
+/// - `#[option(arg_description = "NAME")]` sets the placeholder for the option's value.
+/// - `#[option(hidden)]` hides the option from `--help`.
+/// - `#[option(reverse)]` turns a `bool` field off when the option is given.
+///
+/// # Example
+///
+/// ```
+/// use glib::prelude::*;
+/// use std::path::PathBuf;
+///
+/// #[derive(Debug, glib::Options)]
+/// struct Args {
+///     /// Print more output
+///     #[option(short = 'v')]
+///     verbose: bool,
+///     #[option(arg_description = "N")]
+///     jobs: Option<i32>,
+///     include: Vec<PathBuf>,
+/// }
+///
+/// let mut args = vec!["prog".into(), "-v".into(), "--include".into(), "src".into()];
+/// let parsed = Args::parse(&mut args).unwrap();
+/// assert!(parsed.verbose);
+/// assert_eq!(parsed.jobs, None);
+/// assert_eq!(parsed.include, [PathBuf::from("src")]);
+/// ```
+///
+/// [`glib::Options`]: ../glib/trait.Options.html
+/// [`glib::OptionArgType`]: ../glib/trait.OptionArgType.html
+#[proc_macro_derive(Options, attributes(option))]
+#[proc_macro_error]
+pub fn options_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let gen = options_derive::impl_options(&input);
+    gen.into()
+}
+
+/// Attribute macro for generating a typed wrapper around `gio::Settings` from a GSettings
+/// schema file.
+///
+/// The schema is read at compile time from `file`, relative to the crate's `Cargo.toml`. If the
+/// file defines several schemas, `id` selects one of them. Keys, their types and their enums
+/// are checked during the build, so code using a key that was removed or changed its type fails
+/// to compile. The schema itself still needs to be compiled and installed, for example with
+/// `glib_build_tools::compile_schemas`.
+///
+/// The unit struct is turned into a newtype around `gio::Settings` that dereferences to it and
+/// gets the following methods for every key, with dashes in key names replaced by underscores:
+/// - `key_name()` returning the value.
+/// - `set_key_name(value)` which checks `<range>` and `<choices>` before writing the value.
+/// - `bind_key_name(object, property)` returning a `gio::BindingBuilder`.
+/// - `connect_key_name_changed(f)`.
+///
+/// Basic types, string arrays and byte strings map to the corresponding Rust types, any other
+/// type is passed as `glib::Variant`. For keys using an `enum` or `flags` defined in the same
+/// file a Rust enum, respectively `bitflags` type, named after the last component of its id is
+/// generated next to the struct.
+///
+/// A schema with a `path` gets a `new()` constructor, relocatable schemas get `with_path()`
+/// instead. `from_settings()` wraps an existing `gio::Settings` object, for example one using a
+/// memory backend.
+///
+/// This requires the `settings` feature.
+///
+/// # Example
+///
+/// ```ignore
+/// #[gio::settings(file = "data/org.example.App.gschema.xml")]
+/// pub struct AppSettings;
+///
+/// let settings = AppSettings::new();
+/// settings.set_window_width(800)?;
+/// settings.bind_dark_mode(&switch, "active").build();
+/// settings.connect_mode_changed(|settings| println!("{:?}", settings.mode()));
+/// ```
+#[cfg(feature = "settings")]
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn settings(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as syn::AttributeArgs);
+    let input = parse_macro_input!(item as syn::ItemStruct);
+    settings_attribute::impl_settings(args, input).into()
+}
+
+/// Macro for embedding a directory as GResource bundle, registering it the first time the
+/// expression is evaluated.
+///
+/// This replaces a build script calling `glib_build_tools::compile_resources` and
+/// `gio::resources_register_include!` for most applications. All files below the directory,
+/// relative to the crate's `Cargo.toml`, are included with their path relative to it appended
+/// to `prefix`, which defaults to `/`. Hidden files are skipped.
+///
+/// Text formats like `.ui`, `.css` or `.json` are compressed and `.ui`, `.xml` and `.json`
+/// files have blanks stripped, other files are stored unmodified. Use a `.gresource.xml`
+/// manifest with `glib_build_tools::compile_resources` for more control.
+///
+/// Changes to the included files cause a rebuild, but newly added files are only picked up
+/// once the crate is rebuilt for another reason.
+///
+/// This requires the `include_resources` feature.
+///
+/// # Example
+///
+/// ```ignore
+/// fn main() {
+///     gio::include_resources!("resources/", prefix = "/org/example/app");
+///
+///     let data = gio::resources_lookup_data(
+///         "/org/example/app/style.css",
+///         gio::ResourceLookupFlags::NONE,
+///     );
+/// }
+/// ```
+#[cfg(feature = "include_resources")]
+#[proc_macro]
+#[proc_macro_error]
+pub fn include_resources(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as include_resources::IncludeResources);
+    include_resources::impl_include_resources(input).into()
+}
+
 #[proc_macro]
 pub fn cstr_bytes(item: TokenStream) -> TokenStream {
     syn::parse::Parser::parse2(
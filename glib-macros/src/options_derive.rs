@@ -0,0 +1,180 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use proc_macro2::TokenStream;
+use proc_macro_error::abort;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+use crate::utils::crate_ident_new;
+
+#[derive(Default)]
+struct OptionAttrs {
+    long: Option<String>,
+    short: Option<char>,
+    description: Option<String>,
+    arg_description: Option<String>,
+    hidden: bool,
+    reverse: Option<syn::Path>,
+}
+
+fn parse_option_attrs(field: &syn::Field) -> OptionAttrs {
+    let mut attrs = OptionAttrs::default();
+
+    for attr in field.attrs.iter().filter(|a| a.path.is_ident("option")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            Ok(meta) => abort!(meta, "expected #[option(...)]"),
+            Err(e) => abort!(attr, "{}", e),
+        };
+        for nested in list.nested {
+            let meta = match nested {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(lit) => abort!(lit, "unexpected literal"),
+            };
+            let name = meta
+                .path()
+                .get_ident()
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            match (name.as_str(), &meta) {
+                ("hidden", Meta::Path(_)) => attrs.hidden = true,
+                ("reverse", Meta::Path(path)) => attrs.reverse = Some(path.clone()),
+                ("short", Meta::NameValue(nv)) => match &nv.lit {
+                    Lit::Char(c) => attrs.short = Some(c.value()),
+                    lit => abort!(lit, "expected a character literal"),
+                },
+                ("long" | "description" | "arg_description", Meta::NameValue(nv)) => {
+                    let value = match &nv.lit {
+                        Lit::Str(s) => s.value(),
+                        lit => abort!(lit, "expected a string literal"),
+                    };
+                    match name.as_str() {
+                        "long" => attrs.long = Some(value),
+                        "description" => attrs.description = Some(value),
+                        _ => attrs.arg_description = Some(value),
+                    }
+                }
+                _ => abort!(
+                    meta,
+                    "unknown option attribute, expected one of `long`, `short`, `description`, \
+                     `arg_description`, `hidden` or `reverse`"
+                ),
+            }
+        }
+    }
+
+    if attrs.description.is_none() {
+        let doc = field
+            .attrs
+            .iter()
+            .filter(|a| a.path.is_ident("doc"))
+            .filter_map(|a| match a.parse_meta() {
+                Ok(Meta::NameValue(nv)) => match nv.lit {
+                    Lit::Str(s) => Some(s.value().trim().to_owned()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !doc.is_empty() {
+            attrs.description = Some(doc);
+        }
+    }
+
+    attrs
+}
+
+pub fn impl_options(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => abort!(input, "#[derive(glib::Options)] requires named fields"),
+        },
+        _ => abort!(input, "#[derive(glib::Options)] only supports structs"),
+    };
+    if !input.generics.params.is_empty() {
+        abort!(
+            input.generics,
+            "#[derive(glib::Options)] does not support generics"
+        );
+    }
+
+    let crate_ident = crate_ident_new();
+
+    let mut entries = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let attrs = parse_option_attrs(field);
+
+        if let Some(reverse) = &attrs.reverse {
+            let is_bool =
+                matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident("bool"));
+            if !is_bool {
+                return syn::Error::new_spanned(
+                    reverse,
+                    "`reverse` is only supported on `bool` fields",
+                )
+                .into_compile_error();
+            }
+        }
+
+        let long_name = attrs
+            .long
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").replace('_', "-"));
+        let short_name = attrs.short.map(|c| quote! { .short_name(#c) });
+        let description = attrs.description.map(|d| quote! { .description(#d) });
+        let arg_description = attrs
+            .arg_description
+            .map(|d| quote! { .arg_description(#d) });
+        let mut flags = Vec::new();
+        if attrs.hidden {
+            flags.push(quote! { #crate_ident::OptionFlags::HIDDEN });
+        }
+        if attrs.reverse.is_some() {
+            flags.push(quote! { #crate_ident::OptionFlags::REVERSE });
+        }
+        let flags = (!flags.is_empty()).then(|| quote! { .flags(#(#flags)|*) });
+
+        entries.push(quote_spanned! { ty.span() =>
+            #crate_ident::OptionEntry::builder(#long_name)
+                .arg(<#ty as #crate_ident::OptionArgType>::ARG)
+                #short_name
+                #flags
+                #description
+                #arg_description
+                .build()
+        });
+
+        // Reversed switches are on unless given on the command line.
+        let default = if attrs.reverse.is_some() {
+            quote_spanned! { ty.span() => || true }
+        } else {
+            quote_spanned! { ty.span() => <#ty as #crate_ident::OptionArgType>::default_value }
+        };
+        values.push(quote_spanned! { ty.span() =>
+            #ident: <#ty as #crate_ident::OptionArgType>::lookup_option(dict, #long_name)?
+                .unwrap_or_else(#default)
+        });
+    }
+
+    quote! {
+        impl #crate_ident::Options for #name {
+            fn option_entries() -> ::std::vec::Vec<#crate_ident::OptionEntry> {
+                ::std::vec![#(#entries),*]
+            }
+
+            fn from_variant_dict(
+                dict: &#crate_ident::VariantDict,
+            ) -> ::std::result::Result<Self, #crate_ident::Error> {
+                ::std::result::Result::Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,583 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use heck::{ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use proc_macro_error::abort_call_site;
+use quick_xml::events::{BytesStart, Event};
+use quote::{format_ident, quote};
+use syn::{AttributeArgs, ItemStruct, Lit, Meta, NestedMeta};
+
+use crate::utils::gio_crate_ident;
+
+#[derive(Default)]
+struct Key {
+    name: String,
+    type_: Option<String>,
+    enum_: Option<String>,
+    flags: Option<String>,
+    summary: Option<String>,
+    description: Option<String>,
+    range: Option<(String, String)>,
+    choices: Vec<String>,
+}
+
+#[derive(Default)]
+struct Schema {
+    id: String,
+    path: Option<String>,
+    keys: Vec<Key>,
+}
+
+// Both `<enum>` and `<flags>`.
+struct EnumDef {
+    is_flags: bool,
+    values: Vec<(String, i64)>,
+}
+
+#[derive(Default)]
+struct SchemaList {
+    schemas: Vec<Schema>,
+    enums: HashMap<String, EnumDef>,
+}
+
+fn attr(e: &BytesStart, name: &str) -> Result<Option<String>, String> {
+    for a in e.attributes() {
+        let a = a.map_err(|e| e.to_string())?;
+        if a.key.as_ref() == name.as_bytes() {
+            return Ok(Some(
+                a.unescape_value().map_err(|e| e.to_string())?.into_owned(),
+            ));
+        }
+    }
+    Ok(None)
+}
+
+fn required_attr(e: &BytesStart, name: &str) -> Result<String, String> {
+    attr(e, name)?.ok_or_else(|| {
+        format!(
+            "<{}> is missing the `{}` attribute",
+            String::from_utf8_lossy(e.name().as_ref()),
+            name
+        )
+    })
+}
+
+fn parse_schema_list(xml: &str) -> Result<SchemaList, String> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut list = SchemaList::default();
+    let mut schema: Option<Schema> = None;
+    let mut key: Option<Key> = None;
+    let mut enum_: Option<(String, EnumDef)> = None;
+    let mut text_target: Option<&'static str> = None;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("at position {}: {}", reader.buffer_position(), e))?;
+        let (e, is_empty) = match event {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(t) => {
+                if let (Some(target), Some(key)) = (text_target, key.as_mut()) {
+                    let text = t.unescape().map_err(|e| e.to_string())?;
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    match target {
+                        "summary" => key.summary = Some(text),
+                        _ => key.description = Some(text),
+                    }
+                }
+                continue;
+            }
+            Event::End(e) => {
+                match e.name().as_ref() {
+                    b"schema" => list.schemas.extend(schema.take()),
+                    b"key" => {
+                        if let (Some(schema), Some(key)) = (schema.as_mut(), key.take()) {
+                            schema.keys.push(key);
+                        }
+                    }
+                    b"enum" | b"flags" => {
+                        if let Some((id, def)) = enum_.take() {
+                            list.enums.insert(id, def);
+                        }
+                    }
+                    b"summary" | b"description" => text_target = None,
+                    _ => (),
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+
+        match e.name().as_ref() {
+            b"schema" => {
+                let new = Schema {
+                    id: required_attr(&e, "id")?,
+                    path: attr(&e, "path")?,
+                    keys: Vec::new(),
+                };
+                if is_empty {
+                    list.schemas.push(new);
+                } else {
+                    schema = Some(new);
+                }
+            }
+            b"key" if schema.is_some() => {
+                let new = Key {
+                    name: required_attr(&e, "name")?,
+                    type_: attr(&e, "type")?,
+                    enum_: attr(&e, "enum")?,
+                    flags: attr(&e, "flags")?,
+                    ..Default::default()
+                };
+                if is_empty {
+                    schema.as_mut().unwrap().keys.push(new);
+                } else {
+                    key = Some(new);
+                }
+            }
+            b"summary" if key.is_some() => text_target = Some("summary"),
+            b"description" if key.is_some() => text_target = Some("description"),
+            b"range" if key.is_some() => {
+                let range = (required_attr(&e, "min")?, required_attr(&e, "max")?);
+                key.as_mut().unwrap().range = Some(range);
+            }
+            b"choice" if key.is_some() => {
+                let value = required_attr(&e, "value")?;
+                key.as_mut().unwrap().choices.push(value);
+            }
+            name @ (b"enum" | b"flags") => {
+                let def = EnumDef {
+                    is_flags: name == b"flags",
+                    values: Vec::new(),
+                };
+                enum_ = Some((required_attr(&e, "id")?, def));
+            }
+            b"value" if enum_.is_some() => {
+                let nick = required_attr(&e, "nick")?;
+                let value = required_attr(&e, "value")?;
+                let value = value
+                    .parse::<i64>()
+                    .map_err(|_| format!("invalid value `{}` for nick `{}`", value, nick))?;
+                enum_.as_mut().unwrap().1.values.push((nick, value));
+            }
+            _ => (),
+        }
+    }
+
+    Ok(list)
+}
+
+fn ident(name: &str) -> Ident {
+    let name = name.to_snake_case();
+    match syn::parse_str::<Ident>(&name) {
+        Ok(ident) => ident,
+        Err(_) => Ident::new_raw(&name, Span::call_site()),
+    }
+}
+
+fn type_name(id: &str) -> Ident {
+    format_ident!("{}", id.rsplit('.').next().unwrap().to_upper_camel_case())
+}
+
+// Returns the getter type, the setter argument type and, for numbers, the type name used for
+// range bounds. `None` for types which are passed as plain `glib::Variant`.
+fn basic_type(type_: &str) -> Option<(TokenStream, TokenStream, Option<&'static str>)> {
+    Some(match type_ {
+        "b" => (quote!(bool), quote!(bool), None),
+        "y" => (quote!(u8), quote!(u8), Some("u8")),
+        "n" => (quote!(i16), quote!(i16), Some("i16")),
+        "q" => (quote!(u16), quote!(u16), Some("u16")),
+        "i" => (quote!(i32), quote!(i32), Some("i32")),
+        "u" => (quote!(u32), quote!(u32), Some("u32")),
+        "x" => (quote!(i64), quote!(i64), Some("i64")),
+        "t" => (quote!(u64), quote!(u64), Some("u64")),
+        "d" => (quote!(f64), quote!(f64), Some("f64")),
+        "s" => (quote!(::std::string::String), quote!(&str), None),
+        "as" => (
+            quote!(::std::vec::Vec<::std::string::String>),
+            quote!(&[&str]),
+            None,
+        ),
+        "ay" => (quote!(::std::vec::Vec<u8>), quote!(&[u8]), None),
+        _ => return None,
+    })
+}
+
+fn range_literal(value: &str, suffix: &str, key: &str) -> Literal {
+    let invalid = || -> ! { abort_call_site!("invalid range bound `{}` for key `{}`", value, key) };
+    if suffix == "f64" {
+        Literal::f64_unsuffixed(value.parse().unwrap_or_else(|_| invalid()))
+    } else {
+        let value = value.parse::<i128>().unwrap_or_else(|_| invalid());
+        Literal::i128_unsuffixed(value)
+    }
+}
+
+fn impl_enum(id: &str, def: &EnumDef, gio: &TokenStream) -> TokenStream {
+    let name = type_name(id);
+    let doc = format!(
+        "Values of the `{}` {}.",
+        id,
+        if def.is_flags { "flags" } else { "enum" }
+    );
+
+    if def.is_flags {
+        let consts = def.values.iter().map(|(nick, value)| {
+            let const_name = format_ident!("{}", nick.to_shouty_snake_case());
+            let value = Literal::u32_unsuffixed(*value as u32);
+            quote! { const #const_name = #value; }
+        });
+        return quote! {
+            #gio::glib::bitflags::bitflags! {
+                #[doc = #doc]
+                pub struct #name: u32 {
+                    #(#consts)*
+                }
+            }
+        };
+    }
+
+    let variants = def
+        .values
+        .iter()
+        .map(|(nick, _)| format_ident!("{}", nick.to_upper_camel_case()))
+        .collect::<Vec<_>>();
+    let nicks = def.values.iter().map(|(nick, _)| nick).collect::<Vec<_>>();
+    let values = def
+        .values
+        .iter()
+        .map(|(_, value)| Literal::i32_unsuffixed(*value as i32))
+        .collect::<Vec<_>>();
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum #name {
+            #(#variants),*
+        }
+
+        impl #name {
+            pub fn nick(self) -> &'static str {
+                match self {
+                    #(Self::#variants => #nicks),*
+                }
+            }
+
+            pub fn from_nick(nick: &str) -> ::std::option::Option<Self> {
+                match nick {
+                    #(#nicks => ::std::option::Option::Some(Self::#variants),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            pub fn value(self) -> i32 {
+                match self {
+                    #(Self::#variants => #values),*
+                }
+            }
+
+            pub fn from_value(value: i32) -> ::std::option::Option<Self> {
+                match value {
+                    #(#values => ::std::option::Option::Some(Self::#variants),)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    }
+}
+
+fn impl_key(key: &Key, list: &SchemaList, gio: &TokenStream) -> TokenStream {
+    let name = &key.name;
+    let getter = ident(name);
+    let setter = format_ident!("set_{}", name.to_snake_case());
+    let bind = format_ident!("bind_{}", name.to_snake_case());
+    let connect = format_ident!("connect_{}_changed", name.to_snake_case());
+
+    let mut doc = Vec::new();
+    doc.extend(key.summary.as_ref().map(|s| quote!(#[doc = #s])));
+    if let Some(description) = &key.description {
+        if key.summary.is_some() {
+            doc.push(quote!(#[doc = ""]));
+        }
+        doc.push(quote!(#[doc = #description]));
+    }
+
+    let accessors = if let Some(id) = key.enum_.as_ref().or(key.flags.as_ref()) {
+        let def = list.enums.get(id).unwrap_or_else(|| {
+            abort_call_site!(
+                "key `{}` refers to `{}` which is not defined in the schema file",
+                name,
+                id
+            )
+        });
+        let ty = type_name(id);
+        if def.is_flags {
+            quote! {
+                #(#doc)*
+                pub fn #getter(&self) -> #ty {
+                    #ty::from_bits_truncate(#gio::prelude::SettingsExt::flags(&self.0, #name))
+                }
+
+                pub fn #setter(&self, value: #ty) -> ::std::result::Result<(), #gio::glib::BoolError> {
+                    #gio::prelude::SettingsExt::set_flags(&self.0, #name, value.bits())
+                }
+            }
+        } else {
+            quote! {
+                #(#doc)*
+                pub fn #getter(&self) -> #ty {
+                    let value = #gio::prelude::SettingsExt::enum_(&self.0, #name);
+                    #ty::from_value(value).unwrap_or_else(|| {
+                        panic!("Invalid value {} for key '{}', installed schema is outdated", value, #name)
+                    })
+                }
+
+                pub fn #setter(&self, value: #ty) -> ::std::result::Result<(), #gio::glib::BoolError> {
+                    #gio::prelude::SettingsExt::set_enum(&self.0, #name, value.value())
+                }
+            }
+        }
+    } else {
+        let type_ = key.type_.as_deref().unwrap_or_else(|| {
+            abort_call_site!("key `{}` needs a `type`, `enum` or `flags` attribute", name)
+        });
+        let basic = basic_type(type_);
+        let suffix = basic.as_ref().and_then(|(_, _, suffix)| *suffix);
+
+        let mut checks = Vec::new();
+        if let (Some((min, max)), Some(suffix)) = (&key.range, suffix) {
+            let min = range_literal(min, suffix, name);
+            let max = range_literal(max, suffix, name);
+            checks.push(quote! {
+                if !(#min..=#max).contains(&value) {
+                    return ::std::result::Result::Err(#gio::glib::bool_error!(
+                        "Value {} for key '{}' is outside of the range {}..={}",
+                        value, #name, #min, #max
+                    ));
+                }
+            });
+        }
+        if !key.choices.is_empty() && type_ == "s" {
+            let choices = &key.choices;
+            checks.push(quote! {
+                if ![#(#choices),*].contains(&value) {
+                    return ::std::result::Result::Err(#gio::glib::bool_error!(
+                        "Value '{}' for key '{}' is not one of the valid choices",
+                        value, #name
+                    ));
+                }
+            });
+        }
+
+        let (get_ty, set_ty, get, set) = match basic {
+            Some((get_ty, set_ty, _)) => (
+                get_ty.clone(),
+                set_ty,
+                quote!(#gio::prelude::SettingsExtManual::get::<#get_ty>(&self.0, #name)),
+                quote!(#gio::prelude::SettingsExtManual::set(&self.0, #name, &value)),
+            ),
+            None => (
+                quote!(#gio::glib::Variant),
+                quote!(&#gio::glib::Variant),
+                quote!(#gio::prelude::SettingsExt::value(&self.0, #name)),
+                quote!(#gio::prelude::SettingsExt::set_value(&self.0, #name, value)),
+            ),
+        };
+
+        quote! {
+            #(#doc)*
+            pub fn #getter(&self) -> #get_ty {
+                #get
+            }
+
+            pub fn #setter(&self, value: #set_ty) -> ::std::result::Result<(), #gio::glib::BoolError> {
+                #(#checks)*
+                #set
+            }
+        }
+    };
+
+    quote! {
+        #accessors
+
+        pub fn #bind<'a, P: #gio::glib::IsA<#gio::glib::Object>>(
+            &'a self,
+            object: &'a P,
+            property: &'a str,
+        ) -> #gio::BindingBuilder<'a> {
+            #gio::prelude::SettingsExtManual::bind(&self.0, #name, object, property)
+        }
+
+        pub fn #connect<F: Fn(&Self) + 'static>(&self, f: F) -> #gio::glib::SignalHandlerId {
+            #gio::prelude::SettingsExt::connect_changed(&self.0, ::std::option::Option::Some(#name), move |settings, _| {
+                f(&Self(::std::clone::Clone::clone(settings)))
+            })
+        }
+    }
+}
+
+// Aborts if two keys, or a key and one of the methods every settings struct has, would generate
+// methods with the same name.
+fn check_method_names(schema: &Schema) {
+    let constructor = if schema.path.is_some() {
+        "new"
+    } else {
+        "with_path"
+    };
+    let mut methods = [constructor, "from_settings", "settings"]
+        .into_iter()
+        .map(|method| (method.to_owned(), None))
+        .collect::<HashMap<String, Option<&str>>>();
+
+    for key in &schema.keys {
+        let name = key.name.to_snake_case();
+        for method in [
+            name.clone(),
+            format!("set_{}", name),
+            format!("bind_{}", name),
+            format!("connect_{}_changed", name),
+        ] {
+            match methods.get(&method) {
+                Some(Some(other)) => abort_call_site!(
+                    "keys `{}` and `{}` both generate the method `{}`",
+                    other,
+                    key.name,
+                    method
+                ),
+                Some(None) => abort_call_site!(
+                    "key `{}` generates the method `{}` which clashes with the generated `{}()`",
+                    key.name,
+                    method,
+                    method
+                ),
+                None => {
+                    methods.insert(method, Some(&key.name));
+                }
+            }
+        }
+    }
+}
+
+pub fn impl_settings(args: AttributeArgs, input: ItemStruct) -> TokenStream {
+    let mut file = None;
+    let mut id = None;
+    for arg in &args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(nv)) => match (&nv.lit, nv.path.get_ident()) {
+                (Lit::Str(s), Some(name)) if name == "file" => file = Some(s.value()),
+                (Lit::Str(s), Some(name)) if name == "id" => id = Some(s.value()),
+                _ => abort_call_site!("unknown argument, expected `file` or `id`"),
+            },
+            _ => abort_call_site!("unknown argument, expected `file` or `id`"),
+        }
+    }
+    let file = file.unwrap_or_else(|| abort_call_site!("#[settings] requires `file = \"path\"`"));
+    if !matches!(input.fields, syn::Fields::Unit) {
+        abort_call_site!("#[settings] must be applied to a unit struct");
+    }
+
+    let path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(&file);
+    let xml = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| abort_call_site!("failed to read `{}`: {}", path.display(), e));
+    let list = parse_schema_list(&xml)
+        .unwrap_or_else(|e| abort_call_site!("failed to parse `{}`: {}", path.display(), e));
+
+    let schema = match &id {
+        Some(id) => list.schemas.iter().find(|s| &s.id == id),
+        None if list.schemas.len() == 1 => list.schemas.first(),
+        None => abort_call_site!(
+            "`{}` contains several schemas, select one with `id = \"...\"`",
+            file
+        ),
+    }
+    .unwrap_or_else(|| {
+        abort_call_site!(
+            "schema `{}` not found in `{}`",
+            id.as_deref().unwrap_or_default(),
+            file
+        )
+    });
+
+    let gio = gio_crate_ident();
+    let ItemStruct {
+        attrs,
+        vis,
+        ident: name,
+        ..
+    } = input;
+    let schema_id = &schema.id;
+    let path_str = path.to_string_lossy();
+
+    let mut enum_ids = schema
+        .keys
+        .iter()
+        .filter_map(|k| k.enum_.as_ref().or(k.flags.as_ref()))
+        .collect::<Vec<_>>();
+    enum_ids.sort();
+    enum_ids.dedup();
+    let enums = enum_ids
+        .iter()
+        .filter_map(|id| list.enums.get(*id).map(|def| impl_enum(id, def, &gio)));
+    check_method_names(schema);
+    let keys = schema.keys.iter().map(|key| impl_key(key, &list, &gio));
+
+    let new = if schema.path.is_some() {
+        quote! {
+            pub fn new() -> Self {
+                Self(#gio::Settings::new(Self::SCHEMA_ID))
+            }
+        }
+    } else {
+        quote! {
+            pub fn with_path(path: &str) -> Self {
+                Self(#gio::Settings::with_path(Self::SCHEMA_ID, path))
+            }
+        }
+    };
+
+    quote! {
+        // Rebuild when the schema changes.
+        const _: &str = include_str!(#path_str);
+
+        #(#attrs)*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        #vis struct #name(#gio::Settings);
+
+        #(#enums)*
+
+        impl #name {
+            pub const SCHEMA_ID: &'static str = #schema_id;
+
+            #new
+
+            #[doc = "Wraps existing settings, for example ones created with a custom backend or schema source."]
+            #[doc = ""]
+            #[doc = "Panics if `settings` use a different schema."]
+            pub fn from_settings(settings: #gio::Settings) -> Self {
+                let id = #gio::prelude::SettingsExt::schema_id(&settings);
+                assert_eq!(id.as_deref(), ::std::option::Option::Some(Self::SCHEMA_ID));
+                Self(settings)
+            }
+
+            pub fn settings(&self) -> &#gio::Settings {
+                &self.0
+            }
+
+            #(#keys)*
+        }
+
+        impl ::std::ops::Deref for #name {
+            type Target = #gio::Settings;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    }
+}
@@ -138,6 +138,16 @@ pub fn crate_ident_new() -> TokenStream {
     })
 }
 
+#[cfg(any(feature = "include_resources", feature = "settings"))]
+pub fn gio_crate_ident() -> TokenStream {
+    let name = match crate_name("gio") {
+        Ok(proc_macro_crate::FoundCrate::Name(name)) => name,
+        _ => "gio".to_owned(),
+    };
+    let gio = Ident::new(&name, Span::call_site());
+    quote!(#gio)
+}
+
 // Generate i32 to enum mapping, used to implement
 // glib::translate::TryFromGlib<i32>, such as:
 //
@@ -0,0 +1,31 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::{env, process::Command};
+
+// `std::backtrace` is only stable since Rust 1.65, newer than the minimum supported version.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    if env::var_os("CARGO_FEATURE_BACKTRACE").is_none() {
+        return;
+    }
+
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .expect("Failed to run rustc");
+    let version = String::from_utf8_lossy(&output.stdout);
+    // `rustc 1.65.0 (897e37553 2022-11-02)`
+    let minor = version
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.split('.').nth(1))
+        .and_then(|minor| minor.parse::<u32>().ok());
+    if let Some(minor) = minor {
+        assert!(
+            minor >= 65,
+            "The `backtrace` feature of glib requires Rust 1.65 or newer, found {}",
+            version.trim()
+        );
+    }
+}
@@ -0,0 +1,524 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::fmt;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::ready;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::{Continue, IOCondition, MainContext, Source, SourceImpl, UnixFdTag};
+
+// Conditions that are reported by the kernel without being requested and that can't be cleared.
+const CLOSED: IOCondition = IOCondition::from_bits_truncate(
+    IOCondition::ERR.bits() | IOCondition::HUP.bits() | IOCondition::NVAL.bits(),
+);
+const READABLE: IOCondition =
+    IOCondition::from_bits_truncate(IOCondition::IN.bits() | IOCondition::PRI.bits());
+const WRITABLE: IOCondition = IOCondition::OUT;
+
+struct FdState {
+    // `None` once the file descriptor is closed on the other end and not polled anymore.
+    tag: Option<UnixFdTag>,
+    // Only conditions somebody is waiting for are polled, otherwise e.g. an idle writable file
+    // descriptor would wake up the main context all the time.
+    interest: IOCondition,
+    ready: IOCondition,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+struct FdSource {
+    state: Mutex<FdState>,
+}
+
+impl SourceImpl for FdSource {
+    fn check(&self, source: &Source) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.tag {
+            Some(ref tag) => source
+                .query_unix_fd(tag)
+                .intersects(state.interest | CLOSED),
+            None => false,
+        }
+    }
+
+    fn dispatch(&self, source: &Source) -> Continue {
+        let (read_waker, write_waker) = {
+            let mut state = self.state.lock().unwrap();
+            let revents = match state.tag {
+                Some(ref tag) => source.query_unix_fd(tag),
+                None => return Continue(true),
+            };
+            state.ready |= revents;
+
+            let mut read_waker = None;
+            let mut write_waker = None;
+            if revents.intersects(READABLE | CLOSED) {
+                state.interest.remove(READABLE);
+                read_waker = state.read_waker.take();
+            }
+            if revents.intersects(WRITABLE | CLOSED) {
+                state.interest.remove(WRITABLE);
+                write_waker = state.write_waker.take();
+            }
+
+            if revents.intersects(CLOSED) {
+                // This never changes again and would otherwise be reported in every iteration
+                source.remove_unix_fd(state.tag.take().unwrap());
+            } else {
+                source.modify_unix_fd(state.tag.as_ref().unwrap(), state.interest);
+            }
+
+            (read_waker, write_waker)
+        };
+
+        if let Some(waker) = read_waker {
+            waker.wake();
+        }
+        if let Some(waker) = write_waker {
+            waker.wake();
+        }
+
+        Continue(true)
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A file descriptor that is polled by a [`MainContext`] for readiness.
+///
+/// This allows waiting asynchronously until the file descriptor of e.g. a pipe, an eventfd or a
+/// third-party C library becomes readable or writable, from futures that run on a main context.
+/// The file descriptor is added to the main context once and only polled for the conditions that
+/// are currently waited for.
+///
+/// The file descriptor must be in non-blocking mode. Readiness is only a hint: after an I/O
+/// operation fails with [`io::ErrorKind::WouldBlock`] the readiness has to be cleared with
+/// [`AsyncFdReadyGuard::clear_ready`], or the operation has to be done with
+/// [`AsyncFdReadyGuard::try_io`] which does that automatically.
+///
+/// [`AsyncRead`] and [`AsyncWrite`] are implemented by reading from and writing to the file
+/// descriptor directly.
+///
+/// ```
+/// # #[cfg(unix)]
+/// # {
+/// use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+/// use std::os::unix::io::FromRawFd;
+///
+/// let mut fds = [0; 2];
+/// unsafe {
+///     assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+///     for fd in fds {
+///         libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK);
+///     }
+/// }
+/// let [read_fd, write_fd] = fds;
+///
+/// let c = glib::MainContext::new();
+/// c.block_on(async {
+///     let mut reader = glib::AsyncFd::new(unsafe { std::fs::File::from_raw_fd(read_fd) });
+///     let mut writer = glib::AsyncFd::new(unsafe { std::fs::File::from_raw_fd(write_fd) });
+///
+///     writer.write_all(b"hello").await.unwrap();
+///     drop(writer);
+///
+///     let mut buf = String::new();
+///     reader.read_to_string(&mut buf).await.unwrap();
+///     assert_eq!(buf, "hello");
+/// });
+/// # }
+/// ```
+pub struct AsyncFd<T: AsRawFd> {
+    inner: Option<T>,
+    source: Source,
+}
+
+impl<T: AsRawFd> AsyncFd<T> {
+    // rustdoc-stripper-ignore-next
+    /// Polls the file descriptor of `inner` on the thread default main context.
+    ///
+    /// This is the main context futures are polled from when they are spawned on a main context
+    /// or run with [`MainContext::block_on`].
+    pub fn new(inner: T) -> Self {
+        Self::with_context(inner, &MainContext::ref_thread_default())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Polls the file descriptor of `inner` on `context`.
+    #[doc(alias = "g_source_add_unix_fd")]
+    pub fn with_context(inner: T, context: &MainContext) -> Self {
+        let source = Source::new(
+            FdSource {
+                state: Mutex::new(FdState {
+                    tag: None,
+                    interest: IOCondition::empty(),
+                    ready: IOCondition::empty(),
+                    read_waker: None,
+                    write_waker: None,
+                }),
+            },
+            Some("AsyncFd"),
+            crate::PRIORITY_DEFAULT,
+        );
+        let tag = source.add_unix_fd(inner.as_raw_fd(), IOCondition::empty());
+        source.imp::<FdSource>().unwrap().state.lock().unwrap().tag = Some(tag);
+        source.attach(Some(context));
+
+        Self {
+            inner: Some(inner),
+            source,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns a shared reference to the wrapped value.
+    pub fn get_ref(&self) -> &T {
+        self.inner.as_ref().unwrap()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns a mutable reference to the wrapped value.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.as_mut().unwrap()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Stops polling the file descriptor and returns the wrapped value.
+    pub fn into_inner(mut self) -> T {
+        self.source.destroy();
+        self.inner.take().unwrap()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits until the file descriptor is readable.
+    ///
+    /// This also completes once the other end of the file descriptor was closed or an error
+    /// occurred, which is then reported by the next read.
+    pub async fn readable(&self) -> AsyncFdReadyGuard<'_, T> {
+        futures_util::future::poll_fn(|cx| self.poll_read_ready(cx)).await
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits until the file descriptor is writable.
+    ///
+    /// This also completes once the other end of the file descriptor was closed or an error
+    /// occurred, which is then reported by the next write.
+    pub async fn writable(&self) -> AsyncFdReadyGuard<'_, T> {
+        futures_util::future::poll_fn(|cx| self.poll_write_ready(cx)).await
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Polls for read readiness.
+    ///
+    /// Only the waker of the most recent call is woken up once the file descriptor is readable.
+    pub fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<AsyncFdReadyGuard<'_, T>> {
+        self.poll_ready(cx, READABLE)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Polls for write readiness.
+    ///
+    /// Only the waker of the most recent call is woken up once the file descriptor is writable.
+    pub fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<AsyncFdReadyGuard<'_, T>> {
+        self.poll_ready(cx, WRITABLE)
+    }
+
+    fn poll_ready(
+        &self,
+        cx: &mut Context<'_>,
+        condition: IOCondition,
+    ) -> Poll<AsyncFdReadyGuard<'_, T>> {
+        let mut state = self.imp().state.lock().unwrap();
+        if state.ready.intersects(condition | CLOSED) {
+            return Poll::Ready(AsyncFdReadyGuard {
+                async_fd: self,
+                condition,
+            });
+        }
+
+        let waker = if condition == READABLE {
+            &mut state.read_waker
+        } else {
+            &mut state.write_waker
+        };
+        match waker {
+            Some(waker) if waker.will_wake(cx.waker()) => (),
+            _ => *waker = Some(cx.waker().clone()),
+        }
+
+        if !state.interest.contains(condition) {
+            state.interest |= condition;
+            if let Some(ref tag) = state.tag {
+                self.source.modify_unix_fd(tag, state.interest);
+            }
+        }
+
+        Poll::Pending
+    }
+
+    fn imp(&self) -> &FdSource {
+        self.source.imp::<FdSource>().unwrap()
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for AsyncFd<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.get_ref().as_raw_fd()
+    }
+}
+
+impl<T: AsRawFd> Drop for AsyncFd<T> {
+    fn drop(&mut self) {
+        // The file descriptor must not be polled anymore once it is closed
+        self.source.destroy();
+    }
+}
+
+impl<T: AsRawFd + fmt::Debug> fmt::Debug for AsyncFd<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncFd")
+            .field("inner", self.get_ref())
+            .field("ready", &self.imp().state.lock().unwrap().ready)
+            .finish()
+    }
+}
+
+impl<T: AsRawFd> AsyncRead for AsyncFd<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.poll_read_ready(cx));
+            if let Some(res) = guard.try_io(|fd| {
+                let n =
+                    unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len()) };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            }) {
+                return Poll::Ready(res);
+            }
+        }
+    }
+}
+
+impl<T: AsRawFd> AsyncWrite for AsyncFd<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = ready!(self.poll_write_ready(cx));
+            if let Some(res) = guard.try_io(|fd| {
+                let n = unsafe { libc::write(fd.as_raw_fd(), buf.as_ptr() as *const _, buf.len()) };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as usize)
+                }
+            }) {
+                return Poll::Ready(res);
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Readiness of an [`AsyncFd`] for reading or writing.
+///
+/// The readiness stays set until it is cleared, so dropping the guard without clearing it makes
+/// the next wait complete immediately.
+pub struct AsyncFdReadyGuard<'a, T: AsRawFd> {
+    async_fd: &'a AsyncFd<T>,
+    condition: IOCondition,
+}
+
+impl<'a, T: AsRawFd> AsyncFdReadyGuard<'a, T> {
+    // rustdoc-stripper-ignore-next
+    /// Returns the [`AsyncFd`] this guard belongs to.
+    pub fn get_ref(&self) -> &'a AsyncFd<T> {
+        self.async_fd
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The conditions that were reported for the file descriptor.
+    pub fn ready(&self) -> IOCondition {
+        self.async_fd.imp().state.lock().unwrap().ready & (self.condition | CLOSED)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Clears the readiness, so the next wait completes only once the file descriptor is ready
+    /// again.
+    ///
+    /// Errors and the other end being closed are never cleared.
+    pub fn clear_ready(&mut self) {
+        self.async_fd
+            .imp()
+            .state
+            .lock()
+            .unwrap()
+            .ready
+            .remove(self.condition);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Runs the I/O operation `f` and clears the readiness if it fails with
+    /// [`io::ErrorKind::WouldBlock`].
+    ///
+    /// Returns `None` in that case, otherwise the result of `f`. Interrupted operations are
+    /// retried.
+    pub fn try_io<R>(
+        &mut self,
+        mut f: impl FnMut(&'a AsyncFd<T>) -> io::Result<R>,
+    ) -> Option<io::Result<R>> {
+        loop {
+            match f(self.async_fd) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.clear_ready();
+                    return None;
+                }
+                res => return Some(res),
+            }
+        }
+    }
+}
+
+impl<'a, T: AsRawFd> fmt::Debug for AsyncFdReadyGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncFdReadyGuard")
+            .field("ready", &self.ready())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+    use std::fs::File;
+    use std::os::unix::io::FromRawFd;
+    use std::time::Duration;
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(libc::pipe(fds.as_mut_ptr()), 0);
+            for fd in fds {
+                assert_eq!(libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK), 0);
+            }
+            (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))
+        }
+    }
+
+    #[test]
+    fn test_readable() {
+        let c = MainContext::new();
+        let (reader, mut writer) = pipe();
+
+        c.block_on(async {
+            let reader = AsyncFd::new(reader);
+
+            let res =
+                crate::future_with_timeout(Duration::from_millis(10), reader.readable()).await;
+            assert!(res.is_err());
+
+            std::io::Write::write_all(&mut writer, b"x").unwrap();
+            let mut guard = reader.readable().await;
+            assert!(guard.ready().contains(IOCondition::IN));
+
+            let mut buf = [0u8; 4];
+            let res = guard.try_io(|fd| std::io::Read::read(&mut fd.get_ref(), &mut buf));
+            assert_eq!(res.unwrap().unwrap(), 1);
+            // Still marked as ready until reading would block
+            let res = guard.try_io(|fd| std::io::Read::read(&mut fd.get_ref(), &mut buf));
+            assert!(res.is_none());
+
+            let res =
+                crate::future_with_timeout(Duration::from_millis(10), reader.readable()).await;
+            assert!(res.is_err());
+        });
+    }
+
+    #[test]
+    fn test_read_write() {
+        let c = MainContext::new();
+        let (reader, writer) = pipe();
+
+        c.block_on(async {
+            let mut reader = AsyncFd::new(reader);
+            let mut writer = AsyncFd::new(writer);
+
+            // More than fits into the pipe buffer, so writing has to wait for reading
+            let data = (0..1024 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+            let writing = c.spawn_local_task({
+                let data = data.clone();
+                async move {
+                    writer.write_all(&data).await.unwrap();
+                }
+            });
+
+            let mut received = Vec::new();
+            reader.read_to_end(&mut received).await.unwrap();
+            assert!(received == data);
+            writing.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_hangup() {
+        let c = MainContext::new();
+        let (reader, writer) = pipe();
+
+        c.block_on(async {
+            let reader = AsyncFd::new(reader);
+            drop(writer);
+
+            let mut guard = reader.readable().await;
+            assert!(guard.ready().contains(IOCondition::HUP));
+            guard.clear_ready();
+
+            // The hangup is not cleared
+            let guard = reader.readable().await;
+            assert!(guard.ready().contains(IOCondition::HUP));
+        });
+
+        // Nothing is polled anymore
+        assert!(!c.pending());
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let c = MainContext::new();
+        let (reader, _writer) = pipe();
+
+        let reader = AsyncFd::with_context(reader, &c);
+        assert!(!reader
+            .imp()
+            .state
+            .lock()
+            .unwrap()
+            .ready
+            .contains(IOCondition::IN));
+        let _reader: File = reader.into_inner();
+        assert!(!c.pending());
+    }
+}
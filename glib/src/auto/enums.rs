@@ -892,6 +892,80 @@ impl FromGlib<ffi::GOptionArg> for OptionArg {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[non_exhaustive]
+#[doc(alias = "GOptionError")]
+pub enum OptionError {
+    #[doc(alias = "G_OPTION_ERROR_UNKNOWN_OPTION")]
+    UnknownOption,
+    #[doc(alias = "G_OPTION_ERROR_BAD_VALUE")]
+    BadValue,
+    #[doc(alias = "G_OPTION_ERROR_FAILED")]
+    Failed,
+    #[doc(hidden)]
+    __Unknown(i32),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "OptionError::{}",
+            match *self {
+                Self::UnknownOption => "UnknownOption",
+                Self::BadValue => "BadValue",
+                Self::Failed => "Failed",
+                _ => "Unknown",
+            }
+        )
+    }
+}
+
+#[doc(hidden)]
+impl IntoGlib for OptionError {
+    type GlibType = ffi::GOptionError;
+
+    fn into_glib(self) -> ffi::GOptionError {
+        match self {
+            Self::UnknownOption => ffi::G_OPTION_ERROR_UNKNOWN_OPTION,
+            Self::BadValue => ffi::G_OPTION_ERROR_BAD_VALUE,
+            Self::Failed => ffi::G_OPTION_ERROR_FAILED,
+            Self::__Unknown(value) => value,
+        }
+    }
+}
+
+#[doc(hidden)]
+impl FromGlib<ffi::GOptionError> for OptionError {
+    unsafe fn from_glib(value: ffi::GOptionError) -> Self {
+        match value {
+            ffi::G_OPTION_ERROR_UNKNOWN_OPTION => Self::UnknownOption,
+            ffi::G_OPTION_ERROR_BAD_VALUE => Self::BadValue,
+            ffi::G_OPTION_ERROR_FAILED => Self::Failed,
+            value => Self::__Unknown(value),
+        }
+    }
+}
+
+impl ErrorDomain for OptionError {
+    fn domain() -> Quark {
+        unsafe { from_glib(ffi::g_option_error_quark()) }
+    }
+
+    fn code(self) -> i32 {
+        self.into_glib()
+    }
+
+    fn from(code: i32) -> Option<Self> {
+        match code {
+            ffi::G_OPTION_ERROR_UNKNOWN_OPTION => Some(Self::UnknownOption),
+            ffi::G_OPTION_ERROR_BAD_VALUE => Some(Self::BadValue),
+            ffi::G_OPTION_ERROR_FAILED => Some(Self::Failed),
+            _ => Some(Self::Failed),
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 #[non_exhaustive]
 #[doc(alias = "GSeekType")]
@@ -43,6 +43,7 @@ pub use self::enums::KeyFileError;
 pub use self::enums::LogWriterOutput;
 pub use self::enums::MarkupError;
 pub use self::enums::OptionArg;
+pub use self::enums::OptionError;
 pub use self::enums::SeekType;
 pub use self::enums::TimeType;
 pub use self::enums::UnicodeScript;
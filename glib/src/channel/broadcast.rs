@@ -0,0 +1,617 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! A multi-producer, multi-consumer channel where every value is received by every receiver.
+//!
+//! ```
+//! use futures_util::StreamExt;
+//!
+//! let c = glib::MainContext::new();
+//! let (sender, receiver) = glib::channel::broadcast::channel(4);
+//! let receiver2 = sender.subscribe();
+//!
+//! c.block_on(async move {
+//!     sender.send(1).await.unwrap();
+//!     sender.send(2).await.unwrap();
+//! });
+//!
+//! assert_eq!(c.block_on(receiver.collect::<Vec<_>>()), [1, 2]);
+//! assert_eq!(c.block_on(receiver2.collect::<Vec<_>>()), [1, 2]);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_core::{FusedFuture, FusedStream, Stream};
+use futures_sink::Sink;
+
+use super::{RecvError, SendError, TryRecvError, TrySendError, Wakers};
+
+struct Slot<T> {
+    value: T,
+    // Number of receivers that did not receive the value yet.
+    remaining: usize,
+}
+
+struct State<T> {
+    slots: VecDeque<Slot<T>>,
+    // Position of the first slot since the creation of the channel.
+    head: u64,
+    capacity: usize,
+    // Slots reserved by senders via `Sink::poll_ready()`.
+    reserved: usize,
+    senders: usize,
+    receivers: usize,
+    closed: bool,
+    recv_wakers: Wakers,
+    send_wakers: Wakers,
+}
+
+impl<T> State<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.slots.len() as u64
+    }
+
+    fn is_full(&self) -> bool {
+        self.slots.len() + self.reserved >= self.capacity
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed || self.receivers == 0
+    }
+
+    fn push(&mut self, value: T) {
+        if self.receivers > 0 {
+            self.slots.push_back(Slot {
+                value,
+                remaining: self.receivers,
+            });
+            self.recv_wakers.wake_all();
+        }
+    }
+
+    // Marks the slot at `pos` as received by one receiver.
+    fn consume(&mut self, pos: u64) {
+        let idx = (pos - self.head) as usize;
+        self.slots[idx].remaining -= 1;
+
+        let mut popped = false;
+        while self.slots.front().map_or(false, |slot| slot.remaining == 0) {
+            self.slots.pop_front();
+            self.head += 1;
+            popped = true;
+        }
+        if popped {
+            self.send_wakers.wake_all();
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.recv_wakers.wake_all();
+        self.send_wakers.wake_all();
+    }
+}
+
+struct Channel<T>(Mutex<State<T>>);
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.0.lock().unwrap()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Creates a broadcast channel that holds at most `capacity` values.
+///
+/// A value is kept in the channel until all receivers have received it, so sending waits for
+/// the slowest receiver if the channel is full. Receivers only get values that were sent after
+/// their creation.
+///
+/// # Panics
+///
+/// This panics if `capacity` is `0`.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "Channel capacity must be at least 1");
+
+    let channel = Arc::new(Channel(Mutex::new(State {
+        slots: VecDeque::new(),
+        head: 0,
+        capacity,
+        reserved: 0,
+        senders: 1,
+        receivers: 1,
+        closed: false,
+        recv_wakers: Wakers::default(),
+        send_wakers: Wakers::default(),
+    })));
+
+    (
+        Sender {
+            channel: channel.clone(),
+            reserved: false,
+            disconnected: false,
+        },
+        Receiver {
+            channel,
+            pos: 0,
+            terminated: false,
+        },
+    )
+}
+
+// rustdoc-stripper-ignore-next
+/// The sending side of a broadcast channel.
+///
+/// Closing a sender as [`Sink`] only disconnects that sender, [`close`](Self::close) closes the
+/// channel for all senders.
+pub struct Sender<T> {
+    channel: Arc<Channel<T>>,
+    reserved: bool,
+    disconnected: bool,
+}
+
+impl<T: Clone> Sender<T> {
+    // rustdoc-stripper-ignore-next
+    /// Sends `value` to all receivers, waiting for capacity if the channel is full.
+    ///
+    /// Fails if all receivers were dropped or the channel was closed.
+    pub fn send(&self, value: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sends `value` to all receivers if the channel has capacity for it.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.channel.lock();
+        if self.disconnected || state.is_closed() {
+            Err(TrySendError::Closed(value))
+        } else if state.is_full() {
+            Err(TrySendError::Full(value))
+        } else {
+            state.push(value);
+            Ok(())
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Creates a new receiver that receives all values sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.channel.lock();
+        state.receivers += 1;
+        Receiver {
+            channel: self.channel.clone(),
+            pos: state.tail(),
+            terminated: false,
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // rustdoc-stripper-ignore-next
+    /// Closes the channel.
+    ///
+    /// Values that were already sent can still be received, further values can't be sent.
+    pub fn close(&self) {
+        self.channel.lock().close();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the channel is closed or has no receivers.
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().is_closed()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.channel.lock().receivers
+    }
+
+    fn release(&mut self) {
+        if self.reserved {
+            self.reserved = false;
+            let mut state = self.channel.lock();
+            state.reserved -= 1;
+            state.send_wakers.wake_all();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.release();
+        if self.disconnected {
+            return;
+        }
+        self.disconnected = true;
+
+        let mut state = self.channel.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            state.recv_wakers.wake_all();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        if !self.disconnected {
+            self.channel.lock().senders += 1;
+        }
+        Self {
+            channel: self.channel.clone(),
+            reserved: false,
+            disconnected: self.disconnected,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("receiver_count", &self.receiver_count())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl<T: Clone> Sink<T> for Sender<T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.reserved {
+            return Poll::Ready(Ok(()));
+        }
+
+        let mut state = this.channel.lock();
+        if this.disconnected || state.is_closed() {
+            Poll::Ready(Err(SendError(())))
+        } else if state.is_full() {
+            state.send_wakers.register(cx.waker());
+            Poll::Pending
+        } else {
+            state.reserved += 1;
+            this.reserved = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let mut state = this.channel.lock();
+        if this.reserved {
+            this.reserved = false;
+            state.reserved -= 1;
+        }
+        if this.disconnected || state.is_closed() {
+            return Err(SendError(()));
+        }
+        state.push(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().disconnect();
+        Poll::Ready(Ok(()))
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Sender::send`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    value: Option<T>,
+}
+
+impl<'a, T> Unpin for SendFuture<'a, T> {}
+
+impl<'a, T: Clone> Future for SendFuture<'a, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let value = this
+            .value
+            .take()
+            .expect("SendFuture polled after completion");
+
+        let mut state = this.sender.channel.lock();
+        if this.sender.disconnected || state.is_closed() {
+            Poll::Ready(Err(SendError(value)))
+        } else if state.is_full() {
+            state.send_wakers.register(cx.waker());
+            this.value = Some(value);
+            Poll::Pending
+        } else {
+            state.push(value);
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+impl<'a, T: Clone> FusedFuture for SendFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// The receiving side of a broadcast channel.
+///
+/// Cloning a receiver creates a new receiver that receives the same values as the original one
+/// from its current position on.
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+    // Position of the next value to receive.
+    pos: u64,
+    terminated: bool,
+}
+
+impl<T: Clone> Receiver<T> {
+    // rustdoc-stripper-ignore-next
+    /// Receives the next value, waiting until one is available.
+    ///
+    /// Fails once all senders were dropped or the channel was closed, and no values are left.
+    pub fn recv(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture {
+            receiver: self,
+            terminated: false,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Receives the next value if one is available.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.channel.lock();
+        if self.pos < state.tail() {
+            let pos = self.pos;
+            let value = state.slots[(pos - state.head) as usize].value.clone();
+            state.consume(pos);
+            self.pos += 1;
+            Ok(value)
+        } else if state.senders == 0 || state.closed {
+            Err(TryRecvError::Closed)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        match self.try_recv() {
+            Ok(value) => Poll::Ready(Ok(value)),
+            Err(TryRecvError::Closed) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => {
+                let mut state = self.channel.lock();
+                // A value might have been sent in the meantime.
+                if self.pos < state.tail() || state.senders == 0 || state.closed {
+                    cx.waker().wake_by_ref();
+                } else {
+                    state.recv_wakers.register(cx.waker());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // rustdoc-stripper-ignore-next
+    /// The number of values that were not received by this receiver yet.
+    pub fn len(&self) -> usize {
+        (self.channel.lock().tail() - self.pos) as usize
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether all values were received by this receiver.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        let mut state = self.channel.lock();
+        state.receivers += 1;
+        let start = (self.pos - state.head) as usize;
+        for slot in state.slots.iter_mut().skip(start) {
+            slot.remaining += 1;
+        }
+
+        Self {
+            channel: self.channel.clone(),
+            pos: self.pos,
+            terminated: self.terminated,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        state.receivers -= 1;
+        for pos in self.pos..state.tail() {
+            state.consume(pos);
+        }
+        if state.receivers == 0 {
+            state.send_wakers.wake_all();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T: Clone> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if this.terminated {
+            return Poll::Ready(None);
+        }
+
+        match this.poll_recv(cx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(Some(value)),
+            Poll::Ready(Err(RecvError)) => {
+                this.terminated = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: Clone> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Receiver::recv`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvFuture<'a, T> {
+    receiver: &'a mut Receiver<T>,
+    terminated: bool,
+}
+
+impl<'a, T: Clone> Future for RecvFuture<'a, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let res = this.receiver.poll_recv(cx);
+        if res.is_ready() {
+            this.terminated = true;
+        }
+        res
+    }
+}
+
+impl<'a, T: Clone> FusedFuture for RecvFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use futures_util::{SinkExt, StreamExt};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_broadcast() {
+        let c = MainContext::new();
+        let (sender, receiver) = channel(2);
+        let receivers = [receiver.clone(), receiver, sender.subscribe()];
+        assert_eq!(sender.receiver_count(), 3);
+
+        let results = Rc::new(RefCell::new(Vec::new()));
+        for receiver in receivers {
+            let results = results.clone();
+            c.spawn_local(async move {
+                let values = receiver.collect::<Vec<_>>().await;
+                results.borrow_mut().push(values);
+            });
+        }
+
+        c.block_on(async move {
+            for i in 0..5 {
+                sender.send(i).await.unwrap();
+            }
+        });
+        while c.iteration(false) {}
+
+        assert_eq!(*results.borrow(), vec![vec![0, 1, 2, 3, 4]; 3]);
+    }
+
+    #[test]
+    fn test_slowest_receiver() {
+        let (sender, mut receiver) = channel(2);
+        let mut receiver2 = sender.subscribe();
+
+        sender.try_send(1).unwrap();
+        sender.try_send(2).unwrap();
+        assert_eq!(sender.try_send(3), Err(TrySendError::Full(3)));
+
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        // The second receiver did not receive anything yet.
+        assert_eq!(sender.try_send(3), Err(TrySendError::Full(3)));
+
+        assert_eq!(receiver2.len(), 2);
+        assert_eq!(receiver2.try_recv(), Ok(1));
+        assert!(sender.try_send(3).is_ok());
+
+        // Dropping a receiver frees its values.
+        drop(receiver2);
+        assert!(sender.try_send(4).is_ok());
+
+        // Subscribers only receive new values.
+        let mut receiver3 = sender.subscribe();
+        assert_eq!(receiver3.try_recv(), Err(TryRecvError::Empty));
+
+        assert_eq!(receiver.try_recv(), Ok(3));
+        assert_eq!(receiver.try_recv(), Ok(4));
+
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Closed));
+        assert_eq!(receiver3.try_recv(), Err(TryRecvError::Closed));
+    }
+
+    #[test]
+    fn test_no_receivers() {
+        let c = MainContext::new();
+        let (sender, receiver) = channel(1);
+        drop(receiver);
+        assert!(sender.is_closed());
+        assert_eq!(c.block_on(sender.send(1)), Err(SendError(1)));
+    }
+
+    #[test]
+    fn test_sink_close() {
+        let c = MainContext::new();
+        let (sender, mut receiver) = channel(4);
+
+        let mut closed = sender.clone();
+        c.block_on(closed.send(1)).unwrap();
+        c.block_on(SinkExt::close(&mut closed)).unwrap();
+        assert_eq!(closed.try_send(2), Err(TrySendError::Closed(2)));
+
+        // The other sender can still send
+        assert!(!sender.is_closed());
+        sender.try_send(2).unwrap();
+        drop(sender);
+
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Closed));
+    }
+}
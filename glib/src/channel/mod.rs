@@ -0,0 +1,768 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Asynchronous channels.
+//!
+//! Different to [`MainContext::channel`](crate::MainContext::channel), the receivers of these
+//! channels are consumed from async code, e.g. futures spawned with
+//! [`MainContext::spawn_local`](crate::MainContext::spawn_local), and bounded senders can wait
+//! asynchronously for capacity. Waiting tasks are woken via their [`Waker`], which for futures
+//! spawned on a [`MainContext`](crate::MainContext) directly wakes up the main context without
+//! any additional thread.
+//!
+//! * [`bounded`] and [`unbounded`] create multi-producer, multi-consumer channels where every
+//!   value is received by exactly one receiver.
+//! * [`broadcast::channel`] creates a channel where every value is received by every receiver.
+//! * [`oneshot::channel`] creates a channel for sending a single value.
+//!
+//! ```
+//! use futures_util::StreamExt;
+//!
+//! let c = glib::MainContext::new();
+//! let (sender, receiver) = glib::channel::bounded(1);
+//!
+//! c.spawn_local(async move {
+//!     for i in 0..3 {
+//!         // Waits until the receiver has taken the previous value
+//!         sender.send(i).await.unwrap();
+//!     }
+//! });
+//!
+//! let values = c.block_on(receiver.collect::<Vec<_>>());
+//! assert_eq!(values, [0, 1, 2]);
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::{FusedFuture, FusedStream, Stream};
+use futures_sink::Sink;
+
+pub mod broadcast;
+pub mod oneshot;
+
+// rustdoc-stripper-ignore-next
+/// Error returned when sending on a channel without receivers.
+///
+/// Contains the value that could not be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SendError(..)")
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("sending on a closed channel")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+// rustdoc-stripper-ignore-next
+/// Error returned by `try_send()`.
+///
+/// Contains the value that could not be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    // rustdoc-stripper-ignore-next
+    /// The channel is full.
+    Full(T),
+    // rustdoc-stripper-ignore-next
+    /// The channel has no receivers.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    // rustdoc-stripper-ignore-next
+    /// Returns the value that could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Full(value) | Self::Closed(value) => value,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("sending on a full channel"),
+            Self::Closed(_) => f.write_str("sending on a closed channel"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+// rustdoc-stripper-ignore-next
+/// Error returned when receiving from a channel without senders that has no values left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("receiving on a closed channel")
+    }
+}
+
+impl Error for RecvError {}
+
+// rustdoc-stripper-ignore-next
+/// Error returned by `try_recv()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TryRecvError {
+    // rustdoc-stripper-ignore-next
+    /// The channel currently has no values.
+    Empty,
+    // rustdoc-stripper-ignore-next
+    /// The channel has no senders and no values left.
+    Closed,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("receiving on an empty channel"),
+            Self::Closed => f.write_str("receiving on a closed channel"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+// Wakers of tasks waiting for the channel.
+#[derive(Default)]
+pub(crate) struct Wakers(Vec<Waker>);
+
+impl Wakers {
+    pub(crate) fn register(&mut self, waker: &Waker) {
+        if !self.0.iter().any(|w| w.will_wake(waker)) {
+            self.0.push(waker.clone());
+        }
+    }
+
+    pub(crate) fn wake_all(&mut self) {
+        for waker in self.0.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    capacity: Option<usize>,
+    // Slots reserved by senders via `Sink::poll_ready()`.
+    reserved: usize,
+    senders: usize,
+    receivers: usize,
+    closed: bool,
+    recv_wakers: Wakers,
+    send_wakers: Wakers,
+}
+
+impl<T> State<T> {
+    fn is_full(&self) -> bool {
+        self.capacity.map_or(false, |capacity| {
+            self.queue.len() + self.reserved >= capacity
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed || self.receivers == 0
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.recv_wakers.wake_all();
+        self.send_wakers.wake_all();
+    }
+}
+
+struct Channel<T>(Mutex<State<T>>);
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.0.lock().unwrap()
+    }
+}
+
+fn channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    let channel = Arc::new(Channel(Mutex::new(State {
+        queue: VecDeque::new(),
+        capacity,
+        reserved: 0,
+        senders: 1,
+        receivers: 1,
+        closed: false,
+        recv_wakers: Wakers::default(),
+        send_wakers: Wakers::default(),
+    })));
+
+    (
+        Sender {
+            channel: channel.clone(),
+            reserved: false,
+            disconnected: false,
+        },
+        Receiver {
+            channel,
+            terminated: false,
+        },
+    )
+}
+
+// rustdoc-stripper-ignore-next
+/// Creates a channel that holds at most `capacity` values.
+///
+/// Sending waits until there is capacity for the value.
+///
+/// # Panics
+///
+/// This panics if `capacity` is `0`.
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "Channel capacity must be at least 1");
+    channel(Some(capacity))
+}
+
+// rustdoc-stripper-ignore-next
+/// Creates a channel that can hold an unlimited number of values.
+///
+/// Sending never waits.
+pub fn unbounded<T>() -> (Sender<T>, Receiver<T>) {
+    channel(None)
+}
+
+// rustdoc-stripper-ignore-next
+/// The sending side of a channel created by [`bounded`] or [`unbounded`].
+///
+/// Senders can be cloned for sending from multiple places. The receivers are notified once all
+/// senders are dropped or closed as [`Sink`].
+///
+/// Closing a sender as [`Sink`] only disconnects that sender, [`close`](Self::close) closes the
+/// channel for all senders.
+pub struct Sender<T> {
+    channel: Arc<Channel<T>>,
+    // Whether this sender has a slot reserved by `Sink::poll_ready()`.
+    reserved: bool,
+    // Whether this sender was closed by `Sink::poll_close()`.
+    disconnected: bool,
+}
+
+impl<T> Sender<T> {
+    // rustdoc-stripper-ignore-next
+    /// Sends `value`, waiting for capacity if the channel is full.
+    ///
+    /// Fails if all receivers were dropped or the channel was closed.
+    pub fn send(&self, value: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            value: Some(value),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sends `value` if the channel has capacity for it.
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.channel.lock();
+        if self.disconnected || state.is_closed() {
+            Err(TrySendError::Closed(value))
+        } else if state.is_full() {
+            Err(TrySendError::Full(value))
+        } else {
+            state.queue.push_back(value);
+            state.recv_wakers.wake_all();
+            Ok(())
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Closes the channel.
+    ///
+    /// Values that were already sent can still be received, further values can't be sent.
+    pub fn close(&self) {
+        self.channel.lock().close();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the channel is closed or has no receivers.
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().is_closed()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of values in the channel.
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the channel has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The capacity of the channel, or `None` for unbounded channels.
+    pub fn capacity(&self) -> Option<usize> {
+        self.channel.lock().capacity
+    }
+
+    fn poll_reserve(&mut self, cx: &mut Context) -> Poll<Result<(), SendError<()>>> {
+        if self.reserved {
+            return Poll::Ready(Ok(()));
+        }
+
+        let mut state = self.channel.lock();
+        if self.disconnected || state.is_closed() {
+            Poll::Ready(Err(SendError(())))
+        } else if state.is_full() {
+            state.send_wakers.register(cx.waker());
+            Poll::Pending
+        } else {
+            state.reserved += 1;
+            self.reserved = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn release(&mut self) {
+        if self.reserved {
+            self.reserved = false;
+            let mut state = self.channel.lock();
+            state.reserved -= 1;
+            state.send_wakers.wake_all();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.release();
+        if self.disconnected {
+            return;
+        }
+        self.disconnected = true;
+
+        let mut state = self.channel.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            state.recv_wakers.wake_all();
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        if !self.disconnected {
+            self.channel.lock().senders += 1;
+        }
+        Self {
+            channel: self.channel.clone(),
+            reserved: false,
+            disconnected: self.disconnected,
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity())
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl<T> Sink<T> for Sender<T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_reserve(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let mut state = this.channel.lock();
+        if this.reserved {
+            this.reserved = false;
+            state.reserved -= 1;
+        }
+        if this.disconnected || state.is_closed() {
+            return Err(SendError(()));
+        }
+        state.queue.push_back(item);
+        state.recv_wakers.wake_all();
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().disconnect();
+        Poll::Ready(Ok(()))
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Sender::send`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    value: Option<T>,
+}
+
+impl<'a, T> Unpin for SendFuture<'a, T> {}
+
+impl<'a, T> Future for SendFuture<'a, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let value = this
+            .value
+            .take()
+            .expect("SendFuture polled after completion");
+
+        let mut state = this.sender.channel.lock();
+        if this.sender.disconnected || state.is_closed() {
+            Poll::Ready(Err(SendError(value)))
+        } else if state.is_full() {
+            state.send_wakers.register(cx.waker());
+            this.value = Some(value);
+            Poll::Pending
+        } else {
+            state.queue.push_back(value);
+            state.recv_wakers.wake_all();
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+impl<'a, T> FusedFuture for SendFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// The receiving side of a channel created by [`bounded`] or [`unbounded`].
+///
+/// Receivers can be cloned for receiving from multiple places, every value is received by only
+/// one of them. The senders are notified once all receivers are dropped.
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+    terminated: bool,
+}
+
+impl<T> Receiver<T> {
+    // rustdoc-stripper-ignore-next
+    /// Receives the next value, waiting until one is available.
+    ///
+    /// Fails once all senders were dropped or the channel was closed, and no values are left.
+    pub fn recv(&self) -> RecvFuture<'_, T> {
+        RecvFuture {
+            receiver: self,
+            terminated: false,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Receives the next value if one is available.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.channel.lock();
+        match state.queue.pop_front() {
+            Some(value) => {
+                state.send_wakers.wake_all();
+                Ok(value)
+            }
+            None if state.senders == 0 || state.closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Closes the channel.
+    ///
+    /// Values that were already sent can still be received, further values can't be sent.
+    pub fn close(&self) {
+        self.channel.lock().close();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of values in the channel.
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the channel has no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn poll_recv(&self, cx: &mut Context) -> Poll<Result<T, RecvError>> {
+        match self.try_recv() {
+            Ok(value) => Poll::Ready(Ok(value)),
+            Err(TryRecvError::Closed) => Poll::Ready(Err(RecvError)),
+            Err(TryRecvError::Empty) => {
+                let mut state = self.channel.lock();
+                // A value might have been sent in the meantime.
+                if !state.queue.is_empty() || state.senders == 0 || state.closed {
+                    cx.waker().wake_by_ref();
+                } else {
+                    state.recv_wakers.register(cx.waker());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.channel.lock().receivers += 1;
+        Self {
+            channel: self.channel.clone(),
+            terminated: self.terminated,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        state.receivers -= 1;
+        if state.receivers == 0 {
+            state.send_wakers.wake_all();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        if this.terminated {
+            return Poll::Ready(None);
+        }
+
+        match this.poll_recv(cx) {
+            Poll::Ready(Ok(value)) => Poll::Ready(Some(value)),
+            Poll::Ready(Err(RecvError)) => {
+                this.terminated = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Receiver::recv`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RecvFuture<'a, T> {
+    receiver: &'a Receiver<T>,
+    terminated: bool,
+}
+
+impl<'a, T> Future for RecvFuture<'a, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let res = this.receiver.poll_recv(cx);
+        if res.is_ready() {
+            this.terminated = true;
+        }
+        res
+    }
+}
+
+impl<'a, T> FusedFuture for RecvFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use futures_util::{SinkExt, StreamExt};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn test_unbounded() {
+        let c = MainContext::new();
+        let (sender, receiver) = unbounded();
+
+        let handle = thread::spawn(move || {
+            for i in 0..10 {
+                assert!(sender.try_send(i).is_ok());
+            }
+        });
+
+        let values = c.block_on(receiver.collect::<Vec<_>>());
+        handle.join().unwrap();
+        assert_eq!(values, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_bounded_backpressure() {
+        let c = MainContext::new();
+        let (sender, receiver) = bounded(2);
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let sent_clone = sent.clone();
+        c.spawn_local(async move {
+            for i in 0..5 {
+                sender.send(i).await.unwrap();
+                sent_clone.borrow_mut().push(i);
+            }
+        });
+
+        c.block_on(async move {
+            // Let the sender fill up the channel
+            crate::timeout_future(std::time::Duration::from_millis(10)).await;
+            assert_eq!(*sent.borrow(), [0, 1]);
+            assert_eq!(receiver.len(), 2);
+            assert_eq!(
+                receiver.recv().await.unwrap(),
+                0,
+                "values are received in order"
+            );
+
+            let rest = receiver.collect::<Vec<_>>().await;
+            assert_eq!(rest, [1, 2, 3, 4]);
+            assert_eq!(*sent.borrow(), [0, 1, 2, 3, 4]);
+        });
+    }
+
+    #[test]
+    fn test_try_send() {
+        let (sender, receiver) = bounded(1);
+        assert_eq!(sender.capacity(), Some(1));
+        assert!(sender.try_send(1).is_ok());
+        assert_eq!(sender.try_send(2), Err(TrySendError::Full(2)));
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        sender.close();
+        assert!(sender.is_closed());
+        assert_eq!(sender.try_send(3), Err(TrySendError::Closed(3)));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Closed));
+
+        let (sender, receiver) = unbounded::<i32>();
+        drop(receiver);
+        assert!(sender.is_closed());
+        let c = MainContext::new();
+        assert_eq!(c.block_on(sender.send(1)), Err(SendError(1)));
+    }
+
+    #[test]
+    fn test_multiple_receivers() {
+        let c = MainContext::new();
+        let (sender, receiver) = unbounded();
+        let receiver2 = receiver.clone();
+
+        let sum = Rc::new(RefCell::new(0));
+        for receiver in [receiver, receiver2] {
+            let sum = sum.clone();
+            c.spawn_local(async move {
+                while let Ok(value) = receiver.recv().await {
+                    *sum.borrow_mut() += value;
+                }
+            });
+        }
+
+        c.block_on(async move {
+            for i in 1..=10 {
+                sender.send(i).await.unwrap();
+            }
+        });
+        while c.iteration(false) {}
+        assert_eq!(*sum.borrow(), 55);
+    }
+
+    #[test]
+    fn test_sink() {
+        let c = MainContext::new();
+        let (mut sender, receiver) = bounded(1);
+
+        c.spawn_local(async move {
+            let mut values = futures_util::stream::iter(0..5).map(Ok);
+            sender.send_all(&mut values).await.unwrap();
+            SinkExt::close(&mut sender).await.unwrap();
+        });
+
+        let values = c.block_on(receiver.collect::<Vec<_>>());
+        assert_eq!(values, [0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_sink_close() {
+        let c = MainContext::new();
+        let (sender, receiver) = unbounded();
+
+        // Forwarding closes only the sender clone that is forwarded into
+        let forwarded = c.block_on(
+            futures_util::stream::iter(0..3)
+                .map(Ok)
+                .forward(sender.clone()),
+        );
+        assert_eq!(forwarded, Ok(()));
+        assert!(!sender.is_closed());
+        assert!(sender.try_send(3).is_ok());
+
+        let mut closed = sender.clone();
+        c.block_on(SinkExt::close(&mut closed)).unwrap();
+        assert!(closed.try_send(4).is_err());
+        assert_eq!(receiver.try_recv(), Ok(0));
+
+        // The receiver finishes once the last sender is gone
+        drop(sender);
+        let values = c.block_on(receiver.collect::<Vec<_>>());
+        assert_eq!(values, [1, 2, 3]);
+    }
+}
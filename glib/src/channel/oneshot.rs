@@ -0,0 +1,252 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! A channel for sending a single value.
+//!
+//! ```
+//! let c = glib::MainContext::new();
+//! let (sender, receiver) = glib::channel::oneshot::channel();
+//!
+//! std::thread::spawn(move || sender.send(42).unwrap());
+//!
+//! assert_eq!(c.block_on(receiver), Ok(42));
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use futures_core::FusedFuture;
+
+use super::{RecvError, TryRecvError};
+
+struct State<T> {
+    value: Option<T>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+    recv_waker: Option<Waker>,
+    closed_waker: Option<Waker>,
+}
+
+struct Channel<T>(Mutex<State<T>>);
+
+impl<T> Channel<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        self.0.lock().unwrap()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Creates a channel for sending a single value.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let channel = Arc::new(Channel(Mutex::new(State {
+        value: None,
+        sender_dropped: false,
+        receiver_dropped: false,
+        recv_waker: None,
+        closed_waker: None,
+    })));
+
+    (
+        Sender {
+            channel: channel.clone(),
+        },
+        Receiver {
+            channel,
+            terminated: false,
+        },
+    )
+}
+
+// rustdoc-stripper-ignore-next
+/// The sending side of a oneshot channel.
+pub struct Sender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+impl<T> Sender<T> {
+    // rustdoc-stripper-ignore-next
+    /// Sends `value` to the receiver.
+    ///
+    /// Fails with the value if the receiver was dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut state = self.channel.lock();
+        if state.receiver_dropped {
+            return Err(value);
+        }
+
+        state.value = Some(value);
+        if let Some(waker) = state.recv_waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the receiver was dropped.
+    pub fn is_closed(&self) -> bool {
+        self.channel.lock().receiver_dropped
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns a future that resolves once the receiver is dropped.
+    ///
+    /// This allows stopping work whose result is not needed anymore.
+    pub fn closed(&mut self) -> ClosedFuture<'_, T> {
+        ClosedFuture { sender: self }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        state.sender_dropped = true;
+        if let Some(waker) = state.recv_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Sender::closed`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ClosedFuture<'a, T> {
+    sender: &'a mut Sender<T>,
+}
+
+impl<'a, T> Future for ClosedFuture<'a, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut state = self.sender.channel.lock();
+        if state.receiver_dropped {
+            Poll::Ready(())
+        } else {
+            state.closed_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// The receiving side of a oneshot channel.
+///
+/// This is a future that resolves to the sent value, or to an error if the sender was dropped
+/// without sending a value.
+pub struct Receiver<T> {
+    channel: Arc<Channel<T>>,
+    terminated: bool,
+}
+
+impl<T> Receiver<T> {
+    // rustdoc-stripper-ignore-next
+    /// Receives the value if it was sent already.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut state = self.channel.lock();
+        match state.value.take() {
+            Some(value) => {
+                self.terminated = true;
+                Ok(value)
+            }
+            None if state.sender_dropped => {
+                self.terminated = true;
+                Err(TryRecvError::Closed)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.channel.lock();
+        state.receiver_dropped = true;
+        if let Some(waker) = state.closed_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("terminated", &self.terminated)
+            .finish()
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        assert!(!this.terminated, "Receiver polled after completion");
+
+        let mut state = this.channel.lock();
+        if let Some(value) = state.value.take() {
+            this.terminated = true;
+            Poll::Ready(Ok(value))
+        } else if state.sender_dropped {
+            this.terminated = true;
+            Poll::Ready(Err(RecvError))
+        } else {
+            state.recv_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> FusedFuture for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+
+    #[test]
+    fn test_oneshot() {
+        let c = MainContext::new();
+
+        let (sender, receiver) = channel();
+        c.spawn_local(async move {
+            crate::timeout_future(std::time::Duration::from_millis(10)).await;
+            sender.send("hello").unwrap();
+        });
+        assert_eq!(c.block_on(receiver), Ok("hello"));
+
+        let (sender, mut receiver) = channel::<i32>();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        drop(sender);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Closed));
+        assert!(receiver.is_terminated());
+    }
+
+    #[test]
+    fn test_closed() {
+        let c = MainContext::new();
+
+        let (mut sender, receiver) = channel::<i32>();
+        assert!(!sender.is_closed());
+        c.spawn_local(async move {
+            crate::timeout_future(std::time::Duration::from_millis(10)).await;
+            drop(receiver);
+        });
+        c.block_on(sender.closed());
+        assert!(sender.is_closed());
+        assert_eq!(sender.send(1), Err(1));
+    }
+}
@@ -2,12 +2,15 @@
 
 // TODO: support marshaller.
 
+use std::fmt;
+use std::marker::PhantomData;
 use std::mem;
 use std::ptr;
 use std::slice;
 
 use libc::{c_uint, c_void};
 
+use crate::subclass::SignalId;
 use crate::translate::{from_glib_none, mut_override, ToGlibPtr, ToGlibPtrMut, Uninitialized};
 use crate::value::FromValue;
 use crate::StaticType;
@@ -129,6 +132,285 @@ impl RustClosure {
     }
 }
 
+// rustdoc-stripper-ignore-next
+/// A closure with a signature that is known at compile-time.
+///
+/// `Args` is a tuple of the argument types and `R` the return type of the wrapped Rust closure.
+/// Arguments are retrieved from the [`Value`]s passed to the closure and the return value is
+/// converted back, so no manual [`Value`] handling is needed.
+///
+/// When connected to a signal via
+/// [`ObjectExt::connect_typed_closure`](crate::ObjectExt::connect_typed_closure),
+/// [`SignalGroup::connect_typed_closure`](crate::SignalGroup::connect_typed_closure) or
+/// [`ObjectBuilder::connect_closure`](crate::object::ObjectBuilder::connect_closure), the
+/// signature is checked against the signal once at connect time instead of failing on emission.
+///
+/// # Example
+///
+/// ```
+/// use glib::prelude::*;
+///
+/// let closure = glib::TypedClosure::new(|x: i32, y: i32| x + y);
+///
+/// assert_eq!(closure.param_types(), [glib::Type::I32, glib::Type::I32]);
+/// assert_eq!(closure.return_type(), glib::Type::I32);
+///
+/// let closure = glib::RustClosure::from(closure);
+/// assert_eq!(closure.invoke::<i32>(&[&1i32, &2i32]), 3);
+/// ```
+pub struct TypedClosure<Args, R> {
+    closure: RustClosure,
+    phantom: PhantomData<fn(Args) -> R>,
+}
+
+impl<Args: ClosureArgs, R: ToClosureReturnValue + StaticType + 'static> TypedClosure<Args, R> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new typed closure around a Rust closure.
+    #[doc(alias = "g_closure_new")]
+    pub fn new<F: TypedClosureFn<Args, R> + Send + Sync>(callback: F) -> Self {
+        Self {
+            closure: RustClosure::new(move |values| {
+                let args = Args::from_values(values)
+                    .unwrap_or_else(|err| panic!("Invalid closure arguments: {}", err));
+                callback.call(args).to_closure_return_value()
+            }),
+            phantom: PhantomData,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Creates a new typed closure around a Rust closure.
+    ///
+    /// # Panics
+    ///
+    /// Invoking the closure from a different thread than this one will panic.
+    #[doc(alias = "g_closure_new")]
+    pub fn new_local<F: TypedClosureFn<Args, R>>(callback: F) -> Self {
+        Self {
+            closure: RustClosure::new_local(move |values| {
+                let args = Args::from_values(values)
+                    .unwrap_or_else(|err| panic!("Invalid closure arguments: {}", err));
+                callback.call(args).to_closure_return_value()
+            }),
+            phantom: PhantomData,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the types of the arguments of the closure.
+    pub fn param_types(&self) -> Vec<Type> {
+        Args::param_types()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the return type of the closure.
+    ///
+    /// This is [`Type::UNIT`] for closures without return value.
+    pub fn return_type(&self) -> Type {
+        R::static_type()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Invalidates the closure.
+    ///
+    /// Invoking an invalidated closure has no effect.
+    #[doc(alias = "g_closure_invalidate")]
+    pub fn invalidate(&self) {
+        self.closure.invalidate();
+    }
+}
+
+impl<Args, R> Clone for TypedClosure<Args, R> {
+    fn clone(&self) -> Self {
+        Self {
+            closure: self.closure.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Args, R> fmt::Debug for TypedClosure<Args, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("TypedClosure").field(&self.closure).finish()
+    }
+}
+
+impl<Args, R> From<TypedClosure<Args, R>> for RustClosure {
+    fn from(c: TypedClosure<Args, R>) -> Self {
+        c.closure
+    }
+}
+
+impl<Args, R> From<TypedClosure<Args, R>> for Closure {
+    fn from(c: TypedClosure<Args, R>) -> Self {
+        c.closure.0
+    }
+}
+
+impl<Args, R> AsRef<RustClosure> for TypedClosure<Args, R> {
+    fn as_ref(&self) -> &RustClosure {
+        &self.closure
+    }
+}
+
+impl<Args, R> AsRef<Closure> for TypedClosure<Args, R> {
+    fn as_ref(&self) -> &Closure {
+        &self.closure.0
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Closures that can be connected to a signal.
+///
+/// [`RustClosure`]s are connected as is, [`TypedClosure`]s have their signature checked against
+/// the signal first.
+pub trait SignalClosure: Into<RustClosure> {
+    // rustdoc-stripper-ignore-next
+    /// Checks if the closure can handle emissions of the signal `signal_id` on instances of
+    /// `type_`.
+    fn check_signal(&self, signal_id: SignalId, type_: Type) -> Result<(), crate::BoolError>;
+}
+
+impl SignalClosure for RustClosure {
+    fn check_signal(&self, _signal_id: SignalId, _type_: Type) -> Result<(), crate::BoolError> {
+        Ok(())
+    }
+}
+
+impl<Args: ClosureArgs, R: ToClosureReturnValue + StaticType + 'static> SignalClosure
+    for TypedClosure<Args, R>
+{
+    fn check_signal(&self, signal_id: SignalId, type_: Type) -> Result<(), crate::BoolError> {
+        let query = signal_id.query();
+        let param_types = Args::param_types();
+        let signal_param_types = query.param_types();
+
+        // The instance is passed as first argument in addition to the signal parameters.
+        if param_types.len() != signal_param_types.len() + 1 {
+            return Err(bool_error!(
+                "Closure takes {} arguments but signal '{}' passes {}",
+                param_types.len(),
+                query.signal_name(),
+                signal_param_types.len() + 1
+            ));
+        }
+
+        if !type_.is_a(param_types[0]) {
+            return Err(bool_error!(
+                "Closure expects instance of type '{}' but got '{}'",
+                param_types[0],
+                type_
+            ));
+        }
+
+        for (i, (signal_param_type, param_type)) in
+            signal_param_types.iter().zip(&param_types[1..]).enumerate()
+        {
+            let signal_param_type = signal_param_type.type_();
+            if !signal_param_type.is_a(*param_type) {
+                return Err(bool_error!(
+                    "Closure expects argument {} of type '{}' but signal '{}' passes '{}'",
+                    i + 1,
+                    param_type,
+                    query.signal_name(),
+                    signal_param_type
+                ));
+            }
+        }
+
+        let return_type = self.return_type();
+        let signal_return_type = query.return_type().type_();
+        let valid_return_type = if signal_return_type == Type::UNIT {
+            return_type == Type::UNIT
+        } else {
+            return_type.is_a(signal_return_type)
+        };
+        if !valid_return_type {
+            return Err(bool_error!(
+                "Closure returns '{}' but signal '{}' requires '{}'",
+                return_type,
+                query.signal_name(),
+                signal_return_type
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Argument tuples of [`TypedClosure`]s.
+///
+/// This is implemented for tuples of up to 12 elements that can be retrieved from a [`Value`].
+pub trait ClosureArgs: Sized + 'static {
+    // rustdoc-stripper-ignore-next
+    /// Returns the types of the arguments.
+    fn param_types() -> Vec<Type>;
+
+    // rustdoc-stripper-ignore-next
+    /// Retrieves the arguments from the values passed to the closure.
+    fn from_values(values: &[Value]) -> Result<Self, crate::BoolError>;
+}
+
+// rustdoc-stripper-ignore-next
+/// Rust closures that can be wrapped in a [`TypedClosure`] with arguments `Args`.
+pub trait TypedClosureFn<Args, R>: 'static {
+    fn call(&self, args: Args) -> R;
+}
+
+macro_rules! closure_args(
+    ($n:literal; $($name:ident $idx:tt),*) => {
+        impl<$($name),*> ClosureArgs for ($($name,)*)
+        where
+            $($name: for<'a> FromValue<'a> + StaticType + 'static,)*
+        {
+            fn param_types() -> Vec<Type> {
+                vec![$($name::static_type()),*]
+            }
+
+            fn from_values(values: &[Value]) -> Result<Self, crate::BoolError> {
+                if values.len() != $n {
+                    return Err(bool_error!(
+                        "Invalid number of arguments: expected {}, got {}",
+                        $n,
+                        values.len()
+                    ));
+                }
+
+                Ok(($(
+                    values[$idx].get_owned::<$name>().map_err(|err| {
+                        bool_error!("Invalid argument {}: {}", $idx, err)
+                    })?,
+                )*))
+            }
+        }
+
+        impl<F, R, $($name),*> TypedClosureFn<($($name,)*), R> for F
+        where
+            F: Fn($($name),*) -> R + 'static,
+        {
+            #[allow(unused_variables)]
+            fn call(&self, args: ($($name,)*)) -> R {
+                self($(args.$idx),*)
+            }
+        }
+    }
+);
+
+closure_args!(0;);
+closure_args!(1; A 0);
+closure_args!(2; A 0, B 1);
+closure_args!(3; A 0, B 1, C 2);
+closure_args!(4; A 0, B 1, C 2, D 3);
+closure_args!(5; A 0, B 1, C 2, D 3, E 4);
+closure_args!(6; A 0, B 1, C 2, D 3, E 4, G 5);
+closure_args!(7; A 0, B 1, C 2, D 3, E 4, G 5, H 6);
+closure_args!(8; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7);
+closure_args!(9; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7, J 8);
+closure_args!(10; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7, J 8, K 9);
+closure_args!(11; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7, J 8, K 9, L 10);
+closure_args!(12; A 0, B 1, C 2, D 3, E 4, G 5, H 6, I 7, J 8, K 9, L 10, M 11);
+
 impl From<RustClosure> for Closure {
     fn from(c: RustClosure) -> Self {
         c.0
@@ -443,4 +725,25 @@ mod tests {
         let result = closure.invoke::<i32>(&[&"test", &42]);
         assert_eq!(result, 0);
     }
+
+    #[test]
+    fn test_typed_closure() {
+        let closure = TypedClosure::new(|a: String, b: i32| format!("{} {}", a, b));
+        assert_eq!(closure.param_types(), [Type::STRING, Type::I32]);
+        assert_eq!(closure.return_type(), Type::STRING);
+
+        let closure = RustClosure::from(closure);
+        let result = closure.invoke::<String>(&[&"test", &42]);
+        assert_eq!(result, "test 42");
+
+        let call_count = Arc::new(AtomicUsize::new(0));
+        let count = call_count.clone();
+        let closure = TypedClosure::new(move || {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        assert!(closure.param_types().is_empty());
+        assert_eq!(closure.return_type(), Type::UNIT);
+        RustClosure::from(closure).invoke::<()>(&[]);
+        assert_eq!(call_count.load(Ordering::Relaxed), 1);
+    }
 }
@@ -0,0 +1,228 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::FusedFuture;
+
+struct State {
+    arrived: usize,
+    generation: u64,
+    // Wakers of the waiting tasks of the current generation, indexed by `BarrierWait::slot`.
+    wakers: Vec<Waker>,
+}
+
+// rustdoc-stripper-ignore-next
+/// Lets a number of tasks wait until all of them reached the same point.
+///
+/// The barrier can be reused once all tasks passed it.
+pub struct Barrier {
+    n: usize,
+    state: RefCell<State>,
+}
+
+impl Barrier {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new barrier for `n` tasks.
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            state: RefCell::new(State {
+                arrived: 0,
+                generation: 0,
+                wakers: Vec::new(),
+            }),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits until `n` tasks are waiting.
+    ///
+    /// A task counts as waiting once the returned future is polled the first time. If the
+    /// future is dropped before completing, the task does not count anymore.
+    pub fn wait(&self) -> BarrierWait<'_> {
+        BarrierWait {
+            barrier: self,
+            generation: None,
+            slot: 0,
+            done: false,
+        }
+    }
+}
+
+impl fmt::Debug for Barrier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Barrier")
+            .field("n", &self.n)
+            .field("arrived", &self.state.borrow().arrived)
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Result of waiting on a [`Barrier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult(bool);
+
+impl BarrierWaitResult {
+    // rustdoc-stripper-ignore-next
+    /// Whether this task was the last one to reach the barrier.
+    ///
+    /// This is `true` for exactly one task per use of the barrier.
+    pub fn is_leader(&self) -> bool {
+        self.0
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Barrier::wait`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct BarrierWait<'a> {
+    barrier: &'a Barrier,
+    generation: Option<u64>,
+    slot: usize,
+    done: bool,
+}
+
+impl<'a> Future for BarrierWait<'a> {
+    type Output = BarrierWaitResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<BarrierWaitResult> {
+        let this = self.get_mut();
+        assert!(!this.done, "future polled after completion");
+
+        let wakers = {
+            let mut state = this.barrier.state.borrow_mut();
+            match this.generation {
+                Some(generation) if generation != state.generation => {
+                    this.generation = None;
+                    this.done = true;
+                    return Poll::Ready(BarrierWaitResult(false));
+                }
+                Some(_) => {
+                    let waker = &mut state.wakers[this.slot];
+                    if !waker.will_wake(cx.waker()) {
+                        *waker = cx.waker().clone();
+                    }
+                    return Poll::Pending;
+                }
+                None => {
+                    state.arrived += 1;
+                    if state.arrived < this.barrier.n {
+                        this.generation = Some(state.generation);
+                        this.slot = state.wakers.len();
+                        state.wakers.push(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+
+                    state.arrived = 0;
+                    state.generation += 1;
+                    this.done = true;
+                    std::mem::take(&mut state.wakers)
+                }
+            }
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+        Poll::Ready(BarrierWaitResult(true))
+    }
+}
+
+impl<'a> FusedFuture for BarrierWait<'a> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<'a> Drop for BarrierWait<'a> {
+    fn drop(&mut self) {
+        if let Some(generation) = self.generation {
+            let mut state = self.barrier.state.borrow_mut();
+            if state.generation == generation {
+                state.arrived -= 1;
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for BarrierWait<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BarrierWait")
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_barrier() {
+        let c = MainContext::new();
+        let barrier = Rc::new(Barrier::new(3));
+
+        for _ in 0..2 {
+            let leaders = c.block_on(async {
+                let mut tasks = crate::TaskSet::new();
+                for i in 0..3 {
+                    let barrier = barrier.clone();
+                    tasks.spawn_local(async move {
+                        crate::timeout_future(Duration::from_millis(5 * i)).await;
+                        barrier.wait().await.is_leader()
+                    });
+                }
+
+                let mut leaders = 0;
+                while let Some(res) = tasks.join_next().await {
+                    leaders += res.unwrap() as usize;
+                }
+                leaders
+            });
+            assert_eq!(leaders, 1);
+        }
+    }
+
+    #[test]
+    fn test_cancel() {
+        let c = MainContext::new();
+        let barrier = Barrier::new(2);
+
+        c.block_on(async {
+            let res = crate::future_with_timeout(Duration::from_millis(10), barrier.wait()).await;
+            assert!(res.is_err());
+            // The cancelled waiter does not count
+            let res = crate::future_with_timeout(Duration::from_millis(10), barrier.wait()).await;
+            assert!(res.is_err());
+        });
+    }
+
+    #[test]
+    fn test_repeated_poll() {
+        let barrier = Barrier::new(2);
+        let mut wait = barrier.wait();
+        let mut cx = Context::from_waker(futures_task::noop_waker_ref());
+
+        for _ in 0..10 {
+            assert!(Pin::new(&mut wait).poll(&mut cx).is_pending());
+        }
+        assert_eq!(barrier.state.borrow().wakers.len(), 1);
+
+        let mut other = barrier.wait();
+        assert_eq!(
+            Pin::new(&mut other).poll(&mut cx),
+            Poll::Ready(BarrierWaitResult(true))
+        );
+        assert_eq!(
+            Pin::new(&mut wait).poll(&mut cx),
+            Poll::Ready(BarrierWaitResult(false))
+        );
+        assert!(barrier.state.borrow().wakers.is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Asynchronous synchronization primitives for futures running on a
+//! [`MainContext`](crate::MainContext).
+//!
+//! Different to [`RefCell`](std::cell::RefCell), the [`Mutex`] and [`RwLock`] in this module
+//! can be held across `.await` points: a conflicting access waits asynchronously instead of
+//! panicking. Different to [`std::sync::Mutex`], waiting never blocks the thread and thus never
+//! blocks the main context that would have to run the future currently holding the lock.
+//!
+//! The primitives are meant for futures running on the same thread, e.g. futures spawned with
+//! [`MainContext::spawn_local`](crate::MainContext::spawn_local), and are shared via
+//! [`Rc`](std::rc::Rc). They are not `Sync` and none of the values they protect have to be
+//! `Send`. Waiting tasks are woken via their [`Waker`](std::task::Waker), which for futures
+//! spawned on a main context schedules them on that main context. Waiting tasks are never
+//! polled from inside the function that releases a lock.
+//!
+//! The locks are not reentrant: a task that waits for something that only becomes available
+//! once it releases what it currently holds, e.g. by locking a mutex twice, never completes.
+//! Sub-futures of one task, e.g. the branches of `join!`, can wait for each other as usual.
+//!
+//! ```
+//! use std::rc::Rc;
+//! use std::time::Duration;
+//!
+//! let c = glib::MainContext::new();
+//! let state = Rc::new(glib::future::Mutex::new(Vec::new()));
+//!
+//! c.block_on(async {
+//!     let mut tasks = glib::TaskSet::new();
+//!     for i in 0..3 {
+//!         let state = state.clone();
+//!         tasks.spawn_local(async move {
+//!             let mut state = state.lock().await;
+//!             glib::timeout_future(Duration::from_millis(1)).await;
+//!             state.push(i);
+//!         });
+//!     }
+//!     while tasks.join_next().await.is_some() {}
+//! });
+//!
+//! assert_eq!(*c.block_on(state.lock()), [0, 1, 2]);
+//! ```
+
+mod barrier;
+mod mutex;
+mod notify;
+mod rwlock;
+mod semaphore;
+
+pub use self::barrier::{Barrier, BarrierWait, BarrierWaitResult};
+pub use self::mutex::{Mutex, MutexGuard, MutexLockFuture};
+pub use self::notify::{Notified, Notify};
+pub use self::rwlock::{
+    RwLock, RwLockReadFuture, RwLockReadGuard, RwLockWriteFuture, RwLockWriteGuard,
+};
+pub use self::semaphore::{Acquire, Semaphore, SemaphorePermit};
@@ -0,0 +1,229 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::FusedFuture;
+
+use super::semaphore::{AcquirePermits, Permits};
+
+// rustdoc-stripper-ignore-next
+/// An asynchronous mutual exclusion lock.
+///
+/// The lock is handed out in the order it was requested and can be held across `.await` points.
+pub struct Mutex<T: ?Sized> {
+    permits: Permits,
+    value: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new, unlocked mutex containing `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: Permits::new(1),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Consumes the mutex and returns the contained value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    // rustdoc-stripper-ignore-next
+    /// Waits until the mutex is unlocked and locks it.
+    pub fn lock(&self) -> MutexLockFuture<'_, T> {
+        MutexLockFuture {
+            mutex: self,
+            inner: AcquirePermits::new(&self.permits, 1),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Locks the mutex if it is unlocked and nobody else is waiting for it.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.permits
+            .try_acquire(1)
+            .then(|| MutexGuard { mutex: self })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns a mutable reference to the contained value.
+    ///
+    /// This doesn't need to lock as the mutable borrow guarantees that no guard exists.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("Mutex");
+        match self.try_lock() {
+            Some(guard) => d.field("value", &&*guard),
+            None => d.field("value", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Mutex::lock`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct MutexLockFuture<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+    inner: AcquirePermits<'a>,
+}
+
+impl<'a, T: ?Sized> Future for MutexLockFuture<'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.inner
+            .poll_acquire(cx)
+            .map(|()| MutexGuard { mutex: this.mutex })
+    }
+}
+
+impl<'a, T: ?Sized> FusedFuture for MutexLockFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for MutexLockFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MutexLockFuture").finish_non_exhaustive()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A locked [`Mutex`].
+///
+/// The mutex is unlocked when this is dropped.
+#[must_use = "if unused the mutex is unlocked immediately"]
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.permits.release(1);
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_mutex() {
+        let c = MainContext::new();
+        let mutex = Rc::new(Mutex::new(0));
+
+        c.block_on(async {
+            let mut handles = Vec::new();
+            for i in 0..3 {
+                let mutex = mutex.clone();
+                handles.push(c.spawn_local_task(async move {
+                    let mut guard = mutex.lock().await;
+                    let v = *guard;
+                    // Other tasks run meanwhile but can't get the lock
+                    crate::timeout_future(Duration::from_millis(5 * (3 - i))).await;
+                    *guard = v + 1;
+                }));
+            }
+
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        });
+
+        assert_eq!(*mutex.try_lock().unwrap(), 3);
+        assert_eq!(Rc::try_unwrap(mutex).unwrap().into_inner(), 3);
+    }
+
+    #[test]
+    fn test_try_lock() {
+        let c = MainContext::new();
+        let mutex = Mutex::new(String::from("a"));
+
+        let guard = mutex.try_lock().unwrap();
+        assert!(mutex.try_lock().is_none());
+        assert_eq!(format!("{:?}", mutex), "Mutex { value: <locked> }");
+
+        c.block_on(async {
+            let res = crate::future_with_timeout(Duration::from_millis(10), mutex.lock()).await;
+            assert!(res.is_err());
+        });
+
+        drop(guard);
+        c.block_on(async {
+            mutex.lock().await.push('b');
+        });
+        assert_eq!(format!("{:?}", mutex), "Mutex { value: \"ab\" }");
+    }
+
+    #[test]
+    fn test_join_same_task() {
+        let c = crate::MainContext::new();
+        let mutex = Mutex::new(Vec::new());
+        c.block_on(async {
+            futures_util::join!(
+                async {
+                    let mut guard = mutex.lock().await;
+                    crate::timeout_future(std::time::Duration::from_millis(10)).await;
+                    guard.push("holder");
+                },
+                async {
+                    mutex.lock().await.push("waiter");
+                },
+            );
+        });
+        assert_eq!(*c.block_on(mutex.lock()), ["holder", "waiter"]);
+    }
+}
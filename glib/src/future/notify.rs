@@ -0,0 +1,266 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::FusedFuture;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Notification {
+    One,
+    All,
+}
+
+struct Waiter {
+    id: u64,
+    waker: Option<Waker>,
+    notified: Option<Notification>,
+}
+
+#[derive(Default)]
+struct State {
+    permit: bool,
+    waiters: VecDeque<Waiter>,
+    next_id: u64,
+}
+
+// rustdoc-stripper-ignore-next
+/// Notifies waiting tasks of an event.
+///
+/// [`notify_one`](Self::notify_one) wakes up a single waiting task, or the next task that waits
+/// if none is waiting currently. [`notify_waiters`](Self::notify_waiters) wakes up all currently
+/// waiting tasks.
+#[derive(Default)]
+pub struct Notify {
+    state: RefCell<State>,
+}
+
+impl Notify {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new `Notify`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for a notification.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            id: None,
+            done: false,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Notifies the first waiting task.
+    ///
+    /// If no task is waiting, the next call to [`notified`](Self::notified) completes
+    /// immediately. Multiple notifications without a waiting task are coalesced into one.
+    pub fn notify_one(&self) {
+        let waker = {
+            let mut state = self.state.borrow_mut();
+            match state.waiters.iter_mut().find(|w| w.notified.is_none()) {
+                Some(waiter) => {
+                    waiter.notified = Some(Notification::One);
+                    waiter.waker.take()
+                }
+                None => {
+                    state.permit = true;
+                    None
+                }
+            }
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Notifies all currently waiting tasks.
+    pub fn notify_waiters(&self) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            state
+                .waiters
+                .iter_mut()
+                .filter(|w| w.notified.is_none())
+                .filter_map(|w| {
+                    w.notified = Some(Notification::All);
+                    w.waker.take()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Notify")
+            .field("permit", &state.permit)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Notify::notified`].
+///
+/// The future starts waiting when it is polled the first time.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    id: Option<u64>,
+    done: bool,
+}
+
+impl<'a> Future for Notified<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let this = self.get_mut();
+        assert!(!this.done, "future polled after completion");
+
+        let mut state = this.notify.state.borrow_mut();
+        match this.id {
+            None if state.permit => {
+                state.permit = false;
+                this.done = true;
+                Poll::Ready(())
+            }
+            None => {
+                state.next_id += 1;
+                let id = state.next_id;
+                state.waiters.push_back(Waiter {
+                    id,
+                    waker: Some(cx.waker().clone()),
+                    notified: None,
+                });
+                this.id = Some(id);
+                Poll::Pending
+            }
+            Some(id) => {
+                let pos = state
+                    .waiters
+                    .iter()
+                    .position(|w| w.id == id)
+                    .expect("waiter not queued");
+                if state.waiters[pos].notified.is_some() {
+                    state.waiters.remove(pos);
+                    this.id = None;
+                    this.done = true;
+                    Poll::Ready(())
+                } else {
+                    state.waiters[pos].waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl<'a> FusedFuture for Notified<'a> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<'a> Drop for Notified<'a> {
+    fn drop(&mut self) {
+        let id = match self.id {
+            Some(id) => id,
+            None => return,
+        };
+
+        let forward = {
+            let mut state = self.notify.state.borrow_mut();
+            match state.waiters.iter().position(|w| w.id == id) {
+                Some(pos) => state.waiters.remove(pos).unwrap().notified == Some(Notification::One),
+                None => false,
+            }
+        };
+
+        // Don't lose a notification that was meant for a single waiter
+        if forward {
+            self.notify.notify_one();
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Notified<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Notified")
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_notify_one() {
+        let c = MainContext::new();
+        let notify = Rc::new(Notify::new());
+
+        // A stored notification is consumed by the next waiter
+        notify.notify_one();
+        notify.notify_one();
+        c.block_on(notify.notified());
+        c.block_on(async {
+            let res =
+                crate::future_with_timeout(Duration::from_millis(10), notify.notified()).await;
+            assert!(res.is_err());
+        });
+
+        c.block_on(async {
+            let n = notify.clone();
+            let handle = c.spawn_local_task(async move { n.notified().await });
+            let n = notify.clone();
+            c.spawn_local(async move {
+                crate::timeout_future(Duration::from_millis(10)).await;
+                n.notify_one();
+            });
+            handle.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_notify_waiters() {
+        let c = MainContext::new();
+        let notify = Rc::new(Notify::new());
+
+        c.block_on(async {
+            let mut tasks = crate::TaskSet::new();
+            for _ in 0..3 {
+                let n = notify.clone();
+                tasks.spawn_local(async move { n.notified().await });
+            }
+
+            crate::timeout_future(Duration::from_millis(10)).await;
+            notify.notify_waiters();
+            while let Some(res) = tasks.join_next().await {
+                res.unwrap();
+            }
+        });
+
+        // Nothing is stored for future waiters
+        c.block_on(async {
+            let res =
+                crate::future_with_timeout(Duration::from_millis(10), notify.notified()).await;
+            assert!(res.is_err());
+        });
+    }
+}
@@ -0,0 +1,305 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::FusedFuture;
+
+use super::semaphore::{AcquirePermits, Permits};
+
+// A reader takes one permit, a writer takes all of them.
+const MAX_READERS: usize = usize::MAX >> 3;
+
+// rustdoc-stripper-ignore-next
+/// An asynchronous reader-writer lock.
+///
+/// Any number of readers or a single writer can hold the lock. Readers and writers are served in
+/// the order they requested the lock, so a waiting writer is not starved by new readers.
+pub struct RwLock<T: ?Sized> {
+    permits: Permits,
+    value: UnsafeCell<T>,
+}
+
+impl<T> RwLock<T> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new, unlocked lock containing `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            permits: Permits::new(MAX_READERS),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Consumes the lock and returns the contained value.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    // rustdoc-stripper-ignore-next
+    /// Waits until no writer holds the lock and locks it for reading.
+    pub fn read(&self) -> RwLockReadFuture<'_, T> {
+        RwLockReadFuture {
+            lock: self,
+            inner: AcquirePermits::new(&self.permits, 1),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits until nobody holds the lock and locks it for writing.
+    pub fn write(&self) -> RwLockWriteFuture<'_, T> {
+        RwLockWriteFuture {
+            lock: self,
+            inner: AcquirePermits::new(&self.permits, MAX_READERS),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Locks for reading if no writer holds or waits for the lock.
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        self.permits
+            .try_acquire(1)
+            .then(|| RwLockReadGuard { lock: self })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Locks for writing if nobody holds or waits for the lock.
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        self.permits
+            .try_acquire(MAX_READERS)
+            .then(|| RwLockWriteGuard { lock: self })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns a mutable reference to the contained value.
+    ///
+    /// This doesn't need to lock as the mutable borrow guarantees that no guard exists.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for RwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut d = f.debug_struct("RwLock");
+        match self.try_read() {
+            Some(guard) => d.field("value", &&*guard),
+            None => d.field("value", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`RwLock::read`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RwLockReadFuture<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    inner: AcquirePermits<'a>,
+}
+
+impl<'a, T: ?Sized> Future for RwLockReadFuture<'a, T> {
+    type Output = RwLockReadGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.inner
+            .poll_acquire(cx)
+            .map(|()| RwLockReadGuard { lock: this.lock })
+    }
+}
+
+impl<'a, T: ?Sized> FusedFuture for RwLockReadFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for RwLockReadFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RwLockReadFuture").finish_non_exhaustive()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`RwLock::write`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RwLockWriteFuture<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    inner: AcquirePermits<'a>,
+}
+
+impl<'a, T: ?Sized> Future for RwLockWriteFuture<'a, T> {
+    type Output = RwLockWriteGuard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.inner
+            .poll_acquire(cx)
+            .map(|()| RwLockWriteGuard { lock: this.lock })
+    }
+}
+
+impl<'a, T: ?Sized> FusedFuture for RwLockWriteFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+impl<'a, T: ?Sized> fmt::Debug for RwLockWriteFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RwLockWriteFuture").finish_non_exhaustive()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A [`RwLock`] locked for reading.
+///
+/// The lock is released when this is dropped.
+#[must_use = "if unused the lock is released immediately"]
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T: ?Sized> Deref for RwLockReadGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(1);
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockReadGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A [`RwLock`] locked for writing.
+///
+/// The lock is released when this is dropped.
+#[must_use = "if unused the lock is released immediately"]
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+impl<'a, T: ?Sized> Deref for RwLockWriteGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for RwLockWriteGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for RwLockWriteGuard<'a, T> {
+    fn drop(&mut self) {
+        self.lock.permits.release(MAX_READERS);
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for RwLockWriteGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rwlock() {
+        let c = MainContext::new();
+        let lock = Rc::new(RwLock::new(Vec::new()));
+        let readers = Rc::new(Cell::new(0));
+
+        c.block_on(async {
+            let read = lock.read().await;
+            let read2 = lock.try_read().unwrap();
+            assert!(lock.try_write().is_none());
+
+            let writer = {
+                let lock = lock.clone();
+                c.spawn_local_task(async move {
+                    lock.write().await.push("written");
+                })
+            };
+            let reader = {
+                let lock = lock.clone();
+                let readers = readers.clone();
+                c.spawn_local_task(async move {
+                    let read = lock.read().await;
+                    readers.set(read.len());
+                })
+            };
+
+            // The writer waits for the readers and the new reader waits for the writer
+            crate::timeout_future(Duration::from_millis(10)).await;
+            assert!(!writer.is_finished());
+            assert!(!reader.is_finished());
+            assert!(lock.try_read().is_none());
+
+            drop(read);
+            drop(read2);
+            writer.await.unwrap();
+            reader.await.unwrap();
+        });
+
+        assert_eq!(readers.get(), 1);
+        assert_eq!(*c.block_on(lock.read()), ["written"]);
+    }
+
+    #[test]
+    fn test_join_same_task() {
+        let c = crate::MainContext::new();
+        let lock = RwLock::new(Vec::new());
+        c.block_on(async {
+            futures_util::join!(
+                async {
+                    let read = lock.read().await;
+                    crate::timeout_future(std::time::Duration::from_millis(10)).await;
+                    assert!(read.is_empty());
+                },
+                async {
+                    lock.write().await.push("written");
+                },
+            );
+        });
+        assert_eq!(*c.block_on(lock.read()), ["written"]);
+    }
+}
@@ -0,0 +1,441 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_core::FusedFuture;
+
+struct Waiter {
+    id: u64,
+    needed: usize,
+    waker: Option<Waker>,
+    granted: bool,
+}
+
+struct State {
+    total: usize,
+    available: usize,
+    waiters: VecDeque<Waiter>,
+    next_id: u64,
+}
+
+// Fair counting semaphore that is the base for the `Mutex`, `RwLock` and `Semaphore`.
+//
+// Waiters are served in FIFO order. Permits are handed over to waiters directly when they are
+// released, so a waiter that was woken can't be overtaken by a new acquisition.
+pub(super) struct Permits {
+    state: RefCell<State>,
+}
+
+impl Permits {
+    pub(super) fn new(permits: usize) -> Self {
+        Self {
+            state: RefCell::new(State {
+                total: permits,
+                available: permits,
+                waiters: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    pub(super) fn available(&self) -> usize {
+        self.state.borrow().available
+    }
+
+    pub(super) fn try_acquire(&self, needed: usize) -> bool {
+        let mut state = self.state.borrow_mut();
+        if state.waiters.is_empty() && state.available >= needed {
+            state.available -= needed;
+            true
+        } else {
+            false
+        }
+    }
+
+    // `id` is `None` before the first poll and is set to the waiter id once queued.
+    pub(super) fn poll_acquire(
+        &self,
+        needed: usize,
+        id: &mut Option<u64>,
+        cx: &mut Context,
+    ) -> Poll<()> {
+        let mut state = self.state.borrow_mut();
+
+        match *id {
+            None if state.waiters.is_empty() && state.available >= needed => {
+                state.available -= needed;
+                Poll::Ready(())
+            }
+            None => {
+                state.next_id += 1;
+                let waiter_id = state.next_id;
+                state.waiters.push_back(Waiter {
+                    id: waiter_id,
+                    needed,
+                    waker: Some(cx.waker().clone()),
+                    granted: false,
+                });
+                *id = Some(waiter_id);
+                Poll::Pending
+            }
+            Some(waiter_id) => {
+                let pos = state
+                    .waiters
+                    .iter()
+                    .position(|w| w.id == waiter_id)
+                    .expect("waiter not queued");
+                if state.waiters[pos].granted {
+                    state.waiters.remove(pos);
+                    *id = None;
+                    Poll::Ready(())
+                } else {
+                    let waiter = &mut state.waiters[pos];
+                    if !waiter
+                        .waker
+                        .as_ref()
+                        .map_or(false, |w| w.will_wake(cx.waker()))
+                    {
+                        waiter.waker = Some(cx.waker().clone());
+                    }
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    // Called if an acquisition future is dropped before completing.
+    pub(super) fn cancel(&self, id: u64) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            let pos = match state.waiters.iter().position(|w| w.id == id) {
+                Some(pos) => pos,
+                None => return,
+            };
+            let waiter = state.waiters.remove(pos).unwrap();
+            if waiter.granted {
+                state.available += waiter.needed;
+            }
+            Self::grant(&mut state)
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    pub(super) fn release(&self, permits: usize) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            state.available += permits;
+            Self::grant(&mut state)
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    pub(super) fn add_permits(&self, permits: usize) {
+        let wakers = {
+            let mut state = self.state.borrow_mut();
+            state.total += permits;
+            state.available += permits;
+            Self::grant(&mut state)
+        };
+
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    // Forgets permits of a holder without returning them.
+    pub(super) fn forget(&self, permits: usize) {
+        let mut state = self.state.borrow_mut();
+        state.total -= permits;
+    }
+
+    // Hands over available permits to the waiters in order and returns their wakers. The
+    // wakers are woken after the state is not borrowed anymore.
+    fn grant(state: &mut State) -> Vec<Waker> {
+        let mut wakers = Vec::new();
+        let mut available = state.available;
+        for waiter in state.waiters.iter_mut() {
+            if waiter.granted {
+                continue;
+            }
+            if waiter.needed > available {
+                break;
+            }
+            available -= waiter.needed;
+            waiter.granted = true;
+            wakers.extend(waiter.waker.take());
+        }
+        state.available = available;
+        wakers
+    }
+}
+
+impl fmt::Debug for Permits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("Permits")
+            .field("total", &state.total)
+            .field("available", &state.available)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+// Future for acquiring permits that gives them back if dropped before completion.
+pub(super) struct AcquirePermits<'a> {
+    permits: &'a Permits,
+    needed: usize,
+    id: Option<u64>,
+    done: bool,
+}
+
+impl<'a> AcquirePermits<'a> {
+    pub(super) fn new(permits: &'a Permits, needed: usize) -> Self {
+        Self {
+            permits,
+            needed,
+            id: None,
+            done: false,
+        }
+    }
+
+    pub(super) fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub(super) fn poll_acquire(&mut self, cx: &mut Context) -> Poll<()> {
+        assert!(!self.done, "future polled after completion");
+        let res = self.permits.poll_acquire(self.needed, &mut self.id, cx);
+        if res.is_ready() {
+            self.done = true;
+        }
+        res
+    }
+}
+
+impl<'a> Drop for AcquirePermits<'a> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.permits.cancel(id);
+        }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// An asynchronous counting semaphore.
+///
+/// Permits are handed out in the order they were requested.
+#[derive(Debug)]
+pub struct Semaphore {
+    permits: Permits,
+}
+
+impl Semaphore {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new semaphore with `permits` permits.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Permits::new(permits),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of permits that are currently available.
+    pub fn available_permits(&self) -> usize {
+        self.permits.available()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds `n` new permits to the semaphore.
+    pub fn add_permits(&self, n: usize) {
+        self.permits.add_permits(n);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for a permit.
+    pub fn acquire(&self) -> Acquire<'_> {
+        self.acquire_many(1)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for `n` permits.
+    pub fn acquire_many(&self, n: usize) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            inner: AcquirePermits::new(&self.permits, n),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Acquires a permit if one is available and nobody else is waiting.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.try_acquire_many(1)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Acquires `n` permits if they are available and nobody else is waiting.
+    pub fn try_acquire_many(&self, n: usize) -> Option<SemaphorePermit<'_>> {
+        self.permits.try_acquire(n).then(|| SemaphorePermit {
+            semaphore: self,
+            permits: n,
+        })
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`Semaphore::acquire`] and [`Semaphore::acquire_many`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    inner: AcquirePermits<'a>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        this.inner.poll_acquire(cx).map(|()| SemaphorePermit {
+            semaphore: this.semaphore,
+            permits: this.inner.needed,
+        })
+    }
+}
+
+impl<'a> FusedFuture for Acquire<'a> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+impl<'a> fmt::Debug for Acquire<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Acquire")
+            .field("permits", &self.inner.needed)
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Permits acquired from a [`Semaphore`].
+///
+/// The permits are given back to the semaphore when this is dropped.
+#[must_use = "if unused the permits are given back immediately"]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+    permits: usize,
+}
+
+impl<'a> SemaphorePermit<'a> {
+    // rustdoc-stripper-ignore-next
+    /// The number of permits held.
+    pub fn num_permits(&self) -> usize {
+        self.permits
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Removes the permits from the semaphore instead of giving them back.
+    pub fn forget(self) {
+        self.semaphore.permits.forget(self.permits);
+        std::mem::forget(self);
+    }
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        self.semaphore.permits.release(self.permits);
+    }
+}
+
+impl<'a> fmt::Debug for SemaphorePermit<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SemaphorePermit")
+            .field("permits", &self.permits)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_semaphore() {
+        let c = MainContext::new();
+        let semaphore = Rc::new(Semaphore::new(2));
+
+        let permit = semaphore.try_acquire_many(2).unwrap();
+        assert_eq!(semaphore.available_permits(), 0);
+        assert!(semaphore.try_acquire().is_none());
+        drop(permit);
+        assert_eq!(semaphore.available_permits(), 2);
+
+        c.block_on(async {
+            let permit = semaphore.acquire().await;
+            let s = semaphore.clone();
+            let handle = c.spawn_local_task(async move {
+                let _permits = s.acquire_many(2).await;
+            });
+
+            // The second task waits until the first permit is given back
+            let res = crate::future_with_timeout(
+                Duration::from_millis(20),
+                crate::timeout_future(Duration::from_secs(60)),
+            )
+            .await;
+            assert!(res.is_err());
+            assert!(!handle.is_finished());
+
+            // Waiters are served in order, so a single permit is not handed out now
+            assert!(semaphore.try_acquire().is_none());
+            drop(permit);
+            handle.await.unwrap();
+        });
+        assert_eq!(semaphore.available_permits(), 2);
+
+        semaphore.try_acquire().unwrap().forget();
+        semaphore.add_permits(3);
+        assert_eq!(semaphore.available_permits(), 4);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let c = MainContext::new();
+        let semaphore = Semaphore::new(1);
+
+        let permit = semaphore.try_acquire().unwrap();
+        c.block_on(async {
+            let res =
+                crate::future_with_timeout(Duration::from_millis(10), semaphore.acquire()).await;
+            assert!(res.is_err());
+            drop(permit);
+            // The cancelled waiter does not hold up the queue
+            assert!(semaphore.try_acquire().is_some());
+        });
+    }
+
+    #[test]
+    fn test_join_same_task() {
+        let c = crate::MainContext::new();
+        let semaphore = Semaphore::new(3);
+        c.block_on(async {
+            futures_util::join!(
+                async {
+                    let _permits = semaphore.acquire_many(2).await;
+                    crate::timeout_future(std::time::Duration::from_millis(10)).await;
+                },
+                async {
+                    let _more = semaphore.acquire_many(2).await;
+                },
+            );
+        });
+        assert_eq!(semaphore.available_permits(), 3);
+    }
+}
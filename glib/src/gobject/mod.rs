@@ -10,6 +10,7 @@ mod binding;
 #[cfg_attr(feature = "dox", doc(cfg(feature = "v2_72")))]
 mod binding_group;
 mod flags;
+mod property_expression;
 #[cfg(any(feature = "v2_74", feature = "dox"))]
 #[cfg_attr(feature = "dox", doc(cfg(feature = "v2_74")))]
 mod signal_group;
@@ -20,4 +21,7 @@ pub use binding_group::BindingGroupBuilder;
 
 pub use self::auto::*;
 pub use self::flags::*;
+pub use self::property_expression::{
+    PropertyExpression, PropertyExpressionBuilder, PropertyExpressionFreezeGuard,
+};
 //pub use self::auto::functions::*;
@@ -0,0 +1,674 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use crate::object::PropertyNotificationFreezeGuard;
+use crate::prelude::*;
+use crate::translate::*;
+use crate::Object;
+use crate::ParamFlags;
+use crate::ParamSpec;
+use crate::SignalHandlerId;
+use crate::Value;
+use crate::WeakRef;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+type ComputeFn = Box<dyn Fn(&[Value]) -> Option<Value> + 'static>;
+
+// rustdoc-stripper-ignore-next
+/// A property of a target object computed from the properties of any number of source objects.
+///
+/// Whenever one of the source properties notifies a change, the closure passed to
+/// [`PropertyExpressionBuilder::build`] is called with the current values of all source
+/// properties, in the order they were added, and its return value is written to the target
+/// property.
+///
+/// Source and target objects are only referenced weakly. Once one of them is finalized the
+/// expression stops watching the remaining objects, the same happens when calling
+/// [`Self::unwatch`].
+///
+/// Changing a source property from a different thread than the one that built the expression
+/// will panic.
+///
+/// # Example
+///
+/// ```
+/// # use glib::prelude::*;
+/// # use glib::subclass::prelude::*;
+/// # mod imp {
+/// #     use glib::prelude::*;
+/// #     use glib::subclass::prelude::*;
+/// #     use std::cell::Cell;
+/// #
+/// #     #[derive(Default)]
+/// #     pub struct Switch {
+/// #         pub active: Cell<bool>,
+/// #     }
+/// #
+/// #     #[glib::object_subclass]
+/// #     impl ObjectSubclass for Switch {
+/// #         const NAME: &'static str = "PropertyExpressionDocSwitch";
+/// #         type Type = super::Switch;
+/// #     }
+/// #
+/// #     impl ObjectImpl for Switch {
+/// #         fn properties() -> &'static [glib::ParamSpec] {
+/// #             use once_cell::sync::Lazy;
+/// #             static PROPERTIES: Lazy<Vec<glib::ParamSpec>> =
+/// #                 Lazy::new(|| vec![glib::ParamSpecBoolean::builder("active").build()]);
+/// #             PROPERTIES.as_ref()
+/// #         }
+/// #
+/// #         fn set_property(&self, _id: usize, value: &glib::Value, _pspec: &glib::ParamSpec) {
+/// #             self.active.set(value.get().unwrap());
+/// #         }
+/// #
+/// #         fn property(&self, _id: usize, _pspec: &glib::ParamSpec) -> glib::Value {
+/// #             self.active.get().to_value()
+/// #         }
+/// #     }
+/// # }
+/// #
+/// # glib::wrapper! {
+/// #     pub struct Switch(ObjectSubclass<imp::Switch>);
+/// # }
+/// #
+/// # fn main() {
+/// # let new_switch = || glib::Object::new::<Switch>(&[]);
+/// let (button, a, b) = (new_switch(), new_switch(), new_switch());
+/// a.set_property("active", true);
+///
+/// let expression = glib::PropertyExpression::builder(&button, "active")
+///     .source(&a, "active")
+///     .source(&b, "active")
+///     .sync_create()
+///     .build(|values| {
+///         let a = values[0].get::<bool>().unwrap();
+///         let b = values[1].get::<bool>().unwrap();
+///         Some((a && !b).to_value())
+///     });
+/// assert!(button.property::<bool>("active"));
+///
+/// b.set_property("active", true);
+/// assert!(!button.property::<bool>("active"));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct PropertyExpression(Rc<Inner>);
+
+struct Inner {
+    sources: Vec<(WeakRef<Object>, ParamSpec)>,
+    target: WeakRef<Object>,
+    target_pspec: ParamSpec,
+    func: ComputeFn,
+    handlers: RefCell<Option<Vec<SignalHandlerId>>>,
+    freeze_count: Cell<usize>,
+    pending: Cell<bool>,
+    updating: Cell<bool>,
+}
+
+impl PropertyExpression {
+    // rustdoc-stripper-ignore-next
+    /// Creates a builder for an expression that writes to `target_property` of `target`.
+    pub fn builder<'a>(
+        target: &'a impl IsA<Object>,
+        target_property: &'a str,
+    ) -> PropertyExpressionBuilder<'a> {
+        PropertyExpressionBuilder::new(target, target_property)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the target object, if it's still alive.
+    pub fn target(&self) -> Option<Object> {
+        self.0.target.upgrade()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the name of the target property.
+    pub fn target_property(&self) -> &str {
+        self.0.target_pspec.name()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns whether the expression is still watching its source objects.
+    pub fn is_watching(&self) -> bool {
+        self.0.handlers.borrow().is_some()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Recomputes the target property from the current values of the source properties.
+    pub fn update(&self) {
+        self.0.update();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Stops watching the source objects.
+    ///
+    /// The target property is not updated anymore afterwards.
+    pub fn unwatch(&self) {
+        self.0.unwatch();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Freezes property notifications of all source objects and of the target object.
+    ///
+    /// Changes of source properties while the returned guard is alive are batched, and the
+    /// target property is recomputed at most once when it is dropped. Property notifications
+    /// of the target object are emitted afterwards.
+    pub fn freeze(&self) -> PropertyExpressionFreezeGuard {
+        self.0.freeze_count.set(self.0.freeze_count.get() + 1);
+
+        PropertyExpressionFreezeGuard {
+            target: self.0.target.upgrade().map(|target| target.freeze_notify()),
+            sources: self
+                .0
+                .sources
+                .iter()
+                .filter_map(|(source, _)| source.upgrade())
+                .map(|source| source.freeze_notify())
+                .collect(),
+            inner: self.0.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for PropertyExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertyExpression")
+            .field(
+                "sources",
+                &self
+                    .0
+                    .sources
+                    .iter()
+                    .map(|(source, pspec)| (source.upgrade(), pspec.name()))
+                    .collect::<Vec<_>>(),
+            )
+            .field("target", &self.0.target.upgrade())
+            .field("target_property", &self.0.target_pspec.name())
+            .finish()
+    }
+}
+
+impl Inner {
+    fn update(&self) {
+        if self.freeze_count.get() > 0 {
+            self.pending.set(true);
+            return;
+        }
+
+        // Writing the target property can notify a source property again, e.g. if the target is
+        // also one of the sources. The expression is then recomputed once the current update is
+        // done, until the computed value doesn't change anymore.
+        if self.updating.replace(true) {
+            self.pending.set(true);
+            return;
+        }
+
+        struct ResetUpdating<'a>(&'a Cell<bool>);
+        impl<'a> Drop for ResetUpdating<'a> {
+            fn drop(&mut self) {
+                self.0.set(false);
+            }
+        }
+        let _reset = ResetUpdating(&self.updating);
+
+        let mut previous: Option<Value> = None;
+        loop {
+            self.pending.set(false);
+
+            let sources = self
+                .sources
+                .iter()
+                .map(|(source, _)| source.upgrade())
+                .collect::<Option<Vec<_>>>();
+            let watching = self.handlers.borrow().is_some();
+            let (sources, target) = match (sources, self.target.upgrade()) {
+                (Some(sources), Some(target)) if watching => (sources, target),
+                _ => {
+                    self.unwatch();
+                    return;
+                }
+            };
+
+            let values = sources
+                .iter()
+                .zip(&self.sources)
+                .map(|(source, (_, pspec))| source.property_value(pspec.name()))
+                .collect::<smallvec::SmallVec<[_; 4]>>();
+
+            if let Some(value) = (self.func)(&values) {
+                if previous.map_or(false, |previous| self.target_value_eq(&previous, &value)) {
+                    return;
+                }
+                target.set_property_from_value(self.target_pspec.name(), &value);
+                previous = Some(value);
+            }
+
+            if !self.pending.get() || self.freeze_count.get() > 0 {
+                return;
+            }
+        }
+    }
+
+    fn target_value_eq(&self, a: &Value, b: &Value) -> bool {
+        unsafe {
+            gobject_ffi::g_param_values_cmp(
+                self.target_pspec.to_glib_none().0,
+                a.to_glib_none().0,
+                b.to_glib_none().0,
+            ) == 0
+        }
+    }
+
+    fn unwatch(&self) {
+        let handlers = match self.handlers.take() {
+            Some(handlers) => handlers,
+            None => return,
+        };
+
+        for ((source, _), handler) in self.sources.iter().zip(handlers) {
+            if let Some(source) = source.upgrade() {
+                source.disconnect(handler);
+            }
+        }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Builder for [`PropertyExpression`]s.
+#[must_use = "The builder must be built to be used"]
+pub struct PropertyExpressionBuilder<'a> {
+    sources: Vec<(&'a Object, &'a str)>,
+    target: &'a Object,
+    target_property: &'a str,
+    sync_create: bool,
+}
+
+impl<'a> fmt::Debug for PropertyExpressionBuilder<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertyExpressionBuilder")
+            .field("sources", &self.sources)
+            .field("target", &self.target)
+            .field("target_property", &self.target_property)
+            .field("sync_create", &self.sync_create)
+            .finish()
+    }
+}
+
+impl<'a> PropertyExpressionBuilder<'a> {
+    fn new(target: &'a impl IsA<Object>, target_property: &'a str) -> Self {
+        Self {
+            sources: Vec::new(),
+            target: target.upcast_ref(),
+            target_property,
+            sync_create: false,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds `property` of `source` to the properties the target property is computed from.
+    pub fn source(mut self, source: &'a impl IsA<Object>, property: &'a str) -> Self {
+        self.sources.push((source.upcast_ref(), property));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Computes the target property once when building the expression.
+    pub fn sync_create(self) -> Self {
+        Self {
+            sync_create: true,
+            ..self
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Establish the expression with `func` computing the value of the target property.
+    ///
+    /// `func` is called with the values of the source properties in the order they were added.
+    /// If it returns `None` the target property is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the provided properties do not exist, a source property is not readable
+    /// or the target property is not writable.
+    #[track_caller]
+    pub fn build<F: Fn(&[Value]) -> Option<Value> + 'static>(self, func: F) -> PropertyExpression {
+        let target_pspec = self
+            .target
+            .find_property(self.target_property)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Target property '{}' of type '{}' not found",
+                    self.target_property,
+                    self.target.type_()
+                )
+            });
+        assert!(
+            target_pspec.flags().contains(ParamFlags::WRITABLE),
+            "Target property '{}' of type '{}' is not writable",
+            self.target_property,
+            self.target.type_()
+        );
+
+        let sources = self
+            .sources
+            .iter()
+            .map(|(source, property)| {
+                let pspec = source.find_property(property).unwrap_or_else(|| {
+                    panic!(
+                        "Source property '{}' of type '{}' not found",
+                        property,
+                        source.type_()
+                    )
+                });
+                assert!(
+                    pspec.flags().contains(ParamFlags::READABLE),
+                    "Source property '{}' of type '{}' is not readable",
+                    property,
+                    source.type_()
+                );
+                (source.downgrade(), pspec)
+            })
+            .collect();
+
+        let inner = Rc::new(Inner {
+            sources,
+            target: self.target.downgrade(),
+            target_pspec,
+            func: Box::new(func),
+            handlers: RefCell::new(None),
+            freeze_count: Cell::new(0),
+            pending: Cell::new(false),
+            updating: Cell::new(false),
+        });
+
+        let handlers = self
+            .sources
+            .iter()
+            .zip(&inner.sources)
+            .map(|((source, _), (_, pspec))| {
+                let inner = inner.clone();
+                source.connect_notify_local(Some(pspec.name()), move |_, _| inner.update())
+            })
+            .collect();
+        inner.handlers.replace(Some(handlers));
+
+        if self.sync_create {
+            inner.update();
+        }
+
+        PropertyExpression(inner)
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Guard returned by [`PropertyExpression::freeze`].
+#[must_use = "if unused the property notifications will immediately be thawed"]
+pub struct PropertyExpressionFreezeGuard {
+    sources: Vec<PropertyNotificationFreezeGuard>,
+    target: Option<PropertyNotificationFreezeGuard>,
+    inner: Rc<Inner>,
+}
+
+impl Drop for PropertyExpressionFreezeGuard {
+    fn drop(&mut self) {
+        // Thawing the sources emits their queued notifications, which only mark the expression
+        // as pending while it is still frozen.
+        self.sources.clear();
+
+        let freeze_count = self.inner.freeze_count.get() - 1;
+        self.inner.freeze_count.set(freeze_count);
+        if freeze_count == 0 && self.inner.pending.replace(false) {
+            self.inner.update();
+        }
+
+        // The target is thawed last so that it notifies its property at most once.
+        self.target.take();
+    }
+}
+
+impl fmt::Debug for PropertyExpressionFreezeGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PropertyExpressionFreezeGuard")
+            .field("target", &self.inner.target.upgrade())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::prelude::*;
+    use crate::subclass::prelude::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn enabled_expression(
+        target: &TestObject,
+        a: &TestObject,
+        b: &TestObject,
+    ) -> super::PropertyExpression {
+        super::PropertyExpression::builder(target, "enabled")
+            .source(a, "enabled")
+            .source(b, "busy")
+            .sync_create()
+            .build(|values| {
+                let enabled = values[0].get::<bool>().unwrap();
+                let busy = values[1].get::<bool>().unwrap();
+                Some((enabled && !busy).to_value())
+            })
+    }
+
+    #[test]
+    fn expression() {
+        let a = TestObject::default();
+        let b = TestObject::default();
+        let target = TestObject::default();
+
+        a.set_enabled(true);
+        let expression = enabled_expression(&target, &a, &b);
+        assert!(target.enabled());
+
+        b.set_busy(true);
+        assert!(!target.enabled());
+
+        b.set_busy(false);
+        assert!(target.enabled());
+
+        a.set_enabled(false);
+        assert!(!target.enabled());
+
+        expression.unwatch();
+        assert!(!expression.is_watching());
+        a.set_enabled(true);
+        assert!(!target.enabled());
+    }
+
+    #[test]
+    fn expression_weak_sources() {
+        let a = TestObject::default();
+        let b = TestObject::default();
+        let target = TestObject::default();
+
+        let expression = enabled_expression(&target, &a, &b);
+        assert!(expression.is_watching());
+
+        drop(b);
+        a.set_enabled(true);
+        assert!(!expression.is_watching());
+        assert!(!target.enabled());
+
+        drop(target);
+        assert!(expression.target().is_none());
+    }
+
+    #[test]
+    fn expression_freeze() {
+        let a = TestObject::default();
+        let b = TestObject::default();
+        let target = TestObject::default();
+
+        let computed = Rc::new(Cell::new(0));
+        let expression = super::PropertyExpression::builder(&target, "enabled")
+            .source(&a, "enabled")
+            .source(&b, "busy")
+            .build(crate::clone!(@strong computed => move |values| {
+                computed.set(computed.get() + 1);
+                let enabled = values[0].get::<bool>().unwrap();
+                let busy = values[1].get::<bool>().unwrap();
+                Some((enabled && !busy).to_value())
+            }));
+        assert_eq!(computed.get(), 0);
+
+        let notified = Rc::new(Cell::new(0));
+        target.connect_notify_local(
+            Some("enabled"),
+            crate::clone!(@strong notified => move |_, _| notified.set(notified.get() + 1)),
+        );
+
+        {
+            let _guard = expression.freeze();
+            a.set_enabled(true);
+            b.set_busy(true);
+            b.set_busy(false);
+            assert_eq!(computed.get(), 0);
+            assert!(!target.enabled());
+        }
+        assert_eq!(computed.get(), 1);
+        assert_eq!(notified.get(), 1);
+        assert!(target.enabled());
+    }
+
+    #[test]
+    fn expression_reentrant() {
+        let a = TestObject::default();
+        let b = TestObject::default();
+        let target = TestObject::default();
+
+        let _expression = enabled_expression(&target, &a, &b);
+        target.connect_notify_local(
+            Some("enabled"),
+            crate::clone!(@strong b => move |target, _| {
+                if target.enabled() {
+                    b.set_busy(true);
+                }
+            }),
+        );
+
+        // Changing a source while the target is written recomputes the target afterwards
+        a.set_enabled(true);
+        assert!(b.busy());
+        assert!(!target.enabled());
+    }
+
+    #[test]
+    fn expression_panic() {
+        let a = TestObject::default();
+        let target = TestObject::default();
+
+        let expression = super::PropertyExpression::builder(&target, "enabled")
+            .source(&a, "busy")
+            .build(|values| {
+                let busy = values[0].get::<bool>().unwrap();
+                assert!(!busy, "busy");
+                Some(true.to_value())
+            });
+
+        // Panics can't unwind through the notify signal, so the expression is updated directly
+        *a.imp().busy.borrow_mut() = true;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| expression.update()));
+        assert!(res.is_err());
+        assert!(!target.enabled());
+
+        *a.imp().busy.borrow_mut() = false;
+        expression.update();
+        assert!(target.enabled());
+    }
+
+    mod imp {
+        use super::*;
+
+        use once_cell::sync::Lazy;
+        use std::cell::RefCell;
+
+        use crate as glib;
+
+        #[derive(Debug, Default)]
+        pub struct TestObject {
+            pub enabled: RefCell<bool>,
+            pub busy: RefCell<bool>,
+        }
+
+        #[crate::object_subclass]
+        impl ObjectSubclass for TestObject {
+            const NAME: &'static str = "TestPropertyExpression";
+            type Type = super::TestObject;
+        }
+
+        impl ObjectImpl for TestObject {
+            fn properties() -> &'static [crate::ParamSpec] {
+                static PROPERTIES: Lazy<Vec<crate::ParamSpec>> = Lazy::new(|| {
+                    vec![
+                        crate::ParamSpecBoolean::builder("enabled")
+                            .explicit_notify()
+                            .build(),
+                        crate::ParamSpecBoolean::builder("busy")
+                            .explicit_notify()
+                            .build(),
+                    ]
+                });
+                PROPERTIES.as_ref()
+            }
+
+            fn property(&self, _id: usize, pspec: &crate::ParamSpec) -> crate::Value {
+                let obj = self.instance();
+                match pspec.name() {
+                    "enabled" => obj.enabled().to_value(),
+                    "busy" => obj.busy().to_value(),
+                    _ => unimplemented!(),
+                }
+            }
+
+            fn set_property(&self, _id: usize, value: &crate::Value, pspec: &crate::ParamSpec) {
+                let obj = self.instance();
+                match pspec.name() {
+                    "enabled" => obj.set_enabled(value.get().unwrap()),
+                    "busy" => obj.set_busy(value.get().unwrap()),
+                    _ => unimplemented!(),
+                };
+            }
+        }
+    }
+
+    crate::wrapper! {
+        pub struct TestObject(ObjectSubclass<imp::TestObject>);
+    }
+
+    impl Default for TestObject {
+        fn default() -> Self {
+            crate::Object::new(&[])
+        }
+    }
+
+    impl TestObject {
+        fn enabled(&self) -> bool {
+            *self.imp().enabled.borrow()
+        }
+
+        fn set_enabled(&self, enabled: bool) {
+            if enabled != self.imp().enabled.replace(enabled) {
+                self.notify("enabled");
+            }
+        }
+
+        fn busy(&self) -> bool {
+            *self.imp().busy.borrow()
+        }
+
+        fn set_busy(&self, busy: bool) {
+            if busy != self.imp().busy.replace(busy) {
+                self.notify("busy");
+            }
+        }
+    }
+}
@@ -1,18 +1,30 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
+use crate::closure::{ClosureArgs, SignalClosure, ToClosureReturnValue};
 use crate::signal::connect_raw;
 use crate::signal::SignalHandlerId;
+use crate::subclass::SignalId;
 use crate::translate::*;
 use crate::Object;
 use crate::ObjectType;
 use crate::RustClosure;
 use crate::SignalGroup;
+use crate::StaticType;
+use crate::TypedClosure;
 use crate::Value;
 use std::mem::transmute;
 
 impl SignalGroup {
+    // rustdoc-stripper-ignore-next
+    /// Connects `closure` to the signal `signal_name` of the current and future targets.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the group's target type has no signal `signal_name`.
     #[doc(alias = "g_signal_group_connect_closure")]
+    #[track_caller]
     pub fn connect_closure(&self, signal_name: &str, after: bool, closure: RustClosure) {
+        self.signal_id(signal_name);
         unsafe {
             gobject_ffi::g_signal_group_connect_closure(
                 self.to_glib_none().0,
@@ -23,6 +35,52 @@ impl SignalGroup {
         }
     }
 
+    // rustdoc-stripper-ignore-next
+    /// Connects a [`TypedClosure`] to the signal `signal_name` of the current and future targets.
+    ///
+    /// Same as [`Self::connect_closure`] but the signature of the closure is checked against the
+    /// signal of the group's target type here instead of failing on emission.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the group's target type has no signal `signal_name` or if the signature
+    /// doesn't match.
+    #[track_caller]
+    pub fn connect_typed_closure<Args, R>(
+        &self,
+        signal_name: &str,
+        after: bool,
+        closure: TypedClosure<Args, R>,
+    ) where
+        Args: ClosureArgs,
+        R: ToClosureReturnValue + StaticType + 'static,
+    {
+        let signal_id = self.signal_id(signal_name);
+        let target_type = self.target_type();
+        if let Err(err) = closure.check_signal(signal_id, target_type) {
+            panic!(
+                "Can't connect closure to signal '{}' of type '{}': {}",
+                signal_name, target_type, err
+            );
+        }
+        self.connect_closure(signal_name, after, closure.into());
+    }
+
+    // The target type's signal `signal_name`, same as `ObjectExt::connect_closure()` this panics
+    // instead of only emitting a critical if it doesn't exist.
+    #[track_caller]
+    fn signal_id(&self, signal_name: &str) -> SignalId {
+        let target_type = self.target_type();
+        SignalId::parse_name(signal_name, target_type, true)
+            .map(|(signal_id, _)| signal_id)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Signal '{}' of type '{}' not found",
+                    signal_name, target_type
+                )
+            })
+    }
+
     #[doc(alias = "g_signal_group_connect")]
     #[inline]
     pub fn connect<F>(&self, signal_name: &str, after: bool, callback: F)
@@ -201,4 +259,49 @@ mod tests {
         let ret = obj.emit_by_name::<Option<String>>("sig-with-ret", &[]);
         assert_eq!(ret, None);
     }
+
+    #[test]
+    fn group_emit_typed() {
+        let group = SignalGroup::new(SignalObject::static_type());
+
+        let obj = Object::new::<SignalObject>(&[]);
+        let store = Rc::new(RefCell::new(String::new()));
+        group.connect_typed_closure(
+            "sig-with-args",
+            false,
+            glib::TypedClosure::new_local(
+                glib::clone!(@strong store => move |_: SignalObject, a: u32, b: String| {
+                    store.replace(format!("a {} b {}", a, b));
+                }),
+            ),
+        );
+        group.connect_typed_closure(
+            "sig-with-ret",
+            false,
+            glib::TypedClosure::new(|_: SignalObject| String::from("Hello")),
+        );
+        group.set_target(Some(&obj));
+        obj.emit_by_name::<()>("sig-with-args", &[&5u32, &"World"]);
+        assert_eq!(*store.borrow(), "a 5 b World");
+        let ret = obj.emit_by_name::<String>("sig-with-ret", &[]);
+        assert_eq!(ret, "Hello");
+    }
+
+    #[test]
+    #[should_panic = "Closure expects argument 2 of type 'gint' but signal 'sig-with-args' passes 'gchararray'"]
+    fn group_typed_mismatch() {
+        let group = SignalGroup::new(SignalObject::static_type());
+        group.connect_typed_closure(
+            "sig-with-args",
+            false,
+            glib::TypedClosure::new(|_: SignalObject, _: u32, _: i32| {}),
+        );
+    }
+
+    #[test]
+    #[should_panic = "Signal 'no-such-signal' of type 'SignalObject' not found"]
+    fn group_unknown_signal() {
+        let group = SignalGroup::new(SignalObject::static_type());
+        group.connect_local("no-such-signal", false, |_| None);
+    }
 }
@@ -0,0 +1,350 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Tracking of live objects for finding leaks.
+//!
+//! When enabled, every instance of an [`ObjectSubclass`](crate::subclass::types::ObjectSubclass)
+//! is recorded from instance initialization until it is finalized, together with the backtrace
+//! of its creation if the `backtrace` feature is enabled. Objects of types not implemented in Rust can be added with [`track`].
+//!
+//! Objects whose only remaining references are toggle references are owned by the wrapper of
+//! another language runtime and are not reported as leaks by [`assert_no_leaks!`]. For this the
+//! toggle references have to be added and removed with [`add_toggle_ref`] and
+//! [`remove_toggle_ref`], as GObject does not expose them otherwise. Toggle references that are
+//! added by calling `g_object_add_toggle_ref()` directly, e.g. from C code like GTK or from
+//! PyGObject, are not seen by the tracker, so such objects are still reported as leaks.
+//!
+//! Tracking is disabled by default. It is enabled either by calling [`enable`] or by setting the
+//! `GLIB_RS_TRACK_OBJECTS` environment variable before the first object is created. Only objects
+//! created while tracking is enabled are recorded.
+//!
+//! The `backtrace` feature requires Rust 1.65 or newer.
+//!
+//! ```
+//! # #[cfg(feature = "leak_tracker")]
+//! # {
+//! glib::leak_tracker::enable();
+//!
+//! let obj = glib::Object::new::<glib::Object>(&[]);
+//! glib::leak_tracker::track(&obj);
+//! assert_eq!(glib::leak_tracker::live_objects().len(), 1);
+//!
+//! drop(obj);
+//! glib::assert_no_leaks!();
+//! # }
+//! ```
+
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "backtrace")]
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+use once_cell::sync::Lazy;
+
+use crate::object::{IsA, ObjectExt};
+use crate::{Object, Type};
+
+static ENABLED: Lazy<AtomicBool> =
+    Lazy::new(|| AtomicBool::new(std::env::var_os("GLIB_RS_TRACK_OBJECTS").is_some()));
+
+static LIVE_OBJECTS: Lazy<Mutex<HashMap<usize, Record>>> = Lazy::new(Default::default);
+
+struct Record {
+    type_: Type,
+    thread_id: ThreadId,
+    toggle_refs: u32,
+    #[cfg(feature = "backtrace")]
+    backtrace: Arc<Backtrace>,
+}
+
+// rustdoc-stripper-ignore-next
+/// Enables tracking of newly created objects.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+// rustdoc-stripper-ignore-next
+/// Disables tracking of newly created objects.
+///
+/// Objects that are already tracked stay tracked until they are finalized.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+// rustdoc-stripper-ignore-next
+/// Whether newly created objects are tracked.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// rustdoc-stripper-ignore-next
+/// Tracks `obj` until it is finalized.
+///
+/// Instances of subclasses implemented in Rust are tracked automatically, this allows tracking
+/// objects of other types too. Does nothing if tracking is disabled or `obj` is already tracked.
+#[doc(alias = "g_object_weak_ref")]
+pub fn track(obj: &impl IsA<Object>) {
+    unsafe extern "C" fn weak_notify(_data: ffi::gpointer, obj: *mut gobject_ffi::GObject) {
+        untrack_instance(obj);
+    }
+
+    if !is_enabled() {
+        return;
+    }
+
+    let ptr = obj.as_ptr() as *mut gobject_ffi::GObject;
+    let mut live_objects = LIVE_OBJECTS.lock().unwrap();
+    if live_objects.contains_key(&(ptr as usize)) {
+        return;
+    }
+
+    live_objects.insert(ptr as usize, Record::new(obj.type_()));
+    unsafe {
+        gobject_ffi::g_object_weak_ref(ptr, Some(weak_notify), std::ptr::null_mut());
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Adds a toggle reference to `obj` and records it for the tracker.
+///
+/// # Safety
+///
+/// The same requirements as for `g_object_add_toggle_ref()` apply to `notify` and `data`.
+#[doc(alias = "g_object_add_toggle_ref")]
+pub unsafe fn add_toggle_ref(
+    obj: &impl IsA<Object>,
+    notify: gobject_ffi::GToggleNotify,
+    data: ffi::gpointer,
+) {
+    let ptr = obj.as_ptr() as *mut gobject_ffi::GObject;
+    gobject_ffi::g_object_add_toggle_ref(ptr, notify, data);
+    if let Some(record) = LIVE_OBJECTS.lock().unwrap().get_mut(&(ptr as usize)) {
+        record.toggle_refs += 1;
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Removes a toggle reference added with [`add_toggle_ref`].
+///
+/// # Safety
+///
+/// The same requirements as for `g_object_remove_toggle_ref()` apply to `notify` and `data`.
+#[doc(alias = "g_object_remove_toggle_ref")]
+pub unsafe fn remove_toggle_ref(
+    obj: &impl IsA<Object>,
+    notify: gobject_ffi::GToggleNotify,
+    data: ffi::gpointer,
+) {
+    let ptr = obj.as_ptr() as *mut gobject_ffi::GObject;
+    if let Some(record) = LIVE_OBJECTS.lock().unwrap().get_mut(&(ptr as usize)) {
+        record.toggle_refs = record.toggle_refs.saturating_sub(1);
+    }
+    // `obj` holds another reference so this can't finalize the object while the lock is held.
+    gobject_ffi::g_object_remove_toggle_ref(ptr, notify, data);
+}
+
+// rustdoc-stripper-ignore-next
+/// Returns all tracked objects that are still alive.
+pub fn live_objects() -> Vec<LiveObject> {
+    let live_objects = LIVE_OBJECTS.lock().unwrap();
+    live_objects
+        .iter()
+        .map(|(ptr, record)| LiveObject {
+            ptr: *ptr,
+            type_: record.type_,
+            // SAFETY: The object can't be finalized while the lock is held as it would first have
+            // to be untracked.
+            ref_count: unsafe {
+                std::ptr::read_volatile(&(*(*ptr as *const gobject_ffi::GObject)).ref_count)
+            },
+            thread_id: record.thread_id,
+            toggle_refs: record.toggle_refs,
+            #[cfg(feature = "backtrace")]
+            backtrace: record.backtrace.clone(),
+        })
+        .collect()
+}
+
+// rustdoc-stripper-ignore-next
+/// A tracked object that was not finalized yet.
+#[derive(Clone)]
+pub struct LiveObject {
+    ptr: usize,
+    type_: Type,
+    ref_count: u32,
+    thread_id: ThreadId,
+    toggle_refs: u32,
+    #[cfg(feature = "backtrace")]
+    backtrace: Arc<Backtrace>,
+}
+
+impl LiveObject {
+    // rustdoc-stripper-ignore-next
+    /// The address of the object.
+    pub fn as_ptr(&self) -> *const gobject_ffi::GObject {
+        self.ptr as *const _
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The type of the object.
+    ///
+    /// For instances of Rust subclasses this is the most derived type implemented in Rust.
+    pub fn type_(&self) -> Type {
+        self.type_
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The reference count of the object at the time of calling [`live_objects`].
+    pub fn ref_count(&self) -> u32 {
+        self.ref_count
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The thread the object was created on.
+    pub fn thread_id(&self) -> ThreadId {
+        self.thread_id
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of toggle references added with [`add_toggle_ref`] that are included in the
+    /// [reference count](Self::ref_count).
+    pub fn toggle_refs(&self) -> u32 {
+        self.toggle_refs
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the object is only kept alive by its toggle references.
+    pub fn is_toggled_down(&self) -> bool {
+        self.toggle_refs > 0 && self.ref_count <= self.toggle_refs
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The backtrace of the object's creation.
+    #[cfg(feature = "backtrace")]
+    #[cfg_attr(feature = "dox", doc(cfg(feature = "backtrace")))]
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+}
+
+impl fmt::Debug for LiveObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LiveObject")
+            .field("ptr", &self.as_ptr())
+            .field("type", &self.type_)
+            .field("ref_count", &self.ref_count)
+            .field("thread_id", &self.thread_id)
+            .field("toggle_refs", &self.toggle_refs)
+            .finish()
+    }
+}
+
+impl fmt::Display for LiveObject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {:?} with {} references",
+            self.type_,
+            self.as_ptr(),
+            self.ref_count,
+        )?;
+        if self.toggle_refs > 0 {
+            write!(f, " ({} toggle references)", self.toggle_refs)?;
+        }
+        write!(f, ", created on {:?}", self.thread_id)?;
+        #[cfg(feature = "backtrace")]
+        write!(f, " at:\n{}", self.backtrace)?;
+        Ok(())
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Asserts that no tracked object created on the current thread is alive.
+///
+/// Only objects created on the current thread are considered so that tests running in parallel
+/// don't affect each other. With a type argument only objects of that type or types deriving
+/// from it are considered. Objects that are only kept alive by their toggle references are
+/// ignored, see [`LiveObject::is_toggled_down`].
+///
+/// On failure this panics with the types of all live objects, and their creation backtraces with
+/// the `backtrace` feature.
+///
+/// ```
+/// # #[cfg(feature = "leak_tracker")]
+/// # {
+/// glib::leak_tracker::enable();
+/// let _obj = glib::Object::new::<glib::Object>(&[]);
+/// // Objects of other types than `glib::Binding` are ignored.
+/// glib::assert_no_leaks!(glib::Binding);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_no_leaks {
+    () => {
+        $crate::leak_tracker::assert_no_leaks(None)
+    };
+    ($type:ty) => {
+        $crate::leak_tracker::assert_no_leaks(Some(<$type as $crate::StaticType>::static_type()))
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_no_leaks(type_: Option<Type>) {
+    let thread_id = thread::current().id();
+    let leaks = live_objects()
+        .into_iter()
+        .filter(|obj| {
+            obj.thread_id == thread_id
+                && !obj.is_toggled_down()
+                && type_.map_or(true, |t| obj.type_.is_a(t))
+        })
+        .collect::<Vec<_>>();
+
+    if !leaks.is_empty() {
+        let mut msg = format!("{} objects leaked:", leaks.len());
+        for obj in leaks {
+            msg.push_str("\n\n");
+            msg.push_str(&obj.to_string());
+        }
+        panic!("{}", msg);
+    }
+}
+
+impl Record {
+    fn new(type_: Type) -> Self {
+        Self {
+            type_,
+            thread_id: thread::current().id(),
+            toggle_refs: 0,
+            #[cfg(feature = "backtrace")]
+            backtrace: Arc::new(Backtrace::force_capture()),
+        }
+    }
+}
+
+// Called from the instance initialization of every Rust subclass in the class hierarchy of the
+// object, from the least to the most derived one.
+pub(crate) fn track_instance(obj: *mut gobject_ffi::GObject, type_: Type) {
+    if !is_enabled() {
+        return;
+    }
+
+    LIVE_OBJECTS
+        .lock()
+        .unwrap()
+        .entry(obj as usize)
+        .and_modify(|record| record.type_ = type_)
+        .or_insert_with(|| Record::new(type_));
+}
+
+pub(crate) fn untrack_instance(obj: *mut gobject_ffi::GObject) {
+    if let Some(live_objects) = Lazy::get(&LIVE_OBJECTS) {
+        live_objects.lock().unwrap().remove(&(obj as usize));
+    }
+}
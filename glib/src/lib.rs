@@ -18,7 +18,7 @@ pub use once_cell;
 
 pub use glib_macros::{
     clone, closure, closure_local, flags, object_interface, object_subclass, Boxed, Downgrade,
-    Enum, ErrorDomain, SharedBoxed, Variant,
+    Enum, ErrorDomain, Options, SharedBoxed, Variant,
 };
 
 #[doc(hidden)]
@@ -26,7 +26,7 @@ pub use glib_macros::cstr_bytes;
 
 pub use self::byte_array::ByteArray;
 pub use self::bytes::Bytes;
-pub use self::closure::{Closure, RustClosure};
+pub use self::closure::{Closure, RustClosure, SignalClosure, TypedClosure};
 pub use self::error::{BoolError, Error};
 pub use self::object::{
     BorrowedObject, Cast, Class, InitiallyUnowned, Interface, IsA, Object, ObjectExt, ObjectType,
@@ -91,6 +91,10 @@ mod enums;
 mod functions;
 pub use self::functions::*;
 mod key_file;
+mod option_context;
+pub use self::option_context::{
+    OptionArgType, OptionContext, OptionEntry, OptionEntryBuilder, OptionGroup, Options,
+};
 pub mod prelude;
 pub mod signal;
 pub mod source;
@@ -108,8 +112,10 @@ mod utils;
 pub use self::utils::*;
 mod main_context;
 mod main_context_channel;
-pub use self::main_context::MainContextAcquireGuard;
+pub use self::main_context::{MainContextAcquireGuard, PollFdGuard};
 pub use self::main_context_channel::{Receiver, Sender, SyncSender};
+mod poll_fd;
+pub use self::poll_fd::{poll, PollFd};
 mod date;
 mod date_time;
 mod time_span;
@@ -158,18 +164,49 @@ pub use self::bridged_logging::{rust_log_handler, GlibLogger, GlibLoggerDomain,
 #[macro_use]
 pub mod subclass;
 
+pub mod channel;
+pub mod future;
 mod main_context_futures;
+pub use self::main_context_futures::{JoinError, JoinHandle, TaskSet};
+
 mod source_futures;
 pub use self::source_futures::*;
 
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+mod async_fd;
+#[cfg(any(unix, feature = "dox"))]
+pub use self::async_fd::{AsyncFd, AsyncFdReadyGuard};
+
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+pub mod spawn;
+
+mod source_impl;
+pub use self::source_impl::SourceImpl;
+mod virtual_clock;
+#[cfg(any(unix, feature = "dox"))]
+pub use self::source_impl::UnixFdTag;
+pub use self::virtual_clock::VirtualClock;
+
 mod future_with_timeout;
 pub use self::future_with_timeout::*;
 
 mod thread_pool;
-pub use self::thread_pool::ThreadPool;
+pub use self::thread_pool::{MapParallel, ThreadPool, ThreadPoolScope};
 
 pub mod thread_guard;
 
+pub mod reflect;
+
+#[cfg(any(feature = "leak_tracker", feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "leak_tracker")))]
+pub mod leak_tracker;
+
+#[cfg(any(feature = "profiler", feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(feature = "profiler")))]
+pub mod profiler;
+
 // rustdoc-stripper-ignore-next
 /// This is the log domain used by the [`clone!`][crate::clone!] macro. If you want to use a custom
 /// logger (it prints to stdout by default), you can set your own logger using the corresponding
@@ -3,10 +3,24 @@
 use crate::source::Priority;
 use crate::translate::*;
 use crate::MainContext;
+use crate::PollFd;
 use crate::Source;
 use crate::SourceId;
 use ffi::{self, gboolean, gpointer};
+use std::cell::UnsafeCell;
+use std::fmt;
 use std::mem;
+use std::ptr;
+
+// Fails to compile if the closure `F` captures any variables.
+struct NoCapture<F>(std::marker::PhantomData<F>);
+
+impl<F> NoCapture<F> {
+    const ASSERT: () = assert!(
+        mem::size_of::<F>() == 0,
+        "Poll functions must not capture any variables"
+    );
+}
 
 impl MainContext {
     #[doc(alias = "g_main_context_prepare")]
@@ -23,6 +37,133 @@ impl MainContext {
         }
     }
 
+    // rustdoc-stripper-ignore-next
+    /// Fills `fds` with the file descriptors that have to be polled after
+    /// [`prepare`](MainContext::prepare) and returns the timeout for polling them in milliseconds.
+    ///
+    /// `fds` is resized to the number of file descriptors to poll for sources with at most
+    /// `max_priority`, as returned by `prepare`. A timeout of `-1` means that polling can block
+    /// until any file descriptor is ready.
+    ///
+    /// This is only needed for iterating the main context from an external event loop, which
+    /// has to poll the file descriptors itself and pass them on to [`check`](MainContext::check).
+    /// The main context must be acquired by the calling thread.
+    #[doc(alias = "g_main_context_query")]
+    pub fn query(&self, max_priority: i32, fds: &mut Vec<PollFd>) -> i32 {
+        unsafe {
+            let mut timeout = mem::MaybeUninit::uninit();
+            loop {
+                let n_fds = ffi::g_main_context_query(
+                    self.to_glib_none().0,
+                    max_priority,
+                    timeout.as_mut_ptr(),
+                    fds.as_mut_ptr() as *mut ffi::GPollFD,
+                    fds.len() as i32,
+                ) as usize;
+
+                let fits = n_fds <= fds.len();
+                fds.resize(n_fds, PollFd::from_glib(mem::zeroed()));
+                if fits {
+                    return timeout.assume_init();
+                }
+            }
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Checks whether any source is ready after polling `fds` and returns `true` if
+    /// [`dispatch`](MainContext::dispatch) has to be called.
+    ///
+    /// `fds` must be the file descriptors returned by [`query`](MainContext::query) with their
+    /// polled events set via [`PollFd::set_revents`].
+    #[doc(alias = "g_main_context_check")]
+    pub fn check(&self, max_priority: i32, fds: &mut [PollFd]) -> bool {
+        unsafe {
+            from_glib(ffi::g_main_context_check(
+                self.to_glib_none().0,
+                max_priority,
+                fds.as_mut_ptr() as *mut ffi::GPollFD,
+                fds.len() as i32,
+            ))
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds `fd` to the file descriptors polled by the main context until the returned guard is
+    /// dropped.
+    ///
+    /// The polled events can be retrieved from the guard after each iteration. This wakes up
+    /// the main context when `fd` becomes ready but does not dispatch anything.
+    #[doc(alias = "g_main_context_add_poll")]
+    pub fn add_poll(&self, fd: PollFd, priority: Priority) -> PollFdGuard {
+        let fd = Box::new(UnsafeCell::new(fd));
+        unsafe {
+            ffi::g_main_context_add_poll(
+                self.to_glib_none().0,
+                fd.get() as *mut ffi::GPollFD,
+                priority.into_glib(),
+            );
+        }
+        PollFdGuard {
+            context: self.clone(),
+            fd,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Replaces the function that is used for polling the file descriptors of the main context.
+    ///
+    /// `func` is called with the file descriptors and the timeout in milliseconds, and has to
+    /// set the polled events and return the number of ready file descriptors like [`poll`].
+    /// The default is [`poll`].
+    ///
+    /// As GLib does not pass any user data to the poll function, `func` can't capture any
+    /// variables. This is checked at compile time:
+    ///
+    /// ```compile_fail
+    /// let c = glib::MainContext::new();
+    /// let timeout = 0;
+    /// c.set_poll_func(move |fds, _| glib::poll(fds, timeout));
+    /// ```
+    ///
+    /// [`poll`]: crate::poll
+    #[doc(alias = "g_main_context_set_poll_func")]
+    pub fn set_poll_func<F>(&self, func: F)
+    where
+        F: Fn(&mut [PollFd], i32) -> i32 + Send + Sync + 'static,
+    {
+        unsafe extern "C" fn trampoline<F: Fn(&mut [PollFd], i32) -> i32 + 'static>(
+            fds: *mut ffi::GPollFD,
+            n_fds: u32,
+            timeout: i32,
+        ) -> i32 {
+            // SAFETY: `F` has no data as checked by `NoCapture`.
+            let func: &F = &*ptr::NonNull::<F>::dangling().as_ptr();
+            let fds: &mut [PollFd] = if n_fds == 0 {
+                &mut []
+            } else {
+                std::slice::from_raw_parts_mut(fds as *mut PollFd, n_fds as usize)
+            };
+            func(fds, timeout)
+        }
+
+        let () = NoCapture::<F>::ASSERT;
+        mem::forget(func);
+
+        unsafe {
+            ffi::g_main_context_set_poll_func(self.to_glib_none().0, Some(trampoline::<F>));
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Resets the poll function of the main context to the default one.
+    #[doc(alias = "g_main_context_set_poll_func")]
+    pub fn unset_poll_func(&self) {
+        unsafe {
+            ffi::g_main_context_set_poll_func(self.to_glib_none().0, None);
+        }
+    }
+
     #[doc(alias = "g_main_context_find_source_by_id")]
     pub fn find_source_by_id(&self, source_id: &SourceId) -> Option<Source> {
         unsafe {
@@ -187,6 +328,61 @@ impl<'a> Drop for MainContextAcquireGuard<'a> {
     }
 }
 
+// rustdoc-stripper-ignore-next
+/// A file descriptor added to a [`MainContext`] with [`MainContext::add_poll`].
+///
+/// The file descriptor is removed from the main context again when this is dropped.
+#[must_use = "if unused the file descriptor will be removed immediately"]
+pub struct PollFdGuard {
+    context: MainContext,
+    // Written by the main context from the thread iterating it, so this must only be read by the
+    // thread owning the main context.
+    fd: Box<UnsafeCell<PollFd>>,
+}
+
+// SAFETY: The polled file descriptor is only read after acquiring the main context.
+unsafe impl Send for PollFdGuard {}
+unsafe impl Sync for PollFdGuard {}
+
+impl PollFdGuard {
+    // rustdoc-stripper-ignore-next
+    /// The polled file descriptor.
+    ///
+    /// Its polled events are updated by every iteration of the main context. To not race with
+    /// these updates, this acquires the main context and fails if it is owned by another thread.
+    pub fn fd(&self) -> Result<PollFd, crate::BoolError> {
+        let _acquire = self.context.acquire()?;
+        Ok(unsafe { *self.fd.get() })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The main context the file descriptor was added to.
+    pub fn context(&self) -> &MainContext {
+        &self.context
+    }
+}
+
+impl fmt::Debug for PollFdGuard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PollFdGuard")
+            .field("context", &self.context)
+            .field("fd", &self.fd().ok())
+            .finish()
+    }
+}
+
+impl Drop for PollFdGuard {
+    #[doc(alias = "g_main_context_remove_poll")]
+    fn drop(&mut self) {
+        unsafe {
+            ffi::g_main_context_remove_poll(
+                self.context.to_glib_none().0,
+                self.fd.get() as *mut ffi::GPollFD,
+            );
+        }
+    }
+}
+
 struct ThreadDefaultContext<'a>(&'a MainContext);
 
 impl<'a> ThreadDefaultContext<'a> {
@@ -277,4 +473,172 @@ mod tests {
         })
         .unwrap();
     }
+
+    // Iterates `c` once from an epoll loop instead of the GLib poll function.
+    #[cfg(target_os = "linux")]
+    fn iterate_with_epoll(c: &MainContext, fds: &mut Vec<PollFd>) -> bool {
+        let (_, max_priority) = c.prepare();
+        let timeout = c.query(max_priority, fds);
+
+        unsafe {
+            let epfd = libc::epoll_create1(libc::EPOLL_CLOEXEC);
+            assert!(epfd >= 0);
+
+            for (i, fd) in fds.iter().enumerate() {
+                let mut events = 0;
+                if fd.events().contains(crate::IOCondition::IN) {
+                    events |= libc::EPOLLIN;
+                }
+                if fd.events().contains(crate::IOCondition::OUT) {
+                    events |= libc::EPOLLOUT;
+                }
+                let mut event = libc::epoll_event {
+                    events: events as u32,
+                    u64: i as u64,
+                };
+                assert_eq!(
+                    libc::epoll_ctl(epfd, libc::EPOLL_CTL_ADD, fd.fd(), &mut event),
+                    0
+                );
+            }
+
+            let mut events = [libc::epoll_event { events: 0, u64: 0 }; 16];
+            let n = libc::epoll_wait(epfd, events.as_mut_ptr(), events.len() as i32, timeout);
+            assert!(n >= 0);
+            libc::close(epfd);
+
+            for fd in fds.iter_mut() {
+                fd.set_revents(crate::IOCondition::empty());
+            }
+            for event in &events[..n as usize] {
+                let mut revents = crate::IOCondition::empty();
+                if event.events & libc::EPOLLIN as u32 != 0 {
+                    revents |= crate::IOCondition::IN;
+                }
+                if event.events & libc::EPOLLOUT as u32 != 0 {
+                    revents |= crate::IOCondition::OUT;
+                }
+                if event.events & libc::EPOLLHUP as u32 != 0 {
+                    revents |= crate::IOCondition::HUP;
+                }
+                fds[event.u64 as usize].set_revents(revents);
+            }
+        }
+
+        if c.check(max_priority, fds) {
+            c.dispatch();
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_epoll_loop() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let c = MainContext::new();
+        let _guard = c.acquire().unwrap();
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_clone = count.clone();
+        crate::timeout_source_new(
+            std::time::Duration::from_millis(10),
+            None,
+            crate::PRIORITY_DEFAULT,
+            move || {
+                count_clone.fetch_add(1, Ordering::SeqCst);
+                crate::Continue(false)
+            },
+        )
+        .attach(Some(&c));
+
+        // Wakeups from other threads are received via the fds of the main context.
+        let (sender, receiver) = MainContext::channel(crate::PRIORITY_DEFAULT);
+        let count_clone = count.clone();
+        receiver.attach(Some(&c), move |()| {
+            count_clone.fetch_add(1, Ordering::SeqCst);
+            crate::Continue(false)
+        });
+        let thread = thread::spawn(move || {
+            thread::sleep(std::time::Duration::from_millis(20));
+            sender.send(()).unwrap();
+        });
+
+        let mut fds = Vec::new();
+        while count.load(Ordering::SeqCst) < 2 {
+            iterate_with_epoll(&c, &mut fds);
+        }
+        assert!(!fds.is_empty());
+        thread.join().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_add_poll() {
+        let c = MainContext::new();
+        let _guard = c.acquire().unwrap();
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let [read_fd, write_fd] = fds;
+
+        let poll_fd = c.add_poll(
+            PollFd::new(read_fd, crate::IOCondition::IN),
+            crate::PRIORITY_DEFAULT,
+        );
+        assert_eq!(poll_fd.fd().unwrap().fd(), read_fd);
+        assert_eq!(poll_fd.fd().unwrap().revents(), crate::IOCondition::empty());
+
+        assert_eq!(
+            unsafe { libc::write(write_fd, b"x".as_ptr() as *const _, 1) },
+            1
+        );
+        c.iteration(true);
+        assert_eq!(poll_fd.fd().unwrap().revents(), crate::IOCondition::IN);
+
+        // Another thread can't read the polled events while this thread owns the main context.
+        thread::scope(|s| {
+            s.spawn(|| assert!(poll_fd.fd().is_err())).join().unwrap();
+        });
+
+        let mut query_fds = Vec::new();
+        let (_, max_priority) = c.prepare();
+        c.query(max_priority, &mut query_fds);
+        assert!(query_fds.iter().any(|fd| fd.fd() == read_fd));
+
+        drop(poll_fd);
+        let (_, max_priority) = c.prepare();
+        c.query(max_priority, &mut query_fds);
+        assert!(!query_fds.iter().any(|fd| fd.fd() == read_fd));
+        c.check(max_priority, &mut query_fds);
+
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+    }
+
+    #[test]
+    fn test_poll_func() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static POLLED: AtomicUsize = AtomicUsize::new(0);
+
+        let c = MainContext::new();
+        let _guard = c.acquire().unwrap();
+        c.set_poll_func(|fds, timeout| {
+            POLLED.fetch_add(1, Ordering::SeqCst);
+            crate::poll(fds, timeout)
+        });
+
+        c.iteration(false);
+        assert_eq!(POLLED.load(Ordering::SeqCst), 1);
+
+        c.unset_poll_func();
+        c.iteration(false);
+        assert_eq!(POLLED.load(Ordering::SeqCst), 1);
+    }
 }
@@ -235,19 +235,17 @@ unsafe extern "C" fn dispatch<T, F: FnMut(T) -> Continue + 'static>(
         .channel
         .as_ref()
         .expect("ChannelSource without Channel");
-    loop {
+    crate::source::profile_dispatch(std::any::type_name::<F>(), || loop {
         match channel.try_recv() {
-            Err(mpsc::TryRecvError::Empty) => break,
-            Err(mpsc::TryRecvError::Disconnected) => return ffi::G_SOURCE_REMOVE,
+            Err(mpsc::TryRecvError::Empty) => break ffi::G_SOURCE_CONTINUE,
+            Err(mpsc::TryRecvError::Disconnected) => break ffi::G_SOURCE_REMOVE,
             Ok(item) => {
                 if callback(item) == Continue(false) {
-                    return ffi::G_SOURCE_REMOVE;
+                    break ffi::G_SOURCE_REMOVE;
                 }
             }
         }
-    }
-
-    ffi::G_SOURCE_CONTINUE
+    })
 }
 
 #[cfg(feature = "v2_64")]
@@ -2,12 +2,18 @@
 
 use crate::thread_guard::ThreadGuard;
 use crate::translate::*;
+use futures_channel::oneshot;
 use futures_core::future::Future;
+use futures_core::stream::Stream;
 use futures_core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use futures_core::FusedFuture;
 use futures_task::{FutureObj, LocalFutureObj, LocalSpawn, Spawn, SpawnError};
+use futures_util::future::{AbortHandle, Abortable};
+use std::fmt;
 use std::mem;
 use std::pin::{self, Pin};
 use std::ptr;
+use std::sync::Mutex;
 
 use crate::MainContext;
 use crate::MainLoop;
@@ -46,8 +52,15 @@ struct TaskSource {
     source: ffi::GSource,
     future: FutureWrapper,
     waker: Waker,
+    named: bool,
+    // The type name of the spawned future for the profiler.
+    #[cfg(feature = "profiler")]
+    future_type: &'static str,
 }
 
+// Named task sources that are not finalized yet, for `MainContext::task_names()`.
+static NAMED_TASKS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
 #[repr(C)]
 struct WakerSource {
     source: ffi::GSource,
@@ -62,9 +75,18 @@ impl TaskSource {
         let source = &mut *(source as *mut TaskSource);
         assert!(callback.is_none());
 
+        #[cfg(feature = "profiler")]
+        let res = crate::profiler::dispatch(
+            crate::profiler::DispatchKind::Task,
+            source.future_type,
+            || source.poll(),
+        );
+        #[cfg(not(feature = "profiler"))]
+        let res = source.poll();
+
         // Poll the TaskSource and ensure we're never called again if the
         // contained Future resolved now.
-        if let Poll::Ready(()) = source.poll() {
+        if let Poll::Ready(()) = res {
             ffi::G_SOURCE_REMOVE
         } else {
             ffi::G_SOURCE_CONTINUE
@@ -74,6 +96,13 @@ impl TaskSource {
     unsafe extern "C" fn finalize(source: *mut ffi::GSource) {
         let source = source as *mut TaskSource;
 
+        if (*source).named {
+            let mut named_tasks = NAMED_TASKS.lock().unwrap();
+            if let Some(pos) = named_tasks.iter().position(|s| *s == source as usize) {
+                named_tasks.swap_remove(pos);
+            }
+        }
+
         // This will panic if the future was a local future and is dropped from a different thread
         // than where it was created so try to drop it from the main context if we're on another
         // thread and the main context still exists.
@@ -158,7 +187,12 @@ unsafe impl Sync for WakerSource {}
 impl TaskSource {
     #[allow(clippy::new_ret_no_self)]
     // checker-ignore-item
-    fn new(priority: Priority, future: FutureWrapper) -> Source {
+    fn new(
+        priority: Priority,
+        name: Option<&str>,
+        future_type: &'static str,
+        future: FutureWrapper,
+    ) -> Source {
         unsafe {
             static TASK_SOURCE_FUNCS: ffi::GSourceFuncs = ffi::GSourceFuncs {
                 check: None,
@@ -198,6 +232,16 @@ impl TaskSource {
                 // This creates a new reference to the waker source.
                 let waker = Waker::from_raw(WakerSource::clone_raw(waker_source as *const ()));
                 ptr::write(&mut source.waker, waker);
+                ptr::write(&mut source.named, name.is_some());
+                #[cfg(feature = "profiler")]
+                ptr::write(&mut source.future_type, future_type);
+                #[cfg(not(feature = "profiler"))]
+                let _ = future_type;
+            }
+
+            if let Some(name) = name {
+                ffi::g_source_set_name(source, name.to_glib_none().0);
+                NAMED_TASKS.lock().unwrap().push(source as usize);
             }
 
             // Set ready time to 0 so that the source is immediately dispatched
@@ -236,6 +280,23 @@ impl TaskSource {
     }
 }
 
+// Wraps `f` so that its output is sent to the returned `JoinHandle` and so that it can be aborted.
+fn task<R, F: Future<Output = R>>(
+    f: F,
+) -> (impl Future<Output = ()>, oneshot::Receiver<R>, AbortHandle) {
+    let (sender, receiver) = oneshot::channel();
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let f = Abortable::new(f, abort_registration);
+
+    let f = async move {
+        if let Ok(res) = f.await {
+            let _ = sender.send(res);
+        }
+    };
+
+    (f, receiver, abort_handle)
+}
+
 impl MainContext {
     // rustdoc-stripper-ignore-next
     /// Spawn a new infallible `Future` on the main context.
@@ -268,9 +329,7 @@ impl MainContext {
         priority: Priority,
         f: F,
     ) -> SourceId {
-        let f = FutureObj::new(Box::new(f));
-        let source = TaskSource::new(priority, FutureWrapper::Send(f));
-        source.attach(Some(self))
+        self.spawn_full(priority, None, f).into_source_id()
     }
 
     // rustdoc-stripper-ignore-next
@@ -286,12 +345,163 @@ impl MainContext {
         priority: Priority,
         f: F,
     ) -> SourceId {
+        self.spawn_local_full(priority, None, f).into_source_id()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawn a new infallible `Future` on the main context and return a [`JoinHandle`] for it.
+    ///
+    /// The handle can be awaited for the result of the future, or used for aborting it.
+    /// Dropping the handle detaches the future, which then keeps running.
+    ///
+    /// This can be called from any thread and will execute the future from the thread
+    /// where main context is running, e.g. via a `MainLoop`.
+    pub fn spawn_task<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_task_with_priority(crate::PRIORITY_DEFAULT, f)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawn a new infallible `Future` on the main context and return a [`JoinHandle`] for it.
+    ///
+    /// The given `Future` does not have to be `Send`.
+    ///
+    /// This can be called only from the thread where the main context is running, e.g.
+    /// from any other `Future` that is executed on this main context, or after calling
+    /// `with_thread_default` or `acquire` on the main context.
+    pub fn spawn_local_task<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_local_task_with_priority(crate::PRIORITY_DEFAULT, f)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Like [`spawn_task`](Self::spawn_task), with a non-default priority.
+    pub fn spawn_task_with_priority<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        priority: Priority,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_full(priority, None, f)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Like [`spawn_local_task`](Self::spawn_local_task), with a non-default priority.
+    pub fn spawn_local_task_with_priority<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        priority: Priority,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_local_full(priority, None, f)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawn a new infallible `Future` on the main context, with a name.
+    ///
+    /// The name is set on the underlying [`Source`] and the task is listed by
+    /// [`task_names`](Self::task_names) until it is finished.
+    ///
+    /// This can be called from any thread and will execute the future from the thread
+    /// where main context is running, e.g. via a `MainLoop`.
+    pub fn spawn_with_name<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        name: &str,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_full(crate::PRIORITY_DEFAULT, Some(name), f)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawn a new infallible `Future` on the main context, with a name.
+    ///
+    /// The name is set on the underlying [`Source`] and the task is listed by
+    /// [`task_names`](Self::task_names) until it is finished.
+    ///
+    /// The given `Future` does not have to be `Send`.
+    ///
+    /// This can be called only from the thread where the main context is running, e.g.
+    /// from any other `Future` that is executed on this main context, or after calling
+    /// `with_thread_default` or `acquire` on the main context.
+    pub fn spawn_local_with_name<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        name: &str,
+        f: F,
+    ) -> JoinHandle<R> {
+        self.spawn_local_full(crate::PRIORITY_DEFAULT, Some(name), f)
+    }
+
+    fn spawn_full<R: Send + 'static, F: Future<Output = R> + Send + 'static>(
+        &self,
+        priority: Priority,
+        name: Option<&str>,
+        f: F,
+    ) -> JoinHandle<R> {
+        let (f, receiver, abort_handle) = task(f);
+        let f = FutureObj::new(Box::new(f));
+        let source = TaskSource::new(
+            priority,
+            name,
+            std::any::type_name::<F>(),
+            FutureWrapper::Send(f),
+        );
+        source.attach(Some(self));
+
+        JoinHandle {
+            receiver,
+            abort_handle,
+            source,
+        }
+    }
+
+    fn spawn_local_full<R: 'static, F: Future<Output = R> + 'static>(
+        &self,
+        priority: Priority,
+        name: Option<&str>,
+        f: F,
+    ) -> JoinHandle<R> {
         let _acquire = self
             .acquire()
             .expect("Spawning local futures only allowed on the thread owning the MainContext");
+        let (f, receiver, abort_handle) = task(f);
         let f = LocalFutureObj::new(Box::new(f));
-        let source = TaskSource::new(priority, FutureWrapper::NonSend(ThreadGuard::new(f)));
-        source.attach(Some(self))
+        let source = TaskSource::new(
+            priority,
+            name,
+            std::any::type_name::<F>(),
+            FutureWrapper::NonSend(ThreadGuard::new(f)),
+        );
+        source.attach(Some(self));
+
+        JoinHandle {
+            receiver,
+            abort_handle,
+            source,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the names of all unfinished tasks on this main context that were spawned with
+    /// [`spawn_with_name`](Self::spawn_with_name) or
+    /// [`spawn_local_with_name`](Self::spawn_local_with_name).
+    ///
+    /// This is meant for debugging, e.g. for finding out which tasks are stuck.
+    pub fn task_names(&self) -> Vec<crate::GString> {
+        let named_tasks = NAMED_TASKS.lock().unwrap();
+        named_tasks
+            .iter()
+            .filter_map(|&source| unsafe {
+                let source = source as *mut ffi::GSource;
+                if ffi::g_source_get_context(source) != self.as_ptr()
+                    || ffi::g_source_is_destroyed(source) != ffi::GFALSE
+                {
+                    return None;
+                }
+                from_glib_none(ffi::g_source_get_name(source))
+            })
+            .collect()
     }
 
     // rustdoc-stripper-ignore-next
@@ -320,6 +530,8 @@ impl MainContext {
 
             let source = TaskSource::new(
                 crate::PRIORITY_DEFAULT,
+                None,
+                std::any::type_name::<F>(),
                 FutureWrapper::NonSend(ThreadGuard::new(f)),
             );
             source.attach(Some(self));
@@ -331,9 +543,254 @@ impl MainContext {
     }
 }
 
+// rustdoc-stripper-ignore-next
+/// Error returned by awaiting a [`JoinHandle`] whose task did not complete.
+///
+/// This happens if the task was aborted, or if its main context was destroyed before the task
+/// could complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JoinError(());
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("task was aborted or its main context was destroyed")
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+// rustdoc-stripper-ignore-next
+/// A handle to a task spawned on a [`MainContext`] with [`spawn_task`](MainContext::spawn_task)
+/// or [`spawn_local_task`](MainContext::spawn_local_task).
+///
+/// This is a future that resolves to the output of the task. Dropping the handle detaches the
+/// task, which then keeps running.
+pub struct JoinHandle<T> {
+    receiver: oneshot::Receiver<T>,
+    abort_handle: AbortHandle,
+    source: Source,
+}
+
+impl<T> JoinHandle<T> {
+    // rustdoc-stripper-ignore-next
+    /// Aborts the task.
+    ///
+    /// The future of the task is dropped the next time the main context is iterated, from the
+    /// thread that owns the main context. Awaiting the handle afterwards returns a
+    /// [`JoinError`], unless the task completed before.
+    pub fn abort(&self) {
+        self.abort_handle.abort();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the task is finished, either by completing or by being aborted.
+    pub fn is_finished(&self) -> bool {
+        self.source.is_destroyed()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The [`Source`] that drives the task.
+    pub fn source(&self) -> &Source {
+        &self.source
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Detaches the task and returns the [`SourceId`] of its source.
+    ///
+    /// [`SourceId::remove`] only works for tasks spawned on the [default main
+    /// context](MainContext::default), otherwise destroy the [`source`](Self::source) instead.
+    pub fn into_source_id(self) -> SourceId {
+        unsafe { from_glib(ffi::g_source_get_id(self.source.to_glib_none().0)) }
+    }
+}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("source", &self.source)
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().receiver)
+            .poll(cx)
+            .map_err(|_| JoinError(()))
+    }
+}
+
+impl<T> FusedFuture for JoinHandle<T> {
+    fn is_terminated(&self) -> bool {
+        self.receiver.is_terminated()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A set of tasks spawned on a [`MainContext`].
+///
+/// All tasks that are still running are aborted when the set is dropped, so the tasks can't
+/// outlive the scope that owns the set.
+///
+/// The set is a [`Stream`] of the results of its tasks, in the order they complete.
+///
+/// ```
+/// use futures_util::StreamExt;
+///
+/// let c = glib::MainContext::new();
+/// c.block_on(async {
+///     let mut tasks = glib::TaskSet::new();
+///     tasks.spawn_local(async { 1 });
+///     tasks.spawn_local(async { 2 });
+///
+///     let mut sum = 0;
+///     while let Some(res) = tasks.next().await {
+///         sum += res.unwrap();
+///     }
+///     assert_eq!(sum, 3);
+/// });
+/// ```
+pub struct TaskSet<T> {
+    context: MainContext,
+    tasks: Vec<JoinHandle<T>>,
+}
+
+impl<T> TaskSet<T> {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new, empty set that spawns its tasks on the thread default main context.
+    pub fn new() -> Self {
+        Self::with_context(&MainContext::ref_thread_default())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Creates a new, empty set that spawns its tasks on `context`.
+    pub fn with_context(context: &MainContext) -> Self {
+        Self {
+            context: context.clone(),
+            tasks: Vec::new(),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The main context the tasks are spawned on.
+    pub fn context(&self) -> &MainContext {
+        &self.context
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds an already spawned task to the set.
+    pub fn push(&mut self, handle: JoinHandle<T>) {
+        self.tasks.push(handle);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The number of tasks in the set whose result was not returned yet.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the set has no tasks whose result was not returned yet.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Aborts all tasks in the set.
+    ///
+    /// The aborted tasks stay in the set and return a [`JoinError`].
+    pub fn abort_all(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for the next task to complete and returns its result.
+    ///
+    /// Returns `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<Result<T, JoinError>> {
+        futures_util::future::poll_fn(|cx| self.poll_join_next(cx)).await
+    }
+
+    fn poll_join_next(&mut self, cx: &mut Context) -> Poll<Option<Result<T, JoinError>>> {
+        if self.tasks.is_empty() {
+            return Poll::Ready(None);
+        }
+
+        for i in 0..self.tasks.len() {
+            if let Poll::Ready(res) = Pin::new(&mut self.tasks[i]).poll(cx) {
+                self.tasks.swap_remove(i);
+                return Poll::Ready(Some(res));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<T: Send + 'static> TaskSet<T> {
+    // rustdoc-stripper-ignore-next
+    /// Spawns `f` on the main context of the set and adds it to the set.
+    ///
+    /// See [`MainContext::spawn_task`].
+    pub fn spawn<F: Future<Output = T> + Send + 'static>(&mut self, f: F) {
+        let handle = self.context.spawn_task(f);
+        self.tasks.push(handle);
+    }
+}
+
+impl<T: 'static> TaskSet<T> {
+    // rustdoc-stripper-ignore-next
+    /// Spawns the non-`Send` `f` on the main context of the set and adds it to the set.
+    ///
+    /// See [`MainContext::spawn_local_task`].
+    pub fn spawn_local<F: Future<Output = T> + 'static>(&mut self, f: F) {
+        let handle = self.context.spawn_local_task(f);
+        self.tasks.push(handle);
+    }
+}
+
+impl<T> Default for TaskSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for TaskSet<T> {
+    fn drop(&mut self) {
+        self.abort_all();
+    }
+}
+
+impl<T> fmt::Debug for TaskSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TaskSet")
+            .field("context", &self.context)
+            .field("tasks", &self.tasks)
+            .finish()
+    }
+}
+
+impl<T> Stream for TaskSet<T> {
+    type Item = Result<T, JoinError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_join_next(cx)
+    }
+}
+
 impl Spawn for MainContext {
     fn spawn_obj(&self, f: FutureObj<'static, ()>) -> Result<(), SpawnError> {
-        let source = TaskSource::new(crate::PRIORITY_DEFAULT, FutureWrapper::Send(f));
+        let source = TaskSource::new(
+            crate::PRIORITY_DEFAULT,
+            None,
+            std::any::type_name::<FutureObj<()>>(),
+            FutureWrapper::Send(f),
+        );
         source.attach(Some(self));
         Ok(())
     }
@@ -343,6 +800,8 @@ impl LocalSpawn for MainContext {
     fn spawn_local_obj(&self, f: LocalFutureObj<'static, ()>) -> Result<(), SpawnError> {
         let source = TaskSource::new(
             crate::PRIORITY_DEFAULT,
+            None,
+            std::any::type_name::<LocalFutureObj<()>>(),
             FutureWrapper::NonSend(ThreadGuard::new(f)),
         );
         source.attach(Some(self));
@@ -425,4 +884,100 @@ mod tests {
 
         assert_eq!(v, Some(123));
     }
+
+    #[test]
+    fn test_join_handle() {
+        let c = MainContext::new();
+
+        let handle = c.spawn_task(async { 42 });
+        assert_eq!(c.block_on(handle), Ok(42));
+
+        c.block_on(async {
+            let handle = c.spawn_local_task(async { "local" });
+            assert_eq!(handle.await, Ok("local"));
+        });
+    }
+
+    #[test]
+    fn test_abort() {
+        struct DropFlag(std::rc::Rc<std::cell::Cell<bool>>);
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.set(true);
+            }
+        }
+
+        let c = MainContext::new();
+        let dropped = std::rc::Rc::new(std::cell::Cell::new(false));
+
+        let flag = DropFlag(dropped.clone());
+        let handle = c
+            .with_thread_default(|| {
+                c.spawn_local_task(async move {
+                    let _flag = flag;
+                    futures_util::future::pending::<()>().await;
+                })
+            })
+            .unwrap();
+        assert!(!handle.is_finished());
+
+        handle.abort();
+        assert_eq!(c.block_on(handle), Err(JoinError(())));
+        assert!(dropped.get());
+    }
+
+    #[test]
+    fn test_into_source_id() {
+        let c = MainContext::default();
+
+        let handle = c.spawn_task(futures_util::future::pending::<()>());
+        let source = handle.source().clone();
+        handle.into_source_id().remove();
+        assert!(source.is_destroyed());
+    }
+
+    #[test]
+    fn test_task_set() {
+        use futures_util::StreamExt;
+
+        let c = MainContext::new();
+        let (sender, receiver) = oneshot::channel::<()>();
+
+        let mut tasks = TaskSet::with_context(&c);
+        tasks.spawn(async { 1 });
+        tasks.spawn(async { 2 });
+        c.block_on(async {
+            assert_eq!(
+                tasks.join_next().await.unwrap().unwrap() + tasks.next().await.unwrap().unwrap(),
+                3
+            );
+        });
+        assert!(tasks.is_empty());
+        assert_eq!(c.block_on(tasks.join_next()), None);
+
+        tasks.spawn(async move {
+            let _sender = sender;
+            futures_util::future::pending::<()>().await;
+            0
+        });
+        assert_eq!(tasks.len(), 1);
+        drop(tasks);
+
+        // The sender is dropped once the task is aborted
+        assert!(c.block_on(receiver).is_err());
+    }
+
+    #[test]
+    fn test_task_names() {
+        let c = MainContext::new();
+
+        let handle = c.spawn_with_name("some task", futures_util::future::pending::<()>());
+        let other = MainContext::new();
+        let _other_handle = other.spawn_with_name("other task", async {});
+        assert_eq!(c.task_names(), vec!["some task"]);
+
+        handle.abort();
+        assert!(c.block_on(handle).is_err());
+        assert!(c.task_names().is_empty());
+    }
 }
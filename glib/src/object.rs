@@ -17,13 +17,13 @@ use std::ops;
 use std::pin::Pin;
 use std::ptr;
 
-use crate::closure::TryFromClosureReturnValue;
+use crate::closure::{ClosureArgs, SignalClosure, ToClosureReturnValue, TryFromClosureReturnValue};
 use crate::subclass::{prelude::ObjectSubclass, SignalId};
 use crate::value::ToValue;
 use crate::SignalHandlerId;
 use crate::Type;
 use crate::Value;
-use crate::{Closure, RustClosure};
+use crate::{Closure, RustClosure, TypedClosure};
 
 use crate::thread_guard::thread_id;
 
@@ -1579,6 +1579,7 @@ impl Object {
 pub struct ObjectBuilder<'a, O> {
     type_: Type,
     properties: Vec<(&'a str, Value)>,
+    closures: Vec<(SignalId, Option<Quark>, bool, RustClosure)>,
     phantom: PhantomData<O>,
 }
 
@@ -1587,6 +1588,7 @@ impl<'a, O: IsA<Object> + IsClass> ObjectBuilder<'a, O> {
         ObjectBuilder {
             type_,
             properties: vec![],
+            closures: vec![],
             phantom: PhantomData,
         }
     }
@@ -1603,6 +1605,7 @@ impl<'a, O: IsA<Object> + IsClass> ObjectBuilder<'a, O> {
         let ObjectBuilder {
             type_,
             mut properties,
+            closures,
             ..
         } = self;
         properties.push((name, value.to_value()));
@@ -1610,10 +1613,48 @@ impl<'a, O: IsA<Object> + IsClass> ObjectBuilder<'a, O> {
         ObjectBuilder {
             type_,
             properties,
+            closures,
             phantom: PhantomData,
         }
     }
 
+    // rustdoc-stripper-ignore-next
+    /// Connect `closure` to the signal `signal_name` of the object once it is built.
+    ///
+    /// If `after` is set to `true` then the callback will be called after the default class
+    /// handler of the signal is emitted, otherwise before.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the signal does not exist or if the signature of a
+    /// [`TypedClosure`](crate::TypedClosure) does not match the signal.
+    #[track_caller]
+    pub fn connect_closure(
+        mut self,
+        signal_name: &str,
+        after: bool,
+        closure: impl SignalClosure,
+    ) -> Self {
+        // Signals are only registered once the class is initialized.
+        let _class = ObjectClass::from_type(self.type_);
+        let (signal_id, details) = SignalId::parse_name(signal_name, self.type_, true)
+            .unwrap_or_else(|| {
+                panic!(
+                    "Signal '{}' of type '{}' not found",
+                    signal_name, self.type_
+                )
+            });
+        if let Err(err) = closure.check_signal(signal_id, self.type_) {
+            panic!(
+                "Can't connect closure to signal '{}' of type '{}': {}",
+                signal_name, self.type_, err
+            );
+        }
+        self.closures
+            .push((signal_id, details, after, closure.into()));
+        self
+    }
+
     // rustdoc-stripper-ignore-next
     /// Build the object with the provided properties.
     ///
@@ -1621,9 +1662,15 @@ impl<'a, O: IsA<Object> + IsClass> ObjectBuilder<'a, O> {
     ///
     /// This panics if the object is not instantiable, doesn't have all the given properties or
     /// property values of the wrong type are provided.
+    ///
+    /// Closures added with [`Self::connect_closure`] are connected right after construction, so
+    /// they don't see signals emitted during construction.
     #[track_caller]
     pub fn build(self) -> O {
         let object = Object::with_values(self.type_, &self.properties);
+        for (signal_id, details, after, closure) in self.closures {
+            object.connect_closure_id(signal_id, details, after, closure);
+        }
         unsafe { object.unsafe_cast::<O>() }
     }
 }
@@ -1994,6 +2041,24 @@ pub trait ObjectExt: ObjectType {
         closure: RustClosure,
     ) -> SignalHandlerId;
 
+    // rustdoc-stripper-ignore-next
+    /// Connect a [`TypedClosure`](crate::TypedClosure) to the signal `signal_name` on this
+    /// object.
+    ///
+    /// Same as [`Self::connect_closure`] but the signature of the closure is checked against the
+    /// signal here instead of failing on emission.
+    ///
+    /// This panics if the signal does not exist or if the signature doesn't match.
+    fn connect_typed_closure<Args, R>(
+        &self,
+        signal_name: &str,
+        after: bool,
+        closure: TypedClosure<Args, R>,
+    ) -> SignalHandlerId
+    where
+        Args: ClosureArgs,
+        R: ToClosureReturnValue + StaticType + 'static;
+
     // rustdoc-stripper-ignore-next
     /// Connect a closure to the signal `signal_id` on this object.
     ///
@@ -2706,6 +2771,29 @@ impl<T: ObjectType> ObjectExt for T {
         self.connect_closure_id(signal_id, details, after, closure)
     }
 
+    #[track_caller]
+    fn connect_typed_closure<Args, R>(
+        &self,
+        signal_name: &str,
+        after: bool,
+        closure: TypedClosure<Args, R>,
+    ) -> SignalHandlerId
+    where
+        Args: ClosureArgs,
+        R: ToClosureReturnValue + StaticType + 'static,
+    {
+        let type_ = self.type_();
+        let (signal_id, details) = SignalId::parse_name(signal_name, type_, true)
+            .unwrap_or_else(|| panic!("Signal '{}' of type '{}' not found", signal_name, type_));
+        if let Err(err) = closure.check_signal(signal_id, type_) {
+            panic!(
+                "Can't connect closure to signal '{}' of type '{}': {}",
+                signal_name, type_, err
+            );
+        }
+        self.connect_closure_id(signal_id, details, after, closure.into())
+    }
+
     #[track_caller]
     fn connect_closure_id(
         &self,
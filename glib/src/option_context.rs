@@ -0,0 +1,1149 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, OsString},
+    fmt,
+    num::IntErrorKind,
+    path::PathBuf,
+    ptr,
+    rc::Rc,
+};
+
+use crate::{
+    translate::*, Error, GString, OptionArg, OptionError, OptionFlags, ToVariant, Variant,
+    VariantDict,
+};
+
+type OptionCallback = Box<dyn FnMut(&str, Option<&str>) -> Result<(), Error> + 'static>;
+
+// rustdoc-stripper-ignore-next
+/// A single command line option.
+///
+/// Entries are created with [`OptionEntry::builder`] and added to an [`OptionGroup`], to the
+/// main group of an [`OptionContext`] or to a `gio::Application`.
+///
+/// Unless the entry has a [callback](OptionEntryBuilder::callback), parsed values are collected
+/// in the [`VariantDict`] returned by [`OptionGroup::values`], keyed by the long name and using
+/// the same types as `GApplication` uses for its `handle-local-options` signal:
+///
+/// | [`OptionArg`]                    | Variant type |
+/// |----------------------------------|--------------|
+/// | [`None`](OptionArg::None)        | `b`          |
+/// | [`String`](OptionArg::String)    | `s`          |
+/// | [`Int`](OptionArg::Int)          | `i`          |
+/// | [`Filename`](OptionArg::Filename) | `ay`        |
+/// | [`StringArray`](OptionArg::StringArray) | `as`  |
+/// | [`FilenameArray`](OptionArg::FilenameArray) | `aay` |
+/// | [`Double`](OptionArg::Double)    | `d`          |
+/// | [`Int64`](OptionArg::Int64)      | `x`          |
+#[doc(alias = "GOptionEntry")]
+pub struct OptionEntry {
+    long_name: String,
+    short_name: Option<char>,
+    flags: OptionFlags,
+    arg: OptionArg,
+    description: Option<String>,
+    arg_description: Option<String>,
+    callback: Option<OptionCallback>,
+}
+
+impl OptionEntry {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new builder for an option called `--long-name`.
+    pub fn builder(long_name: &str) -> OptionEntryBuilder {
+        assert!(
+            !long_name.is_empty() && !long_name.starts_with('-') && !long_name.contains('='),
+            "invalid long option name `{}`",
+            long_name
+        );
+        OptionEntryBuilder {
+            entry: OptionEntry {
+                long_name: long_name.to_owned(),
+                short_name: None,
+                flags: OptionFlags::NONE,
+                arg: OptionArg::None,
+                description: None,
+                arg_description: None,
+                callback: None,
+            },
+        }
+    }
+
+    pub fn long_name(&self) -> &str {
+        &self.long_name
+    }
+
+    pub fn short_name(&self) -> Option<char> {
+        self.short_name
+    }
+
+    pub fn flags(&self) -> OptionFlags {
+        self.flags
+    }
+
+    pub fn arg(&self) -> OptionArg {
+        self.arg
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn arg_description(&self) -> Option<&str> {
+        self.arg_description.as_deref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the entry calls a Rust callback instead of storing its value.
+    pub fn has_callback(&self) -> bool {
+        self.callback.is_some()
+    }
+}
+
+impl fmt::Debug for OptionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OptionEntry")
+            .field("long_name", &self.long_name)
+            .field("short_name", &self.short_name)
+            .field("flags", &self.flags)
+            .field("arg", &self.arg)
+            .field("description", &self.description)
+            .field("arg_description", &self.arg_description)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Builder for [`OptionEntry`].
+#[must_use = "The builder must be built to be used"]
+pub struct OptionEntryBuilder {
+    entry: OptionEntry,
+}
+
+impl OptionEntryBuilder {
+    // rustdoc-stripper-ignore-next
+    /// Sets the single character used as `-c` short option.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `short_name` is not a printable ASCII character or is `-`.
+    pub fn short_name(mut self, short_name: char) -> Self {
+        assert!(
+            short_name.is_ascii_graphic() && short_name != '-',
+            "invalid short option name `{}`",
+            short_name
+        );
+        self.entry.short_name = Some(short_name);
+        self
+    }
+
+    pub fn flags(mut self, flags: OptionFlags) -> Self {
+        self.entry.flags = flags;
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the type of the option's argument. Defaults to [`OptionArg::None`], a switch.
+    ///
+    /// # Panics
+    ///
+    /// Panics for [`OptionArg::Callback`], use [`callback`](Self::callback) instead.
+    pub fn arg(mut self, arg: OptionArg) -> Self {
+        assert!(
+            !matches!(arg, OptionArg::Callback | OptionArg::__Unknown(_)),
+            "unsupported option argument type {}",
+            arg
+        );
+        self.entry.arg = arg;
+        self.entry.callback = None;
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.entry.description = Some(description.to_owned());
+        self
+    }
+
+    pub fn arg_description(mut self, arg_description: &str) -> Self {
+        self.entry.arg_description = Some(arg_description.to_owned());
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Calls `func` with the option name as given on the command line and its value every time
+    /// the option is encountered.
+    ///
+    /// By default the option requires a value; use [`OptionFlags::NO_ARG`] or
+    /// [`OptionFlags::OPTIONAL_ARG`] to change that. Values are passed lossily converted to UTF-8.
+    /// Returning an error aborts parsing with that error.
+    pub fn callback<F: FnMut(&str, Option<&str>) -> Result<(), Error> + 'static>(
+        mut self,
+        func: F,
+    ) -> Self {
+        self.entry.arg = OptionArg::Callback;
+        self.entry.callback = Some(Box::new(func));
+        self
+    }
+
+    #[must_use = "Building the entry without using it makes no sense"]
+    pub fn build(self) -> OptionEntry {
+        self.entry
+    }
+}
+
+struct EntryData {
+    long_name: CString,
+    short_name: u8,
+    flags: OptionFlags,
+    arg: OptionArg,
+    description: Option<CString>,
+    arg_description: Option<CString>,
+    callback: Option<OptionCallback>,
+}
+
+impl EntryData {
+    fn new(entry: OptionEntry) -> Self {
+        EntryData {
+            long_name: CString::new(entry.long_name).expect("NUL in option name"),
+            short_name: entry.short_name.map(|c| c as u8).unwrap_or(0),
+            flags: entry.flags,
+            arg: entry.arg,
+            description: entry
+                .description
+                .map(|s| CString::new(s).expect("NUL in option description")),
+            arg_description: entry
+                .arg_description
+                .map(|s| CString::new(s).expect("NUL in option description")),
+            callback: entry.callback,
+        }
+    }
+
+    // Every entry is registered as callback so values can be collected without handing
+    // GLib pointers into Rust memory.
+    fn to_glib(&self) -> ffi::GOptionEntry {
+        let mut flags = self.flags - OptionFlags::REVERSE;
+        match self.arg {
+            OptionArg::None => flags |= OptionFlags::NO_ARG,
+            OptionArg::Filename | OptionArg::FilenameArray => flags |= OptionFlags::FILENAME,
+            _ => (),
+        }
+        ffi::GOptionEntry {
+            long_name: self.long_name.as_ptr(),
+            short_name: self.short_name as _,
+            flags: flags.into_glib() as i32,
+            arg: ffi::G_OPTION_ARG_CALLBACK,
+            arg_data: option_arg_func as *const () as ffi::gpointer,
+            description: self
+                .description
+                .as_ref()
+                .map_or(ptr::null(), |s| s.as_ptr()),
+            arg_description: self
+                .arg_description
+                .as_ref()
+                .map_or(ptr::null(), |s| s.as_ptr()),
+        }
+    }
+}
+
+struct GroupData {
+    name: CString,
+    description: CString,
+    help_description: CString,
+    entries: RefCell<Vec<EntryData>>,
+    values: RefCell<VariantDict>,
+}
+
+impl GroupData {
+    fn find(&self, option_name: &str) -> Option<usize> {
+        let entries = self.entries.borrow();
+        if let Some(long_name) = option_name.strip_prefix("--") {
+            // Options that clash with another group are only reachable as `--group-option`.
+            let prefixed = long_name
+                .strip_prefix(self.name.to_str().unwrap_or_default())
+                .and_then(|s| s.strip_prefix('-'));
+            entries.iter().position(|e| {
+                let name = e.long_name.as_bytes();
+                name == long_name.as_bytes() || Some(name) == prefixed.map(str::as_bytes)
+            })
+        } else {
+            match option_name.as_bytes() {
+                [b'-', c] => entries.iter().position(|e| e.short_name == *c),
+                _ => None,
+            }
+        }
+    }
+
+    fn handle(&self, option_name: &str, value: Option<&CStr>) -> Result<(), Error> {
+        let index = self.find(option_name).ok_or_else(|| {
+            Error::new(
+                OptionError::UnknownOption,
+                &format!("Unknown option {}", option_name),
+            )
+        })?;
+
+        let (long_name, arg, flags, callback) = {
+            let mut entries = self.entries.borrow_mut();
+            let entry = &mut entries[index];
+            (
+                entry.long_name.to_str().unwrap().to_owned(),
+                entry.arg,
+                entry.flags,
+                entry.callback.take(),
+            )
+        };
+
+        if let Some(mut callback) = callback {
+            let value = value.map(|v| v.to_string_lossy());
+            let res = callback(option_name, value.as_deref());
+            // The callback is free to add entries while it runs.
+            self.entries.borrow_mut()[index].callback = Some(callback);
+            return res;
+        }
+
+        let values = self.values.borrow();
+        if arg == OptionArg::None {
+            let enabled = !flags.contains(OptionFlags::REVERSE);
+            values.insert_value(&long_name, &enabled.to_variant());
+            return Ok(());
+        }
+
+        let value = value.ok_or_else(|| {
+            Error::new(
+                OptionError::BadValue,
+                &format!("Missing argument for {}", option_name),
+            )
+        })?;
+        let string = || {
+            value.to_str().map_err(|_| {
+                Error::new(
+                    OptionError::BadValue,
+                    &format!("Invalid UTF-8 in value for {}", option_name),
+                )
+            })
+        };
+        let filename = || unsafe { crate::translate::c_to_path_buf(value.as_ptr()) };
+
+        let variant = match arg {
+            OptionArg::String => string()?.to_variant(),
+            OptionArg::Int => parse_int::<i32>(string()?, option_name)?.to_variant(),
+            OptionArg::Int64 => parse_int::<i64>(string()?, option_name)?.to_variant(),
+            OptionArg::Double => parse_double(string()?, option_name)?.to_variant(),
+            OptionArg::Filename => filename().to_variant(),
+            OptionArg::StringArray => {
+                let mut array = values
+                    .lookup::<Vec<String>>(&long_name)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+                array.push(string()?.to_owned());
+                array.to_variant()
+            }
+            OptionArg::FilenameArray => {
+                let mut array = values
+                    .lookup::<Vec<PathBuf>>(&long_name)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+                array.push(filename());
+                array.to_variant()
+            }
+            _ => unreachable!(),
+        };
+        values.insert_value(&long_name, &variant);
+
+        Ok(())
+    }
+}
+
+fn parse_int<T: TryFrom<i128>>(value: &str, option_name: &str) -> Result<T, Error> {
+    // Same rules as strtol() with base 0 which GLib uses.
+    let s = value.trim_start();
+    let (negative, s) = match s.as_bytes().first() {
+        Some(b'-') => (true, &s[1..]),
+        Some(b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (radix, digits) = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        (16, hex)
+    } else if s.len() > 1 && s.starts_with('0') {
+        (8, &s[1..])
+    } else {
+        (10, s)
+    };
+
+    let invalid = || {
+        Error::new(
+            OptionError::BadValue,
+            &format!("Cannot parse integer value “{}” for {}", value, option_name),
+        )
+    };
+    let out_of_range = || {
+        Error::new(
+            OptionError::BadValue,
+            &format!("Integer value “{}” for {} out of range", value, option_name),
+        )
+    };
+
+    if digits.starts_with(['+', '-']) {
+        return Err(invalid());
+    }
+    let magnitude = match i128::from_str_radix(digits, radix) {
+        Ok(v) => v,
+        Err(e) if matches!(e.kind(), IntErrorKind::PosOverflow) => return Err(out_of_range()),
+        Err(_) => return Err(invalid()),
+    };
+    let v = if negative { -magnitude } else { magnitude };
+    T::try_from(v).map_err(|_| out_of_range())
+}
+
+fn parse_double(value: &str, option_name: &str) -> Result<f64, Error> {
+    let v = value.trim_start().parse::<f64>().map_err(|_| {
+        Error::new(
+            OptionError::BadValue,
+            &format!("Cannot parse double value “{}” for {}", value, option_name),
+        )
+    })?;
+    if v.is_infinite() && !value.to_ascii_lowercase().contains("inf") {
+        return Err(Error::new(
+            OptionError::BadValue,
+            &format!("Double value “{}” for {} out of range", value, option_name),
+        ));
+    }
+    Ok(v)
+}
+
+unsafe extern "C" fn option_arg_func(
+    option_name: *const libc::c_char,
+    value: *const libc::c_char,
+    data: ffi::gpointer,
+    error: *mut *mut ffi::GError,
+) -> ffi::gboolean {
+    let data = &*(data as *const GroupData);
+    let option_name = CStr::from_ptr(option_name).to_string_lossy();
+    let value = if value.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(value))
+    };
+    match data.handle(&option_name, value) {
+        Ok(()) => ffi::GTRUE,
+        Err(err) => {
+            if !error.is_null() {
+                *error = err.into_glib_ptr();
+            }
+            ffi::GFALSE
+        }
+    }
+}
+
+unsafe extern "C" fn pre_parse_func(
+    _context: *mut ffi::GOptionContext,
+    _group: *mut ffi::GOptionGroup,
+    data: ffi::gpointer,
+    _error: *mut *mut ffi::GError,
+) -> ffi::gboolean {
+    let data = &*(data as *const GroupData);
+    *data.values.borrow_mut() = VariantDict::new(None);
+    ffi::GTRUE
+}
+
+unsafe extern "C" fn destroy_group_data(data: ffi::gpointer) {
+    drop(Rc::from_raw(data as *const GroupData));
+}
+
+// rustdoc-stripper-ignore-next
+/// A group of command line options.
+///
+/// Besides the main group of an [`OptionContext`], every group gets its own `--help-NAME`
+/// section. Values of the group's entries are available from [`values`](Self::values) once
+/// parsing finished.
+#[doc(alias = "GOptionGroup")]
+pub struct OptionGroup {
+    ptr: ptr::NonNull<ffi::GOptionGroup>,
+    data: Rc<GroupData>,
+}
+
+impl OptionGroup {
+    #[doc(alias = "g_option_group_new")]
+    pub fn new(name: &str, description: &str, help_description: &str) -> Self {
+        let data = Rc::new(GroupData {
+            name: CString::new(name).expect("NUL in group name"),
+            description: CString::new(description).expect("NUL in group description"),
+            help_description: CString::new(help_description).expect("NUL in group description"),
+            entries: RefCell::new(Vec::new()),
+            values: RefCell::new(VariantDict::new(None)),
+        });
+        unsafe {
+            let ptr = ffi::g_option_group_new(
+                data.name.as_ptr(),
+                data.description.as_ptr(),
+                data.help_description.as_ptr(),
+                Rc::into_raw(data.clone()) as ffi::gpointer,
+                Some(destroy_group_data),
+            );
+            ffi::g_option_group_set_parse_hooks(ptr, Some(pre_parse_func), None);
+            OptionGroup {
+                ptr: ptr::NonNull::new_unchecked(ptr),
+                data,
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.data.name.to_str().unwrap()
+    }
+
+    #[doc(alias = "g_option_group_add_entries")]
+    pub fn add_entries(&self, entries: impl IntoIterator<Item = OptionEntry>) {
+        let mut data_entries = self.data.entries.borrow_mut();
+        let start = data_entries.len();
+        data_entries.extend(entries.into_iter().map(EntryData::new));
+
+        // GLib copies the array but keeps pointing at the strings, which live in `data`.
+        let mut raw = data_entries[start..]
+            .iter()
+            .map(EntryData::to_glib)
+            .collect::<Vec<_>>();
+        raw.push(unsafe { std::mem::zeroed() });
+        unsafe {
+            ffi::g_option_group_add_entries(self.ptr.as_ptr(), raw.as_ptr());
+        }
+    }
+
+    #[doc(alias = "g_option_group_set_translation_domain")]
+    pub fn set_translation_domain(&self, domain: &str) {
+        unsafe {
+            ffi::g_option_group_set_translation_domain(self.ptr.as_ptr(), domain.to_glib_none().0);
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the values collected during the last parse, keyed by long option name.
+    ///
+    /// Options that were not given on the command line are not contained.
+    pub fn values(&self) -> VariantDict {
+        self.data.values.borrow().clone()
+    }
+}
+
+impl Clone for OptionGroup {
+    fn clone(&self) -> Self {
+        unsafe {
+            ffi::g_option_group_ref(self.ptr.as_ptr());
+        }
+        OptionGroup {
+            ptr: self.ptr,
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl Drop for OptionGroup {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::g_option_group_unref(self.ptr.as_ptr());
+        }
+    }
+}
+
+impl fmt::Debug for OptionGroup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OptionGroup")
+            .field("name", &self.name())
+            .finish()
+    }
+}
+
+#[doc(hidden)]
+impl<'a> ToGlibPtr<'a, *mut ffi::GOptionGroup> for OptionGroup {
+    type Storage = &'a Self;
+
+    #[inline]
+    fn to_glib_none(&'a self) -> Stash<'a, *mut ffi::GOptionGroup, Self> {
+        Stash(self.ptr.as_ptr(), self)
+    }
+
+    #[inline]
+    fn to_glib_full(&self) -> *mut ffi::GOptionGroup {
+        unsafe { ffi::g_option_group_ref(self.ptr.as_ptr()) }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A command line parser.
+///
+/// ```
+/// use glib::{OptionArg, OptionContext, OptionEntry};
+///
+/// let context = OptionContext::new(Some("FILE…"));
+/// context.add_main_entries([
+///     OptionEntry::builder("verbose")
+///         .short_name('v')
+///         .description("Print more")
+///         .build(),
+///     OptionEntry::builder("level")
+///         .arg(OptionArg::Int)
+///         .build(),
+/// ]);
+///
+/// let mut args = vec!["prog".into(), "-v".into(), "--level=3".into(), "file".into()];
+/// context.parse(&mut args).unwrap();
+/// assert_eq!(args, ["prog", "file"]);
+///
+/// let values = context.main_group().unwrap().values();
+/// assert_eq!(values.lookup::<bool>("verbose").unwrap(), Some(true));
+/// assert_eq!(values.lookup::<i32>("level").unwrap(), Some(3));
+/// ```
+#[doc(alias = "GOptionContext")]
+pub struct OptionContext {
+    ptr: ptr::NonNull<ffi::GOptionContext>,
+    main_group: RefCell<Option<OptionGroup>>,
+}
+
+impl OptionContext {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new context. `parameter_string` is shown after the program name in `--help`.
+    #[doc(alias = "g_option_context_new")]
+    pub fn new(parameter_string: Option<&str>) -> Self {
+        unsafe {
+            OptionContext {
+                ptr: ptr::NonNull::new_unchecked(ffi::g_option_context_new(
+                    parameter_string.to_glib_none().0,
+                )),
+                main_group: RefCell::new(None),
+            }
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_summary")]
+    pub fn summary(&self) -> Option<GString> {
+        unsafe { from_glib_none(ffi::g_option_context_get_summary(self.ptr.as_ptr())) }
+    }
+
+    #[doc(alias = "g_option_context_set_summary")]
+    pub fn set_summary(&self, summary: Option<&str>) {
+        unsafe {
+            ffi::g_option_context_set_summary(self.ptr.as_ptr(), summary.to_glib_none().0);
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_description")]
+    pub fn description(&self) -> Option<GString> {
+        unsafe { from_glib_none(ffi::g_option_context_get_description(self.ptr.as_ptr())) }
+    }
+
+    #[doc(alias = "g_option_context_set_description")]
+    pub fn set_description(&self, description: Option<&str>) {
+        unsafe {
+            ffi::g_option_context_set_description(self.ptr.as_ptr(), description.to_glib_none().0);
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_help_enabled")]
+    pub fn is_help_enabled(&self) -> bool {
+        unsafe { from_glib(ffi::g_option_context_get_help_enabled(self.ptr.as_ptr())) }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Enables `--help`, which is the default. Note that GLib exits the process after printing
+    /// the help.
+    #[doc(alias = "g_option_context_set_help_enabled")]
+    pub fn set_help_enabled(&self, help_enabled: bool) {
+        unsafe {
+            ffi::g_option_context_set_help_enabled(self.ptr.as_ptr(), help_enabled.into_glib());
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_ignore_unknown_options")]
+    pub fn is_ignore_unknown_options(&self) -> bool {
+        unsafe {
+            from_glib(ffi::g_option_context_get_ignore_unknown_options(
+                self.ptr.as_ptr(),
+            ))
+        }
+    }
+
+    #[doc(alias = "g_option_context_set_ignore_unknown_options")]
+    pub fn set_ignore_unknown_options(&self, ignore_unknown: bool) {
+        unsafe {
+            ffi::g_option_context_set_ignore_unknown_options(
+                self.ptr.as_ptr(),
+                ignore_unknown.into_glib(),
+            );
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_strict_posix")]
+    pub fn is_strict_posix(&self) -> bool {
+        unsafe { from_glib(ffi::g_option_context_get_strict_posix(self.ptr.as_ptr())) }
+    }
+
+    #[doc(alias = "g_option_context_set_strict_posix")]
+    pub fn set_strict_posix(&self, strict_posix: bool) {
+        unsafe {
+            ffi::g_option_context_set_strict_posix(self.ptr.as_ptr(), strict_posix.into_glib());
+        }
+    }
+
+    #[doc(alias = "g_option_context_set_translation_domain")]
+    pub fn set_translation_domain(&self, domain: &str) {
+        unsafe {
+            ffi::g_option_context_set_translation_domain(
+                self.ptr.as_ptr(),
+                domain.to_glib_none().0,
+            );
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_main_group")]
+    pub fn main_group(&self) -> Option<OptionGroup> {
+        self.main_group.borrow().clone()
+    }
+
+    #[doc(alias = "g_option_context_set_main_group")]
+    pub fn set_main_group(&self, group: &OptionGroup) {
+        unsafe {
+            ffi::g_option_context_set_main_group(self.ptr.as_ptr(), group.to_glib_full());
+        }
+        self.main_group.replace(Some(group.clone()));
+    }
+
+    #[doc(alias = "g_option_context_add_group")]
+    pub fn add_group(&self, group: &OptionGroup) {
+        unsafe {
+            ffi::g_option_context_add_group(self.ptr.as_ptr(), group.to_glib_full());
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Adds entries to the main group, creating it if needed.
+    #[doc(alias = "g_option_context_add_main_entries")]
+    pub fn add_main_entries(&self, entries: impl IntoIterator<Item = OptionEntry>) {
+        let group = self.main_group().unwrap_or_else(|| {
+            let group = OptionGroup::new("main", "", "");
+            self.set_main_group(&group);
+            group
+        });
+        group.add_entries(entries);
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Parses `args`, which include the program name as first element. Recognized options are
+    /// removed from `args`, leaving the program name and the remaining arguments.
+    #[doc(alias = "g_option_context_parse_strv")]
+    pub fn parse(&self, args: &mut Vec<OsString>) -> Result<(), Error> {
+        unsafe {
+            let mut argv = args
+                .iter()
+                .map(|arg| arg.to_glib_full())
+                .collect::<Vec<*mut libc::c_char>>();
+            argv.push(ptr::null_mut());
+            let mut argv_ptr = argv.as_mut_ptr();
+            let mut error = ptr::null_mut();
+            let is_ok =
+                ffi::g_option_context_parse_strv(self.ptr.as_ptr(), &mut argv_ptr, &mut error);
+
+            // Consumed arguments were freed by GLib, the rest moved to the front.
+            args.clear();
+            for arg in argv.iter().take_while(|arg| !arg.is_null()) {
+                args.push(crate::translate::c_to_os_string(*arg));
+                ffi::g_free(*arg as ffi::gpointer);
+            }
+
+            debug_assert_eq!(is_ok == ffi::GFALSE, !error.is_null());
+            if error.is_null() {
+                Ok(())
+            } else {
+                Err(from_glib_full(error))
+            }
+        }
+    }
+
+    #[doc(alias = "g_option_context_get_help")]
+    pub fn help(&self, main_help: bool, group: Option<&OptionGroup>) -> GString {
+        unsafe {
+            from_glib_full(ffi::g_option_context_get_help(
+                self.ptr.as_ptr(),
+                main_help.into_glib(),
+                group.map_or(ptr::null_mut(), |g| g.ptr.as_ptr()),
+            ))
+        }
+    }
+}
+
+impl Drop for OptionContext {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::g_option_context_free(self.ptr.as_ptr());
+        }
+    }
+}
+
+impl fmt::Debug for OptionContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OptionContext")
+            .field("summary", &self.summary())
+            .field("main_group", &self.main_group.borrow())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A set of command line options, usually implemented with
+/// [`#[derive(glib::Options)]`](derive@crate::Options).
+pub trait Options: Sized {
+    // rustdoc-stripper-ignore-next
+    /// Returns the entries to register with an [`OptionContext`] or `gio::Application`.
+    fn option_entries() -> Vec<OptionEntry>;
+
+    // rustdoc-stripper-ignore-next
+    /// Builds the options from parsed values, as returned by [`OptionGroup::values`] or passed
+    /// to `gio::Application`'s `handle-local-options` signal.
+    fn from_variant_dict(dict: &VariantDict) -> Result<Self, Error>;
+
+    // rustdoc-stripper-ignore-next
+    /// Parses `args` with a new [`OptionContext`], see [`OptionContext::parse`].
+    fn parse(args: &mut Vec<OsString>) -> Result<Self, Error> {
+        let context = OptionContext::new(None);
+        context.add_main_entries(Self::option_entries());
+        context.parse(args)?;
+        Self::from_variant_dict(&context.main_group().unwrap().values())
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Types that can be used as fields of a [`#[derive(glib::Options)]`](derive@crate::Options)
+/// struct.
+pub trait OptionArgType: Sized {
+    // rustdoc-stripper-ignore-next
+    /// The argument type of the corresponding [`OptionEntry`].
+    const ARG: OptionArg;
+
+    // rustdoc-stripper-ignore-next
+    /// Converts a value as stored by [`OptionGroup`] or `gio::Application`.
+    fn from_option_variant(variant: &Variant) -> Option<Self>;
+
+    // rustdoc-stripper-ignore-next
+    /// The value used when the option was not given.
+    fn default_value() -> Self;
+
+    // rustdoc-stripper-ignore-next
+    /// Looks up `long_name` in `dict`, returning `None` if the option was not given.
+    fn lookup_option(dict: &VariantDict, long_name: &str) -> Result<Option<Self>, Error> {
+        match dict.lookup_value(long_name, None) {
+            None => Ok(None),
+            Some(variant) => Self::from_option_variant(&variant)
+                .map(Some)
+                .ok_or_else(|| {
+                    Error::new(
+                        OptionError::BadValue,
+                        &format!(
+                            "Unexpected value of type {} for option --{}",
+                            variant.type_(),
+                            long_name
+                        ),
+                    )
+                }),
+        }
+    }
+}
+
+macro_rules! option_arg_type {
+    ($name:ty, $arg:ident) => {
+        impl OptionArgType for $name {
+            const ARG: OptionArg = OptionArg::$arg;
+
+            fn from_option_variant(variant: &Variant) -> Option<Self> {
+                variant.get()
+            }
+
+            fn default_value() -> Self {
+                Default::default()
+            }
+        }
+    };
+}
+
+option_arg_type!(bool, None);
+option_arg_type!(String, String);
+option_arg_type!(i32, Int);
+option_arg_type!(i64, Int64);
+option_arg_type!(f64, Double);
+option_arg_type!(Vec<String>, StringArray);
+option_arg_type!(PathBuf, Filename);
+option_arg_type!(OsString, Filename);
+option_arg_type!(Vec<PathBuf>, FilenameArray);
+option_arg_type!(Vec<OsString>, FilenameArray);
+
+impl<T: OptionArgType> OptionArgType for Option<T> {
+    const ARG: OptionArg = T::ARG;
+
+    fn from_option_variant(variant: &Variant) -> Option<Self> {
+        T::from_option_variant(variant).map(Some)
+    }
+
+    fn default_value() -> Self {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as glib;
+    use std::cell::Cell;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parse_values() {
+        let context = OptionContext::new(None);
+        context.add_main_entries([
+            OptionEntry::builder("verbose").short_name('v').build(),
+            OptionEntry::builder("no-color")
+                .flags(OptionFlags::REVERSE)
+                .build(),
+            OptionEntry::builder("name").arg(OptionArg::String).build(),
+            OptionEntry::builder("count")
+                .short_name('c')
+                .arg(OptionArg::Int)
+                .build(),
+            OptionEntry::builder("big").arg(OptionArg::Int64).build(),
+            OptionEntry::builder("ratio").arg(OptionArg::Double).build(),
+            OptionEntry::builder("output")
+                .arg(OptionArg::Filename)
+                .build(),
+            OptionEntry::builder("tag")
+                .arg(OptionArg::StringArray)
+                .build(),
+            OptionEntry::builder("input")
+                .arg(OptionArg::FilenameArray)
+                .build(),
+        ]);
+
+        let mut argv = args(&[
+            "prog",
+            "-v",
+            "--no-color",
+            "--name=foo",
+            "-c",
+            "0x10",
+            "--big",
+            "-5000000000",
+            "--ratio",
+            "0.5",
+            "--output",
+            "/tmp/out",
+            "rest",
+            "--tag",
+            "a",
+            "--tag=b",
+            "--input",
+            "x",
+            "--input",
+            "y",
+        ]);
+        context.parse(&mut argv).unwrap();
+        assert_eq!(argv, args(&["prog", "rest"]));
+
+        let values = context.main_group().unwrap().values();
+        assert_eq!(values.lookup::<bool>("verbose").unwrap(), Some(true));
+        assert_eq!(values.lookup::<bool>("no-color").unwrap(), Some(false));
+        assert_eq!(
+            values.lookup::<String>("name").unwrap().as_deref(),
+            Some("foo")
+        );
+        assert_eq!(values.lookup::<i32>("count").unwrap(), Some(16));
+        assert_eq!(values.lookup::<i64>("big").unwrap(), Some(-5_000_000_000));
+        assert_eq!(values.lookup::<f64>("ratio").unwrap(), Some(0.5));
+        assert_eq!(
+            values.lookup::<PathBuf>("output").unwrap(),
+            Some(PathBuf::from("/tmp/out"))
+        );
+        assert_eq!(
+            values.lookup::<Vec<String>>("tag").unwrap(),
+            Some(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(
+            values.lookup::<Vec<PathBuf>>("input").unwrap(),
+            Some(vec![PathBuf::from("x"), PathBuf::from("y")])
+        );
+
+        // Values from a previous parse are dropped.
+        let mut argv = args(&["prog", "--tag", "c"]);
+        context.parse(&mut argv).unwrap();
+        let values = context.main_group().unwrap().values();
+        assert!(!values.contains("verbose"));
+        assert_eq!(
+            values.lookup::<Vec<String>>("tag").unwrap(),
+            Some(vec!["c".to_owned()])
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let context = OptionContext::new(None);
+        context.add_main_entries([
+            OptionEntry::builder("count").arg(OptionArg::Int).build(),
+            OptionEntry::builder("ratio").arg(OptionArg::Double).build(),
+        ]);
+
+        for (arg, message) in [
+            (
+                "--count=12a",
+                "Cannot parse integer value “12a” for --count",
+            ),
+            (
+                "--count=0x100000000",
+                "Integer value “0x100000000” for --count out of range",
+            ),
+            ("--ratio=x", "Cannot parse double value “x” for --ratio"),
+        ] {
+            let err = context.parse(&mut args(&["prog", arg])).unwrap_err();
+            assert!(err.matches(OptionError::BadValue));
+            assert_eq!(err.message(), message);
+        }
+
+        let err = context
+            .parse(&mut args(&["prog", "--unknown"]))
+            .unwrap_err();
+        assert!(err.matches(OptionError::UnknownOption));
+
+        context.set_ignore_unknown_options(true);
+        let mut argv = args(&["prog", "--unknown", "--count", "-07"]);
+        context.parse(&mut argv).unwrap();
+        assert_eq!(argv, args(&["prog", "--unknown"]));
+        let values = context.main_group().unwrap().values();
+        assert_eq!(values.lookup::<i32>("count").unwrap(), Some(-7));
+    }
+
+    #[test]
+    fn callbacks_and_groups() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let calls = Rc::new(Cell::new(0));
+
+        let context = OptionContext::new(None);
+        context.add_main_entries([OptionEntry::builder("define")
+            .short_name('D')
+            .callback({
+                let seen = seen.clone();
+                move |name, value| {
+                    let value = value.unwrap();
+                    if value.is_empty() {
+                        return Err(Error::new(OptionError::Failed, "empty define"));
+                    }
+                    seen.borrow_mut().push(format!("{}={}", name, value));
+                    Ok(())
+                }
+            })
+            .build()]);
+
+        let group = OptionGroup::new("extra", "Extra options", "Show extra options");
+        group.add_entries([
+            OptionEntry::builder("ping")
+                .flags(OptionFlags::NO_ARG)
+                .callback({
+                    let calls = calls.clone();
+                    move |_, value| {
+                        assert!(value.is_none());
+                        calls.set(calls.get() + 1);
+                        Ok(())
+                    }
+                })
+                .build(),
+            OptionEntry::builder("level").arg(OptionArg::Int).build(),
+        ]);
+        context.add_group(&group);
+
+        let mut argv = args(&[
+            "prog",
+            "-D",
+            "a",
+            "--define=b",
+            "--ping",
+            "--level=2",
+            "--ping",
+        ]);
+        context.parse(&mut argv).unwrap();
+        assert_eq!(argv, args(&["prog"]));
+        assert_eq!(*seen.borrow(), ["-D=a", "--define=b"]);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(group.values().lookup::<i32>("level").unwrap(), Some(2));
+
+        let err = context
+            .parse(&mut args(&["prog", "--define="]))
+            .unwrap_err();
+        assert!(err.matches(OptionError::Failed));
+
+        let help = context.help(false, Some(&group));
+        assert!(help.contains("--level"), "{}", help);
+    }
+
+    #[test]
+    fn derive() {
+        #[derive(Debug, PartialEq, glib::Options)]
+        struct Args {
+            /// Print more output
+            #[option(short = 'v')]
+            verbose: bool,
+            #[option(long = "no-color", reverse, description = "Disable colors")]
+            color: bool,
+            #[option(arg_description = "NAME")]
+            name: Option<String>,
+            jobs: i32,
+            include: Vec<PathBuf>,
+            #[option(hidden)]
+            debug_level: i64,
+        }
+
+        let entries = Args::option_entries();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].long_name(), "verbose");
+        assert_eq!(entries[0].short_name(), Some('v'));
+        assert_eq!(entries[0].description(), Some("Print more output"));
+        assert_eq!(entries[1].long_name(), "no-color");
+        assert_eq!(entries[1].flags(), OptionFlags::REVERSE);
+        assert_eq!(entries[2].arg(), OptionArg::String);
+        assert_eq!(entries[2].arg_description(), Some("NAME"));
+        assert_eq!(entries[4].arg(), OptionArg::FilenameArray);
+        assert_eq!(entries[5].long_name(), "debug-level");
+        assert_eq!(entries[5].flags(), OptionFlags::HIDDEN);
+
+        let mut argv = args(&[
+            "prog",
+            "file",
+            "--jobs=4",
+            "--include",
+            "a",
+            "--include",
+            "b",
+            "-v",
+        ]);
+        let parsed = Args::parse(&mut argv).unwrap();
+        assert_eq!(argv, args(&["prog", "file"]));
+        assert_eq!(
+            parsed,
+            Args {
+                verbose: true,
+                color: true,
+                name: None,
+                jobs: 4,
+                include: vec![PathBuf::from("a"), PathBuf::from("b")],
+                debug_level: 0,
+            }
+        );
+
+        let parsed = Args::parse(&mut args(&["prog", "--no-color", "--name", "x"])).unwrap();
+        assert!(!parsed.color);
+        assert_eq!(parsed.name.as_deref(), Some("x"));
+
+        let dict = VariantDict::new(None);
+        dict.insert("jobs", &"many");
+        let err = Args::from_variant_dict(&dict).unwrap_err();
+        assert!(err.matches(OptionError::BadValue));
+    }
+}
@@ -0,0 +1,120 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::fmt;
+
+#[cfg(all(not(unix), feature = "dox"))]
+use libc::c_int as RawFd;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+
+use crate::translate::*;
+use crate::IOCondition;
+
+// rustdoc-stripper-ignore-next
+/// A file descriptor and the events to poll it for, together with the events that were polled.
+///
+/// This is used for polling the file descriptors of a [`MainContext`](crate::MainContext) from
+/// an external event loop via [`MainContext::query`](crate::MainContext::query) and
+/// [`MainContext::check`](crate::MainContext::check), and for adding external file descriptors
+/// to a main context via [`MainContext::add_poll`](crate::MainContext::add_poll).
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+#[doc(alias = "GPollFD")]
+pub struct PollFd(ffi::GPollFD);
+
+impl PollFd {
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// Creates a new `PollFd` for polling `fd` for `events`.
+    pub fn new(fd: RawFd, events: IOCondition) -> Self {
+        Self(ffi::GPollFD {
+            fd,
+            events: events.into_glib() as u16,
+            revents: 0,
+        })
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// The file descriptor to poll.
+    pub fn fd(&self) -> RawFd {
+        self.0.fd
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The events to poll for.
+    pub fn events(&self) -> IOCondition {
+        IOCondition::from_bits_truncate(self.0.events as u32)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the events to poll for.
+    pub fn set_events(&mut self, events: IOCondition) {
+        self.0.events = events.into_glib() as u16;
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The events that were polled.
+    pub fn revents(&self) -> IOCondition {
+        IOCondition::from_bits_truncate(self.0.revents as u32)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the events that were polled.
+    ///
+    /// External event loops have to set this before passing the file descriptors to
+    /// [`MainContext::check`](crate::MainContext::check).
+    pub fn set_revents(&mut self, revents: IOCondition) {
+        self.0.revents = revents.into_glib() as u16;
+    }
+}
+
+impl fmt::Debug for PollFd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PollFd")
+            .field("fd", &self.0.fd)
+            .field("events", &self.events())
+            .field("revents", &self.revents())
+            .finish()
+    }
+}
+
+impl PartialEq for PollFd {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.fd == other.0.fd
+            && self.0.events == other.0.events
+            && self.0.revents == other.0.revents
+    }
+}
+
+impl Eq for PollFd {}
+
+#[doc(hidden)]
+impl FromGlib<ffi::GPollFD> for PollFd {
+    #[inline]
+    unsafe fn from_glib(val: ffi::GPollFD) -> Self {
+        Self(val)
+    }
+}
+
+#[doc(hidden)]
+impl IntoGlib for PollFd {
+    type GlibType = ffi::GPollFD;
+
+    #[inline]
+    fn into_glib(self) -> ffi::GPollFD {
+        self.0
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Polls `fds` like the default poll function of a main context.
+///
+/// `timeout` is in milliseconds, `-1` blocks until any file descriptor is ready. Returns the
+/// number of file descriptors with events, or `-1` on error.
+#[doc(alias = "g_poll")]
+pub fn poll(fds: &mut [PollFd], timeout: i32) -> i32 {
+    unsafe { ffi::g_poll(fds.as_mut_ptr() as *mut _, fds.len() as u32, timeout) }
+}
@@ -5,6 +5,6 @@
 
 pub use crate::param_spec::ParamSpecBuilderExt;
 pub use crate::{
-    Cast, Continue, IsA, ObjectExt, ObjectType, ParamSpecType, StaticType, StaticTypeExt,
+    Cast, Continue, IsA, ObjectExt, ObjectType, Options, ParamSpecType, StaticType, StaticTypeExt,
     StaticVariantType, ToSendValue, ToValue, ToVariant,
 };
@@ -0,0 +1,779 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Profiling of main context dispatching.
+//!
+//! A [`Profiler`] installed on a [`MainContext`] measures how long the sources of the main context
+//! take to dispatch, and records iterations of the main context that take longer than a
+//! [threshold](Profiler::set_stall_threshold) as [`Stall`]s. With the `backtrace` feature, a
+//! backtrace is captured right after every single dispatch that exceeds the threshold on its own
+//! so that the slow source or future can be found.
+//!
+//! Dispatch times are recorded for all sources with callbacks implemented in Rust: sources
+//! created by [`idle_add`](crate::idle_add), [`timeout_add`](crate::timeout_add) and the other
+//! source functions of this crate, implementations of [`SourceImpl`], futures spawned on the main
+//! context and main context channels. Sources implemented in C are not measured individually but
+//! their dispatch time is included in the duration of the iteration.
+//!
+//! The `backtrace` feature requires Rust 1.65 or newer.
+//!
+//! ```
+//! # #[cfg(feature = "profiler")]
+//! # {
+//! use std::time::Duration;
+//!
+//! let c = glib::MainContext::new();
+//! let profiler = glib::profiler::Profiler::new(&c);
+//! profiler.set_stall_threshold(Some(Duration::from_millis(50)));
+//!
+//! c.block_on(c.spawn_with_name("fetch", async { 42 })).unwrap();
+//!
+//! let report = profiler.report();
+//! assert!(report.sources().iter().any(|s| s.name() == Some("fetch")));
+//! println!("{}", report);
+//! # }
+//! ```
+
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+
+use crate::translate::*;
+use crate::{Continue, LogLevel, MainContext, Source, SourceImpl};
+
+// Main contexts with a profiler. The number of entries is kept separately so that dispatching
+// doesn't have to take the lock if no profiler exists at all.
+static PROFILERS: Mutex<Vec<(usize, Weak<Inner>)>> = Mutex::new(Vec::new());
+static N_PROFILERS: AtomicUsize = AtomicUsize::new(0);
+
+// Only the most recent stalls are kept.
+const MAX_STALLS: usize = 64;
+
+// rustdoc-stripper-ignore-next
+/// What was dispatched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DispatchKind {
+    // rustdoc-stripper-ignore-next
+    /// A source callback.
+    Source,
+    // rustdoc-stripper-ignore-next
+    /// A future spawned on the main context.
+    Task,
+}
+
+impl fmt::Display for DispatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Source => "source",
+            Self::Task => "task",
+        })
+    }
+}
+
+// An iteration of the main context that is currently dispatching.
+struct Iteration {
+    start: Instant,
+    dispatches: Vec<Dispatch>,
+}
+
+#[derive(Default)]
+struct State {
+    sources: HashMap<u32, SourceStats>,
+    stalls: VecDeque<Stall>,
+    threshold: Option<Duration>,
+    // Indexed by the main loop depth as iterations can be nested.
+    iterations: Vec<Option<Iteration>>,
+}
+
+struct Inner {
+    context: MainContext,
+    state: Mutex<State>,
+    marker: Mutex<Option<Source>>,
+}
+
+impl Inner {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn iteration_started(&self, depth: usize) {
+        let mut state = self.lock();
+        state.iterations.resize_with(depth + 1, || None);
+        state.iterations[depth] = Some(Iteration {
+            start: Instant::now(),
+            dispatches: Vec::new(),
+        });
+    }
+
+    fn iteration_finished(&self, depth: usize) {
+        let mut state = self.lock();
+        let iteration = match state.iterations.get_mut(depth).and_then(Option::take) {
+            Some(iteration) => iteration,
+            None => return,
+        };
+
+        let duration = iteration.start.elapsed();
+        if state
+            .threshold
+            .map_or(true, |threshold| duration < threshold)
+        {
+            return;
+        }
+
+        if state.stalls.len() == MAX_STALLS {
+            state.stalls.pop_front();
+        }
+        state.stalls.push_back(Stall {
+            duration,
+            dispatches: iteration.dispatches,
+        });
+    }
+
+    fn record(&self, depth: usize, dispatch: Dispatch) {
+        let mut state = self.lock();
+
+        // This is called from the dispatch wrapper, so the backtrace still contains the trampoline
+        // of the source or the task that was dispatched.
+        #[cfg(feature = "backtrace")]
+        let dispatch = Dispatch {
+            backtrace: state
+                .threshold
+                .filter(|threshold| dispatch.duration >= *threshold)
+                .map(|_| Arc::new(Backtrace::force_capture())),
+            ..dispatch
+        };
+
+        let stats = state
+            .sources
+            .entry(dispatch.source_id)
+            .or_insert_with(|| SourceStats {
+                name: dispatch.name.clone(),
+                source_id: Some(dispatch.source_id),
+                kind: dispatch.kind,
+                count: 0,
+                total: Duration::ZERO,
+                max: Duration::ZERO,
+            });
+        stats.count += 1;
+        stats.total += dispatch.duration;
+        stats.max = stats.max.max(dispatch.duration);
+
+        if let Some(Some(iteration)) = state.iterations.get_mut(depth) {
+            iteration.dispatches.push(dispatch);
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(source) = self.marker.get_mut().unwrap().take() {
+            source.destroy();
+        }
+
+        let mut profilers = PROFILERS.lock().unwrap();
+        let ptr = self.context.as_ptr() as usize;
+        // A new profiler could've been installed already if this one was dropped concurrently
+        profilers.retain(|(context, inner)| *context != ptr || inner.strong_count() > 0);
+        N_PROFILERS.store(profilers.len(), Ordering::SeqCst);
+    }
+}
+
+// Runs first in every iteration as it has the highest possible priority. An iteration is
+// considered to last from checking the sources after polling until preparing the next iteration.
+struct Marker(Weak<Inner>);
+
+impl SourceImpl for Marker {
+    fn prepare(&self, _source: &Source) -> (bool, Option<Duration>) {
+        if let Some(inner) = self.0.upgrade() {
+            inner.iteration_finished(main_depth());
+        }
+        (false, None)
+    }
+
+    fn check(&self, _source: &Source) -> bool {
+        if let Some(inner) = self.0.upgrade() {
+            inner.iteration_started(main_depth());
+        }
+        false
+    }
+
+    fn dispatch(&self, _source: &Source) -> Continue {
+        Continue(true)
+    }
+}
+
+fn main_depth() -> usize {
+    unsafe { ffi::g_main_depth() as usize }
+}
+
+// Measures `f` if the source that is currently dispatched belongs to a main context with a
+// profiler. `callback` is the type name of what is dispatched.
+pub(crate) fn dispatch<R>(kind: DispatchKind, callback: &'static str, f: impl FnOnce() -> R) -> R {
+    if N_PROFILERS.load(Ordering::SeqCst) == 0 {
+        return f();
+    }
+
+    let (inner, source_id, name) = unsafe {
+        let source = ffi::g_main_current_source();
+        if source.is_null() {
+            return f();
+        }
+
+        let context = ffi::g_source_get_context(source) as usize;
+        let inner = PROFILERS
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(c, _)| *c == context)
+            .and_then(|(_, inner)| inner.upgrade());
+        let inner = match inner {
+            Some(inner) => inner,
+            None => return f(),
+        };
+
+        // Queried before dispatching as the source might be destroyed by the callback
+        (
+            inner,
+            ffi::g_source_get_id(source),
+            from_glib_none(ffi::g_source_get_name(source)),
+        )
+    };
+
+    let start = Instant::now();
+    let res = f();
+    let duration = start.elapsed();
+
+    // The depth is increased while dispatching
+    inner.record(
+        main_depth().saturating_sub(1),
+        Dispatch {
+            name,
+            source_id,
+            kind,
+            callback,
+            duration,
+            #[cfg(feature = "backtrace")]
+            backtrace: None,
+        },
+    );
+
+    res
+}
+
+// rustdoc-stripper-ignore-next
+/// Measures dispatching of the sources of a [`MainContext`].
+///
+/// See the [module documentation](self) for details. The profiler is removed from the main
+/// context once all clones of it are dropped.
+#[derive(Clone)]
+pub struct Profiler(Arc<Inner>);
+
+impl Profiler {
+    // rustdoc-stripper-ignore-next
+    /// Installs a profiler on `context`.
+    ///
+    /// If the main context already has a profiler, that one is returned.
+    pub fn new(context: &MainContext) -> Self {
+        let mut profilers = PROFILERS.lock().unwrap();
+        let ptr = context.as_ptr() as usize;
+        if let Some(inner) = profilers
+            .iter()
+            .find(|(c, _)| *c == ptr)
+            .and_then(|(_, inner)| inner.upgrade())
+        {
+            return Self(inner);
+        }
+
+        let inner = Arc::new(Inner {
+            context: context.clone(),
+            state: Mutex::new(State::default()),
+            marker: Mutex::new(None),
+        });
+
+        let marker = Source::new(Marker(Arc::downgrade(&inner)), Some("Profiler"), unsafe {
+            from_glib(i32::MIN)
+        });
+        marker.attach(Some(context));
+        *inner.marker.lock().unwrap() = Some(marker);
+
+        profilers.retain(|(c, _)| *c != ptr);
+        profilers.push((ptr, Arc::downgrade(&inner)));
+        N_PROFILERS.store(profilers.len(), Ordering::SeqCst);
+
+        Self(inner)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The main context this profiler belongs to.
+    pub fn context(&self) -> &MainContext {
+        &self.0.context
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the duration after which an iteration of the main context is recorded as [`Stall`].
+    ///
+    /// Stalls are not recorded by default. Only the 64 most recent stalls are kept.
+    pub fn set_stall_threshold(&self, threshold: Option<Duration>) {
+        self.0.lock().threshold = threshold;
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The duration after which an iteration of the main context is recorded as [`Stall`].
+    pub fn stall_threshold(&self) -> Option<Duration> {
+        self.0.lock().threshold
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the statistics recorded so far.
+    pub fn report(&self) -> Report {
+        let state = self.0.lock();
+        let mut sources = state.sources.values().cloned().collect::<Vec<_>>();
+        sources.sort_by_key(|s| std::cmp::Reverse(s.total));
+
+        Report {
+            sources,
+            stalls: state.stalls.iter().cloned().collect(),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Discards the statistics recorded so far.
+    pub fn reset(&self) {
+        let mut state = self.0.lock();
+        state.sources.clear();
+        state.stalls.clear();
+    }
+}
+
+impl fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Profiler")
+            .field("context", &self.0.context)
+            .field("stall_threshold", &self.stall_threshold())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Dispatch statistics of a source.
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    name: Option<String>,
+    source_id: Option<u32>,
+    kind: DispatchKind,
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl SourceStats {
+    // rustdoc-stripper-ignore-next
+    /// The name of the source.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The raw ID of the source, see [`SourceId::as_raw`](crate::SourceId::as_raw).
+    ///
+    /// This is `None` for statistics aggregated by [`Report::by_name`].
+    pub fn source_id(&self) -> Option<u32> {
+        self.source_id
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// What was dispatched.
+    pub fn kind(&self) -> DispatchKind {
+        self.kind
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// How often the source was dispatched.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The accumulated dispatch time.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The longest dispatch time.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+}
+
+impl fmt::Display for SourceStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.kind, self.name().unwrap_or("<unnamed>"))?;
+        if let Some(id) = self.source_id {
+            write!(f, " ({})", id)?;
+        }
+        write!(
+            f,
+            ": {} dispatches, {:?} total, {:?} max",
+            self.count, self.total, self.max
+        )
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A single dispatch of a source during a [`Stall`].
+#[derive(Debug, Clone)]
+pub struct Dispatch {
+    name: Option<String>,
+    source_id: u32,
+    kind: DispatchKind,
+    callback: &'static str,
+    duration: Duration,
+    #[cfg(feature = "backtrace")]
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+impl Dispatch {
+    // rustdoc-stripper-ignore-next
+    /// The name of the source.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The raw ID of the source, see [`SourceId::as_raw`](crate::SourceId::as_raw).
+    pub fn source_id(&self) -> u32 {
+        self.source_id
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// What was dispatched.
+    pub fn kind(&self) -> DispatchKind {
+        self.kind
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The type name of the dispatched closure, source implementation or future.
+    ///
+    /// This identifies unnamed sources. For futures this is the type of the future passed to
+    /// e.g. [`MainContext::spawn`](crate::MainContext::spawn).
+    pub fn callback(&self) -> &'static str {
+        self.callback
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// How long dispatching took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The backtrace captured right after dispatching, if the dispatch alone took longer than the
+    /// [threshold](Profiler::set_stall_threshold).
+    #[cfg(feature = "backtrace")]
+    #[cfg_attr(feature = "dox", doc(cfg(feature = "backtrace")))]
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// An iteration of the main context that took longer than the
+/// [threshold](Profiler::set_stall_threshold).
+#[derive(Debug, Clone)]
+pub struct Stall {
+    duration: Duration,
+    dispatches: Vec<Dispatch>,
+}
+
+impl Stall {
+    // rustdoc-stripper-ignore-next
+    /// How long the iteration took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The measured sources dispatched during the iteration.
+    ///
+    /// Any remaining time was spent in sources implemented in C.
+    pub fn dispatches(&self) -> &[Dispatch] {
+        &self.dispatches
+    }
+}
+
+impl fmt::Display for Stall {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "iteration took {:?}", self.duration)?;
+        for dispatch in &self.dispatches {
+            write!(
+                f,
+                "\n  {} {} ({}, {}): {:?}",
+                dispatch.kind,
+                dispatch.name().unwrap_or("<unnamed>"),
+                dispatch.source_id,
+                dispatch.callback,
+                dispatch.duration
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// The statistics recorded by a [`Profiler`].
+#[derive(Debug, Clone)]
+pub struct Report {
+    sources: Vec<SourceStats>,
+    stalls: Vec<Stall>,
+}
+
+impl Report {
+    // rustdoc-stripper-ignore-next
+    /// The statistics of all sources that were dispatched, sorted by their total dispatch time.
+    pub fn sources(&self) -> &[SourceStats] {
+        &self.sources
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The statistics aggregated by source name, sorted by their total dispatch time.
+    ///
+    /// This is useful for sources that are created repeatedly, e.g. one-shot idle sources.
+    pub fn by_name(&self) -> Vec<SourceStats> {
+        let mut aggregated: Vec<SourceStats> = Vec::new();
+        for stats in &self.sources {
+            match aggregated
+                .iter_mut()
+                .find(|s| s.name == stats.name && s.kind == stats.kind)
+            {
+                Some(s) => {
+                    s.count += stats.count;
+                    s.total += stats.total;
+                    s.max = s.max.max(stats.max);
+                }
+                None => aggregated.push(SourceStats {
+                    source_id: None,
+                    ..stats.clone()
+                }),
+            }
+        }
+        aggregated.sort_by_key(|s| std::cmp::Reverse(s.total));
+        aggregated
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The recorded stalls, oldest first.
+    pub fn stalls(&self) -> &[Stall] {
+        &self.stalls
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Logs the report as structured log messages.
+    ///
+    /// Every source is logged as info message with the fields `GLIB_RS_SOURCE_NAME`,
+    /// `GLIB_RS_SOURCE_ID`, `GLIB_RS_DISPATCH_KIND`, `GLIB_RS_DISPATCH_COUNT`,
+    /// `GLIB_RS_DISPATCH_TOTAL_US` and `GLIB_RS_DISPATCH_MAX_US`. Every stall is logged as warning
+    /// with the field `GLIB_RS_STALL_US`. With the `backtrace` feature it is followed by a warning
+    /// for each of its dispatches with a backtrace with the fields `GLIB_RS_SOURCE_NAME`, `GLIB_RS_SOURCE_ID`, `GLIB_RS_CALLBACK`,
+    /// `GLIB_RS_DISPATCH_US` and `GLIB_RS_BACKTRACE`.
+    #[doc(alias = "g_log_structured")]
+    pub fn log(&self, log_domain: Option<&str>) {
+        for stats in &self.sources {
+            crate::log_structured!(
+                log_domain,
+                LogLevel::Info,
+                {
+                    "MESSAGE" => "{}", stats;
+                    "GLIB_RS_SOURCE_NAME" => stats.name().unwrap_or_default();
+                    "GLIB_RS_SOURCE_ID" => "{}", stats.source_id.unwrap_or_default();
+                    "GLIB_RS_DISPATCH_KIND" => "{}", stats.kind;
+                    "GLIB_RS_DISPATCH_COUNT" => "{}", stats.count;
+                    "GLIB_RS_DISPATCH_TOTAL_US" => "{}", stats.total.as_micros();
+                    "GLIB_RS_DISPATCH_MAX_US" => "{}", stats.max.as_micros();
+                }
+            );
+        }
+
+        for stall in &self.stalls {
+            crate::log_structured!(
+                log_domain,
+                LogLevel::Warning,
+                {
+                    "MESSAGE" => "{}", stall;
+                    "GLIB_RS_STALL_US" => "{}", stall.duration.as_micros();
+                }
+            );
+
+            #[cfg(feature = "backtrace")]
+            for dispatch in &stall.dispatches {
+                let backtrace = match dispatch.backtrace() {
+                    Some(backtrace) => backtrace,
+                    None => continue,
+                };
+                crate::log_structured!(
+                    log_domain,
+                    LogLevel::Warning,
+                    {
+                        "MESSAGE" => "slow {} {} took {:?}",
+                            dispatch.kind, dispatch.name().unwrap_or("<unnamed>"), dispatch.duration;
+                        "GLIB_RS_SOURCE_NAME" => dispatch.name().unwrap_or_default();
+                        "GLIB_RS_SOURCE_ID" => "{}", dispatch.source_id;
+                        "GLIB_RS_CALLBACK" => dispatch.callback;
+                        "GLIB_RS_DISPATCH_US" => "{}", dispatch.duration.as_micros();
+                        "GLIB_RS_BACKTRACE" => "{}", backtrace;
+                    }
+                );
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for stats in &self.sources {
+            writeln!(f, "{}", stats)?;
+        }
+        for stall in &self.stalls {
+            writeln!(f, "stall: {}", stall)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_stats() {
+        let c = MainContext::new();
+        let profiler = Profiler::new(&c);
+
+        let source = crate::idle_source_new(Some("idle"), crate::PRIORITY_DEFAULT, {
+            let mut count = 0;
+            move || {
+                std::thread::sleep(Duration::from_millis(5));
+                count += 1;
+                Continue(count < 3)
+            }
+        });
+        let id = source.attach(Some(&c));
+        c.block_on(c.spawn_with_name("task", async {})).unwrap();
+        while c.iteration(false) {}
+
+        let report = profiler.report();
+        let idle = report
+            .sources()
+            .iter()
+            .find(|s| s.name() == Some("idle"))
+            .unwrap();
+        assert_eq!(idle.source_id(), Some(unsafe { id.as_raw() }));
+        assert_eq!(idle.kind(), DispatchKind::Source);
+        assert_eq!(idle.count(), 3);
+        assert!(idle.total() >= Duration::from_millis(15));
+        assert!(idle.max() >= Duration::from_millis(5));
+
+        let task = report
+            .sources()
+            .iter()
+            .find(|s| s.name() == Some("task"))
+            .unwrap();
+        assert_eq!(task.kind(), DispatchKind::Task);
+        assert!(report.stalls().is_empty());
+
+        // The same profiler is returned for the same main context
+        assert_eq!(
+            Profiler::new(&c).report().sources().len(),
+            report.sources().len()
+        );
+        profiler.reset();
+        assert!(profiler.report().sources().is_empty());
+    }
+
+    #[test]
+    fn test_by_name() {
+        let c = MainContext::new();
+        let profiler = Profiler::new(&c);
+
+        for _ in 0..3 {
+            crate::idle_source_new(Some("oneshot"), crate::PRIORITY_DEFAULT, || Continue(false))
+                .attach(Some(&c));
+        }
+        while c.iteration(false) {}
+
+        let report = profiler.report();
+        assert_eq!(report.sources().len(), 3);
+        let by_name = report.by_name();
+        assert_eq!(by_name.len(), 1);
+        assert_eq!(by_name[0].name(), Some("oneshot"));
+        assert_eq!(by_name[0].source_id(), None);
+        assert_eq!(by_name[0].count(), 3);
+    }
+
+    #[test]
+    fn test_stall() {
+        let c = MainContext::new();
+        let profiler = Profiler::new(&c);
+        profiler.set_stall_threshold(Some(Duration::from_millis(20)));
+
+        crate::idle_source_new(Some("fast"), crate::PRIORITY_DEFAULT, || Continue(false))
+            .attach(Some(&c));
+        while c.iteration(false) {}
+        assert!(profiler.report().stalls().is_empty());
+
+        crate::idle_source_new(Some("slow"), crate::PRIORITY_DEFAULT, || {
+            std::thread::sleep(Duration::from_millis(30));
+            Continue(false)
+        })
+        .attach(Some(&c));
+        while c.iteration(false) {}
+
+        let report = profiler.report();
+        assert_eq!(report.stalls().len(), 1);
+        let stall = &report.stalls()[0];
+        assert!(stall.duration() >= Duration::from_millis(30));
+        assert_eq!(stall.dispatches().len(), 1);
+        let dispatch = &stall.dispatches()[0];
+        assert_eq!(dispatch.name(), Some("slow"));
+        assert!(dispatch
+            .callback()
+            .starts_with("glib::profiler::tests::test_stall"));
+        #[cfg(feature = "backtrace")]
+        assert!(dispatch.backtrace().is_some());
+        assert!(stall.to_string().contains("slow"));
+
+        profiler.reset();
+        let _handle = c.spawn(async {
+            std::thread::sleep(Duration::from_millis(30));
+        });
+        while c.iteration(false) {}
+
+        let report = profiler.report();
+        let dispatch = report
+            .stalls()
+            .iter()
+            .flat_map(Stall::dispatches)
+            .find(|dispatch| dispatch.kind() == DispatchKind::Task)
+            .unwrap();
+        assert_eq!(dispatch.name(), None);
+        assert!(dispatch.callback().contains("test_stall"));
+        #[cfg(feature = "backtrace")]
+        assert!(dispatch.backtrace().is_some());
+    }
+
+    #[test]
+    fn test_uninstall() {
+        let c = MainContext::new();
+        let profiler = Profiler::new(&c);
+        drop(profiler);
+
+        crate::idle_source_new(None, crate::PRIORITY_DEFAULT, || Continue(false)).attach(Some(&c));
+        while c.iteration(false) {}
+
+        assert!(Profiler::new(&c).report().sources().is_empty());
+    }
+}
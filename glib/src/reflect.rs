@@ -0,0 +1,655 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Runtime reflection of the GObject type system.
+//!
+//! [`TypeInfo`] describes a registered [`Type`] as plain data: its ancestors, interfaces,
+//! properties, signals, enum or flags values and its class and instance size. Descriptions can be
+//! converted to [`Variant`]s or JSON, either for a single type or for the whole type hierarchy
+//! registered at the time of calling [`hierarchy`].
+//!
+//! ```
+//! let info = glib::reflect::TypeInfo::new(glib::Binding::static_type()).unwrap();
+//!
+//! assert_eq!(info.ancestors(), [glib::Type::OBJECT]);
+//! assert!(info.properties().iter().any(|pspec| pspec.name() == "source-property"));
+//! # use glib::prelude::*;
+//! ```
+
+use std::fmt::{self, Write};
+
+use crate::object::ObjectClass;
+use crate::subclass::signal::SignalQuery;
+use crate::subclass::SignalId;
+use crate::translate::*;
+use crate::{
+    EnumClass, FlagsClass, ParamSpec, StaticVariantType, ToVariant, Type, Variant, VariantClass,
+    VariantDict, VariantTy,
+};
+
+// rustdoc-stripper-ignore-next
+/// Description of a registered [`Type`].
+pub struct TypeInfo {
+    type_: Type,
+    ancestors: Vec<Type>,
+    interfaces: Vec<Type>,
+    children: Vec<Type>,
+    class_size: Option<u32>,
+    instance_size: Option<u32>,
+    is_abstract: bool,
+    is_instantiatable: bool,
+    is_derivable: bool,
+    properties: Vec<ParamSpec>,
+    signals: Vec<SignalQuery>,
+    enum_class: Option<EnumClass>,
+    flags_class: Option<FlagsClass>,
+}
+
+impl TypeInfo {
+    // rustdoc-stripper-ignore-next
+    /// Describes `type_`.
+    ///
+    /// This initializes the class of classed types, and the default vtable of interfaces, so
+    /// that their properties and signals are registered.
+    ///
+    /// Returns `None` if `type_` is not a valid registered type.
+    pub fn new(type_: Type) -> Option<Self> {
+        if !is_registered(type_) {
+            return None;
+        }
+
+        // Only filled in for static classed types.
+        let query = unsafe {
+            let mut query = std::mem::MaybeUninit::zeroed();
+            gobject_ffi::g_type_query(type_.into_glib(), query.as_mut_ptr());
+            Some(query.assume_init()).filter(|query| query.type_ != gobject_ffi::G_TYPE_INVALID)
+        };
+
+        let test_flags =
+            |flags| unsafe { from_glib(gobject_ffi::g_type_test_flags(type_.into_glib(), flags)) };
+
+        let properties = if type_.is_a(Type::INTERFACE) {
+            interface_properties(type_)
+        } else if type_.is_a(Type::OBJECT) {
+            ObjectClass::from_type(type_)
+                .map(|class| class.list_properties().to_vec())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let signals = if type_.is_a(Type::INTERFACE) || type_.is_a(Type::OBJECT) {
+            signal_ids(type_)
+                .into_iter()
+                .map(|signal_id| signal_id.query())
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Some(Self {
+            type_,
+            ancestors: std::iter::successors(type_.parent(), |t| t.parent()).collect(),
+            interfaces: if type_.is_a(Type::INTERFACE) {
+                type_.interface_prerequisites().to_vec()
+            } else {
+                type_.interfaces().to_vec()
+            },
+            children: type_.children().to_vec(),
+            class_size: query.as_ref().map(|query| query.class_size),
+            instance_size: query.as_ref().map(|query| query.instance_size),
+            is_abstract: test_flags(gobject_ffi::G_TYPE_FLAG_ABSTRACT),
+            is_instantiatable: test_flags(gobject_ffi::G_TYPE_FLAG_INSTANTIATABLE),
+            is_derivable: test_flags(gobject_ffi::G_TYPE_FLAG_DERIVABLE),
+            properties,
+            signals,
+            enum_class: EnumClass::new(type_),
+            flags_class: FlagsClass::new(type_),
+        })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The described type.
+    pub fn type_(&self) -> Type {
+        self.type_
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The parent types, starting with the direct parent and ending with the fundamental type.
+    pub fn ancestors(&self) -> &[Type] {
+        &self.ancestors
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The interfaces implemented by the type.
+    ///
+    /// For interfaces these are the prerequisites.
+    pub fn interfaces(&self) -> &[Type] {
+        &self.interfaces
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The types directly derived from the type.
+    pub fn children(&self) -> &[Type] {
+        &self.children
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The size of the class structure, or `0` for types without class.
+    ///
+    /// This is `None` if GLib doesn't provide the size, which is the case for all types but
+    /// static classed types, for example for interfaces and boxed types.
+    #[doc(alias = "g_type_query")]
+    pub fn class_size(&self) -> Option<u32> {
+        self.class_size
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The size of the instance structure, or `0` for non-instantiatable types.
+    ///
+    /// This is `None` if the class size is.
+    #[doc(alias = "g_type_query")]
+    pub fn instance_size(&self) -> Option<u32> {
+        self.instance_size
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the type is abstract.
+    pub fn is_abstract(&self) -> bool {
+        self.is_abstract
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether instances of the type can be created.
+    pub fn is_instantiatable(&self) -> bool {
+        self.is_instantiatable
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the type can be derived from.
+    pub fn is_derivable(&self) -> bool {
+        self.is_derivable
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// All properties of the type, including inherited ones.
+    ///
+    /// [`ParamSpec::owner_type`] gives the type that installed the property.
+    pub fn properties(&self) -> &[ParamSpec] {
+        &self.properties
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The signals registered on this type, excluding inherited ones.
+    pub fn signals(&self) -> &[SignalQuery] {
+        &self.signals
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The enum class if the type is an enum.
+    pub fn enum_class(&self) -> Option<&EnumClass> {
+        self.enum_class.as_ref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The flags class if the type is a flags type.
+    pub fn flags_class(&self) -> Option<&FlagsClass> {
+        self.flags_class.as_ref()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Converts the description to a JSON object.
+    ///
+    /// The object has the same members as the [`Variant`] returned by [`ToVariant::to_variant`].
+    pub fn to_json(&self) -> String {
+        variant_to_json(&self.to_variant())
+    }
+}
+
+impl fmt::Debug for TypeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypeInfo")
+            .field("type", &self.type_)
+            .field("ancestors", &self.ancestors)
+            .field("interfaces", &self.interfaces)
+            .field("children", &self.children)
+            .field("class_size", &self.class_size)
+            .field("instance_size", &self.instance_size)
+            .field("is_abstract", &self.is_abstract)
+            .field("is_instantiatable", &self.is_instantiatable)
+            .field("is_derivable", &self.is_derivable)
+            .field("properties", &self.properties)
+            .field("signals", &self.signals)
+            .field("enum_class", &self.enum_class)
+            .field("flags_class", &self.flags_class)
+            .finish()
+    }
+}
+
+impl StaticVariantType for TypeInfo {
+    fn static_variant_type() -> std::borrow::Cow<'static, VariantTy> {
+        std::borrow::Cow::Borrowed(VariantTy::VARDICT)
+    }
+}
+
+impl ToVariant for TypeInfo {
+    // rustdoc-stripper-ignore-next
+    /// Converts the description to an `a{sv}` dictionary.
+    ///
+    /// Types are referred to by name as type IDs are not stable between processes.
+    fn to_variant(&self) -> Variant {
+        let type_names = |types: &[Type]| {
+            types
+                .iter()
+                .map(|t| t.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let dict = VariantDict::new(None);
+        dict.insert("name", &self.type_.name());
+        dict.insert("ancestors", &type_names(&self.ancestors));
+        dict.insert("interfaces", &type_names(&self.interfaces));
+        dict.insert("children", &type_names(&self.children));
+        if let Some(class_size) = self.class_size {
+            dict.insert("class-size", &class_size);
+        }
+        if let Some(instance_size) = self.instance_size {
+            dict.insert("instance-size", &instance_size);
+        }
+        dict.insert("abstract", &self.is_abstract);
+        dict.insert("instantiatable", &self.is_instantiatable);
+        dict.insert("derivable", &self.is_derivable);
+        dict.insert_value(
+            "properties",
+            &vardict_array(self.properties.iter().map(property_to_variant)),
+        );
+        dict.insert_value(
+            "signals",
+            &vardict_array(self.signals.iter().map(signal_to_variant)),
+        );
+        if let Some(ref enum_class) = self.enum_class {
+            dict.insert_value(
+                "values",
+                &vardict_array(enum_class.values().iter().map(|value| {
+                    let dict = VariantDict::new(None);
+                    dict.insert("name", &value.name());
+                    dict.insert("nick", &value.nick());
+                    dict.insert("value", &value.value());
+                    dict.end()
+                })),
+            );
+        }
+        if let Some(ref flags_class) = self.flags_class {
+            dict.insert_value(
+                "values",
+                &vardict_array(flags_class.values().iter().map(|value| {
+                    let dict = VariantDict::new(None);
+                    dict.insert("name", &value.name());
+                    dict.insert("nick", &value.nick());
+                    dict.insert("value", &value.value());
+                    dict.end()
+                })),
+            );
+        }
+        dict.end()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Returns all currently registered types.
+///
+/// Types are listed depth-first, starting with the fundamental types and with every type
+/// preceding the types derived from it.
+#[doc(alias = "g_type_fundamental_next")]
+pub fn registered_types() -> Vec<Type> {
+    fn add_with_children(types: &mut Vec<Type>, type_: Type) {
+        types.push(type_);
+        for child in type_.children().iter() {
+            add_with_children(types, *child);
+        }
+    }
+
+    let mut types = Vec::new();
+    let next = unsafe { gobject_ffi::g_type_fundamental_next() };
+    for id in 1..(next >> gobject_ffi::G_TYPE_FUNDAMENTAL_SHIFT) {
+        let fundamental: Type = unsafe { from_glib(id << gobject_ffi::G_TYPE_FUNDAMENTAL_SHIFT) };
+        if is_registered(fundamental) {
+            add_with_children(&mut types, fundamental);
+        }
+    }
+    types
+}
+
+// rustdoc-stripper-ignore-next
+/// Describes all currently registered types.
+///
+/// See [`registered_types`] for the order of the types.
+pub fn hierarchy() -> Vec<TypeInfo> {
+    registered_types()
+        .into_iter()
+        .filter_map(TypeInfo::new)
+        .collect()
+}
+
+// rustdoc-stripper-ignore-next
+/// Describes all currently registered types as an `aa{sv}` array.
+///
+/// See [`TypeInfo::to_variant`](ToVariant::to_variant) for the contents of the elements.
+pub fn hierarchy_to_variant() -> Variant {
+    vardict_array(hierarchy().iter().map(ToVariant::to_variant))
+}
+
+// rustdoc-stripper-ignore-next
+/// Describes all currently registered types as a JSON array.
+///
+/// See [`TypeInfo::to_json`] for the contents of the elements.
+pub fn hierarchy_to_json() -> String {
+    variant_to_json(&hierarchy_to_variant())
+}
+
+fn is_registered(type_: Type) -> bool {
+    type_.is_valid() && unsafe { !gobject_ffi::g_type_name(type_.into_glib()).is_null() }
+}
+
+fn interface_properties(type_: Type) -> Vec<ParamSpec> {
+    if !type_.is_a(Type::OBJECT) {
+        return Vec::new();
+    }
+
+    unsafe {
+        let iface = gobject_ffi::g_type_default_interface_ref(type_.into_glib());
+        if iface.is_null() {
+            return Vec::new();
+        }
+
+        let mut n_properties = 0;
+        let props = gobject_ffi::g_object_interface_list_properties(iface, &mut n_properties);
+        let properties = FromGlibContainer::from_glib_container_num(props, n_properties as usize);
+        gobject_ffi::g_type_default_interface_unref(iface);
+        properties
+    }
+}
+
+fn signal_ids(type_: Type) -> Vec<SignalId> {
+    unsafe {
+        let mut n_ids = 0;
+        let ids = gobject_ffi::g_signal_list_ids(type_.into_glib(), &mut n_ids);
+        let signal_ids = if n_ids == 0 {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(ids, n_ids as usize)
+                .iter()
+                .map(|id| from_glib(*id))
+                .collect()
+        };
+        ffi::g_free(ids as ffi::gpointer);
+        signal_ids
+    }
+}
+
+fn vardict_array(iter: impl IntoIterator<Item = Variant>) -> Variant {
+    Variant::array_from_iter_with_type(VariantTy::VARDICT, iter)
+}
+
+fn property_to_variant(pspec: &ParamSpec) -> Variant {
+    let dict = VariantDict::new(None);
+    dict.insert("name", &pspec.name());
+    dict.insert("nick", &pspec.nick());
+    if let Some(blurb) = pspec.blurb() {
+        dict.insert("blurb", &blurb);
+    }
+    dict.insert("flags", &pspec.flags().bits());
+    dict.insert("param-type", &pspec.type_().name());
+    dict.insert("value-type", &pspec.value_type().name());
+    dict.insert("owner-type", &pspec.owner_type().name());
+    dict.insert(
+        "default-value",
+        &pspec.default_value().content_debug_string().as_str(),
+    );
+    dict.end()
+}
+
+fn signal_to_variant(query: &SignalQuery) -> Variant {
+    let dict = VariantDict::new(None);
+    dict.insert("name", &query.signal_name());
+    dict.insert("flags", &query.flags().bits());
+    dict.insert("return-type", &query.return_type().type_().name());
+    dict.insert(
+        "param-types",
+        &query
+            .param_types()
+            .iter()
+            .map(|t| t.type_().name().to_owned())
+            .collect::<Vec<_>>(),
+    );
+    dict.end()
+}
+
+fn variant_to_json(variant: &Variant) -> String {
+    let mut json = String::new();
+    write_json(&mut json, variant);
+    json
+}
+
+fn write_json(json: &mut String, variant: &Variant) {
+    match variant.classify() {
+        VariantClass::Boolean => json.push_str(if variant.get::<bool>().unwrap() {
+            "true"
+        } else {
+            "false"
+        }),
+        VariantClass::Byte => write!(json, "{}", variant.get::<u8>().unwrap()).unwrap(),
+        VariantClass::Int16 => write!(json, "{}", variant.get::<i16>().unwrap()).unwrap(),
+        VariantClass::Uint16 => write!(json, "{}", variant.get::<u16>().unwrap()).unwrap(),
+        VariantClass::Int32 => write!(json, "{}", variant.get::<i32>().unwrap()).unwrap(),
+        VariantClass::Uint32 => write!(json, "{}", variant.get::<u32>().unwrap()).unwrap(),
+        VariantClass::Int64 => write!(json, "{}", variant.get::<i64>().unwrap()).unwrap(),
+        VariantClass::Uint64 => write!(json, "{}", variant.get::<u64>().unwrap()).unwrap(),
+        VariantClass::Double => {
+            let value = variant.get::<f64>().unwrap();
+            if value.is_finite() {
+                write!(json, "{}", value).unwrap();
+            } else {
+                json.push_str("null");
+            }
+        }
+        VariantClass::String | VariantClass::ObjectPath | VariantClass::Signature => {
+            write_json_string(json, variant.str().unwrap())
+        }
+        VariantClass::Variant => write_json(json, &variant.as_variant().unwrap()),
+        VariantClass::Maybe => match variant.as_maybe() {
+            Some(child) => write_json(json, &child),
+            None => json.push_str("null"),
+        },
+        VariantClass::Array if variant.type_().element().is_dict_entry() => {
+            json.push('{');
+            for (i, entry) in variant.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                let key = entry.child_value(0);
+                match key.str() {
+                    Some(key) => write_json_string(json, key),
+                    None => write_json_string(json, &key.print(false)),
+                }
+                json.push(':');
+                write_json(json, &entry.child_value(1));
+            }
+            json.push('}');
+        }
+        VariantClass::Array | VariantClass::Tuple | VariantClass::DictEntry => {
+            json.push('[');
+            for (i, child) in variant.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_json(json, &child);
+            }
+            json.push(']');
+        }
+        _ => write_json_string(json, &variant.print(false)),
+    }
+}
+
+fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StaticType;
+
+    #[test]
+    fn describe_object() {
+        let info = TypeInfo::new(crate::Binding::static_type()).unwrap();
+        assert_eq!(info.type_(), crate::Binding::static_type());
+        assert_eq!(info.ancestors(), [Type::OBJECT]);
+        assert!(info.class_size().unwrap() > 0);
+        assert!(info.instance_size().unwrap() > 0);
+        assert!(info.is_instantiatable());
+        assert!(!info.is_abstract());
+
+        let pspec = info
+            .properties()
+            .iter()
+            .find(|pspec| pspec.name() == "source-property")
+            .unwrap();
+        assert_eq!(pspec.value_type(), Type::STRING);
+        assert_eq!(pspec.owner_type(), crate::Binding::static_type());
+
+        let info = TypeInfo::new(Type::OBJECT).unwrap();
+        assert!(info.ancestors().is_empty());
+        assert!(info.children().contains(&crate::Binding::static_type()));
+        let notify = info
+            .signals()
+            .iter()
+            .find(|query| query.signal_name() == "notify")
+            .unwrap();
+        assert_eq!(notify.param_types(), [Type::PARAM_SPEC]);
+    }
+
+    #[test]
+    fn describe_interface() {
+        let type_plugin: Type = unsafe { from_glib(gobject_ffi::g_type_plugin_get_type()) };
+        let info = TypeInfo::new(type_plugin).unwrap();
+        assert_eq!(info.type_(), type_plugin);
+        assert_eq!(info.ancestors(), [Type::INTERFACE]);
+        assert_eq!(info.class_size(), None);
+        assert_eq!(info.instance_size(), None);
+        assert!(!info.is_instantiatable());
+
+        let dict = VariantDict::new(Some(&info.to_variant()));
+        assert!(!dict.contains("class-size"));
+        assert!(registered_types().contains(&type_plugin));
+    }
+
+    #[test]
+    fn describe_boxed() {
+        let info = TypeInfo::new(crate::Bytes::static_type()).unwrap();
+        assert_eq!(info.ancestors(), [Type::BOXED]);
+        assert_eq!(info.class_size(), None);
+        assert!(info.properties().is_empty());
+        assert!(registered_types().contains(&crate::Bytes::static_type()));
+
+        let unregistered: Type = unsafe { from_glib(255 << gobject_ffi::G_TYPE_FUNDAMENTAL_SHIFT) };
+        assert!(TypeInfo::new(unregistered).is_none());
+    }
+
+    fn test_enum_type() -> Type {
+        static TYPE: once_cell::sync::Lazy<Type> = once_cell::sync::Lazy::new(|| unsafe {
+            let values = Box::leak(Box::new([
+                gobject_ffi::GEnumValue {
+                    value: 0,
+                    value_name: b"TEST_REFLECT_ENUM_CONTROL\0".as_ptr() as *const _,
+                    value_nick: b"control\0".as_ptr() as *const _,
+                },
+                gobject_ffi::GEnumValue {
+                    value: 0,
+                    value_name: std::ptr::null(),
+                    value_nick: std::ptr::null(),
+                },
+            ]));
+            from_glib(gobject_ffi::g_enum_register_static(
+                b"TestReflectEnum\0".as_ptr() as *const _,
+                values.as_ptr(),
+            ))
+        });
+        *TYPE
+    }
+
+    #[test]
+    fn describe_enum_and_flags() {
+        let info = TypeInfo::new(test_enum_type()).unwrap();
+        assert_eq!(info.ancestors(), [Type::ENUM]);
+        assert!(info
+            .enum_class()
+            .unwrap()
+            .value_by_nick("control")
+            .is_some());
+        assert!(info.flags_class().is_none());
+
+        let info = TypeInfo::new(crate::BindingFlags::static_type()).unwrap();
+        assert_eq!(info.ancestors(), [Type::FLAGS]);
+        assert!(info
+            .flags_class()
+            .unwrap()
+            .value_by_nick("bidirectional")
+            .is_some());
+
+        assert!(TypeInfo::new(Type::INVALID).is_none());
+    }
+
+    #[test]
+    fn serialize() {
+        let info = TypeInfo::new(crate::Binding::static_type()).unwrap();
+        let variant = info.to_variant();
+        assert_eq!(variant.type_(), VariantTy::VARDICT);
+        let dict = VariantDict::new(Some(&variant));
+        assert_eq!(
+            dict.lookup::<String>("name").unwrap().as_deref(),
+            Some("GBinding")
+        );
+        assert_eq!(
+            dict.lookup::<Vec<String>>("ancestors").unwrap(),
+            Some(vec![String::from("GObject")])
+        );
+
+        let json = info.to_json();
+        assert!(json.starts_with('{'));
+        assert!(json.contains(r#""name":"GBinding""#));
+        assert!(json.contains(r#""ancestors":["GObject"]"#));
+        assert!(json.contains(r#""name":"source-property""#));
+
+        let types = registered_types();
+        let object = types.iter().position(|t| *t == Type::OBJECT).unwrap();
+        let binding = types
+            .iter()
+            .position(|t| *t == crate::Binding::static_type())
+            .unwrap();
+        assert!(object < binding);
+
+        let hierarchy = hierarchy_to_variant();
+        assert_eq!(hierarchy.n_children(), types.len());
+        assert!(hierarchy_to_json().starts_with("[{"));
+    }
+
+    #[test]
+    fn json_strings() {
+        let mut json = String::new();
+        write_json_string(&mut json, "a \"b\"\n\\c\u{1}");
+        assert_eq!(json, r#""a \"b\"\n\\c\u0001""#);
+    }
+}
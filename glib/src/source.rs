@@ -105,18 +105,36 @@ impl IntoGlib for Continue {
     }
 }
 
+// Dispatches `f` through the profiler of the main context, if any. `callback` is the type name of
+// the closure or source implementation that is dispatched.
+#[inline]
+pub(crate) fn profile_dispatch<R>(callback: &'static str, f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "profiler")]
+    {
+        crate::profiler::dispatch(crate::profiler::DispatchKind::Source, callback, f)
+    }
+    #[cfg(not(feature = "profiler"))]
+    {
+        let _ = callback;
+        f()
+    }
+}
+
 unsafe extern "C" fn trampoline<F: FnMut() -> Continue + Send + 'static>(
     func: gpointer,
 ) -> gboolean {
     let func: &RefCell<F> = &*(func as *const RefCell<F>);
-    (*func.borrow_mut())().into_glib()
+    profile_dispatch(std::any::type_name::<F>(), || (*func.borrow_mut())()).into_glib()
 }
 
 unsafe extern "C" fn trampoline_local<F: FnMut() -> Continue + 'static>(
     func: gpointer,
 ) -> gboolean {
     let func: &ThreadGuard<RefCell<F>> = &*(func as *const ThreadGuard<RefCell<F>>);
-    (*func.get_ref().borrow_mut())().into_glib()
+    profile_dispatch(std::any::type_name::<F>(), || {
+        (*func.get_ref().borrow_mut())()
+    })
+    .into_glib()
 }
 
 unsafe extern "C" fn destroy_closure<F: FnMut() -> Continue + Send + 'static>(ptr: gpointer) {
@@ -137,13 +155,75 @@ fn into_raw_local<F: FnMut() -> Continue + 'static>(func: F) -> gpointer {
     Box::into_raw(func) as gpointer
 }
 
+// The thread default main context, or `NULL` for the global default main context.
+fn thread_default_context() -> *mut ffi::GMainContext {
+    unsafe { ffi::g_main_context_get_thread_default() }
+}
+
+// Same as `g_timeout_add_full()` with the default priority, but uses the virtual clock of the
+// default main context if there is one.
+unsafe fn timeout_add_full(
+    interval: Duration,
+    func: ffi::GSourceFunc,
+    data: gpointer,
+    notify: ffi::GDestroyNotify,
+) -> SourceId {
+    match crate::virtual_clock::timeout_source_new(std::ptr::null_mut(), interval) {
+        Some(source) => attach_default(source, func, data, notify),
+        None => from_glib(ffi::g_timeout_add_full(
+            ffi::G_PRIORITY_DEFAULT,
+            interval.as_millis() as _,
+            func,
+            data,
+            notify,
+        )),
+    }
+}
+
+// Same as `g_timeout_add_seconds_full()` with the default priority, but uses the virtual clock of
+// the default main context if there is one.
+unsafe fn timeout_add_seconds_full(
+    interval: u32,
+    func: ffi::GSourceFunc,
+    data: gpointer,
+    notify: ffi::GDestroyNotify,
+) -> SourceId {
+    match crate::virtual_clock::timeout_source_new(
+        std::ptr::null_mut(),
+        Duration::from_secs(interval as u64),
+    ) {
+        Some(source) => attach_default(source, func, data, notify),
+        None => from_glib(ffi::g_timeout_add_seconds_full(
+            ffi::G_PRIORITY_DEFAULT,
+            interval,
+            func,
+            data,
+            notify,
+        )),
+    }
+}
+
+unsafe fn attach_default(
+    source: *mut ffi::GSource,
+    func: ffi::GSourceFunc,
+    data: gpointer,
+    notify: ffi::GDestroyNotify,
+) -> SourceId {
+    ffi::g_source_set_callback(source, func, data, notify);
+    let id = ffi::g_source_attach(source, std::ptr::null_mut());
+    ffi::g_source_unref(source);
+    from_glib(id)
+}
+
 unsafe extern "C" fn trampoline_child_watch<F: FnMut(Pid, i32) + Send + 'static>(
     pid: ffi::GPid,
     status: i32,
     func: gpointer,
 ) {
     let func: &RefCell<F> = &*(func as *const RefCell<F>);
-    (*func.borrow_mut())(Pid(pid), status)
+    profile_dispatch(std::any::type_name::<F>(), || {
+        (*func.borrow_mut())(Pid(pid), status)
+    })
 }
 
 unsafe extern "C" fn trampoline_child_watch_local<F: FnMut(Pid, i32) + 'static>(
@@ -152,7 +232,9 @@ unsafe extern "C" fn trampoline_child_watch_local<F: FnMut(Pid, i32) + 'static>(
     func: gpointer,
 ) {
     let func: &ThreadGuard<RefCell<F>> = &*(func as *const ThreadGuard<RefCell<F>>);
-    (*func.get_ref().borrow_mut())(Pid(pid), status)
+    profile_dispatch(std::any::type_name::<F>(), || {
+        (*func.get_ref().borrow_mut())(Pid(pid), status)
+    })
 }
 
 unsafe extern "C" fn destroy_closure_child_watch<F: FnMut(Pid, i32) + Send + 'static>(
@@ -187,7 +269,10 @@ unsafe extern "C" fn trampoline_unix_fd<
     func: gpointer,
 ) -> gboolean {
     let func: &RefCell<F> = &*(func as *const RefCell<F>);
-    (*func.borrow_mut())(fd, from_glib(condition)).into_glib()
+    profile_dispatch(std::any::type_name::<F>(), || {
+        (*func.borrow_mut())(fd, from_glib(condition))
+    })
+    .into_glib()
 }
 
 #[cfg(any(unix, feature = "dox"))]
@@ -200,7 +285,10 @@ unsafe extern "C" fn trampoline_unix_fd_local<
     func: gpointer,
 ) -> gboolean {
     let func: &ThreadGuard<RefCell<F>> = &*(func as *const ThreadGuard<RefCell<F>>);
-    (*func.get_ref().borrow_mut())(fd, from_glib(condition)).into_glib()
+    profile_dispatch(std::any::type_name::<F>(), || {
+        (*func.get_ref().borrow_mut())(fd, from_glib(condition))
+    })
+    .into_glib()
 }
 
 #[cfg(any(unix, feature = "dox"))]
@@ -394,13 +482,12 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
-        from_glib(ffi::g_timeout_add_full(
-            ffi::G_PRIORITY_DEFAULT,
-            interval.as_millis() as _,
+        timeout_add_full(
+            interval,
             Some(trampoline::<F>),
             into_raw(func),
             Some(destroy_closure::<F>),
-        ))
+        )
     }
 }
 
@@ -454,13 +541,12 @@ where
         let _acquire = context
             .acquire()
             .expect("default main context already acquired by another thread");
-        from_glib(ffi::g_timeout_add_full(
-            ffi::G_PRIORITY_DEFAULT,
-            interval.as_millis() as _,
+        timeout_add_full(
+            interval,
             Some(trampoline_local::<F>),
             into_raw_local(func),
             Some(destroy_closure_local::<F>),
-        ))
+        )
     }
 }
 
@@ -508,13 +594,12 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
-        from_glib(ffi::g_timeout_add_seconds_full(
-            ffi::G_PRIORITY_DEFAULT,
+        timeout_add_seconds_full(
             interval,
             Some(trampoline::<F>),
             into_raw(func),
             Some(destroy_closure::<F>),
-        ))
+        )
     }
 }
 
@@ -565,13 +650,12 @@ where
         let _acquire = context
             .acquire()
             .expect("default main context already acquired by another thread");
-        from_glib(ffi::g_timeout_add_seconds_full(
-            ffi::G_PRIORITY_DEFAULT,
+        timeout_add_seconds_full(
             interval,
             Some(trampoline_local::<F>),
             into_raw_local(func),
             Some(destroy_closure_local::<F>),
-        ))
+        )
     }
 }
 
@@ -916,7 +1000,8 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
-        let source = ffi::g_timeout_source_new(interval.as_millis() as _);
+        let source = crate::virtual_clock::timeout_source_new(thread_default_context(), interval)
+            .unwrap_or_else(|| ffi::g_timeout_source_new(interval.as_millis() as _));
         ffi::g_source_set_callback(
             source,
             Some(trampoline::<F>),
@@ -951,7 +1036,11 @@ where
     F: FnMut() -> Continue + Send + 'static,
 {
     unsafe {
-        let source = ffi::g_timeout_source_new_seconds(interval);
+        let source = crate::virtual_clock::timeout_source_new(
+            thread_default_context(),
+            Duration::from_secs(interval as u64),
+        )
+        .unwrap_or_else(|| ffi::g_timeout_source_new_seconds(interval));
         ffi::g_source_set_callback(
             source,
             Some(trampoline::<F>),
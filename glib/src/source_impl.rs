@@ -0,0 +1,441 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::any::Any;
+use std::mem;
+use std::ptr;
+use std::time::Duration;
+
+#[cfg(all(not(unix), feature = "dox"))]
+use libc::c_int as RawFd;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+
+use crate::translate::*;
+#[cfg(any(unix, feature = "dox"))]
+use crate::IOCondition;
+use crate::{Continue, Priority, Source};
+
+// rustdoc-stripper-ignore-next
+/// Trait for implementing custom [`Source`]s.
+///
+/// Sources created from an implementation of this trait with [`Source::new`] become ready
+/// whenever [`prepare`](SourceImpl::prepare) or [`check`](SourceImpl::check) return `true`, when
+/// their [ready time](Source::set_ready_time) is reached, or when one of their
+/// [file descriptors](Source::add_unix_fd) polls the requested condition. Once ready
+/// [`dispatch`](SourceImpl::dispatch) is called.
+///
+/// All functions are called from the thread that is iterating the main context the source is
+/// attached to.
+pub trait SourceImpl: Send + Sync + 'static {
+    // rustdoc-stripper-ignore-next
+    /// Called before polling the file descriptors of the main context.
+    ///
+    /// Returns whether the source is ready already, and optionally the maximum time until the
+    /// main context should call [`check`](SourceImpl::check) again.
+    ///
+    /// Sources that only rely on their ready time or file descriptors don't need to implement
+    /// this.
+    fn prepare(&self, _source: &Source) -> (bool, Option<Duration>) {
+        (false, None)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Called after polling the file descriptors of the main context.
+    ///
+    /// Returns whether the source is ready. The conditions of the file descriptors can be
+    /// retrieved with [`Source::query_unix_fd`].
+    fn check(&self, _source: &Source) -> bool {
+        false
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Called when the source is ready.
+    ///
+    /// The source is destroyed if this returns `Continue(false)`.
+    fn dispatch(&self, source: &Source) -> Continue;
+
+    // rustdoc-stripper-ignore-next
+    /// Called when the last reference to the source is dropped, right before the implementation
+    /// itself is dropped.
+    fn finalize(&self) {}
+}
+
+trait AnySourceImpl: SourceImpl {
+    fn as_any(&self) -> &dyn Any;
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: SourceImpl> AnySourceImpl for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+}
+
+#[repr(C)]
+struct RustSource {
+    source: ffi::GSource,
+    imp: Box<dyn AnySourceImpl>,
+}
+
+static SOURCE_FUNCS: ffi::GSourceFuncs = ffi::GSourceFuncs {
+    prepare: Some(prepare),
+    check: Some(check),
+    dispatch: Some(dispatch),
+    finalize: Some(finalize),
+    closure_callback: None,
+    closure_marshal: None,
+};
+
+unsafe extern "C" fn prepare(source: *mut ffi::GSource, timeout: *mut i32) -> ffi::gboolean {
+    let imp = &(*(source as *mut RustSource)).imp;
+    let (ready, next) = imp.prepare(&from_glib_borrow(source));
+    *timeout = match next {
+        Some(next) => next.as_millis().min(i32::MAX as u128) as i32,
+        None => -1,
+    };
+    ready.into_glib()
+}
+
+unsafe extern "C" fn check(source: *mut ffi::GSource) -> ffi::gboolean {
+    let imp = &(*(source as *mut RustSource)).imp;
+    imp.check(&from_glib_borrow(source)).into_glib()
+}
+
+unsafe extern "C" fn dispatch(
+    source: *mut ffi::GSource,
+    _callback: ffi::GSourceFunc,
+    _user_data: ffi::gpointer,
+) -> ffi::gboolean {
+    let imp = &(*(source as *mut RustSource)).imp;
+    crate::source::profile_dispatch(imp.type_name(), || imp.dispatch(&from_glib_borrow(source)))
+        .into_glib()
+}
+
+unsafe extern "C" fn finalize(source: *mut ffi::GSource) {
+    let source = &mut *(source as *mut RustSource);
+    source.imp.finalize();
+    ptr::drop_in_place(&mut source.imp);
+}
+
+impl Source {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new source that is implemented by `imp`.
+    ///
+    /// The source has to be attached to a main context with [`Source::attach`].
+    #[doc(alias = "g_source_new")]
+    pub fn new<T: SourceImpl>(imp: T, name: Option<&str>, priority: Priority) -> Source {
+        unsafe {
+            let source = ffi::g_source_new(
+                mut_override(&SOURCE_FUNCS),
+                mem::size_of::<RustSource>() as u32,
+            ) as *mut RustSource;
+            assert!(!source.is_null());
+
+            let imp: Box<dyn AnySourceImpl> = Box::new(imp);
+            ptr::write(&mut (*source).imp, imp);
+
+            ffi::g_source_set_priority(&mut (*source).source, priority.into_glib());
+            if let Some(name) = name {
+                ffi::g_source_set_name(&mut (*source).source, name.to_glib_none().0);
+            }
+
+            from_glib_full(source as *mut ffi::GSource)
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Returns the implementation of the source if it was created by [`Source::new`] with an
+    /// implementation of type `T`.
+    pub fn imp<T: SourceImpl>(&self) -> Option<&T> {
+        unsafe {
+            let source = self.as_ptr();
+            if !ptr::eq((*source).source_funcs, &SOURCE_FUNCS) {
+                return None;
+            }
+
+            (*(source as *const RustSource)).imp.as_any().downcast_ref()
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the monotonic time in microseconds at which the source becomes ready.
+    ///
+    /// A ready time of `0` makes the source ready immediately, `-1` removes the ready time.
+    /// The time is compared against [`Source::time`] and can be set from any thread.
+    #[doc(alias = "g_source_set_ready_time")]
+    pub fn set_ready_time(&self, ready_time: i64) {
+        unsafe {
+            ffi::g_source_set_ready_time(self.as_ptr(), ready_time);
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// Adds `fd` to the file descriptors polled by the main context for `events` while the source
+    /// is attached.
+    ///
+    /// This must only be called on sources created with [`Source::new`], usually from their
+    /// implementation. `fd` must stay open until it is removed again or the source is destroyed.
+    #[doc(alias = "g_source_add_unix_fd")]
+    pub fn add_unix_fd(&self, fd: RawFd, events: IOCondition) -> UnixFdTag {
+        self.assert_custom();
+        unsafe {
+            let tag = ffi::g_source_add_unix_fd(self.as_ptr(), fd, events.into_glib());
+            UnixFdTag {
+                source: self.as_ptr() as usize,
+                tag: tag as usize,
+            }
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// Changes the events polled for the file descriptor of `tag`.
+    #[doc(alias = "g_source_modify_unix_fd")]
+    pub fn modify_unix_fd(&self, tag: &UnixFdTag, new_events: IOCondition) {
+        self.assert_tag(tag);
+        unsafe {
+            ffi::g_source_modify_unix_fd(
+                self.as_ptr(),
+                tag.tag as ffi::gpointer,
+                new_events.into_glib(),
+            );
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// Returns the events that were polled for the file descriptor of `tag` in the last
+    /// iteration of the main context.
+    ///
+    /// This is only meaningful from [`SourceImpl::check`] and [`SourceImpl::dispatch`].
+    #[doc(alias = "g_source_query_unix_fd")]
+    pub fn query_unix_fd(&self, tag: &UnixFdTag) -> IOCondition {
+        self.assert_tag(tag);
+        unsafe {
+            from_glib(ffi::g_source_query_unix_fd(
+                self.as_ptr(),
+                tag.tag as ffi::gpointer,
+            ))
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    #[cfg_attr(feature = "dox", doc(cfg(unix)))]
+    // rustdoc-stripper-ignore-next
+    /// Stops polling the file descriptor of `tag`.
+    #[doc(alias = "g_source_remove_unix_fd")]
+    pub fn remove_unix_fd(&self, tag: UnixFdTag) {
+        self.assert_tag(&tag);
+        unsafe {
+            ffi::g_source_remove_unix_fd(self.as_ptr(), tag.tag as ffi::gpointer);
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    fn assert_custom(&self) {
+        unsafe {
+            assert!(
+                ptr::eq((*self.as_ptr()).source_funcs, &SOURCE_FUNCS),
+                "File descriptors can only be added to sources created with Source::new()"
+            );
+        }
+    }
+
+    #[cfg(any(unix, feature = "dox"))]
+    fn assert_tag(&self, tag: &UnixFdTag) {
+        assert_eq!(
+            tag.source,
+            self.as_ptr() as usize,
+            "File descriptor tag belongs to a different source"
+        );
+    }
+}
+
+#[cfg(any(unix, feature = "dox"))]
+#[cfg_attr(feature = "dox", doc(cfg(unix)))]
+// rustdoc-stripper-ignore-next
+/// Tag of a file descriptor added to a [`Source`] with [`Source::add_unix_fd`].
+///
+/// The tag can only be used with the source it was returned from.
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnixFdTag {
+    source: usize,
+    tag: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MainContext, MainLoop};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    struct ReadySource {
+        main_loop: MainLoop,
+        dispatched: AtomicUsize,
+        finalized: Arc<AtomicBool>,
+    }
+
+    impl SourceImpl for ReadySource {
+        fn dispatch(&self, source: &Source) -> Continue {
+            source.set_ready_time(-1);
+            if self.dispatched.fetch_add(1, Ordering::SeqCst) == 2 {
+                self.main_loop.quit();
+                Continue(false)
+            } else {
+                source.set_ready_time(0);
+                Continue(true)
+            }
+        }
+
+        fn finalize(&self) {
+            self.finalized.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_ready_time() {
+        let c = MainContext::new();
+        let l = MainLoop::new(Some(&c), false);
+        let finalized = Arc::new(AtomicBool::new(false));
+
+        let source = Source::new(
+            ReadySource {
+                main_loop: l.clone(),
+                dispatched: AtomicUsize::new(0),
+                finalized: finalized.clone(),
+            },
+            Some("ready"),
+            crate::PRIORITY_HIGH,
+        );
+        assert_eq!(source.name().as_deref(), Some("ready"));
+        assert_eq!(source.priority(), crate::PRIORITY_HIGH.into_glib());
+        source.set_ready_time(0);
+        source.attach(Some(&c));
+
+        l.run();
+
+        assert!(source.is_destroyed());
+        let imp = source.imp::<ReadySource>().unwrap();
+        assert_eq!(imp.dispatched.load(Ordering::SeqCst), 3);
+        assert!(!finalized.load(Ordering::SeqCst));
+
+        drop(source);
+        assert!(finalized.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_imp() {
+        struct Other;
+        impl SourceImpl for Other {
+            fn dispatch(&self, _source: &Source) -> Continue {
+                Continue(false)
+            }
+        }
+
+        let source = Source::new(Other, None, crate::PRIORITY_DEFAULT);
+        assert!(source.imp::<Other>().is_some());
+        assert!(source.imp::<ReadySource>().is_none());
+
+        let idle = crate::idle_source_new(None, crate::PRIORITY_DEFAULT, || Continue(false));
+        assert!(idle.imp::<Other>().is_none());
+    }
+
+    #[cfg(unix)]
+    struct PipeSource {
+        main_loop: MainLoop,
+        fd: RawFd,
+        tag: Mutex<Option<UnixFdTag>>,
+        received: Mutex<Vec<u8>>,
+    }
+
+    #[cfg(unix)]
+    impl SourceImpl for PipeSource {
+        fn check(&self, source: &Source) -> bool {
+            let tag = self.tag.lock().unwrap();
+            source
+                .query_unix_fd(tag.as_ref().unwrap())
+                .contains(IOCondition::IN)
+        }
+
+        fn dispatch(&self, source: &Source) -> Continue {
+            let mut buf = [0u8; 16];
+            let n = unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut _, buf.len()) };
+            assert!(n > 0);
+
+            let mut received = self.received.lock().unwrap();
+            received.extend_from_slice(&buf[..n as usize]);
+            if received.ends_with(b"\n") {
+                let tag = self.tag.lock().unwrap().take().unwrap();
+                source.remove_unix_fd(tag);
+                self.main_loop.quit();
+                Continue(false)
+            } else {
+                Continue(true)
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_fd() {
+        let c = MainContext::new();
+        let l = MainLoop::new(Some(&c), false);
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let [read_fd, write_fd] = fds;
+
+        let source = Source::new(
+            PipeSource {
+                main_loop: l.clone(),
+                fd: read_fd,
+                tag: Mutex::new(None),
+                received: Mutex::new(Vec::new()),
+            },
+            None,
+            crate::PRIORITY_DEFAULT,
+        );
+        let tag = source.add_unix_fd(read_fd, IOCondition::OUT);
+        source.modify_unix_fd(&tag, IOCondition::IN);
+        *source.imp::<PipeSource>().unwrap().tag.lock().unwrap() = Some(tag);
+        source.attach(Some(&c));
+
+        let writer = std::thread::spawn(move || {
+            for chunk in [&b"hello "[..], &b"world\n"[..]] {
+                std::thread::sleep(Duration::from_millis(10));
+                let n = unsafe { libc::write(write_fd, chunk.as_ptr() as *const _, chunk.len()) };
+                assert_eq!(n, chunk.len() as isize);
+            }
+        });
+
+        l.run();
+        writer.join().unwrap();
+
+        assert_eq!(
+            &*source.imp::<PipeSource>().unwrap().received.lock().unwrap(),
+            b"hello world\n"
+        );
+
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[should_panic(expected = "File descriptors can only be added to sources created")]
+    fn test_unix_fd_foreign_source() {
+        let idle = crate::idle_source_new(None, crate::PRIORITY_DEFAULT, || Continue(false));
+        idle.add_unix_fd(0, IOCondition::IN);
+    }
+}
@@ -0,0 +1,695 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+// rustdoc-stripper-ignore-next
+//! Spawning of child processes.
+//!
+//! [`Command`] builds the arguments for [`g_spawn_async_with_pipes`][spawn], and the resulting
+//! [`Child`] can be awaited on a [`MainContext`](crate::MainContext), optionally capturing its
+//! output without blocking.
+//!
+//! ```
+//! # #[cfg(unix)]
+//! # {
+//! use glib::spawn::{Command, Stdio};
+//!
+//! let c = glib::MainContext::new();
+//! let child = Command::new("sh")
+//!     .args(["-c", "echo $GREETING"])
+//!     .env("GREETING", "hello")
+//!     .stdout(Stdio::Piped)
+//!     .spawn()
+//!     .unwrap();
+//! let output = c.block_on(child.wait_with_output()).unwrap();
+//!
+//! assert!(output.status.success());
+//! assert_eq!(output.stdout, b"hello\n");
+//! # }
+//! ```
+//!
+//! [spawn]: https://docs.gtk.org/glib/func.spawn_async_with_pipes.html
+
+use std::ffi::{CString, OsStr, OsString};
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+use std::ptr;
+
+use futures_util::io::AsyncReadExt;
+
+use crate::translate::*;
+use crate::{AsyncFd, Error, FileError, Pid, SpawnFlags};
+
+// rustdoc-stripper-ignore-next
+/// Where a standard stream of a child process is connected to.
+#[derive(Debug)]
+pub enum Stdio {
+    // rustdoc-stripper-ignore-next
+    /// The stream of the parent process is inherited.
+    ///
+    /// This is the default for standard output and standard error.
+    Inherit,
+    // rustdoc-stripper-ignore-next
+    /// The stream is connected to `/dev/null`.
+    ///
+    /// This is the default for standard input.
+    Null,
+    // rustdoc-stripper-ignore-next
+    /// A pipe is created and the other end is returned by the [`Child`].
+    Piped,
+    // rustdoc-stripper-ignore-next
+    /// The stream is connected to the given file descriptor.
+    Fd(OwnedFd),
+}
+
+// rustdoc-stripper-ignore-next
+/// Builder for spawning a child process.
+///
+/// The program is looked up in `PATH` unless the flags are changed with
+/// [`flags`](Self::flags).
+#[must_use = "The process is only spawned by `spawn()`"]
+pub struct Command {
+    argv: Vec<OsString>,
+    env: Option<Vec<(OsString, Option<OsString>)>>,
+    clear_env: bool,
+    cwd: Option<PathBuf>,
+    flags: SpawnFlags,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+    fds: Vec<(OwnedFd, RawFd)>,
+    child_setup: Option<Box<dyn FnMut()>>,
+}
+
+impl Command {
+    // rustdoc-stripper-ignore-next
+    /// Creates a builder for running `program`.
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            argv: vec![program.as_ref().to_owned()],
+            env: None,
+            clear_env: false,
+            cwd: None,
+            flags: SpawnFlags::SEARCH_PATH,
+            stdin: Stdio::Null,
+            stdout: Stdio::Inherit,
+            stderr: Stdio::Inherit,
+            fds: Vec::new(),
+            child_setup: None,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Appends an argument.
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.argv.push(arg.as_ref().to_owned());
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Appends multiple arguments.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.argv
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets an environment variable for the child process.
+    ///
+    /// The child process inherits the environment of the parent process otherwise.
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.env
+            .get_or_insert_with(Vec::new)
+            .push((key.as_ref().to_owned(), Some(value.as_ref().to_owned())));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Removes an environment variable for the child process.
+    pub fn env_remove(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.env
+            .get_or_insert_with(Vec::new)
+            .push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Starts the child process with an empty environment, apart from the variables set with
+    /// [`env`](Self::env).
+    pub fn env_clear(mut self) -> Self {
+        self.clear_env = true;
+        self.env = Some(Vec::new());
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the working directory of the child process.
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the flags for spawning, [`SpawnFlags::SEARCH_PATH`] by default.
+    ///
+    /// The flags for the standard streams are set according to [`stdin`](Self::stdin),
+    /// [`stdout`](Self::stdout) and [`stderr`](Self::stderr), and
+    /// [`SpawnFlags::DO_NOT_REAP_CHILD`] is always set for waiting for the child.
+    pub fn flags(mut self, flags: SpawnFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the standard input of the child process.
+    pub fn stdin(mut self, stdin: Stdio) -> Self {
+        self.stdin = stdin;
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the standard output of the child process.
+    pub fn stdout(mut self, stdout: Stdio) -> Self {
+        self.stdout = stdout;
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets the standard error of the child process.
+    pub fn stderr(mut self, stderr: Stdio) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Makes `source` available as file descriptor `target` in the child process.
+    ///
+    /// All other file descriptors apart from the standard streams are closed in the child process
+    /// unless [`SpawnFlags::LEAVE_DESCRIPTORS_OPEN`] is set. `source` is closed in the parent
+    /// process after spawning.
+    pub fn fd(mut self, source: OwnedFd, target: RawFd) -> Self {
+        self.fds.push((source, target));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets a function that is called in the child process right before the program is executed.
+    ///
+    /// # Safety
+    ///
+    /// The function runs in the child process after forking, where only async-signal-safe
+    /// functions can be called. In particular it must not allocate or take locks.
+    pub unsafe fn child_setup<F: FnMut() + 'static>(mut self, func: F) -> Self {
+        self.child_setup = Some(Box::new(func));
+        self
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawns the child process.
+    #[doc(alias = "g_spawn_async_with_pipes")]
+    pub fn spawn(self) -> Result<Child, Error> {
+        let argv = self
+            .argv
+            .iter()
+            .map(|arg| to_cstring(arg.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let envp = match self.env {
+            Some(ref changes) => Some(environment(self.clear_env, changes)?),
+            None => None,
+        };
+        let cwd = match self.cwd {
+            Some(ref cwd) => Some(to_cstring(cwd.as_os_str().as_bytes())?),
+            None => None,
+        };
+
+        let mut flags = self.flags | SpawnFlags::DO_NOT_REAP_CHILD;
+        flags.remove(
+            SpawnFlags::CHILD_INHERITS_STDIN
+                | SpawnFlags::STDOUT_TO_DEV_NULL
+                | SpawnFlags::STDERR_TO_DEV_NULL,
+        );
+        match self.stdin {
+            Stdio::Inherit => flags |= SpawnFlags::CHILD_INHERITS_STDIN,
+            // Overridden by the mapping below
+            Stdio::Null | Stdio::Fd(_) | Stdio::Piped => (),
+        }
+        if let Stdio::Null = self.stdout {
+            flags |= SpawnFlags::STDOUT_TO_DEV_NULL;
+        }
+        if let Stdio::Null = self.stderr {
+            flags |= SpawnFlags::STDERR_TO_DEV_NULL;
+        }
+
+        let piped = [&self.stdin, &self.stdout, &self.stderr].map(|s| matches!(s, Stdio::Piped));
+
+        let mut fds = self.fds;
+        for (stdio, target) in [(self.stdin, 0), (self.stdout, 1), (self.stderr, 2)] {
+            if let Stdio::Fd(fd) = stdio {
+                fds.push((fd, target));
+            }
+        }
+
+        let mut setup = ChildSetup {
+            min_fd: fds
+                .iter()
+                .map(|(source, target)| source.as_raw_fd().max(*target))
+                .max()
+                .unwrap_or(0)
+                + 1,
+            fds: fds
+                .iter()
+                .map(|(source, target)| (source.as_raw_fd(), *target))
+                .collect(),
+            tmp: vec![-1; fds.len()],
+            func: self.child_setup,
+        };
+        let needs_setup = !setup.fds.is_empty() || setup.func.is_some();
+
+        unsafe {
+            let mut argv_ptrs = argv
+                .iter()
+                .map(|arg| mut_override(arg.as_ptr()))
+                .chain(std::iter::once(ptr::null_mut()))
+                .collect::<Vec<_>>();
+            let mut envp_ptrs = envp.as_ref().map(|envp| {
+                envp.iter()
+                    .map(|var| mut_override(var.as_ptr()))
+                    .chain(std::iter::once(ptr::null_mut()))
+                    .collect::<Vec<_>>()
+            });
+
+            let mut pid = mem::MaybeUninit::uninit();
+            let mut pipes = [-1; 3];
+            let [stdin_ptr, stdout_ptr, stderr_ptr] = [0, 1, 2].map(|i| {
+                if piped[i] {
+                    &mut pipes[i] as *mut RawFd
+                } else {
+                    ptr::null_mut()
+                }
+            });
+            let mut error = ptr::null_mut();
+            let ok: bool = from_glib(ffi::g_spawn_async_with_pipes(
+                cwd.as_ref().map_or(ptr::null(), |cwd| cwd.as_ptr()),
+                argv_ptrs.as_mut_ptr(),
+                envp_ptrs
+                    .as_mut()
+                    .map_or(ptr::null_mut(), |envp| envp.as_mut_ptr()),
+                flags.into_glib(),
+                if needs_setup {
+                    Some(child_setup_func)
+                } else {
+                    None
+                },
+                &mut setup as *mut ChildSetup as ffi::gpointer,
+                pid.as_mut_ptr(),
+                stdin_ptr,
+                stdout_ptr,
+                stderr_ptr,
+                &mut error,
+            ));
+            drop(fds);
+
+            if !ok {
+                return Err(from_glib_full(error));
+            }
+
+            let [stdin, stdout, stderr] = pipes.map(|fd| {
+                if fd >= 0 {
+                    Some(OwnedFd::from_raw_fd(fd))
+                } else {
+                    None
+                }
+            });
+
+            Ok(Child {
+                pid: from_glib(pid.assume_init()),
+                stdin,
+                stdout,
+                stderr,
+                watched: false,
+            })
+        }
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Command")
+            .field("argv", &self.argv)
+            .field("env", &self.env)
+            .field("clear_env", &self.clear_env)
+            .field("cwd", &self.cwd)
+            .field("flags", &self.flags)
+            .field("stdin", &self.stdin)
+            .field("stdout", &self.stdout)
+            .field("stderr", &self.stderr)
+            .field("fds", &self.fds)
+            .finish_non_exhaustive()
+    }
+}
+
+fn to_cstring(bytes: &[u8]) -> Result<CString, Error> {
+    CString::new(bytes).map_err(|_| {
+        Error::new(
+            FileError::Inval,
+            "Spawn arguments must not contain NUL bytes",
+        )
+    })
+}
+
+fn environment(
+    clear: bool,
+    changes: &[(OsString, Option<OsString>)],
+) -> Result<Vec<CString>, Error> {
+    let mut vars: Vec<(OsString, OsString)> = if clear {
+        Vec::new()
+    } else {
+        std::env::vars_os().collect()
+    };
+    for (key, value) in changes {
+        vars.retain(|(k, _)| k != key);
+        if let Some(value) = value {
+            vars.push((key.clone(), value.clone()));
+        }
+    }
+
+    vars.into_iter()
+        .map(|(key, value)| {
+            let mut var = key.into_vec();
+            var.push(b'=');
+            var.extend_from_slice(value.as_bytes());
+            to_cstring(&var)
+        })
+        .collect()
+}
+
+// Everything is allocated before forking as the child process can only call async-signal-safe
+// functions.
+struct ChildSetup {
+    fds: Vec<(RawFd, RawFd)>,
+    tmp: Vec<RawFd>,
+    min_fd: RawFd,
+    func: Option<Box<dyn FnMut()>>,
+}
+
+unsafe extern "C" fn child_setup_func(user_data: ffi::gpointer) {
+    let setup = &mut *(user_data as *mut ChildSetup);
+
+    // Move all sources out of the way first so that no source is overwritten by the mapping of
+    // another one. The temporary copies are closed again when executing the program.
+    for (tmp, (source, _)) in setup.tmp.iter_mut().zip(&setup.fds) {
+        *tmp = libc::fcntl(*source, libc::F_DUPFD_CLOEXEC, setup.min_fd);
+        if *tmp < 0 {
+            libc::_exit(127);
+        }
+    }
+    for (tmp, (_, target)) in setup.tmp.iter().zip(&setup.fds) {
+        if libc::dup2(*tmp, *target) < 0 {
+            libc::_exit(127);
+        }
+    }
+
+    if let Some(ref mut func) = setup.func {
+        func();
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A spawned child process.
+///
+/// If the child is dropped without waiting for it, it is reaped by the global default main
+/// context once it exits. If the future of [`wait`](Child::wait) is dropped before the child
+/// exited, the child is reaped by the main context the future was polled from instead.
+#[derive(Debug)]
+pub struct Child {
+    pid: Pid,
+    stdin: Option<OwnedFd>,
+    stdout: Option<OwnedFd>,
+    stderr: Option<OwnedFd>,
+    // Set once `wait()` attached a child watch, which then reaps the child even if the future
+    // is dropped early.
+    watched: bool,
+}
+
+impl Child {
+    // rustdoc-stripper-ignore-next
+    /// The process ID of the child.
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Takes the write end of the standard input pipe, if it was [piped](Stdio::Piped).
+    pub fn take_stdin(&mut self) -> Option<OwnedFd> {
+        self.stdin.take()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Takes the read end of the standard output pipe, if it was [piped](Stdio::Piped).
+    pub fn take_stdout(&mut self) -> Option<OwnedFd> {
+        self.stdout.take()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Takes the read end of the standard error pipe, if it was [piped](Stdio::Piped).
+    pub fn take_stderr(&mut self) -> Option<OwnedFd> {
+        self.stderr.take()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for the child to exit.
+    ///
+    /// The standard input pipe is closed first so the child doesn't wait for more input. The
+    /// future must be polled from a [`MainContext`](crate::MainContext).
+    #[doc(alias = "g_child_watch_add")]
+    pub async fn wait(mut self) -> ExitStatus {
+        self.stdin = None;
+
+        let main_context = crate::MainContext::ref_thread_default();
+        assert!(
+            main_context.is_owner(),
+            "Spawning futures only allowed if the thread is owning the MainContext"
+        );
+
+        // The source is not destroyed if the future is dropped before the child exits. GLib
+        // might already have reaped the child without having dispatched the source yet, so
+        // watching the child again would race with other processes reusing its PID.
+        let (send, recv) = futures_channel::oneshot::channel();
+        let mut send = Some(send);
+        let source = crate::child_watch_source_new(
+            self.pid,
+            None,
+            crate::PRIORITY_DEFAULT,
+            move |_, status| {
+                let _ = send.take().unwrap().send(status);
+            },
+        );
+        source.attach(Some(&main_context));
+        self.watched = true;
+
+        let status = recv
+            .await
+            .expect("child watch destroyed before the child exited");
+        ExitStatus::from_raw(status)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Waits for the child to exit and collects everything it writes to its
+    /// [piped](Stdio::Piped) standard output and standard error.
+    ///
+    /// Both pipes are read concurrently, so the child can't block on a full pipe. The future must
+    /// be polled from a [`MainContext`](crate::MainContext).
+    pub async fn wait_with_output(mut self) -> io::Result<Output> {
+        async fn read_to_end(fd: Option<OwnedFd>) -> io::Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            if let Some(fd) = fd {
+                unsafe {
+                    let flags = libc::fcntl(fd.as_raw_fd(), libc::F_GETFL);
+                    libc::fcntl(fd.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK);
+                }
+                AsyncFd::new(fd).read_to_end(&mut buf).await?;
+            }
+            Ok(buf)
+        }
+
+        let stdout = self.stdout.take();
+        let stderr = self.stderr.take();
+        let (stdout, stderr, status) =
+            futures_util::future::join3(read_to_end(stdout), read_to_end(stderr), self.wait())
+                .await;
+
+        Ok(Output {
+            status,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        if !self.watched {
+            crate::child_watch_add(self.pid, |_, _| ());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MainContext;
+    use std::fs::File;
+    use std::io::{Read, Write};
+
+    fn pipe() -> (OwnedFd, OwnedFd) {
+        let (read_fd, write_fd) = crate::unix_open_pipe(libc::FD_CLOEXEC).unwrap();
+        unsafe {
+            (
+                OwnedFd::from_raw_fd(read_fd),
+                OwnedFd::from_raw_fd(write_fd),
+            )
+        }
+    }
+
+    #[test]
+    fn test_output() {
+        let c = MainContext::new();
+        let child = Command::new("sh")
+            .args(["-c", "echo out; echo err >&2; exit 3"])
+            .stdout(Stdio::Piped)
+            .stderr(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        let output = c.block_on(child.wait_with_output()).unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn test_env_and_cwd() {
+        let c = MainContext::new();
+        let child = Command::new("sh")
+            .args(["-c", "echo \"$FOO:$HOME\"; pwd"])
+            .env_clear()
+            .env("FOO", "bar")
+            .current_dir("/")
+            .stdout(Stdio::Piped)
+            .spawn()
+            .unwrap();
+        let output = c.block_on(child.wait_with_output()).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"bar:\n/\n");
+    }
+
+    #[test]
+    fn test_stdin() {
+        let c = MainContext::new();
+        let mut child = Command::new("cat")
+            .stdin(Stdio::Piped)
+            .stdout(Stdio::Piped)
+            .spawn()
+            .unwrap();
+
+        File::from(child.take_stdin().unwrap())
+            .write_all(b"hello")
+            .unwrap();
+        let output = c.block_on(child.wait_with_output()).unwrap();
+        assert_eq!(output.stdout, b"hello");
+    }
+
+    #[test]
+    fn test_cancelled_wait() {
+        let c = MainContext::new();
+        let child = Command::new("sleep").arg("0.1").spawn().unwrap();
+        let pid = child.pid();
+
+        let res = c.block_on(crate::future_with_timeout(
+            std::time::Duration::from_millis(10),
+            child.wait(),
+        ));
+        assert!(res.is_err());
+
+        // The child is still reaped by the main context of the wait once it exits
+        let start = std::time::Instant::now();
+        while unsafe { libc::kill(pid.0, 0) } == 0 {
+            assert!(start.elapsed() < std::time::Duration::from_secs(5));
+            c.iteration(false);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_fd_mapping() {
+        let c = MainContext::new();
+        let (read_a, write_a) = pipe();
+        let (read_b, write_b) = pipe();
+        let (stdout_read, stdout_write) = pipe();
+
+        // The targets overlap with the sources of the other mapping
+        let a = write_a.as_raw_fd();
+        let b = write_b.as_raw_fd();
+        let status = c.block_on(
+            Command::new("sh")
+                .args(["-c", &format!("echo a >&{}; echo b >&{}; echo out", b, a)])
+                .fd(write_a, b)
+                .fd(write_b, a)
+                .stdout(Stdio::Fd(stdout_write))
+                .spawn()
+                .unwrap()
+                .wait(),
+        );
+        assert!(status.success());
+
+        let mut buf = String::new();
+        File::from(read_a).read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "a\n");
+        buf.clear();
+        File::from(read_b).read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "b\n");
+        buf.clear();
+        File::from(stdout_read).read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "out\n");
+    }
+
+    #[test]
+    fn test_child_setup() {
+        let c = MainContext::new();
+        let (read, write) = pipe();
+
+        let write_fd = write.as_raw_fd();
+        let child = unsafe {
+            Command::new("true")
+                .child_setup(move || {
+                    libc::write(write_fd, b"setup".as_ptr() as *const _, 5);
+                })
+                .spawn()
+                .unwrap()
+        };
+        drop(write);
+        assert!(c.block_on(child.wait()).success());
+
+        let mut buf = String::new();
+        File::from(read).read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "setup");
+    }
+
+    #[test]
+    fn test_spawn_error() {
+        let err = Command::new("/nonexistent/program").spawn().unwrap_err();
+        assert_eq!(err.domain(), crate::Quark::from_str("g-exec-error-quark"));
+        let err = Command::new("echo").arg("a\0b").spawn().unwrap_err();
+        assert!(err.matches(FileError::Inval));
+    }
+}
@@ -576,6 +576,65 @@ mod test {
         assert!(name_changed_triggered.load(Ordering::Relaxed));
     }
 
+    #[test]
+    fn test_typed_closure_signals() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let name_changed_triggered = Arc::new(AtomicBool::new(false));
+        let name_changed_clone = name_changed_triggered.clone();
+        let obj = Object::builder::<SimpleObject>()
+            .property("name", "old-name")
+            .connect_closure(
+                "name-changed",
+                false,
+                crate::TypedClosure::new(move |_: SimpleObject, name: String| {
+                    assert_eq!(name, "new-name");
+                    name_changed_clone.store(true, Ordering::Relaxed);
+                }),
+            )
+            .build();
+
+        obj.connect_typed_closure(
+            "create-string",
+            false,
+            crate::TypedClosure::new(|_: Object| String::from("return value")),
+        );
+
+        assert_eq!(
+            obj.emit_by_name::<String>("change-name", &[&"new-name"]),
+            "old-name"
+        );
+        assert!(name_changed_triggered.load(Ordering::Relaxed));
+        assert_eq!(
+            obj.emit_by_name::<String>("create-string", &[]),
+            "return value"
+        );
+    }
+
+    #[test]
+    #[should_panic = "Closure returns 'gint' but signal 'create-string' requires 'gchararray'"]
+    fn test_typed_closure_wrong_return_type() {
+        let obj = Object::with_type(SimpleObject::static_type(), &[]);
+        obj.connect_typed_closure(
+            "create-string",
+            false,
+            crate::TypedClosure::new(|_: Object| 0i32),
+        );
+    }
+
+    #[test]
+    #[should_panic = "Closure takes 1 arguments but signal 'name-changed' passes 2"]
+    fn test_typed_closure_wrong_arguments() {
+        let _ = Object::builder::<SimpleObject>()
+            .connect_closure(
+                "name-changed",
+                false,
+                crate::TypedClosure::new(|_: SimpleObject| {}),
+            )
+            .build();
+    }
+
     #[test]
     fn test_signal_return_expected_type() {
         let obj = Object::with_type(SimpleObject::static_type(), &[]);
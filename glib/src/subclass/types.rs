@@ -858,6 +858,9 @@ unsafe extern "C" fn instance_init<T: ObjectSubclass>(
         },
     );
 
+    #[cfg(feature = "leak_tracker")]
+    crate::leak_tracker::track_instance(obj as *mut _, T::type_());
+
     // Any additional instance initialization.
     T::Instance::instance_init(&mut *(obj as *mut _));
 
@@ -870,6 +873,9 @@ unsafe extern "C" fn instance_init<T: ObjectSubclass>(
 }
 
 unsafe extern "C" fn finalize<T: ObjectSubclass>(obj: *mut gobject_ffi::GObject) {
+    #[cfg(feature = "leak_tracker")]
+    crate::leak_tracker::untrack_instance(obj);
+
     // Retrieve the private struct and drop it for freeing all associated memory.
     let mut data = T::type_data();
     let private_offset = data.as_mut().private_offset;
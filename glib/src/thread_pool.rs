@@ -1,18 +1,86 @@
 // Take a look at the license at the top of the repository in the LICENSE file.
 
 use crate::translate::*;
+use crate::Priority;
 
-use futures_channel::oneshot;
+use futures_channel::{mpsc, oneshot};
+use futures_core::stream::Stream;
+use futures_task::{FutureObj, Spawn, SpawnError};
+use futures_util::task::ArcWake;
+use std::cmp;
+use std::fmt;
 use std::future::Future;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::task::{Context, Poll};
 
 #[derive(Debug)]
 #[doc(alias = "GThreadPool")]
-pub struct ThreadPool(ptr::NonNull<ffi::GThreadPool>);
+pub struct ThreadPool {
+    pool: ptr::NonNull<ffi::GThreadPool>,
+    // Shared with futures spawned on the pool so they can push themselves again once woken up.
+    handle: Arc<PoolHandle>,
+    sorted: AtomicBool,
+}
 
 unsafe impl Send for ThreadPool {}
 unsafe impl Sync for ThreadPool {}
 
+// The pool pointer is unset before the pool is freed, jobs pushed afterwards are dropped.
+#[derive(Debug)]
+struct PoolHandle(RwLock<Option<PoolPtr>>);
+
+#[derive(Debug, Clone, Copy)]
+struct PoolPtr(ptr::NonNull<ffi::GThreadPool>);
+
+unsafe impl Send for PoolPtr {}
+unsafe impl Sync for PoolPtr {}
+
+impl PoolHandle {
+    fn push(&self, job: Box<Job>) -> Result<(), crate::Error> {
+        let pool = self.0.read().unwrap();
+        match *pool {
+            Some(pool) => unsafe { push_job(pool.0.as_ptr(), job) },
+            None => Ok(()),
+        }
+    }
+}
+
+static JOB_SEQ: AtomicU64 = AtomicU64::new(0);
+
+struct Job {
+    priority: i32,
+    seq: u64,
+    func: Box<dyn FnOnce() + Send + 'static>,
+}
+
+impl Job {
+    fn new(priority: Priority, func: Box<dyn FnOnce() + Send + 'static>) -> Box<Self> {
+        Box::new(Job {
+            priority: priority.into_glib(),
+            seq: JOB_SEQ.fetch_add(1, Ordering::Relaxed),
+            func,
+        })
+    }
+}
+
+unsafe fn push_job(pool: *mut ffi::GThreadPool, job: Box<Job>) -> Result<(), crate::Error> {
+    let mut err = ptr::null_mut();
+
+    let job = Box::into_raw(job);
+    let ret: bool = from_glib(ffi::g_thread_pool_push(pool, job as *mut _, &mut err));
+    if ret {
+        Ok(())
+    } else {
+        let _ = Box::from_raw(job);
+        Err(from_glib_full(err))
+    }
+}
+
 impl ThreadPool {
     #[doc(alias = "g_thread_pool_new")]
     pub fn shared(max_threads: Option<u32>) -> Result<Self, crate::Error> {
@@ -28,7 +96,7 @@ impl ThreadPool {
             if pool.is_null() {
                 Err(from_glib_full(err))
             } else {
-                Ok(ThreadPool(ptr::NonNull::new_unchecked(pool)))
+                Ok(ThreadPool::from_ptr(ptr::NonNull::new_unchecked(pool)))
             }
         }
     }
@@ -47,29 +115,56 @@ impl ThreadPool {
             if pool.is_null() {
                 Err(from_glib_full(err))
             } else {
-                Ok(ThreadPool(ptr::NonNull::new_unchecked(pool)))
+                Ok(ThreadPool::from_ptr(ptr::NonNull::new_unchecked(pool)))
             }
         }
     }
 
+    fn from_ptr(pool: ptr::NonNull<ffi::GThreadPool>) -> Self {
+        ThreadPool {
+            pool,
+            handle: Arc::new(PoolHandle(RwLock::new(Some(PoolPtr(pool))))),
+            sorted: AtomicBool::new(false),
+        }
+    }
+
     #[doc(alias = "g_thread_pool_push")]
     pub fn push<F: FnOnce() + Send + 'static>(&self, func: F) -> Result<(), crate::Error> {
         unsafe {
-            let func: Box<dyn FnOnce() + Send + 'static> = Box::new(func);
-            let func = Box::new(func);
-            let mut err = ptr::null_mut();
+            push_job(
+                self.pool.as_ptr(),
+                Job::new(crate::PRIORITY_DEFAULT, Box::new(func)),
+            )
+        }
+    }
 
-            let func = Box::into_raw(func);
-            let ret: bool = from_glib(ffi::g_thread_pool_push(
-                self.0.as_ptr(),
-                func as *mut _,
-                &mut err,
-            ));
-            if ret {
-                Ok(())
-            } else {
-                let _ = Box::from_raw(func);
-                Err(from_glib_full(err))
+    // rustdoc-stripper-ignore-next
+    /// Pushes `func` to the pool with the given priority.
+    ///
+    /// Queued jobs with a numerically lower priority value are started first, jobs with the same
+    /// priority in the order they were pushed. Jobs pushed with [`push`](Self::push) have the
+    /// default priority.
+    ///
+    /// Note that with more than one thread the order is not strictly guaranteed as the threads
+    /// take jobs from the queue concurrently.
+    #[doc(alias = "g_thread_pool_set_sort_function")]
+    pub fn push_with_priority<F: FnOnce() + Send + 'static>(
+        &self,
+        priority: Priority,
+        func: F,
+    ) -> Result<(), crate::Error> {
+        self.ensure_sorted();
+        unsafe { push_job(self.pool.as_ptr(), Job::new(priority, Box::new(func))) }
+    }
+
+    fn ensure_sorted(&self) {
+        if !self.sorted.swap(true, Ordering::SeqCst) {
+            unsafe {
+                ffi::g_thread_pool_set_sort_function(
+                    self.pool.as_ptr(),
+                    Some(sort_func),
+                    ptr::null_mut(),
+                );
             }
         }
     }
@@ -87,12 +182,155 @@ impl ThreadPool {
         Ok(async move { receiver.await.expect("Dropped before executing") })
     }
 
+    // rustdoc-stripper-ignore-next
+    /// Runs `f` with a [`ThreadPoolScope`] for pushing jobs that can borrow non-`'static` data.
+    ///
+    /// This returns once `f` returned and all jobs pushed via the scope finished, so the jobs
+    /// can borrow anything that outlives this call. If any of the jobs panicked, this panics
+    /// once all jobs finished.
+    ///
+    /// This blocks the calling thread and must not be called from a job of the same pool if the
+    /// pool has no threads left for running the scoped jobs.
+    ///
+    /// ```
+    /// let pool = glib::ThreadPool::shared(None).unwrap();
+    /// let mut values = vec![1, 2, 3, 4];
+    ///
+    /// pool.spawn_scoped(|scope| {
+    ///     for v in &mut values {
+    ///         scope.spawn(move || *v *= 2).unwrap();
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(values, [2, 4, 6, 8]);
+    /// ```
+    pub fn spawn_scoped<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope ThreadPoolScope<'scope, 'env>) -> R,
+    {
+        let scope = ThreadPoolScope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                pending: Mutex::new(0),
+                finished: Condvar::new(),
+                panicked: AtomicBool::new(false),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+
+        // Wait for the jobs even if `f` panics as they might borrow from its environment.
+        let res = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        let mut pending = scope.state.pending.lock().unwrap();
+        while *pending > 0 {
+            pending = scope.state.finished.wait(pending).unwrap();
+        }
+        drop(pending);
+
+        match res {
+            Err(err) => panic::resume_unwind(err),
+            Ok(_) if scope.state.panicked.load(Ordering::SeqCst) => {
+                panic!("a scoped thread pool job panicked")
+            }
+            Ok(res) => res,
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Runs `f` for every item of `items` on the pool and returns a future that resolves to the
+    /// results in the order of the items.
+    ///
+    /// The future is meant to be awaited on a [`MainContext`](crate::MainContext), which then
+    /// receives the results without blocking. Dropping the future skips all items that were not
+    /// started yet.
+    ///
+    /// If `f` panics, the items that were not started yet are skipped and the future resolves to
+    /// the panic payload, like [`std::thread::JoinHandle::join`].
+    ///
+    /// ```
+    /// let c = glib::MainContext::new();
+    /// let pool = glib::ThreadPool::shared(None).unwrap();
+    ///
+    /// let squares = pool.map_parallel(1..=4, |v: u32| v * v).unwrap();
+    /// assert_eq!(c.block_on(squares).unwrap(), [1, 4, 9, 16]);
+    /// ```
+    pub fn map_parallel<I, T, R, F>(&self, items: I, f: F) -> Result<MapParallel<R>, crate::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Send + 'static,
+        R: Send + 'static,
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::unbounded();
+
+        let mut len = 0;
+        for (idx, item) in items.into_iter().enumerate() {
+            let f = f.clone();
+            let cancelled = cancelled.clone();
+            let sender = sender.clone();
+            self.push(move || {
+                if cancelled.load(Ordering::SeqCst) {
+                    return;
+                }
+                let res = panic::catch_unwind(AssertUnwindSafe(|| f(item)));
+                if res.is_err() {
+                    cancelled.store(true, Ordering::SeqCst);
+                }
+                let _ = sender.unbounded_send((idx, res));
+            })?;
+            len += 1;
+        }
+
+        Ok(MapParallel {
+            receiver,
+            results: (0..len).map(|_| None).collect(),
+            remaining: len,
+            cancelled,
+        })
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Spawns a `Send` future on the pool with the given priority.
+    ///
+    /// The future is polled from the threads of the pool, and whenever it is woken up it is
+    /// queued again with the same priority. Futures that are still pending when the pool is
+    /// dropped are dropped without being polled again.
+    ///
+    /// [`Spawn`] is implemented for `ThreadPool` and spawns futures with the default priority.
+    pub fn spawn_with_priority<F: Future<Output = ()> + Send + 'static>(
+        &self,
+        priority: Priority,
+        f: F,
+    ) -> Result<(), crate::Error> {
+        if priority != crate::PRIORITY_DEFAULT {
+            self.ensure_sorted();
+        }
+        self.spawn_obj_with_priority(priority, FutureObj::new(Box::new(f)))
+    }
+
+    fn spawn_obj_with_priority(
+        &self,
+        priority: Priority,
+        f: FutureObj<'static, ()>,
+    ) -> Result<(), crate::Error> {
+        let task = Arc::new(FutureTask {
+            future: Mutex::new(Some(f)),
+            pool: self.handle.clone(),
+            priority,
+            scheduled: AtomicBool::new(true),
+        });
+        self.handle.push(FutureTask::job(task))
+    }
+
     #[doc(alias = "g_thread_pool_set_max_threads")]
     pub fn set_max_threads(&self, max_threads: Option<u32>) -> Result<(), crate::Error> {
         unsafe {
             let mut err = ptr::null_mut();
             let ret: bool = from_glib(ffi::g_thread_pool_set_max_threads(
-                self.0.as_ptr(),
+                self.pool.as_ptr(),
                 max_threads.map(|v| v as i32).unwrap_or(-1),
                 &mut err,
             ));
@@ -108,7 +346,7 @@ impl ThreadPool {
     #[doc(alias = "get_max_threads")]
     pub fn max_threads(&self) -> Option<u32> {
         unsafe {
-            let max_threads = ffi::g_thread_pool_get_max_threads(self.0.as_ptr());
+            let max_threads = ffi::g_thread_pool_get_max_threads(self.pool.as_ptr());
             if max_threads == -1 {
                 None
             } else {
@@ -120,13 +358,13 @@ impl ThreadPool {
     #[doc(alias = "g_thread_pool_get_num_threads")]
     #[doc(alias = "get_num_threads")]
     pub fn num_threads(&self) -> u32 {
-        unsafe { ffi::g_thread_pool_get_num_threads(self.0.as_ptr()) }
+        unsafe { ffi::g_thread_pool_get_num_threads(self.pool.as_ptr()) }
     }
 
     #[doc(alias = "g_thread_pool_unprocessed")]
     #[doc(alias = "get_unprocessed")]
     pub fn unprocessed(&self) -> u32 {
-        unsafe { ffi::g_thread_pool_unprocessed(self.0.as_ptr()) }
+        unsafe { ffi::g_thread_pool_unprocessed(self.pool.as_ptr()) }
     }
 
     #[doc(alias = "g_thread_pool_set_max_unused_threads")]
@@ -174,17 +412,214 @@ impl ThreadPool {
     }
 }
 
+impl Spawn for ThreadPool {
+    fn spawn_obj(&self, f: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+        self.spawn_obj_with_priority(crate::PRIORITY_DEFAULT, f)
+            .map_err(|_| SpawnError::shutdown())
+    }
+}
+
 impl Drop for ThreadPool {
     fn drop(&mut self) {
+        // Futures that are woken up from now on are not pushed anymore. This must not be locked
+        // while freeing the pool as running futures might be woken up meanwhile.
+        self.handle.0.write().unwrap().take();
+
         unsafe {
-            ffi::g_thread_pool_free(self.0.as_ptr(), ffi::GFALSE, ffi::GTRUE);
+            ffi::g_thread_pool_free(self.pool.as_ptr(), ffi::GFALSE, ffi::GTRUE);
+        }
+    }
+}
+
+unsafe extern "C" fn spawn_func(job: ffi::gpointer, _data: ffi::gpointer) {
+    let job: Box<Job> = Box::from_raw(job as *mut _);
+    (job.func)()
+}
+
+unsafe extern "C" fn sort_func(
+    a: ffi::gconstpointer,
+    b: ffi::gconstpointer,
+    _data: ffi::gpointer,
+) -> i32 {
+    let a = &*(a as *const Job);
+    let b = &*(b as *const Job);
+    match (a.priority, a.seq).cmp(&(b.priority, b.seq)) {
+        cmp::Ordering::Less => -1,
+        cmp::Ordering::Equal => 0,
+        cmp::Ordering::Greater => 1,
+    }
+}
+
+// A future spawned on a pool. Every poll is a separate job of the pool.
+struct FutureTask {
+    future: Mutex<Option<FutureObj<'static, ()>>>,
+    pool: Arc<PoolHandle>,
+    priority: Priority,
+    scheduled: AtomicBool,
+}
+
+impl FutureTask {
+    fn job(task: Arc<Self>) -> Box<Job> {
+        let priority = task.priority;
+        Job::new(priority, Box::new(move || task.run()))
+    }
+
+    fn run(self: Arc<Self>) {
+        self.scheduled.store(false, Ordering::SeqCst);
+
+        let mut future = self.future.lock().unwrap();
+        if let Some(ref mut f) = *future {
+            let waker = futures_util::task::waker_ref(&self);
+            let mut cx = Context::from_waker(&waker);
+            if Pin::new(f).poll(&mut cx).is_ready() {
+                *future = None;
+            }
+        }
+    }
+}
+
+impl ArcWake for FutureTask {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        if !arc_self.scheduled.swap(true, Ordering::SeqCst) {
+            let _ = arc_self.pool.push(FutureTask::job(arc_self.clone()));
         }
     }
 }
 
-unsafe extern "C" fn spawn_func(func: ffi::gpointer, _data: ffi::gpointer) {
-    let func: Box<Box<dyn FnOnce()>> = Box::from_raw(func as *mut _);
-    func()
+struct ScopeState {
+    pending: Mutex<usize>,
+    finished: Condvar,
+    panicked: AtomicBool,
+}
+
+impl ScopeState {
+    fn job_finished(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            self.finished.notify_all();
+        }
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// A scope for pushing jobs that borrow non-`'static` data to a [`ThreadPool`].
+///
+/// See [`ThreadPool::spawn_scoped`].
+pub struct ThreadPoolScope<'scope, 'env: 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env> ThreadPoolScope<'scope, 'env> {
+    // rustdoc-stripper-ignore-next
+    /// Pushes `func` to the pool.
+    ///
+    /// `func` can borrow anything that outlives the scope.
+    pub fn spawn<F: FnOnce() + Send + 'scope>(&'scope self, func: F) -> Result<(), crate::Error> {
+        self.spawn_with_priority(crate::PRIORITY_DEFAULT, func)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Pushes `func` to the pool with the given priority.
+    ///
+    /// See [`ThreadPool::push_with_priority`].
+    pub fn spawn_with_priority<F: FnOnce() + Send + 'scope>(
+        &'scope self,
+        priority: Priority,
+        func: F,
+    ) -> Result<(), crate::Error> {
+        *self.state.pending.lock().unwrap() += 1;
+
+        let state = self.state.clone();
+        let func = move || {
+            if panic::catch_unwind(AssertUnwindSafe(func)).is_err() {
+                state.panicked.store(true, Ordering::SeqCst);
+            }
+            state.job_finished();
+        };
+
+        let func: Box<dyn FnOnce() + Send + 'scope> = Box::new(func);
+        // SAFETY: `ThreadPool::spawn_scoped` doesn't return before all jobs finished, so
+        // anything borrowed by the job outlives it.
+        let func: Box<dyn FnOnce() + Send + 'static> = unsafe { std::mem::transmute(func) };
+
+        if priority != crate::PRIORITY_DEFAULT {
+            self.pool.ensure_sorted();
+        }
+        let res = unsafe { push_job(self.pool.pool.as_ptr(), Job::new(priority, func)) };
+        if res.is_err() {
+            // The job was dropped without running
+            self.state.job_finished();
+        }
+        res
+    }
+}
+
+impl<'scope, 'env> fmt::Debug for ThreadPoolScope<'scope, 'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadPoolScope")
+            .field("pool", &self.pool)
+            .field("pending", &*self.state.pending.lock().unwrap())
+            .finish()
+    }
+}
+
+// rustdoc-stripper-ignore-next
+/// Future returned by [`ThreadPool::map_parallel`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct MapParallel<R> {
+    receiver: mpsc::UnboundedReceiver<(usize, std::thread::Result<R>)>,
+    results: Vec<Option<R>>,
+    remaining: usize,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<R> Future for MapParallel<R> {
+    type Output = std::thread::Result<Vec<R>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        while this.remaining > 0 {
+            match Pin::new(&mut this.receiver).poll_next(cx) {
+                Poll::Ready(Some((idx, Ok(res)))) => {
+                    this.results[idx] = Some(res);
+                    this.remaining -= 1;
+                }
+                Poll::Ready(Some((_, Err(err)))) => {
+                    this.remaining = 0;
+                    return Poll::Ready(Err(err));
+                }
+                // The pool runs all queued jobs before it is freed.
+                Poll::Ready(None) => unreachable!("parallel map job dropped without running"),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(Ok(std::mem::take(&mut this.results)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect()))
+    }
+}
+
+impl<R> Unpin for MapParallel<R> {}
+
+impl<R> Drop for MapParallel<R> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<R> fmt::Debug for MapParallel<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MapParallel")
+            .field("len", &self.results.len())
+            .field("remaining", &self.remaining)
+            .finish()
+    }
 }
 
 #[cfg(test)]
@@ -216,4 +651,120 @@ mod tests {
         let res = c.block_on(fut);
         assert!(res);
     }
+
+    #[test]
+    fn test_push_with_priority() {
+        use std::sync::mpsc;
+
+        let p = ThreadPool::exclusive(1).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let (order_sender, order_receiver) = mpsc::channel();
+
+        // Block the only thread until all jobs are queued
+        p.push(move || receiver.recv().unwrap()).unwrap();
+        for (priority, v) in [
+            (crate::PRIORITY_LOW, 3),
+            (crate::PRIORITY_DEFAULT, 1),
+            (crate::PRIORITY_HIGH, 0),
+            (crate::PRIORITY_DEFAULT, 2),
+        ] {
+            let order_sender = order_sender.clone();
+            p.push_with_priority(priority, move || order_sender.send(v).unwrap())
+                .unwrap();
+        }
+        drop(order_sender);
+        sender.send(()).unwrap();
+
+        assert_eq!(order_receiver.iter().collect::<Vec<_>>(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_spawn_scoped() {
+        let p = ThreadPool::shared(Some(4)).unwrap();
+        let mut values = vec![0; 16];
+        let total = std::sync::atomic::AtomicUsize::new(0);
+
+        let res = p.spawn_scoped(|scope| {
+            for (i, v) in values.iter_mut().enumerate() {
+                let total = &total;
+                scope
+                    .spawn(move || {
+                        *v = i * 2;
+                        total.fetch_add(i, Ordering::SeqCst);
+                    })
+                    .unwrap();
+            }
+            "done"
+        });
+
+        assert_eq!(res, "done");
+        assert_eq!(values, (0..16).map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(total.into_inner(), (0..16).sum());
+    }
+
+    #[test]
+    #[should_panic(expected = "a scoped thread pool job panicked")]
+    fn test_spawn_scoped_panic() {
+        let p = ThreadPool::shared(None).unwrap();
+        p.spawn_scoped(|scope| {
+            scope.spawn(|| panic!("job panic")).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_spawn() {
+        use futures_util::task::SpawnExt;
+
+        let c = crate::MainContext::new();
+        let p = ThreadPool::shared(None).unwrap();
+        let (sender, receiver) = oneshot::channel();
+        let (sender2, receiver2) = oneshot::channel();
+
+        // The spawned future is woken up from the main context and polled again on the pool
+        p.spawn(async move {
+            let v: i32 = receiver.await.unwrap();
+            sender2.send(v + 1).unwrap();
+        })
+        .unwrap();
+
+        sender.send(1).unwrap();
+        assert_eq!(c.block_on(receiver2), Ok(2));
+    }
+
+    #[test]
+    fn test_map_parallel() {
+        let c = crate::MainContext::new();
+        let p = ThreadPool::shared(Some(4)).unwrap();
+
+        let res = p
+            .map_parallel(0..32u64, |v| {
+                std::thread::sleep(std::time::Duration::from_millis(32 - v));
+                v * v
+            })
+            .unwrap();
+        assert_eq!(
+            c.block_on(res).unwrap(),
+            (0..32).map(|v| v * v).collect::<Vec<_>>()
+        );
+
+        let res = p.map_parallel(Vec::<u32>::new(), |v| v).unwrap();
+        assert!(c.block_on(res).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_map_parallel_panic() {
+        let c = crate::MainContext::new();
+        let p = ThreadPool::shared(Some(2)).unwrap();
+
+        let res = p
+            .map_parallel(0..4u32, |v| {
+                if v == 2 {
+                    panic!("map panic");
+                }
+                v
+            })
+            .unwrap();
+        let err = c.block_on(res).unwrap_err();
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"map panic"));
+    }
 }
@@ -591,7 +591,7 @@ impl Value {
         }
     }
 
-    fn content_debug_string(&self) -> GString {
+    pub(crate) fn content_debug_string(&self) -> GString {
         unsafe { from_glib_full(gobject_ffi::g_strdup_value_contents(self.to_glib_none().0)) }
     }
 }
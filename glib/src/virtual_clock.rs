@@ -0,0 +1,763 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use crate::translate::*;
+use crate::{Continue, MainContext, Source, SourceImpl};
+
+// Main contexts with a virtual clock. The number of entries is kept separately so that creating
+// timeouts doesn't have to take the lock if no virtual clock exists at all.
+static CLOCKS: Mutex<Vec<(usize, Weak<Handle>)>> = Mutex::new(Vec::new());
+static N_CLOCKS: AtomicUsize = AtomicUsize::new(0);
+
+struct ClockState {
+    // Virtual time in microseconds since the clock was created.
+    now: i64,
+    // Timeout sources using this clock that are not finalized yet.
+    timers: Vec<usize>,
+    // Suppresses auto-advancing while `advance()` dispatches.
+    advancing: bool,
+}
+
+struct Clock {
+    state: Mutex<ClockState>,
+}
+
+impl Clock {
+    fn lock(&self) -> MutexGuard<'_, ClockState> {
+        self.state.lock().unwrap()
+    }
+
+    fn now(&self) -> i64 {
+        self.lock().now
+    }
+
+    // Earliest expiry of all timeouts that can still be dispatched.
+    fn next_expiry(&self) -> Option<i64> {
+        let state = self.lock();
+        state
+            .timers
+            .iter()
+            .filter_map(|&timer| unsafe { TimeoutSource::live_expiry(timer) })
+            .min()
+    }
+
+    // Moves the time forward to `time` and makes all timeouts ready that expired by then.
+    fn advance_to(&self, time: i64) {
+        let mut state = self.lock();
+        if time > state.now {
+            state.now = time;
+        }
+
+        for &timer in &state.timers {
+            unsafe {
+                if let Some(expiry) = TimeoutSource::live_expiry(timer) {
+                    if expiry <= state.now {
+                        ffi::g_source_set_ready_time(timer as *mut ffi::GSource, 0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Timeout source that uses the virtual time of a `Clock` instead of the monotonic time.
+//
+// The clock is taken from the main context the source is expected to be attached to when it is
+// created, and checked against the main context it is actually attached to when it is prepared
+// the first time. Without a clock it behaves like a normal GLib timeout. With a clock, the ready
+// time is only set (to 0, i.e. immediately) by the clock once the virtual time reached the expiry.
+#[repr(C)]
+struct TimeoutSource {
+    source: ffi::GSource,
+    clock: Mutex<Option<Arc<Clock>>>,
+    resolved: AtomicBool,
+    // Monotonic time at creation.
+    created: i64,
+    interval: i64,
+    expiry: AtomicI64,
+}
+
+impl TimeoutSource {
+    #[allow(clippy::new_ret_no_self)]
+    unsafe fn new(clock: Option<Arc<Clock>>, interval: Duration) -> *mut ffi::GSource {
+        static TIMEOUT_SOURCE_FUNCS: ffi::GSourceFuncs = ffi::GSourceFuncs {
+            prepare: Some(TimeoutSource::prepare),
+            check: None,
+            dispatch: Some(TimeoutSource::dispatch),
+            finalize: Some(TimeoutSource::finalize),
+            closure_callback: None,
+            closure_marshal: None,
+        };
+
+        let source = ffi::g_source_new(
+            mut_override(&TIMEOUT_SOURCE_FUNCS),
+            mem::size_of::<TimeoutSource>() as u32,
+        );
+
+        let timeout = &mut *(source as *mut TimeoutSource);
+        ptr::write(&mut timeout.clock, Mutex::new(None));
+        ptr::write(&mut timeout.resolved, AtomicBool::new(false));
+        ptr::write(&mut timeout.created, ffi::g_get_monotonic_time());
+        ptr::write(
+            &mut timeout.interval,
+            interval.as_micros().min(i64::MAX as u128) as i64,
+        );
+        ptr::write(&mut timeout.expiry, AtomicI64::new(i64::MAX));
+        timeout.set_clock(clock);
+
+        source
+    }
+
+    // Moves the timeout from its current clock, if any, to `clock`. The expiry is relative to
+    // the current time of the new clock, or to the creation for real time.
+    unsafe fn set_clock(&self, clock: Option<Arc<Clock>>) {
+        let source = self as *const Self as *mut ffi::GSource;
+        let mut current = self.clock.lock().unwrap();
+
+        if let Some(old) = current.take() {
+            let mut state = old.lock();
+            if let Some(pos) = state.timers.iter().position(|t| *t == source as usize) {
+                state.timers.swap_remove(pos);
+            }
+        }
+
+        match clock {
+            Some(clock) => {
+                {
+                    let mut state = clock.lock();
+                    self.expiry
+                        .store(state.now.saturating_add(self.interval), Ordering::SeqCst);
+                    state.timers.push(source as usize);
+                }
+                *current = Some(clock);
+                ffi::g_source_set_ready_time(source, if self.interval == 0 { 0 } else { -1 });
+            }
+            None => {
+                ffi::g_source_set_ready_time(source, self.created.saturating_add(self.interval));
+            }
+        }
+    }
+
+    // The expiry of the timeout if it is attached and not destroyed or being finalized.
+    unsafe fn live_expiry(timer: usize) -> Option<i64> {
+        let timer = timer as *mut TimeoutSource;
+        let source = timer as *mut ffi::GSource;
+        if ptr::read_volatile(&(*source).ref_count) == 0
+            || ffi::g_source_get_context(source).is_null()
+            || ffi::g_source_is_destroyed(source) != ffi::GFALSE
+        {
+            return None;
+        }
+
+        Some((*timer).expiry.load(Ordering::SeqCst))
+    }
+
+    unsafe extern "C" fn prepare(source: *mut ffi::GSource, timeout: *mut i32) -> ffi::gboolean {
+        let this = &*(source as *mut TimeoutSource);
+        *timeout = -1;
+
+        if this.resolved.swap(true, Ordering::SeqCst) {
+            return ffi::GFALSE;
+        }
+
+        let clock = context_clock(ffi::g_source_get_context(source));
+        let unchanged = match (&*this.clock.lock().unwrap(), &clock) {
+            (Some(current), Some(clock)) => Arc::ptr_eq(current, clock),
+            (None, None) => true,
+            _ => false,
+        };
+        if !unchanged {
+            this.set_clock(clock);
+        }
+
+        ffi::GFALSE
+    }
+
+    unsafe extern "C" fn dispatch(
+        source: *mut ffi::GSource,
+        callback: ffi::GSourceFunc,
+        user_data: ffi::gpointer,
+    ) -> ffi::gboolean {
+        let timeout = &*(source as *mut TimeoutSource);
+        let callback = match callback {
+            Some(callback) => callback,
+            None => return ffi::G_SOURCE_REMOVE,
+        };
+
+        ffi::g_source_set_ready_time(source, -1);
+        let again = callback(user_data);
+
+        if again != ffi::G_SOURCE_REMOVE {
+            // Same as GLib, the next expiry is relative to the dispatch time.
+            let clock = timeout.clock.lock().unwrap().clone();
+            match clock {
+                Some(clock) => {
+                    let now = clock.now();
+                    let expiry = now.saturating_add(timeout.interval);
+                    timeout.expiry.store(expiry, Ordering::SeqCst);
+                    if expiry <= now {
+                        ffi::g_source_set_ready_time(source, 0);
+                    }
+                }
+                None => ffi::g_source_set_ready_time(
+                    source,
+                    ffi::g_source_get_time(source).saturating_add(timeout.interval),
+                ),
+            }
+        }
+
+        again
+    }
+
+    unsafe extern "C" fn finalize(source: *mut ffi::GSource) {
+        let timeout = source as *mut TimeoutSource;
+
+        if let Some(clock) = (*timeout).clock.get_mut().unwrap().take() {
+            let mut state = clock.lock();
+            if let Some(pos) = state.timers.iter().position(|t| *t == source as usize) {
+                state.timers.swap_remove(pos);
+            }
+        }
+
+        ptr::drop_in_place(&mut (*timeout).clock);
+    }
+}
+
+// The clock of `context`, or of the global default main context if `NULL`.
+unsafe fn context_clock(context: *mut ffi::GMainContext) -> Option<Arc<Clock>> {
+    let context = if context.is_null() {
+        ffi::g_main_context_default()
+    } else {
+        context
+    };
+
+    let clocks = CLOCKS.lock().unwrap();
+    clocks
+        .iter()
+        .find(|(c, _)| *c == context as usize)
+        .and_then(|(_, handle)| handle.upgrade())
+        .map(|handle| handle.clock.clone())
+}
+
+// Lowest priority source that is only prepared if no other source is ready, i.e. if the main
+// context is idle, and that then moves the clock to the next expiry.
+struct AutoAdvance {
+    clock: Arc<Clock>,
+}
+
+impl SourceImpl for AutoAdvance {
+    fn prepare(&self, _source: &Source) -> (bool, Option<Duration>) {
+        if self.clock.lock().advancing {
+            return (false, None);
+        }
+
+        // Timeouts that expired already but are not ready are blocked, e.g. by a recursive
+        // dispatch, and the time must not move forward because of them.
+        match self.clock.next_expiry() {
+            Some(expiry) if expiry > self.clock.now() => {
+                self.clock.advance_to(expiry);
+                (false, Some(Duration::ZERO))
+            }
+            _ => (false, None),
+        }
+    }
+
+    fn dispatch(&self, _source: &Source) -> Continue {
+        Continue(true)
+    }
+}
+
+struct Handle {
+    context: MainContext,
+    clock: Arc<Clock>,
+    auto_advance: Mutex<Option<Source>>,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        if let Some(source) = self.auto_advance.get_mut().unwrap().take() {
+            source.destroy();
+        }
+
+        let mut clocks = CLOCKS.lock().unwrap();
+        let ptr = self.context.as_ptr() as usize;
+        // A new clock could've been installed already if this one was dropped concurrently
+        clocks.retain(|(context, handle)| *context != ptr || handle.strong_count() > 0);
+        N_CLOCKS.store(clocks.len(), Ordering::SeqCst);
+    }
+}
+
+// Creates a virtual timeout source if any virtual clock exists. `context` is the main context the
+// source is most likely attached to, or `NULL` for the global default main context.
+pub(crate) unsafe fn timeout_source_new(
+    context: *mut ffi::GMainContext,
+    interval: Duration,
+) -> Option<*mut ffi::GSource> {
+    if N_CLOCKS.load(Ordering::SeqCst) == 0 {
+        return None;
+    }
+
+    Some(TimeoutSource::new(context_clock(context), interval))
+}
+
+// rustdoc-stripper-ignore-next
+/// A virtual monotonic clock for a [`MainContext`], for testing code that uses timeouts.
+///
+/// Timeout sources for a main context with a virtual clock don't expire after real time has
+/// passed but only once the virtual time is moved forward with [`advance`](Self::advance) or,
+/// if enabled, [automatically](Self::set_auto_advance) whenever the main context is idle. Tests
+/// of timeouts thus run instantly and deterministically.
+///
+/// This affects all timeouts created with [`timeout_add`](crate::timeout_add),
+/// [`timeout_source_new`](crate::timeout_source_new), [`timeout_future`](crate::timeout_future),
+/// [`interval_stream`](crate::interval_stream) and their variants that are attached to the main
+/// context of the clock, independent of the thread or thread default main context they were
+/// created on. For futures and streams that is the main context they are polled from. Timeouts
+/// created before any virtual clock existed are not affected.
+///
+/// The expiry of a timeout is relative to the virtual time when it was created if it's attached
+/// to the thread default main context of its creation, or to the global default main context for
+/// [`timeout_add`](crate::timeout_add). Otherwise the clock is only known once the main context
+/// prepares the timeout the first time and its expiry is relative to that time.
+///
+/// Everything else is not affected: sources that set a [ready time](Source::set_ready_time)
+/// directly are compared against the real monotonic time as usual, which is e.g. what the
+/// futures executor relies on for waking up tasks. Virtual timeouts themselves behave like GLib
+/// timeouts, i.e. a timeout that is dispatched late is dispatched once and its next expiry is
+/// relative to the time it was dispatched.
+///
+/// [`Source::time`] (`g_source_get_time()`) keeps returning the real monotonic time, also for
+/// virtual timeouts. Use [`now`](Self::now) to get the virtual time.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let clock = glib::VirtualClock::new();
+/// let c = clock.context();
+///
+/// let handle = c.spawn_task(async {
+///     glib::timeout_future(Duration::from_secs(3600)).await;
+///     "done"
+/// });
+///
+/// clock.advance(Duration::from_secs(1800));
+/// assert!(!handle.is_finished());
+/// clock.advance(Duration::from_secs(1800));
+/// assert!(handle.is_finished());
+/// assert_eq!(clock.now(), Duration::from_secs(3600));
+/// ```
+#[derive(Clone)]
+pub struct VirtualClock(Arc<Handle>);
+
+impl VirtualClock {
+    // rustdoc-stripper-ignore-next
+    /// Creates a new main context with a virtual clock.
+    ///
+    /// The virtual time starts at zero.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::install(&MainContext::new())
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Installs a virtual clock on an existing main context.
+    ///
+    /// This can be used for the global default main context, e.g. for testing code that uses
+    /// [`timeout_add_local`](crate::timeout_add_local). If the main context already has a virtual
+    /// clock, that one is returned.
+    ///
+    /// The virtual clock is removed again from the main context once all clones of the returned
+    /// value are dropped. Virtual timeouts that still exist at that point are never dispatched.
+    pub fn install(context: &MainContext) -> Self {
+        let mut clocks = CLOCKS.lock().unwrap();
+        let ptr = context.as_ptr() as usize;
+        if let Some(handle) = clocks
+            .iter()
+            .find(|(c, _)| *c == ptr)
+            .and_then(|(_, handle)| handle.upgrade())
+        {
+            return Self(handle);
+        }
+
+        let handle = Arc::new(Handle {
+            context: context.clone(),
+            clock: Arc::new(Clock {
+                state: Mutex::new(ClockState {
+                    now: 0,
+                    timers: Vec::new(),
+                    advancing: false,
+                }),
+            }),
+            auto_advance: Mutex::new(None),
+        });
+        clocks.retain(|(c, _)| *c != ptr);
+        clocks.push((ptr, Arc::downgrade(&handle)));
+        N_CLOCKS.store(clocks.len(), Ordering::SeqCst);
+
+        Self(handle)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The main context this clock belongs to.
+    pub fn context(&self) -> &MainContext {
+        &self.0.context
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The current virtual time, relative to the creation of the clock.
+    pub fn now(&self) -> Duration {
+        Duration::from_micros(self.0.clock.now() as u64)
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// The time until the next virtual timeout expires, if any.
+    ///
+    /// This is zero if a timeout expired already but was not dispatched yet.
+    pub fn next_deadline(&self) -> Option<Duration> {
+        let clock = &self.0.clock;
+        let now = clock.now();
+        clock
+            .next_expiry()
+            .map(|expiry| Duration::from_micros((expiry - now).max(0) as u64))
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Moves the virtual time forward by `duration`.
+    ///
+    /// If the main context can be acquired by the current thread and nothing is dispatched
+    /// currently, this steps through all expiries up to the new time and dispatches everything
+    /// that becomes ready at each of them before moving on, as if the time passed in real time.
+    ///
+    /// Otherwise, e.g. when called from a future that runs on the main context, the time is
+    /// moved forward in one step and all timeouts that expired are dispatched the next time the
+    /// main context is iterated.
+    pub fn advance(&self, duration: Duration) {
+        let clock = &self.0.clock;
+        let target = clock
+            .now()
+            .saturating_add(duration.as_micros().min(i64::MAX as u128) as i64);
+
+        let _acquire = match self.0.context.acquire() {
+            Ok(acquire) if unsafe { ffi::g_main_depth() } == 0 => acquire,
+            _ => {
+                clock.advance_to(target);
+                return;
+            }
+        };
+
+        clock.lock().advancing = true;
+        struct ResetAdvancing<'a>(&'a Clock);
+        impl<'a> Drop for ResetAdvancing<'a> {
+            fn drop(&mut self) {
+                self.0.lock().advancing = false;
+            }
+        }
+        let _reset = ResetAdvancing(clock);
+
+        self.dispatch_ready();
+        while let Some(expiry) = clock.next_expiry() {
+            if expiry > target {
+                break;
+            }
+
+            let now = clock.now();
+            clock.advance_to(expiry);
+            if !self.dispatch_ready() && expiry <= now {
+                // Expired timeouts that can't be dispatched currently, e.g. because they're
+                // blocked by a recursive dispatch.
+                break;
+            }
+        }
+
+        clock.advance_to(target);
+        self.dispatch_ready();
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Sets whether the virtual time automatically moves forward to the next expiry whenever the
+    /// main context is idle, i.e. when nothing else is ready to be dispatched.
+    ///
+    /// With this, e.g. [`MainContext::block_on`] on a future that waits for a timeout completes
+    /// immediately.
+    pub fn set_auto_advance(&self, auto_advance: bool) {
+        let mut source = self.0.auto_advance.lock().unwrap();
+        match (auto_advance, source.is_some()) {
+            (true, false) => {
+                let s = Source::new(
+                    AutoAdvance {
+                        clock: self.0.clock.clone(),
+                    },
+                    Some("VirtualClock auto-advance"),
+                    unsafe { from_glib(i32::MAX) },
+                );
+                s.attach(Some(&self.0.context));
+                *source = Some(s);
+            }
+            (false, true) => {
+                source.take().unwrap().destroy();
+            }
+            _ => (),
+        }
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Whether the virtual time automatically moves forward when the main context is idle.
+    pub fn is_auto_advance(&self) -> bool {
+        self.0.auto_advance.lock().unwrap().is_some()
+    }
+
+    // rustdoc-stripper-ignore-next
+    /// Dispatches everything that is ready without blocking until nothing is ready anymore.
+    ///
+    /// With [auto-advancing](Self::set_auto_advance) this also moves the virtual time forward
+    /// until no timeout is left, which never happens if e.g. an
+    /// [`interval_stream`](crate::interval_stream) is still active.
+    ///
+    /// This panics if the main context can't be acquired by the current thread.
+    pub fn run_until_stalled(&self) {
+        let _acquire = self
+            .0
+            .context
+            .acquire()
+            .expect("main context already acquired by another thread");
+        self.dispatch_ready();
+    }
+
+    fn dispatch_ready(&self) -> bool {
+        let mut dispatched = false;
+        while self.0.context.iteration(false) {
+            dispatched = true;
+        }
+        dispatched
+    }
+}
+
+impl std::fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("context", &self.0.context)
+            .field("now", &self.now())
+            .field("auto_advance", &self.is_auto_advance())
+            .finish()
+    }
+}
+
+impl MainContext {
+    // rustdoc-stripper-ignore-next
+    /// The [`VirtualClock`] of this main context, if it has one.
+    pub fn virtual_clock(&self) -> Option<VirtualClock> {
+        if N_CLOCKS.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+
+        let ptr = self.as_ptr() as usize;
+        CLOCKS
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(c, _)| *c == ptr)
+            .and_then(|(_, handle)| handle.upgrade())
+            .map(VirtualClock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_advance() {
+        let clock = VirtualClock::new();
+        let c = clock.context();
+        assert_eq!(c.virtual_clock().unwrap().now(), Duration::ZERO);
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        c.with_thread_default(|| {
+            for secs in [3, 1, 2] {
+                let log = log.clone();
+                let clock = clock.clone();
+                c.spawn_local(async move {
+                    crate::timeout_future(Duration::from_secs(secs)).await;
+                    log.borrow_mut().push((secs, clock.now()));
+                });
+            }
+        })
+        .unwrap();
+
+        clock.run_until_stalled();
+        assert_eq!(clock.next_deadline(), Some(Duration::from_secs(1)));
+        clock.advance(Duration::from_millis(1500));
+        assert_eq!(*log.borrow(), [(1, Duration::from_secs(1))]);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(
+            *log.borrow(),
+            [
+                (1, Duration::from_secs(1)),
+                (2, Duration::from_secs(2)),
+                (3, Duration::from_secs(3))
+            ]
+        );
+        assert_eq!(clock.now(), Duration::from_millis(11500));
+        assert_eq!(clock.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_interval() {
+        let clock = VirtualClock::new();
+        let c = clock.context();
+
+        let ticks = Rc::new(RefCell::new(Vec::new()));
+        let handle = c
+            .with_thread_default(|| {
+                let ticks = ticks.clone();
+                let clock = clock.clone();
+                c.spawn_local_task(async move {
+                    let mut interval = crate::interval_stream(Duration::from_millis(100)).take(5);
+                    while interval.next().await.is_some() {
+                        ticks.borrow_mut().push(clock.now().as_millis());
+                    }
+                })
+            })
+            .unwrap();
+
+        clock.run_until_stalled();
+        clock.advance(Duration::from_millis(350));
+        assert_eq!(*ticks.borrow(), [100, 200, 300]);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(*ticks.borrow(), [100, 200, 300, 400, 500]);
+        assert!(handle.is_finished());
+    }
+
+    #[test]
+    fn test_timeout_source() {
+        let clock = VirtualClock::new();
+        let c = clock.context();
+
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = c
+            .with_thread_default(|| {
+                let count = count.clone();
+                crate::timeout_source_new(Duration::from_secs(1), None, crate::PRIORITY_DEFAULT, {
+                    move || {
+                        count.fetch_add(1, Ordering::SeqCst);
+                        Continue(true)
+                    }
+                })
+            })
+            .unwrap();
+        source.attach(Some(c));
+
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+
+        source.destroy();
+        assert_eq!(clock.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_attach_other_context() {
+        let clock = VirtualClock::new();
+        let c = clock.context();
+        let other = MainContext::new();
+
+        let new_source = |count: &Arc<std::sync::atomic::AtomicUsize>| {
+            let count = count.clone();
+            crate::timeout_source_new(
+                Duration::from_millis(10),
+                None,
+                crate::PRIORITY_DEFAULT,
+                move || {
+                    count.fetch_add(1, Ordering::SeqCst);
+                    Continue(false)
+                },
+            )
+        };
+
+        // Created on another thread but attached to the context with the clock
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = std::thread::spawn({
+            let count = count.clone();
+            move || new_source(&count)
+        })
+        .join()
+        .unwrap();
+        source.attach(Some(c));
+        clock.run_until_stalled();
+        std::thread::sleep(Duration::from_millis(20));
+        clock.run_until_stalled();
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert_eq!(clock.next_deadline(), Some(Duration::from_millis(10)));
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Created with the clock's context as thread default but attached elsewhere
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let source = c.with_thread_default(|| new_source(&count)).unwrap();
+        source.attach(Some(&other));
+        while other.iteration(false) {}
+        assert_eq!(clock.next_deadline(), None);
+        assert!(source.ready_time() > 0);
+        other.block_on(crate::timeout_future(Duration::from_millis(20)));
+        while other.iteration(false) {}
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // Futures use the clock of the context they're polled from
+        let start = std::time::Instant::now();
+        c.with_thread_default(|| {
+            other.block_on(crate::timeout_future(Duration::from_millis(20)));
+        })
+        .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(clock.now(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_auto_advance() {
+        let clock = VirtualClock::new();
+        let c = clock.context();
+        clock.set_auto_advance(true);
+        assert!(clock.is_auto_advance());
+
+        let start = std::time::Instant::now();
+        c.block_on(async {
+            crate::timeout_future(Duration::from_secs(60)).await;
+            crate::future_with_timeout(
+                Duration::from_secs(10),
+                crate::timeout_future(Duration::from_secs(20)),
+            )
+            .await
+            .unwrap_err();
+        });
+        assert_eq!(clock.now(), Duration::from_secs(70));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        clock.set_auto_advance(false);
+        let handle = c.spawn_task(crate::timeout_future(Duration::from_secs(1)));
+        clock.run_until_stalled();
+        assert!(!handle.is_finished());
+        clock.advance(Duration::from_secs(1));
+        assert!(handle.is_finished());
+    }
+
+    #[test]
+    fn test_uninstall() {
+        let c = MainContext::new();
+        let clock = VirtualClock::install(&c);
+        assert!(c.virtual_clock().is_some());
+        assert_eq!(
+            VirtualClock::install(&c).context().as_ptr(),
+            clock.context().as_ptr()
+        );
+        drop(clock);
+        assert!(c.virtual_clock().is_none());
+    }
+}
@@ -0,0 +1,138 @@
+// Take a look at the license at the top of the repository in the LICENSE file.
+
+#![cfg(feature = "leak_tracker")]
+
+use std::thread;
+
+use glib::{ffi, gobject_ffi};
+use glib::leak_tracker::*;
+use glib::prelude::*;
+use glib::translate::from_glib_none;
+use serial_test::serial;
+
+mod imp {
+    use glib::subclass::prelude::*;
+
+    #[derive(Default)]
+    pub struct TrackedObject;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for TrackedObject {
+        const NAME: &'static str = "LeakTrackerTrackedObject";
+        type Type = super::TrackedObject;
+    }
+
+    impl ObjectImpl for TrackedObject {}
+}
+
+glib::wrapper! {
+    pub struct TrackedObject(ObjectSubclass<imp::TrackedObject>);
+}
+
+#[test]
+#[serial]
+fn track_subclass() {
+    enable();
+
+    let obj = glib::Object::new::<TrackedObject>(&[]);
+    let other = obj.clone();
+    let live = live_objects()
+        .into_iter()
+        .find(|live| live.as_ptr() == obj.as_ptr() as *const _)
+        .unwrap();
+    assert_eq!(live.type_(), TrackedObject::static_type());
+    assert_eq!(live.ref_count(), 2);
+    assert_eq!(live.thread_id(), thread::current().id());
+
+    drop(obj);
+    let res = std::panic::catch_unwind(|| glib::assert_no_leaks!(TrackedObject));
+    let msg = res.unwrap_err().downcast::<String>().unwrap();
+    assert!(msg.starts_with("1 objects leaked:\n\nLeakTrackerTrackedObject at"));
+
+    drop(other);
+    glib::assert_no_leaks!(TrackedObject);
+    glib::assert_no_leaks!();
+}
+
+#[test]
+#[serial]
+fn track_foreign() {
+    enable();
+
+    let obj = glib::Object::new::<glib::Object>(&[]);
+    let ptr = obj.as_ptr() as *const gobject_ffi::GObject;
+    assert!(!live_objects().iter().any(|live| live.as_ptr() == ptr));
+
+    track(&obj);
+    track(&obj);
+    assert_eq!(
+        live_objects()
+            .iter()
+            .filter(|live| live.as_ptr() == ptr)
+            .count(),
+        1
+    );
+
+    drop(obj);
+    assert!(!live_objects().iter().any(|live| live.as_ptr() == ptr));
+}
+
+#[test]
+#[serial]
+fn toggle_refs() {
+    unsafe extern "C" fn toggle_notify(
+        _data: ffi::gpointer,
+        _obj: *mut gobject_ffi::GObject,
+        _is_last_ref: ffi::gboolean,
+    ) {
+    }
+
+    enable();
+
+    let obj = glib::Object::new::<TrackedObject>(&[]);
+    let ptr = obj.as_ptr() as *const gobject_ffi::GObject;
+    unsafe { add_toggle_ref(&obj, Some(toggle_notify), std::ptr::null_mut()) };
+    let live = live_objects()
+        .into_iter()
+        .find(|live| live.as_ptr() == ptr)
+        .unwrap();
+    assert_eq!(live.ref_count(), 2);
+    assert_eq!(live.toggle_refs(), 1);
+    assert!(!live.is_toggled_down());
+    assert!(std::panic::catch_unwind(|| glib::assert_no_leaks!(TrackedObject)).is_err());
+
+    // Only the toggle reference is left, like for an object owned by a JavaScript wrapper.
+    drop(obj);
+    let live = live_objects()
+        .into_iter()
+        .find(|live| live.as_ptr() == ptr)
+        .unwrap();
+    assert!(live.is_toggled_down());
+    glib::assert_no_leaks!(TrackedObject);
+
+    unsafe {
+        let obj = from_glib_none::<_, glib::Object>(ptr as *mut gobject_ffi::GObject);
+        remove_toggle_ref(&obj, Some(toggle_notify), std::ptr::null_mut());
+    }
+    assert!(!live_objects().iter().any(|live| live.as_ptr() == ptr));
+}
+
+#[test]
+#[serial]
+fn other_threads() {
+    enable();
+
+    let (created_tx, created_rx) = std::sync::mpsc::channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        let obj = glib::Object::new::<TrackedObject>(&[]);
+        created_tx.send(()).unwrap();
+        done_rx.recv().unwrap();
+        drop(obj);
+    });
+
+    created_rx.recv().unwrap();
+    glib::assert_no_leaks!(TrackedObject);
+    done_tx.send(()).unwrap();
+    handle.join().unwrap();
+}